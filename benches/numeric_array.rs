@@ -0,0 +1,53 @@
+//! Compares the generic and fast paths for encoding and decoding large
+//! numeric arrays.
+//!
+//! Run with `cargo bench --bench numeric_array`.
+
+use std::{hint::black_box, time::{Duration, Instant}};
+
+use dcbor::{prelude::*, NumericElement};
+
+const COUNT: usize = 1_000_000;
+const ROUNDS: u32 = 5;
+
+fn time(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        f();
+    }
+    start.elapsed() / ROUNDS
+}
+
+fn compare<T>(name: &str, values: Vec<T>)
+where
+    T: NumericElement + TryFrom<CBOR, Error = anyhow::Error>,
+    CBOR: From<Vec<T>>,
+{
+    let data = CBOR::from(values.clone()).to_cbor_data();
+    assert_eq!(CBOR::encode_numeric_array(&values), data);
+
+    let generic_encode = time(|| {
+        black_box(CBOR::from(black_box(values.clone())).to_cbor_data());
+    });
+    let fast_encode = time(|| {
+        black_box(CBOR::encode_numeric_array(black_box(&values)));
+    });
+    let generic_decode = time(|| {
+        let cbor = CBOR::try_from_data(black_box(&data)).unwrap();
+        black_box(Vec::<T>::try_from(cbor).unwrap());
+    });
+    let fast_decode = time(|| {
+        black_box(CBOR::decode_numeric_array::<T>(black_box(&data)).unwrap());
+    });
+
+    println!("{name}, {COUNT} elements:");
+    println!("  encode: generic {generic_encode:?}, fast {fast_encode:?}");
+    println!("  decode: generic {generic_decode:?}, fast {fast_decode:?}");
+}
+
+fn main() {
+    compare("Vec<u64>", (0..COUNT as u64).map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> (i % 64)).collect());
+    compare("Vec<i64>", (0..COUNT as i64).map(|i| if i % 2 == 0 { i * 1_000 } else { -i }).collect());
+    #[cfg(feature = "float")]
+    compare("Vec<f64>", (0..COUNT).map(|i| i as f64 / 7.0).collect());
+}
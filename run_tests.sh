@@ -4,5 +4,15 @@ set -e
 
 cargo test
 cargo test --features multithreaded
+cargo test --no-default-features --features no_std,format,float
+cargo test --no-default-features --features no_std,format,float,multithreaded
+cargo test --no-default-features --features std,float
+cargo test --no-default-features --features std,format
+cargo test --no-default-features --features std
+cargo test --no-default-features --features no_std,float
 cargo test --no-default-features --features no_std
-cargo test --no-default-features --features no_std,multithreaded
+cargo build --features wasm
+cargo test --features ffi
+cargo test --features simd
+cargo test --features encoding-cache
+cargo test --features encoding-cache,multithreaded
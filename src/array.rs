@@ -1,9 +1,43 @@
 import_stdlib!();
 
-use crate::{error::CBORError, CBORCase, CBOR};
+use crate::{error::{within, CBORError}, CBORCase, PathElement, CBOR};
 
 use anyhow::{bail, Error, Result};
 
+/// Sorts the given values by their binary encodings, the same order dCBOR
+/// uses for map keys.
+///
+/// This is useful for protocols that represent sets as arrays, which must be
+/// in a deterministic order.
+pub fn sort_canonical(values: &mut [CBOR]) {
+    values.sort_by_cached_key(|value| value.encoded());
+}
+
+impl CBOR {
+    /// If this is an array, returns a copy with its elements sorted by their
+    /// binary encodings, as [`sort_canonical`] does.
+    ///
+    /// Returns an error if this is not an array.
+    pub fn sorted_array(&self) -> Result<CBOR> {
+        match self.as_case() {
+            CBORCase::Array(array) => {
+                let mut array = array.clone();
+                sort_canonical(&mut array);
+                Ok(array.into())
+            },
+            _ => bail!(CBORError::WrongType),
+        }
+    }
+}
+
+/// Decodes an array element, noting its index in any error.
+fn decode_element<T>(index: usize, cbor: CBOR) -> Result<T>
+where
+    T: TryFrom<CBOR, Error = Error>,
+{
+    cbor.try_into().map_err(|error: Error| within(error, || PathElement::Index(index)).context(format!("array element {}", index)))
+}
+
 impl<T> From<Vec<T>> for CBOR where T: Into<CBOR> {
     fn from(vec: Vec<T>) -> Self {
         CBORCase::Array(vec.into_iter().map(|x| x.into()).collect()).into()
@@ -16,6 +50,12 @@ impl<T> From<&[T]> for CBOR where T: Into<CBOR> + Clone {
     }
 }
 
+impl<T> From<&Vec<T>> for CBOR where T: Into<CBOR> + Clone {
+    fn from(vec: &Vec<T>) -> Self {
+        vec.as_slice().into()
+    }
+}
+
 impl<T> TryFrom<CBOR> for Vec<T>
 where
     T: TryFrom<CBOR, Error = Error> + Clone,
@@ -26,12 +66,12 @@ where
         match cbor.into_case() {
             CBORCase::Array(cbor_array) => {
                 let mut result = Vec::new();
-                for cbor in cbor_array {
-                    result.push(cbor.try_into()?);
+                for (index, cbor) in cbor_array.into_iter().enumerate() {
+                    result.push(decode_element(index, cbor)?);
                 }
                 Ok(result)
             },
-            _ => panic!()
+            _ => bail!(CBORError::WrongType)
         }
     }
 }
@@ -42,6 +82,29 @@ impl<T, const N: usize> From<[T; N]> for CBOR where T: Into<CBOR> {
     }
 }
 
+impl<T, const N: usize> TryFrom<CBOR> for [T; N]
+where
+    T: TryFrom<CBOR, Error = Error>,
+{
+    type Error = Error;
+
+    fn try_from(cbor: CBOR) -> Result<Self> {
+        match cbor.into_case() {
+            CBORCase::Array(cbor_array) => {
+                if cbor_array.len() != N {
+                    bail!(CBORError::WrongArrayLength(N, cbor_array.len()));
+                }
+                let mut result = Vec::with_capacity(N);
+                for (index, cbor) in cbor_array.into_iter().enumerate() {
+                    result.push(decode_element(index, cbor)?);
+                }
+                Ok(result.try_into().ok().unwrap())
+            },
+            _ => bail!(CBORError::WrongType)
+        }
+    }
+}
+
 impl<T> From<VecDeque<T>> for CBOR where T: Into<CBOR> {
     fn from(deque: VecDeque<T>) -> Self {
         CBORCase::Array(deque.into_iter().map(|x| x.into()).collect()).into()
@@ -58,8 +121,8 @@ where
         match cbor.into_case() {
             CBORCase::Array(cbor_array) => {
                 let mut result = VecDeque::new();
-                for cbor in cbor_array {
-                    result.push_back(cbor.try_into()?);
+                for (index, cbor) in cbor_array.into_iter().enumerate() {
+                    result.push_back(decode_element(index, cbor)?);
                 }
                 Ok(result)
             },
@@ -84,8 +147,106 @@ where
         match cbor.into_case() {
             CBORCase::Array(cbor_array) => {
                 let mut result = HashSet::new();
-                for cbor in cbor_array {
-                    result.insert(cbor.try_into()?);
+                for (index, cbor) in cbor_array.into_iter().enumerate() {
+                    result.insert(decode_element(index, cbor)?);
+                }
+                Ok(result)
+            },
+            _ => bail!(CBORError::WrongType)
+        }
+    }
+}
+
+impl<T> From<BTreeSet<T>> for CBOR where T: Into<CBOR> {
+    fn from(set: BTreeSet<T>) -> Self {
+        CBORCase::Array(set.into_iter().map(|x| x.into()).collect()).into()
+    }
+}
+
+impl<T> TryFrom<CBOR> for BTreeSet<T>
+where
+    T: TryFrom<CBOR, Error = Error> + cmp::Ord,
+{
+    type Error = Error;
+
+    fn try_from(cbor: CBOR) -> Result<Self> {
+        match cbor.into_case() {
+            CBORCase::Array(cbor_array) => {
+                let mut result = BTreeSet::new();
+                for (index, cbor) in cbor_array.into_iter().enumerate() {
+                    result.insert(decode_element(index, cbor)?);
+                }
+                Ok(result)
+            },
+            _ => bail!(CBORError::WrongType)
+        }
+    }
+}
+
+/// Encodes the heap's elements in ascending order, so the encoding does not
+/// depend on the heap's internal layout.
+impl<T> From<BinaryHeap<T>> for CBOR where T: Into<CBOR> + cmp::Ord {
+    fn from(heap: BinaryHeap<T>) -> Self {
+        CBORCase::Array(heap.into_sorted_vec().into_iter().map(|x| x.into()).collect()).into()
+    }
+}
+
+impl<T> TryFrom<CBOR> for BinaryHeap<T>
+where
+    T: TryFrom<CBOR, Error = Error> + cmp::Ord,
+{
+    type Error = Error;
+
+    fn try_from(cbor: CBOR) -> Result<Self> {
+        match cbor.into_case() {
+            CBORCase::Array(cbor_array) => {
+                let mut result = BinaryHeap::new();
+                for (index, cbor) in cbor_array.into_iter().enumerate() {
+                    result.push(decode_element(index, cbor)?);
+                }
+                Ok(result)
+            },
+            _ => bail!(CBORError::WrongType)
+        }
+    }
+}
+
+/// `None` is encoded as `null`.
+impl<T> From<Option<T>> for CBOR where T: Into<CBOR> {
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(value) => value.into(),
+            None => CBOR::null(),
+        }
+    }
+}
+
+impl CBOR {
+    /// Decodes this value as `T`, or as `None` if it is `null`.
+    ///
+    /// This is the counterpart of the `From<Option<T>>` conversion.
+    pub fn try_into_option<T>(self) -> Result<Option<T>>
+    where
+        T: TryFrom<CBOR, Error = Error>,
+    {
+        if self.is_null() {
+            Ok(None)
+        } else {
+            Ok(Some(self.try_into()?))
+        }
+    }
+
+    /// Decodes this value as an array whose elements are `T` or `null`.
+    pub fn try_into_option_array<T>(self) -> Result<Vec<Option<T>>>
+    where
+        T: TryFrom<CBOR, Error = Error>,
+    {
+        match self.into_case() {
+            CBORCase::Array(cbor_array) => {
+                let mut result = Vec::with_capacity(cbor_array.len());
+                for (index, cbor) in cbor_array.into_iter().enumerate() {
+                    result.push(cbor.try_into_option()
+                        .map_err(|error| within(error, || PathElement::Index(index)).context(format!("array element {}", index)))?);
                 }
                 Ok(result)
             },
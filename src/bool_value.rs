@@ -13,6 +13,12 @@ impl From<bool> for CBOR {
     }
 }
 
+impl From<&bool> for CBOR {
+    fn from(value: &bool) -> Self {
+        (*value).into()
+    }
+}
+
 impl TryFrom<CBOR> for bool {
     type Error = Error;
 
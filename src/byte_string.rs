@@ -1,9 +1,18 @@
 import_stdlib!();
 
-use anyhow::Error;
-
-use crate::CBOR;
-
+use anyhow::{bail, Error, Result};
+#[cfg(feature = "text-encoding")]
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::{error::decode_hex, string_util::hex_encode, CBOR};
+#[cfg(feature = "text-encoding")]
+use crate::CBORError;
+
+/// A CBOR byte string.
+///
+/// Comparisons with `==` and `<` return as soon as a byte differs, so they are
+/// not constant time. With the `subtle` feature, use
+/// `subtle::ConstantTimeEq::ct_eq` to compare byte strings holding secrets.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct ByteString(Vec<u8>);
 
@@ -40,6 +49,69 @@ impl ByteString {
     }
 }
 
+/// Text encodings of the bytes.
+impl ByteString {
+    /// Decodes a byte string from hexadecimal, in either case.
+    ///
+    /// Returns [`CBORError::InvalidHex`](crate::CBORError::InvalidHex) if the
+    /// text is not well-formed hexadecimal.
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        match decode_hex(hex) {
+            Ok(data) => Ok(Self(data)),
+            Err(err) => bail!(err),
+        }
+    }
+
+    /// Returns the bytes in lowercase hexadecimal.
+    pub fn to_hex(&self) -> String {
+        hex_encode(&self.0)
+    }
+
+    /// Decodes a byte string from padded base64 (RFC 4648 §4). Requires the
+    /// `text-encoding` feature.
+    ///
+    /// Returns [`CBORError::InvalidBase64`] if the text is not padded base64.
+    ///
+    /// ```
+    /// # use dcbor::ByteString;
+    /// let bytes = ByteString::from_base64("3q2+7w==").unwrap();
+    /// assert_eq!(bytes.to_hex(), "deadbeef");
+    /// assert_eq!(bytes.to_base64(), "3q2+7w==");
+    /// ```
+    #[cfg(feature = "text-encoding")]
+    pub fn from_base64(text: &str) -> Result<Self> {
+        match STANDARD.decode(text) {
+            Ok(data) => Ok(Self(data)),
+            Err(_) => bail!(CBORError::InvalidBase64),
+        }
+    }
+
+    /// Returns the bytes in padded base64 (RFC 4648 §4). Requires the
+    /// `text-encoding` feature.
+    #[cfg(feature = "text-encoding")]
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(&self.0)
+    }
+}
+
+/// Compares the contents of byte strings in constant time.
+///
+/// Byte strings of different lengths compare unequal without their contents
+/// being examined, so timing reveals whether the lengths differ.
+#[cfg(feature = "subtle")]
+impl subtle::ConstantTimeEq for ByteString {
+    fn ct_eq(&self, other: &Self) -> subtle::Choice {
+        self.0.as_slice().ct_eq(other.0.as_slice())
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for ByteString {
+    fn zeroize(&mut self) {
+        self.0.zeroize()
+    }
+}
+
 impl From<ByteString> for Vec<u8> {
     fn from(value: ByteString) -> Self {
         value.0
@@ -64,6 +136,13 @@ impl From<&[u8]> for ByteString {
     }
 }
 
+/// Borrowed data is copied, while owned data is moved without copying.
+impl From<Cow<'_, [u8]>> for ByteString {
+    fn from(data: Cow<'_, [u8]>) -> Self {
+        Self(data.into_owned())
+    }
+}
+
 impl From<&str> for ByteString {
     fn from(value: &str) -> Self {
         Self(value.as_bytes().to_vec())
@@ -76,6 +155,18 @@ impl From<ByteString> for CBOR {
     }
 }
 
+impl From<&ByteString> for CBOR {
+    fn from(value: &ByteString) -> Self {
+        value.clone().into()
+    }
+}
+
+impl From<Cow<'_, [u8]>> for CBOR {
+    fn from(data: Cow<'_, [u8]>) -> Self {
+        ByteString::from(data).into()
+    }
+}
+
 impl TryFrom<CBOR> for ByteString {
     type Error = Error;
 
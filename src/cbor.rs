@@ -1,38 +1,137 @@
 import_stdlib!();
 
-use anyhow::{bail, Result};
-use unicode_normalization::UnicodeNormalization;
+use anyhow::{bail, Error, Result};
 
-use crate::{decode::decode_cbor, error::CBORError, tag::Tag, varint::{EncodeVarInt, MajorType}, Map, Simple, ByteString};
+#[cfg(feature = "float")]
+use crate::float::canonical_float_case;
+use crate::{decode::{decode_cbor, decode_cbor_opt, decode_cbor_prefix, DecodeOptions}, error::{decode_hex, CBORError}, tag::Tag, text::{is_nfc, to_nfc}, varint::{EncodeVarInt, MajorType}, Map, Simple, ByteString};
 
-use super::string_util::flanked;
+use super::string_util::{flanked, hex_encode};
 
 #[cfg(feature = "multithreaded")]
-use sync::Arc as RefCounted;
+use Arc as RefCounted;
 
 #[cfg(not(feature = "multithreaded"))]
 use rc::Rc as RefCounted;
 
 /// A symbolic representation of CBOR data.
 #[derive(Clone)]
-pub struct CBOR(RefCounted<CBORCase>);
+pub struct CBOR(RefCounted<CBORNode>);
+
+struct CBORNode {
+    case: CBORCase,
+    #[cfg(feature = "spans")]
+    span: Option<ops::Range<usize>>,
+    #[cfg(feature = "encoding-cache")]
+    encoding: EncodingCache,
+}
+
+impl CBORNode {
+    fn new(case: CBORCase) -> Self {
+        Self {
+            case,
+            #[cfg(feature = "spans")]
+            span: None,
+            #[cfg(feature = "encoding-cache")]
+            encoding: EncodingCache::default(),
+        }
+    }
+}
+
+/// The encoding of a node, computed when first requested by
+/// [`CBOR::to_cbor_data`] and kept for the life of the node.
+#[cfg(feature = "encoding-cache")]
+#[derive(Default)]
+struct EncodingCache {
+    #[cfg(not(feature = "multithreaded"))]
+    cell: core::cell::OnceCell<Vec<u8>>,
+    #[cfg(all(feature = "multithreaded", feature = "std"))]
+    cell: std::sync::OnceLock<Vec<u8>>,
+    #[cfg(all(feature = "multithreaded", not(feature = "std")))]
+    cell: spin::Once<Vec<u8>>,
+}
+
+#[cfg(feature = "encoding-cache")]
+impl EncodingCache {
+    fn get(&self) -> Option<&Vec<u8>> {
+        self.cell.get()
+    }
+
+    fn get_or_init(&self, f: impl FnOnce() -> Vec<u8>) -> &Vec<u8> {
+        #[cfg(all(feature = "multithreaded", not(feature = "std")))]
+        return self.cell.call_once(f);
+        #[cfg(not(all(feature = "multithreaded", not(feature = "std"))))]
+        self.cell.get_or_init(f)
+    }
+
+    #[cfg(feature = "zeroize")]
+    fn get_mut(&mut self) -> Option<&mut Vec<u8>> {
+        self.cell.get_mut()
+    }
+}
 
 impl CBOR {
     pub fn as_case(&self) -> &CBORCase {
-        &self.0
+        &self.0.case
     }
 
     pub fn into_case(self) -> CBORCase {
         match RefCounted::try_unwrap(self.0) {
-            Ok(b) => b,
-            Err(ref_counted) => (*ref_counted).clone(),
+            Ok(node) => node.case,
+            Err(ref_counted) => ref_counted.case.clone(),
+        }
+    }
+
+    /// Returns the range of bytes this item occupied in the data it was
+    /// decoded from.
+    ///
+    /// Returns `None` unless the item was decoded with
+    /// [`DecodeOptions::record_spans`] enabled. Requires the `spans` feature.
+    #[cfg(feature = "spans")]
+    pub fn encoded_span(&self) -> Option<ops::Range<usize>> {
+        self.0.span.clone()
+    }
+
+    #[cfg(feature = "spans")]
+    pub(crate) fn set_encoded_span(&mut self, span: ops::Range<usize>) {
+        if RefCounted::get_mut(&mut self.0).is_none() {
+            self.0 = RefCounted::new(CBORNode::new(self.0.case.clone()));
+        }
+        if let Some(node) = RefCounted::get_mut(&mut self.0) {
+            node.span = Some(span);
         }
     }
 }
 
+/// Floats are canonicalized, so that an integral float such as
+/// `CBORCase::Simple(Simple::Float(1.0))` is held as the integer it encodes
+/// as.
 impl From<CBORCase> for CBOR {
     fn from(case: CBORCase) -> Self {
-        Self(RefCounted::new(case))
+        #[cfg(feature = "float")]
+        let case = match case {
+            CBORCase::Simple(Simple::Float(n)) => canonical_float_case(n),
+            case => case,
+        };
+        Self(RefCounted::new(CBORNode::new(case)))
+    }
+}
+
+/// Cloning a `CBOR` only increments a reference count.
+impl From<&CBOR> for CBOR {
+    fn from(value: &CBOR) -> Self {
+        value.clone()
+    }
+}
+
+impl CBOR {
+    /// Creates CBOR from a reference to a value, without requiring the value
+    /// itself to be cloned.
+    ///
+    /// This is implemented for references to the primitive types, strings,
+    /// byte strings, maps, slices, and vectors.
+    pub fn from_ref<'a, T>(value: &'a T) -> CBOR where &'a T: Into<CBOR>, T: ?Sized {
+        value.into()
     }
 }
 
@@ -58,6 +157,69 @@ pub enum CBORCase {
     Simple(Simple)
 }
 
+/// Zeroizes the contents of this value, then replaces it with `null`.
+///
+/// Cloning a `CBOR` shares its contents rather than copying them, so contents
+/// still shared with another `CBOR` are left for the last of them to zeroize.
+/// Wrap decoded secrets in [`zeroize::Zeroizing`] to zeroize them when they
+/// are dropped.
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for CBOR {
+    fn zeroize(&mut self) {
+        if let Some(node) = RefCounted::get_mut(&mut self.0) {
+            match &mut node.case {
+                CBORCase::Unsigned(n) | CBORCase::Negative(n) => n.zeroize(),
+                CBORCase::ByteString(b) => b.zeroize(),
+                CBORCase::Text(s) => s.zeroize(),
+                CBORCase::Array(a) => a.zeroize(),
+                CBORCase::Map(m) => m.zeroize(),
+                CBORCase::Tagged(_, item) => item.zeroize(),
+                #[cfg(feature = "float")]
+                CBORCase::Simple(Simple::Float(n)) => n.zeroize(),
+                CBORCase::Simple(_) => {},
+            }
+            #[cfg(feature = "encoding-cache")]
+            if let Some(encoding) = node.encoding.get_mut() {
+                encoding.zeroize();
+            }
+        }
+        *self = CBOR::null();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl CBOR {
+    /// Returns the content of this byte string in a buffer that is zeroized
+    /// when dropped.
+    ///
+    /// The content is moved rather than copied unless it is shared with
+    /// another `CBOR`.
+    pub fn into_zeroizing_bytes(self) -> Result<zeroize::Zeroizing<Vec<u8>>> {
+        match self.into_case() {
+            CBORCase::ByteString(b) => Ok(zeroize::Zeroizing::new(b.into())),
+            _ => bail!(CBORError::WrongType),
+        }
+    }
+
+    /// Returns the content of this text string in a buffer that is zeroized
+    /// when dropped.
+    ///
+    /// The content is moved rather than copied unless it is shared with
+    /// another `CBOR`.
+    pub fn into_zeroizing_text(self) -> Result<zeroize::Zeroizing<String>> {
+        match self.into_case() {
+            CBORCase::Text(s) => Ok(zeroize::Zeroizing::new(s)),
+            _ => bail!(CBORError::WrongType),
+        }
+    }
+
+    /// Returns the encoding of this value in a buffer that is zeroized when
+    /// dropped.
+    pub fn to_zeroizing_cbor_data(&self) -> zeroize::Zeroizing<Vec<u8>> {
+        zeroize::Zeroizing::new(self.encoded())
+    }
+}
+
 /// Affordances for decoding CBOR from binary representation.
 impl CBOR {
     /// Decodes the given date into CBOR symbolic representation.
@@ -65,16 +227,68 @@ impl CBOR {
         decode_cbor(data)
     }
 
+    /// Decodes the given data into CBOR symbolic representation, using the
+    /// given options.
+    pub fn try_from_data_opt(data: impl AsRef<[u8]>, options: &DecodeOptions) -> Result<CBOR> {
+        decode_cbor_opt(data, options)
+    }
+
+    /// Decodes the single CBOR item at the start of the given data, returning
+    /// it along with the number of bytes consumed.
+    ///
+    /// Any data following the item is left undecoded rather than causing an
+    /// [`CBORError::UnusedData`] error, allowing sequences of items to be
+    /// decoded from a single buffer.
+    pub fn try_from_data_prefix(data: impl AsRef<[u8]>) -> Result<(CBOR, usize)> {
+        decode_cbor_prefix(data)
+    }
+
     /// Decodes the given data into CBOR symbolic representation given as a hexadecimal string.
     ///
-    /// Panics if the string is not well-formed hexadecimal with no spaces or
-    /// other characters.
+    /// Returns [`CBORError::InvalidHex`] if the string is not well-formed
+    /// hexadecimal with no spaces or other characters.
     pub fn try_from_hex(hex: &str) -> Result<CBOR> {
-        let data = hex::decode(hex).unwrap();
+        let data = match decode_hex(hex) {
+            Ok(data) => data,
+            Err(err) => bail!(err),
+        };
         Self::try_from_data(data)
     }
 
+    /// Returns the encoded hexadecimal representation of this CBOR.
+    pub fn hex(&self) -> String {
+        hex_encode(&self.to_cbor_data())
+    }
+
+    /// Encodes this CBOR into binary representation.
+    ///
+    /// With the `encoding-cache` feature, the encoding is kept with this
+    /// value, and with every `CBOR` cloned from it, so later calls, and
+    /// encoding values that contain it, copy it rather than encoding it again.
     pub fn to_cbor_data(&self) -> Vec<u8> {
+        #[cfg(feature = "encoding-cache")]
+        return self.0.encoding.get_or_init(|| self.encoded()).clone();
+        #[cfg(not(feature = "encoding-cache"))]
+        self.encoded()
+    }
+
+    /// Returns the encoding of this CBOR if it has been cached by
+    /// [`CBOR::to_cbor_data`], without copying it.
+    #[cfg(feature = "encoding-cache")]
+    pub fn cached_encoding(&self) -> Option<&[u8]> {
+        self.0.encoding.get().map(Vec::as_slice)
+    }
+
+    /// Returns the encoding of this CBOR, from the cache if it is there, but
+    /// without adding it to the cache.
+    ///
+    /// This is used for items within a value being encoded, so that only the
+    /// values whose encodings are requested are cached.
+    pub(crate) fn encoded(&self) -> Vec<u8> {
+        #[cfg(feature = "encoding-cache")]
+        if let Some(encoding) = self.cached_encoding() {
+            return encoding.to_vec();
+        }
         match self.as_case() {
             CBORCase::Unsigned(x) => x.encode_varint(MajorType::Unsigned),
             CBORCase::Negative(x) => x.encode_varint(MajorType::Negative),
@@ -84,7 +298,7 @@ impl CBOR {
                 buf
             },
             CBORCase::Text(x) => {
-                let nfc = x.nfc().collect::<String>();
+                let nfc = to_nfc(x);
                 let mut buf = nfc.len().encode_varint(MajorType::Text);
                 buf.extend(nfc.as_bytes());
                 buf
@@ -92,14 +306,14 @@ impl CBOR {
             CBORCase::Array(x) => {
                 let mut buf = x.len().encode_varint(MajorType::Array);
                 for item in x {
-                    buf.extend(item.to_cbor_data());
+                    buf.extend(item.encoded());
                 }
                 buf
             },
             CBORCase::Map(x) => x.cbor_data(),
             CBORCase::Tagged(tag, item) => {
                 let mut buf = tag.value().encode_varint(MajorType::Tagged);
-                buf.extend(item.to_cbor_data());
+                buf.extend(item.encoded());
                 buf
             },
             CBORCase::Simple(x) => x.cbor_data(),
@@ -115,9 +329,25 @@ impl CBOR {
 
     /// Create a new CBOR value representing a byte string given as a hexadecimal string.
     ///
-    /// Panics if the string is not well-formed hexadecimal.
+    /// Panics if the string is not well-formed hexadecimal. See
+    /// [`CBOR::try_to_byte_string_from_hex`].
+    #[deprecated(note = "use `CBOR::try_to_byte_string_from_hex` or `ByteString::from_hex`, which return an error rather than panicking")]
     pub fn to_byte_string_from_hex(hex: impl AsRef<str>) -> CBOR {
-        Self::to_byte_string(hex::decode(hex.as_ref()).unwrap())
+        match Self::try_to_byte_string_from_hex(hex) {
+            Ok(cbor) => cbor,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Create a new CBOR value representing a byte string given as a hexadecimal string.
+    ///
+    /// Returns [`CBORError::InvalidHex`] if the string is not well-formed
+    /// hexadecimal.
+    pub fn try_to_byte_string_from_hex(hex: impl AsRef<str>) -> Result<CBOR> {
+        match decode_hex(hex.as_ref()) {
+            Ok(data) => Ok(Self::to_byte_string(data)),
+            Err(err) => bail!(err),
+        }
     }
 
     /// Create a new CBOR value representing a tagged value.
@@ -195,6 +425,30 @@ impl CBOR {
         }
     }
 
+    /// Extract the CBOR value as a tagged value, converting its content to
+    /// `T`.
+    ///
+    /// This suits values whose tag is itself data, such as one that
+    /// distinguishes subtypes sharing the same content layout.
+    ///
+    /// Returns `Err` if the value is not a tagged value, or its content cannot
+    /// be converted to `T`.
+    ///
+    /// ```
+    /// # use dcbor::prelude::*;
+    /// let cbor = CBOR::to_tagged_value(40000, vec![1, 2]);
+    /// let (tag, content): (Tag, Vec<u32>) = cbor.try_into_tagged_pair().unwrap();
+    /// assert_eq!(tag.value(), 40000);
+    /// assert_eq!(content, vec![1, 2]);
+    /// ```
+    pub fn try_into_tagged_pair<T>(self) -> Result<(Tag, T)>
+    where
+        T: TryFrom<CBOR, Error = Error>,
+    {
+        let (tag, value) = self.try_into_tagged_value()?;
+        Ok((tag, T::try_from(value)?))
+    }
+
     /// Extract the CBOR value as a simple value.
     ///
     /// Returns `Ok` if the value is a simple value, `Err` otherwise.
@@ -206,6 +460,33 @@ impl CBOR {
     }
 }
 
+impl CBOR {
+    /// Returns the number of elements in an array, the number of entries in a
+    /// map, or the number of bytes in a byte string or text string.
+    ///
+    /// A text string is measured in its Unicode Normalization Form C, as it
+    /// is encoded and as map keys are compared by [`Map::get_by_str`].
+    ///
+    /// Returns `None` for other values, including tagged values.
+    pub fn len(&self) -> Option<usize> {
+        match self.as_case() {
+            CBORCase::ByteString(b) => Some(b.len()),
+            CBORCase::Text(t) => Some(if is_nfc(t) { t.len() } else { to_nfc(t).len() }),
+            CBORCase::Array(a) => Some(a.len()),
+            CBORCase::Map(m) => Some(m.len()),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the value is an empty array, map, byte string, or
+    /// text string.
+    ///
+    /// Returns `false` for other values, which have no length.
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+}
+
 /// Associated constants for common CBOR simple values.
 impl CBOR {
     /// The CBOR simple value representing `false`.
@@ -224,6 +505,34 @@ impl CBOR {
     }
 }
 
+/// The default value is `null`, so structs with `CBOR` fields can derive
+/// `Default`.
+impl Default for CBOR {
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+/// Compares the encodings of values in constant time.
+///
+/// Only the comparison of the encodings is constant time. Each value is
+/// encoded first, and the time that takes depends on the data: on the
+/// structure of the value, and on its contents, such as text strings that
+/// must be normalized and floats that must be reduced. Use this only where
+/// the structure of the values is not secret, and otherwise compare the
+/// secrets themselves, e.g. as [`ByteString`]s, which are compared without
+/// being encoded.
+///
+/// Values whose encodings have different lengths compare unequal without
+/// their contents being examined, so timing reveals whether the lengths
+/// differ.
+#[cfg(feature = "subtle")]
+impl subtle::ConstantTimeEq for CBOR {
+    fn ct_eq(&self, other: &Self) -> subtle::Choice {
+        self.encoded().as_slice().ct_eq(other.encoded().as_slice())
+    }
+}
+
 impl PartialEq for CBOR {
     fn eq(&self, other: &Self) -> bool {
         match (self.as_case(), other.as_case()) {
@@ -267,7 +576,7 @@ impl fmt::Debug for CBOR {
         match self.as_case() {
             CBORCase::Unsigned(x) => f.debug_tuple("unsigned").field(x).finish(),
             CBORCase::Negative(x) => f.debug_tuple("negative").field(&(-1 - (*x as i128))).finish(),
-            CBORCase::ByteString(x) => f.write_fmt(format_args!("bytes({})", hex::encode(x))),
+            CBORCase::ByteString(x) => f.write_fmt(format_args!("bytes({})", hex_encode(x))),
             CBORCase::Text(x) => f.debug_tuple("text").field(x).finish(),
             CBORCase::Array(x) => f.debug_tuple("array").field(x).finish(),
             CBORCase::Map(x) => f.debug_tuple("map").field(x).finish(),
@@ -282,7 +591,7 @@ impl fmt::Display for CBOR {
         let s = match self.as_case() {
             CBORCase::Unsigned(x) => format!("{}", x),
             CBORCase::Negative(x) => format!("{}", -1 - (*x as i128)),
-            CBORCase::ByteString(x) => format!("h'{}'", hex::encode(x)),
+            CBORCase::ByteString(x) => format!("h'{}'", hex_encode(x)),
             CBORCase::Text(x) => format_string(x),
             CBORCase::Array(x) => format_array(x),
             CBORCase::Map(x) => format_map(x),
@@ -2,11 +2,20 @@ import_stdlib!();
 
 use crate::CBOR;
 
+/// A type that can be converted to CBOR without being consumed.
+///
+/// This is implemented for every type that is `Into<CBOR> + Clone`. Functions
+/// that build CBOR, such as [`Map::insert`](crate::Map::insert) and
+/// [`CBOR::to_tagged_value`], accept any `impl Into<CBOR>`. This crate's types
+/// also implement `From<&T> for CBOR`, so a reference can be passed directly to
+/// keep using the value afterwards. For other types, pass `value.to_cbor()`.
 pub trait CBOREncodable: Into<CBOR> + Clone {
+    /// Returns the CBOR representation of this value.
     fn to_cbor(&self) -> CBOR {
         self.clone().into()
     }
 
+    /// Returns the binary-encoded CBOR representation of this value.
     fn to_cbor_data(&self) -> Vec<u8> {
         self.to_cbor().to_cbor_data()
     }
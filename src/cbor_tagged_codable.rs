@@ -4,3 +4,60 @@ use crate::{CBORTaggedEncodable, CBORTaggedDecodable};
 pub trait CBORTaggedCodable { }
 
 impl<T> CBORTaggedCodable for T where T: CBORTaggedEncodable + CBORTaggedDecodable { }
+
+/// Implements tagged CBOR coding for a single-field tuple struct (newtype)
+/// whose field is itself convertible to and from CBOR.
+///
+/// This provides `CBORTagged`, `CBORTaggedEncodable`, `CBORTaggedDecodable`,
+/// `From<T> for CBOR`, and `TryFrom<CBOR> for T`. If more than one tag is
+/// given, the first is used for writing and all are accepted for reading.
+///
+/// ```
+/// use dcbor::prelude::*;
+///
+/// #[derive(Debug, Clone, PartialEq)]
+/// struct Digest(ByteString);
+///
+/// dcbor::impl_cbor_tagged_newtype!(Digest, 700);
+///
+/// let digest = Digest(ByteString::from([1, 2, 3]));
+/// let cbor: CBOR = digest.clone().into();
+/// assert_eq!(cbor.hex(), "d902bc43010203");
+/// assert_eq!(Digest::try_from(cbor).unwrap(), digest);
+/// ```
+#[macro_export]
+macro_rules! impl_cbor_tagged_newtype {
+    ($type:ty, $($tag:expr),+ $(,)?) => {
+        impl $crate::CBORTagged for $type {
+            fn cbor_tags() -> $crate::__private::Vec<$crate::Tag> {
+                $crate::__private::vec![$($crate::Tag::from($tag)),+]
+            }
+        }
+
+        impl $crate::CBORTaggedEncodable for $type {
+            fn untagged_cbor(&self) -> $crate::CBOR {
+                self.0.clone().into()
+            }
+        }
+
+        impl $crate::CBORTaggedDecodable for $type {
+            fn from_untagged_cbor(cbor: $crate::CBOR) -> $crate::__private::Result<Self> {
+                Ok(Self(cbor.try_into()?))
+            }
+        }
+
+        impl From<$type> for $crate::CBOR {
+            fn from(value: $type) -> Self {
+                $crate::CBORTaggedEncodable::tagged_cbor(&value)
+            }
+        }
+
+        impl TryFrom<$crate::CBOR> for $type {
+            type Error = $crate::__private::Error;
+
+            fn try_from(cbor: $crate::CBOR) -> $crate::__private::Result<Self> {
+                <Self as $crate::CBORTaggedDecodable>::from_tagged_cbor(cbor)
+            }
+        }
+    };
+}
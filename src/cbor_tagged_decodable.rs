@@ -1,6 +1,6 @@
-use anyhow::{bail, Result, Error};
+use anyhow::{bail, Result};
 
-use crate::{CBOR, CBORError, CBORTagged, CBORCase};
+use crate::{error::with_path, CBOR, CBORError, CBORTagged, CBORCase, Tag};
 
 /// A type that can be decoded from CBOR with a specific tag.
 ///
@@ -11,14 +11,29 @@ pub trait CBORTaggedDecodable: TryFrom<CBOR> + CBORTagged {
     fn from_untagged_cbor(cbor: CBOR) -> Result<Self> where Self: Sized;
 
     /// Creates an instance of this type by decoding it from tagged CBOR.
+    ///
+    /// Returns [`CBORError::WrongTag`] if the value has a tag other than the
+    /// type's only tag, or [`CBORError::WrongTagOf`] if the type accepts
+    /// several tags. When decoding arrays and maps of the type, the error
+    /// carries the [`ValuePath`](crate::ValuePath) of the offending value.
     fn from_tagged_cbor(cbor: CBOR) -> Result<Self> where Self: Sized {
+        Self::from_tagged_cbor_with_tag(cbor).map(|(value, _)| value)
+    }
+
+    /// Creates an instance of this type by decoding it from tagged CBOR,
+    /// also returning which of the type's [`cbor_tags`](CBORTagged::cbor_tags)
+    /// the value was tagged with.
+    ///
+    /// For types that accept legacy tags, this lets migration code measure
+    /// how often each tag is still in use.
+    fn from_tagged_cbor_with_tag(cbor: CBOR) -> Result<(Self, Tag)> where Self: Sized {
         match cbor.into_case() {
             CBORCase::Tagged(tag, item) => {
                 let cbor_tags = Self::cbor_tags();
-                if cbor_tags.iter().any(|t| *t == tag) {
-                    Self::from_untagged_cbor(item)
-                } else {
-                    bail!(CBORError::WrongTag(cbor_tags[0].clone(), tag))
+                match cbor_tags.iter().find(|cbor_tag| **cbor_tag == tag) {
+                    Some(cbor_tag) => Ok((Self::from_untagged_cbor(item)?, cbor_tag.clone())),
+                    None if cbor_tags.len() == 1 => Err(with_path(CBORError::WrongTag(cbor_tags[0].clone(), tag))),
+                    None => Err(with_path(CBORError::WrongTagOf(cbor_tags, tag))),
                 }
             },
             _ => bail!(CBORError::WrongType)
@@ -27,12 +42,12 @@ pub trait CBORTaggedDecodable: TryFrom<CBOR> + CBORTagged {
 
     /// Creates an instance of this type by decoding it from binary encoded tagged CBOR.
     fn from_tagged_cbor_data(data: impl AsRef<[u8]>) -> Result<Self> where Self: Sized {
-        Self::from_tagged_cbor(CBOR::try_from_data(data).map_err(Error::msg)?)
+        Self::from_tagged_cbor(CBOR::try_from_data(data)?)
     }
 
     /// Creates an instance of this type by decoding it from binary encoded untagged CBOR.
     fn from_untagged_cbor_data(data: impl AsRef<[u8]>) -> Result<Self> where Self: Sized {
-        Self::from_untagged_cbor(CBOR::try_from_data(data).map_err(Error::msg)?)
+        Self::from_untagged_cbor(CBOR::try_from_data(data)?)
     }
 }
 
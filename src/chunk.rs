@@ -0,0 +1,117 @@
+import_stdlib!();
+
+use anyhow::{bail, Error, Result};
+
+use crate::{CBORCase, CBORError, Tag, CBOR, TAG_BYTE_STRING_CHUNK};
+
+/// Splitting large byte strings into chunks, for transports that limit the
+/// size of each message.
+impl CBOR {
+    /// Returns this byte string split into chunks of `chunk_size` bytes, each
+    /// a self-describing value that can be sent as a message of its own.
+    ///
+    /// Each chunk is an array `[index, count, length, bytes]` tagged with
+    /// [`TAG_BYTE_STRING_CHUNK`], where `index` is the position of the chunk
+    /// from 0, `count` is the number of chunks, and `length` is the length of
+    /// the whole byte string. Every chunk but the last holds exactly
+    /// `chunk_size` bytes, and the last between 1 and `chunk_size` bytes, so
+    /// the chunks of a given byte string are deterministic. An empty byte
+    /// string is a single empty chunk.
+    ///
+    /// Returns [`CBORError::WrongType`] if this value is not a byte string,
+    /// or [`CBORError::InvalidChunkSize`] if `chunk_size` is 0.
+    pub fn to_byte_string_chunks(&self, chunk_size: usize) -> Result<Vec<CBOR>> {
+        if chunk_size == 0 {
+            bail!(CBORError::InvalidChunkSize);
+        }
+        let CBORCase::ByteString(data) = self.as_case() else {
+            bail!(CBORError::WrongType);
+        };
+        let data = data.data();
+        let parts: Vec<&[u8]> = if data.is_empty() { vec![data] } else { data.chunks(chunk_size).collect() };
+        let count = parts.len();
+        Ok(parts.into_iter().enumerate().map(|(index, part)| {
+            let chunk: Vec<CBOR> = vec![index.into(), count.into(), data.len().into(), CBOR::to_byte_string(part)];
+            CBOR::to_tagged_value(TAG_BYTE_STRING_CHUNK, chunk)
+        }).collect())
+    }
+
+    /// Reassembles a byte string from the chunks returned by
+    /// [`CBOR::to_byte_string_chunks`], which may be given in any order.
+    ///
+    /// Returns [`CBORError::WrongTag`] if a chunk is not tagged with
+    /// [`TAG_BYTE_STRING_CHUNK`], or [`CBORError::MissingChunk`] if a chunk
+    /// is missing. Returns [`CBORError::InvalidChunk`] if a chunk is
+    /// malformed, is given twice, disagrees with the others about the number
+    /// of chunks or the length of the byte string, or is not sized as
+    /// `to_byte_string_chunks` would size it.
+    pub fn try_join_byte_string_chunks<'a>(chunks: impl IntoIterator<Item = &'a CBOR>) -> Result<CBOR> {
+        // The chunks by index, with their positions among those given.
+        let mut parts: BTreeMap<usize, (usize, &[u8])> = BTreeMap::new();
+        let mut expected: Option<(usize, usize)> = None;
+        for (position, chunk) in chunks.into_iter().enumerate() {
+            let (index, count, length, bytes) = parse_chunk(chunk, position)?;
+            if *expected.get_or_insert((count, length)) != (count, length)
+                || index >= count
+                || parts.insert(index, (position, bytes)).is_some()
+            {
+                bail!(CBORError::InvalidChunk(position));
+            }
+        }
+        let Some((count, length)) = expected else {
+            bail!(CBORError::MissingChunk(0));
+        };
+        if parts.len() < count {
+            let missing = parts.keys().enumerate().find(|(position, index)| position != *index)
+                .map_or(parts.len(), |(position, _)| position);
+            bail!(CBORError::MissingChunk(missing));
+        }
+        let chunk_size = parts[&0].1.len();
+        let mut data = Vec::with_capacity(length.min(chunk_size.saturating_mul(count)));
+        let mut last_position = 0;
+        for (index, (position, bytes)) in parts {
+            let is_last = index == count - 1;
+            let sized = if is_last {
+                bytes.len() <= chunk_size && (!bytes.is_empty() || length == 0)
+            } else {
+                bytes.len() == chunk_size
+            };
+            if !sized {
+                bail!(CBORError::InvalidChunk(position));
+            }
+            data.extend_from_slice(bytes);
+            last_position = position;
+        }
+        if data.len() != length {
+            bail!(CBORError::InvalidChunk(last_position));
+        }
+        Ok(CBOR::to_byte_string(data))
+    }
+}
+
+/// Returns the index, count, length, and bytes of a chunk.
+fn parse_chunk(chunk: &CBOR, position: usize) -> Result<(usize, usize, usize, &[u8])> {
+    let CBORCase::Tagged(tag, item) = chunk.as_case() else {
+        bail!(CBORError::WrongType);
+    };
+    if tag.value() != TAG_BYTE_STRING_CHUNK {
+        bail!(CBORError::WrongTag(Tag::with_value(TAG_BYTE_STRING_CHUNK), tag.clone()));
+    }
+    let invalid = || Error::msg(CBORError::InvalidChunk(position));
+    let CBORCase::Array(fields) = item.as_case() else {
+        return Err(invalid());
+    };
+    let number = |field: &CBOR| match field.as_case() {
+        CBORCase::Unsigned(n) => usize::try_from(*n).map_err(|_| invalid()),
+        _ => Err(invalid()),
+    };
+    match fields.as_slice() {
+        [index, count, length, bytes] => {
+            let CBORCase::ByteString(bytes) = bytes.as_case() else {
+                return Err(invalid());
+            };
+            Ok((number(index)?, number(count)?, number(length)?, bytes.data()))
+        },
+        _ => Err(invalid()),
+    }
+}
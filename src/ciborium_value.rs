@@ -0,0 +1,73 @@
+import_stdlib!();
+
+use anyhow::{bail, Error, Result};
+use ciborium::value::{Integer, Value};
+
+use crate::{CBORCase, CBORError, DuplicatePolicy, Map, Simple, CBOR};
+
+/// Converts CBOR to a `ciborium` value.
+///
+/// Map entries are produced in canonical order.
+impl From<CBOR> for Value {
+    fn from(cbor: CBOR) -> Self {
+        match cbor.into_case() {
+            CBORCase::Unsigned(n) => Value::Integer(n.into()),
+            CBORCase::Negative(n) => Value::Integer(Integer::try_from(-1 - n as i128).unwrap()),
+            CBORCase::ByteString(bytes) => Value::Bytes(bytes.into()),
+            CBORCase::Text(s) => Value::Text(s),
+            CBORCase::Array(array) => Value::Array(array.into_iter().map(Value::from).collect()),
+            CBORCase::Map(map) => Value::Map(
+                map.iter().map(|(key, value)| (key.clone().into(), value.clone().into())).collect()
+            ),
+            CBORCase::Tagged(tag, item) => Value::Tag(tag.value(), Box::new(item.into())),
+            CBORCase::Simple(Simple::False) => Value::Bool(false),
+            CBORCase::Simple(Simple::True) => Value::Bool(true),
+            CBORCase::Simple(Simple::Null) => Value::Null,
+            #[cfg(feature = "float")]
+            CBORCase::Simple(Simple::Float(f)) => Value::Float(f),
+        }
+    }
+}
+
+/// Converts a `ciborium` value to CBOR, normalizing it into canonical form.
+///
+/// Floats are reduced as for any other CBOR, map entries are sorted, and
+/// text is normalized when encoded. Returns an error if a map contains
+/// duplicate keys, or without the `float` feature, if there is a float.
+impl TryFrom<Value> for CBOR {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self> {
+        let cbor = match value {
+            Value::Integer(n) => {
+                let n = i128::from(n);
+                match u64::try_from(n) {
+                    Ok(n) => n.into(),
+                    // Integer only holds values whose CBOR encoding is valid.
+                    Err(_) => CBORCase::Negative((-1 - n) as u64).into(),
+                }
+            },
+            Value::Bytes(bytes) => CBOR::to_byte_string(bytes),
+            #[cfg(feature = "float")]
+            Value::Float(f) => f.into(),
+            #[cfg(not(feature = "float"))]
+            Value::Float(_) => bail!(CBORError::UnsupportedFloat),
+            Value::Text(s) => s.into(),
+            Value::Bool(b) => b.into(),
+            Value::Null => CBOR::null(),
+            Value::Tag(tag, item) => CBOR::to_tagged_value(tag, CBOR::try_from(*item)?),
+            Value::Array(array) => array.into_iter()
+                .map(CBOR::try_from)
+                .collect::<Result<Vec<_>>>()?
+                .into(),
+            Value::Map(entries) => {
+                let entries = entries.into_iter()
+                    .map(|(key, value)| Ok((CBOR::try_from(key)?, CBOR::try_from(value)?)))
+                    .collect::<Result<Vec<_>>>()?;
+                Map::from_iter_with(entries, DuplicatePolicy::Error)?.into()
+            },
+            _ => bail!(CBORError::WrongType),
+        };
+        Ok(cbor)
+    }
+}
@@ -0,0 +1,375 @@
+//! Encoding of literal CBOR at compile time, for [`const_cbor!`](crate::const_cbor).
+//!
+//! The macro passes its input to these functions as text. They parse it
+//! twice: once to find the length of the encoding, which sizes the array
+//! holding it, and once to write the encoding. Problems with the input are
+//! reported by panicking, which fails compilation.
+
+/// Encodes literal CBOR at compile time, producing its dCBOR encoding as a
+/// `&'static [u8]`.
+///
+/// The input is written much like diagnostic notation, using Rust literals:
+///
+/// - unsigned and negative integers in decimal, e.g. `42` or `-1`, which may
+///   contain `_` separators;
+/// - text strings, e.g. `"hello"`, which must be ASCII so that they are
+///   known to be in Unicode Normalization Form C;
+/// - byte strings, e.g. `b"\x01\x02"`;
+/// - `true`, `false`, and `null`;
+/// - arrays, e.g. `[1, "two"]`;
+/// - maps, e.g. `{1: "one", 2: "two"}`, whose keys must be written in
+///   canonical order, as the encoding is not sorted;
+/// - tagged values, e.g. `40000([1, 2])`.
+///
+/// Floating point values are not supported. Input that is not supported, or
+/// would not be valid dCBOR, fails to compile.
+///
+/// ```
+/// # use dcbor::{prelude::*, const_cbor};
+/// const HEADER: &[u8] = const_cbor!({1: "v1", 2: [true, null], 3: 40000(b"\x00\xff")});
+///
+/// let cbor = CBOR::try_from_data(HEADER).unwrap();
+/// # #[cfg(feature = "format")]
+/// assert_eq!(cbor.diagnostic_flat(), r#"{1: "v1", 2: [true, null], 3: 40000(h'00ff')}"#);
+/// ```
+///
+/// ```compile_fail
+/// // The keys are not in canonical order.
+/// const DATA: &[u8] = dcbor::const_cbor!({2: "two", 1: "one"});
+/// ```
+#[macro_export]
+macro_rules! const_cbor {
+    ($($input:tt)+) => {{
+        const SOURCE: &str = stringify!($($input)+);
+        const LEN: usize = $crate::__private::const_cbor_len(SOURCE);
+        const DATA: [u8; LEN] = $crate::__private::const_cbor_encode::<LEN>(SOURCE);
+        &DATA as &'static [u8]
+    }};
+}
+
+/// Returns the length of the encoding of the literal CBOR in `source`.
+pub const fn const_cbor_len(source: &str) -> usize {
+    let mut out = [0u8; 0];
+    let mut encoder = Encoder { src: source.as_bytes(), pos: 0, out: &mut out, len: 0, measuring: true };
+    encoder.encode();
+    encoder.len
+}
+
+/// Returns the encoding of the literal CBOR in `source`, which is `N` bytes
+/// long.
+pub const fn const_cbor_encode<const N: usize>(source: &str) -> [u8; N] {
+    let mut out = [0u8; N];
+    let mut encoder = Encoder { src: source.as_bytes(), pos: 0, out: &mut out, len: 0, measuring: false };
+    encoder.encode();
+    out
+}
+
+struct Encoder<'a, 'b> {
+    src: &'a [u8],
+    pos: usize,
+    out: &'b mut [u8],
+    len: usize,
+    /// Whether only the length of the encoding is being found, in which case
+    /// nothing is written to `out`.
+    measuring: bool,
+}
+
+impl Encoder<'_, '_> {
+    const fn encode(&mut self) {
+        self.item();
+        self.skip_whitespace();
+        if self.pos != self.src.len() {
+            panic!("const_cbor!: unexpected input after the item");
+        }
+    }
+
+    const fn item(&mut self) {
+        self.skip_whitespace();
+        match self.peek() {
+            b'[' => {
+                self.pos += 1;
+                let count = self.count_items(b']');
+                self.write_head(4, count);
+                let mut i = 0;
+                while i < count {
+                    self.item();
+                    self.skip_separator(b']');
+                    i += 1;
+                }
+                self.expect(b']');
+            },
+            b'{' => {
+                self.pos += 1;
+                let count = self.count_items(b'}');
+                self.write_head(5, count);
+                let mut previous_key: Option<(usize, usize)> = None;
+                let mut i = 0;
+                while i < count {
+                    let key_start = self.len;
+                    self.item();
+                    let key = (key_start, self.len);
+                    if let Some(previous_key) = previous_key {
+                        self.check_key_order(previous_key, key);
+                    }
+                    previous_key = Some(key);
+                    self.skip_whitespace();
+                    self.expect(b':');
+                    self.item();
+                    self.skip_separator(b'}');
+                    i += 1;
+                }
+                self.expect(b'}');
+            },
+            b'"' => self.string(3),
+            b'b' if self.peek_at(1) == b'"' => {
+                self.pos += 1;
+                self.string(2);
+            },
+            b'-' => {
+                self.pos += 1;
+                self.skip_whitespace();
+                let magnitude = self.integer();
+                if magnitude == 0 {
+                    self.write_head(0, 0);
+                } else {
+                    self.write_head(1, (magnitude - 1) as u64);
+                }
+            },
+            b'0'..=b'9' => {
+                let value = self.integer();
+                if value > u64::MAX as u128 {
+                    panic!("const_cbor!: integer out of range");
+                }
+                let value = value as u64;
+                self.skip_whitespace();
+                if self.peek() == b'(' {
+                    self.pos += 1;
+                    self.write_head(6, value);
+                    self.item();
+                    self.skip_whitespace();
+                    self.expect(b')');
+                } else {
+                    self.write_head(0, value);
+                }
+            },
+            _ if self.keyword(b"false") => self.write_byte(0xf4),
+            _ if self.keyword(b"true") => self.write_byte(0xf5),
+            _ if self.keyword(b"null") => self.write_byte(0xf6),
+            _ => panic!("const_cbor!: expected an integer, string, `true`, `false`, `null`, array, map, or tagged value"),
+        }
+    }
+
+    /// Returns the number of array items or map entries before the closing
+    /// delimiter, without writing anything.
+    const fn count_items(&self, close: u8) -> u64 {
+        let mut out = [0u8; 0];
+        let mut counter = Encoder { src: self.src, pos: self.pos, out: &mut out, len: 0, measuring: true };
+        let mut count = 0;
+        loop {
+            counter.skip_whitespace();
+            if counter.peek() == close {
+                return count;
+            }
+            counter.item();
+            if close == b'}' {
+                counter.skip_whitespace();
+                counter.expect(b':');
+                counter.item();
+            }
+            counter.skip_separator(close);
+            count += 1;
+        }
+    }
+
+    /// Skips the comma following an item, which is optional before the
+    /// closing delimiter.
+    const fn skip_separator(&mut self, close: u8) {
+        self.skip_whitespace();
+        if self.peek() == b',' {
+            self.pos += 1;
+            self.skip_whitespace();
+        } else if self.peek() != close {
+            panic!("const_cbor!: expected `,` between items");
+        }
+    }
+
+    /// Parses a decimal integer, with optional `_` separators, which may be
+    /// up to 2^64, the magnitude of the lowest negative integer.
+    const fn integer(&mut self) -> u128 {
+        let mut value: u128 = 0;
+        let mut digits = 0;
+        while self.pos < self.src.len() {
+            let c = self.src[self.pos];
+            match c {
+                b'0'..=b'9' => {
+                    value = value * 10 + (c - b'0') as u128;
+                    if value > u64::MAX as u128 + 1 {
+                        panic!("const_cbor!: integer out of range");
+                    }
+                    digits += 1;
+                },
+                b'_' => { },
+                b'a'..=b'z' | b'A'..=b'Z' | b'.' => panic!("const_cbor!: integers must be decimal, with no suffix; floats are not supported"),
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        if digits == 0 {
+            panic!("const_cbor!: expected an integer");
+        }
+        value
+    }
+
+    /// Writes the string literal at the current position, as a text string
+    /// (major type 3) or byte string (major type 2).
+    const fn string(&mut self, major_type: u8) {
+        self.pos += 1;
+        let start = self.pos;
+        let mut len = 0;
+        while let Some((_, next)) = self.string_byte(major_type) {
+            len += 1;
+            self.pos = next;
+        }
+        self.write_head(major_type, len);
+        self.pos = start;
+        while let Some((byte, next)) = self.string_byte(major_type) {
+            self.write_byte(byte);
+            self.pos = next;
+        }
+        self.expect(b'"');
+    }
+
+    /// Returns the next byte of the string literal being read and the
+    /// position following it, or `None` at its closing quote.
+    const fn string_byte(&self, major_type: u8) -> Option<(u8, usize)> {
+        if self.pos >= self.src.len() {
+            panic!("const_cbor!: unterminated string");
+        }
+        let c = self.src[self.pos];
+        let (byte, next) = match c {
+            b'"' => return None,
+            b'\\' => {
+                let escaped = self.peek_at(1);
+                match escaped {
+                    b'n' => (b'\n', self.pos + 2),
+                    b'r' => (b'\r', self.pos + 2),
+                    b't' => (b'\t', self.pos + 2),
+                    b'0' => (0, self.pos + 2),
+                    b'\\' | b'"' | b'\'' => (escaped, self.pos + 2),
+                    b'x' => {
+                        let byte = hex_digit(self.peek_at(2)) * 16 + hex_digit(self.peek_at(3));
+                        (byte, self.pos + 4)
+                    },
+                    _ => panic!("const_cbor!: unsupported escape in string"),
+                }
+            },
+            _ => (c, self.pos + 1),
+        };
+        if major_type == 3 && byte >= 0x80 {
+            panic!("const_cbor!: text strings must be ASCII");
+        }
+        Some((byte, next))
+    }
+
+    /// Consumes the keyword if it is next in the input.
+    const fn keyword(&mut self, keyword: &[u8]) -> bool {
+        let mut i = 0;
+        while i < keyword.len() {
+            if self.peek_at(i) != keyword[i] {
+                return false;
+            }
+            i += 1;
+        }
+        if self.peek_at(i).is_ascii_alphanumeric() || self.peek_at(i) == b'_' {
+            return false;
+        }
+        self.pos += keyword.len();
+        true
+    }
+
+    /// Panics unless the key written at `key` follows the one written at
+    /// `previous_key` in canonical order.
+    const fn check_key_order(&self, previous_key: (usize, usize), key: (usize, usize)) {
+        if self.measuring {
+            return;
+        }
+        let mut i = 0;
+        loop {
+            let a = previous_key.0 + i;
+            let b = key.0 + i;
+            if a == previous_key.1 {
+                if b == key.1 {
+                    panic!("const_cbor!: duplicate map key");
+                }
+                return;
+            }
+            if b == key.1 || self.out[a] > self.out[b] {
+                panic!("const_cbor!: map keys must be in canonical order");
+            }
+            if self.out[a] < self.out[b] {
+                return;
+            }
+            i += 1;
+        }
+    }
+
+    /// Writes the head of an item in its shortest form.
+    const fn write_head(&mut self, major_type: u8, value: u64) {
+        let major_type = major_type << 5;
+        let bytes = value.to_be_bytes();
+        let (additional, len) = if value <= 23 {
+            (value as u8, 0)
+        } else if value <= u8::MAX as u64 {
+            (24, 1)
+        } else if value <= u16::MAX as u64 {
+            (25, 2)
+        } else if value <= u32::MAX as u64 {
+            (26, 4)
+        } else {
+            (27, 8)
+        };
+        self.write_byte(major_type | additional);
+        let mut i = 8 - len;
+        while i < 8 {
+            self.write_byte(bytes[i]);
+            i += 1;
+        }
+    }
+
+    const fn write_byte(&mut self, byte: u8) {
+        if !self.measuring {
+            self.out[self.len] = byte;
+        }
+        self.len += 1;
+    }
+
+    const fn skip_whitespace(&mut self) {
+        while self.pos < self.src.len() && self.src[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    const fn expect(&mut self, c: u8) {
+        if self.peek() != c {
+            panic!("const_cbor!: unexpected character");
+        }
+        self.pos += 1;
+    }
+
+    const fn peek(&self) -> u8 {
+        self.peek_at(0)
+    }
+
+    const fn peek_at(&self, offset: usize) -> u8 {
+        if self.pos + offset < self.src.len() { self.src[self.pos + offset] } else { 0 }
+    }
+}
+
+const fn hex_digit(c: u8) -> u8 {
+    match c {
+        b'0'..=b'9' => c - b'0',
+        b'a'..=b'f' => c - b'a' + 10,
+        b'A'..=b'F' => c - b'A' + 10,
+        _ => panic!("const_cbor!: invalid `\\x` escape in string"),
+    }
+}
@@ -0,0 +1,93 @@
+//! A recommended format for storing a dCBOR document in a file.
+//!
+//! A container is the self-described CBOR tag 55799, whose content is an
+//! array of a format version chosen by the application and the document
+//! itself:
+//!
+//! ```text
+//! 55799([version, payload])
+//! ```
+//!
+//! The tag makes every container begin with the bytes [`MAGIC`], which
+//! identify the file as CBOR, and the version lets applications recognize
+//! documents written by older or newer releases. Using this format, rather
+//! than a wrapper of their own, lets tools read any application's files.
+//!
+//! ```
+//! # use dcbor::{prelude::*, container};
+//! let document = CBOR::from(vec!["hello", "world"]);
+//! let data = container::to_data(1, &document);
+//! assert!(data.starts_with(&container::MAGIC));
+//!
+//! let (version, payload) = container::from_data(&data).unwrap();
+//! assert_eq!(version, 1);
+//! assert_eq!(payload, document);
+//! ```
+
+import_stdlib!();
+
+use anyhow::{bail, Result};
+
+use crate::{CBORCase, CBORError, Tag, CBOR, TAG_SELF_DESCRIBED_CBOR};
+
+/// The bytes every container begins with: the head of the self-described
+/// CBOR tag.
+pub const MAGIC: [u8; 3] = [0xd9, 0xd9, 0xf7];
+
+/// Returns `true` if the data begins with [`MAGIC`].
+///
+/// This is a quick check for identifying files, and does not mean the rest
+/// of the data is a valid container.
+pub fn is_container(data: impl AsRef<[u8]>) -> bool {
+    data.as_ref().starts_with(&MAGIC)
+}
+
+/// Returns the container of the given version holding the payload.
+pub fn to_cbor(version: u64, payload: &CBOR) -> CBOR {
+    CBOR::to_tagged_value(TAG_SELF_DESCRIBED_CBOR, vec![CBOR::from(version), payload.clone()])
+}
+
+/// Returns the encoding of the container of the given version holding the
+/// payload.
+pub fn to_data(version: u64, payload: &CBOR) -> Vec<u8> {
+    to_cbor(version, payload).to_cbor_data()
+}
+
+/// Extracts the version and payload from a container.
+///
+/// Returns [`CBORError::WrongTag`] or [`CBORError::WrongType`] if the value
+/// is not a container.
+pub fn from_cbor(cbor: CBOR) -> Result<(u64, CBOR)> {
+    let content = cbor.try_into_expected_tagged_value(Tag::with_value(TAG_SELF_DESCRIBED_CBOR))?;
+    let mut items = content.try_into_array()?;
+    if items.len() != 2 {
+        bail!(CBORError::WrongArrayLength(2, items.len()));
+    }
+    let payload = items.pop().unwrap();
+    let version = match items[0].as_case() {
+        CBORCase::Unsigned(version) => *version,
+        _ => bail!(CBORError::WrongType),
+    };
+    Ok((version, payload))
+}
+
+/// Decodes a container, returning its version and payload.
+///
+/// The data must be valid dCBOR, as for [`CBOR::try_from_data`].
+pub fn from_data(data: impl AsRef<[u8]>) -> Result<(u64, CBOR)> {
+    from_cbor(CBOR::try_from_data(data)?)
+}
+
+/// Writes the container of the given version holding the payload to a file,
+/// replacing any existing file.
+#[cfg(feature = "std")]
+pub fn write_file(path: impl AsRef<std::path::Path>, version: u64, payload: &CBOR) -> Result<()> {
+    std::fs::write(path, to_data(version, payload))?;
+    Ok(())
+}
+
+/// Reads a container from a file, returning its version and payload.
+#[cfg(feature = "std")]
+pub fn read_file(path: impl AsRef<std::path::Path>) -> Result<(u64, CBOR)> {
+    from_data(std::fs::read(path)?)
+}
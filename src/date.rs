@@ -2,19 +2,29 @@ import_stdlib!();
 
 use ops::{Add, Sub};
 
-use chrono::{DateTime, Utc, TimeZone, SecondsFormat, NaiveDate, NaiveDateTime, Timelike};
+use chrono::{DateTime, Utc, TimeZone, NaiveDate, NaiveDateTime, Timelike};
+#[cfg(feature = "format")]
+use chrono::SecondsFormat;
 
 use anyhow::{bail, Error, Result};
 
-use crate::{CBORTaggedEncodable, Tag, CBOR, CBORTaggedDecodable, CBORTagged};
+use crate::{CBORTaggedEncodable, Tag, CBOR, CBORCase, CBORError, CBORTaggedDecodable, CBORTagged};
 
 /// A CBOR-friendly representation of a date and time.
+///
+/// Without the `float` feature, fractional seconds cannot be encoded, so
+/// dates have a resolution of one second. The fallible constructors,
+/// [`Date::try_from_timestamp`] and [`Date::from_string`], return
+/// [`CBORError::UnsupportedFloat`] when given a fraction of a second, and the
+/// others discard it, rounding towards the past.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Date(DateTime<Utc>);
 
 impl Date {
     /// Creates a new `Date` from the given chrono `DateTime`.
     pub fn from_datetime(date_time: DateTime<Utc>) -> Self {
+        #[cfg(not(feature = "float"))]
+        let date_time = date_time.with_nanosecond(0).unwrap_or(date_time);
         Date(date_time)
     }
 
@@ -29,19 +39,34 @@ impl Date {
     }
 
     /// Creates a new `Date` from seconds since (or before) the Unix epoch.
+    ///
+    /// The fraction is rounded to the nearest nanosecond.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the timestamp is not finite or is outside the range of
+    /// dates. See [`Date::try_from_timestamp`].
     pub fn from_timestamp(seconds_since_unix_epoch: f64) -> Self {
-        let whole_seconds_since_unix_epoch = seconds_since_unix_epoch.trunc() as i64;
-        let nsecs = (seconds_since_unix_epoch.fract() * 1_000_000_000.0) as u32;
-        Self::from_datetime(Utc.timestamp_opt(whole_seconds_since_unix_epoch, nsecs).unwrap())
+        Self::from_datetime(datetime_from_timestamp(seconds_since_unix_epoch).expect("timestamp out of range"))
+    }
+
+    /// Creates a new `Date` from seconds since (or before) the Unix epoch.
+    ///
+    /// The fraction is rounded to the nearest nanosecond. Returns
+    /// [`CBORError::OutOfRange`] if the timestamp is not finite or is outside
+    /// the range of dates.
+    pub fn try_from_timestamp(seconds_since_unix_epoch: f64) -> Result<Self> {
+        Self::try_from_datetime(datetime_from_timestamp(seconds_since_unix_epoch)?)
     }
 
     /// Creates a new `Date` from a string containing an ISO-8601 (RFC-3339) date (with or without time).
     pub fn from_string(value: impl Into<String>) -> Result<Self> {
         let value = value.into();
         // try parsing as DateTime
-        if let Ok(dt) = DateTime::parse_from_rfc3339(&value) {
-            return Ok(Self::from_datetime(dt.with_timezone(&Utc)));
-        }
+        let err = match DateTime::parse_from_rfc3339(&value) {
+            Ok(dt) => return Self::try_from_datetime(dt.with_timezone(&Utc)),
+            Err(err) => err,
+        };
 
         // try parsing as just a date (with assumed zero time)
         if let Ok(d) = NaiveDate::parse_from_str(&value, "%Y-%m-%d") {
@@ -49,7 +74,17 @@ impl Date {
             return Ok(Self::from_datetime(DateTime::from_naive_utc_and_offset(dt, Utc)));
         }
 
-        bail!("Invalid date string")
+        Err(Error::new(err).context("Invalid date string"))
+    }
+
+    /// Creates a new `Date` from a `DateTime` that must be representable
+    /// exactly.
+    fn try_from_datetime(date_time: DateTime<Utc>) -> Result<Self> {
+        #[cfg(not(feature = "float"))]
+        if date_time.nanosecond() != 0 {
+            bail!(CBORError::UnsupportedFloat);
+        }
+        Ok(Self::from_datetime(date_time))
     }
 
     /// Creates a new `Date` containing the current date and time.
@@ -76,6 +111,25 @@ impl Date {
     }
 }
 
+/// Converts seconds since the Unix epoch, rounded to the nearest nanosecond.
+fn datetime_from_timestamp(seconds_since_unix_epoch: f64) -> Result<DateTime<Utc>> {
+    let whole_seconds = seconds_since_unix_epoch.floor();
+    // Also excludes NaN and the infinities.
+    if !(whole_seconds >= i64::MIN as f64 && whole_seconds < i64::MAX as f64) {
+        bail!(CBORError::OutOfRange);
+    }
+    let mut whole_seconds_since_unix_epoch = whole_seconds as i64;
+    let mut nsecs = ((seconds_since_unix_epoch - whole_seconds) * 1_000_000_000.0).round() as u32;
+    if nsecs == 1_000_000_000 {
+        whole_seconds_since_unix_epoch += 1;
+        nsecs = 0;
+    }
+    match Utc.timestamp_opt(whole_seconds_since_unix_epoch, nsecs).single() {
+        Some(dt) => Ok(dt),
+        None => bail!(CBORError::OutOfRange),
+    }
+}
+
 // Support adding seconds as f64
 impl Add<f64> for Date {
     type Output = Self;
@@ -147,6 +201,12 @@ impl From<Date> for CBOR {
     }
 }
 
+impl From<&Date> for CBOR {
+    fn from(value: &Date) -> Self {
+        value.tagged_cbor()
+    }
+}
+
 impl AsRef<Date> for Date {
     fn as_ref(&self) -> &Self {
         self
@@ -168,25 +228,83 @@ impl CBORTagged for Date {
 }
 
 impl CBORTaggedEncodable for Date {
+    /// Dates with whole seconds are encoded as integers, so they round-trip
+    /// exactly whatever their magnitude. Dates with fractional seconds, which
+    /// only exist with the `float` feature, are encoded as floats.
     fn untagged_cbor(&self) -> CBOR {
-        self.timestamp().into()
+        let dt = self.datetime();
+        #[cfg(feature = "float")]
+        if dt.nanosecond() != 0 {
+            return self.timestamp().into();
+        }
+        dt.timestamp().into()
     }
 }
 
 impl CBORTaggedDecodable for Date {
     fn from_untagged_cbor(cbor: CBOR) -> Result<Self> {
-        let n = cbor.clone().try_into()?;
-        Ok(Date::from_timestamp(n))
+        match cbor.as_case() {
+            CBORCase::Unsigned(_) | CBORCase::Negative(_) => {
+                let seconds: i64 = cbor.try_into()?;
+                match Utc.timestamp_opt(seconds, 0).single() {
+                    Some(dt) => Ok(Self::from_datetime(dt)),
+                    None => bail!(CBORError::OutOfRange),
+                }
+            },
+            #[cfg(feature = "float")]
+            _ => {
+                let n = cbor.try_into()?;
+                Date::try_from_timestamp(n)
+            },
+            #[cfg(not(feature = "float"))]
+            _ => bail!(CBORError::WrongType),
+        }
+    }
+}
+
+/// How a [`Date`] is formatted as text.
+#[cfg(feature = "format")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DateFormat {
+    /// The date alone if the time is midnight, e.g. `2023-02-08`, and
+    /// otherwise RFC 3339 to the second, e.g. `2023-02-08T11:11:54Z`. This is
+    /// the format used by `Display`.
+    #[default]
+    Standard,
+    /// RFC 3339 to the second, e.g. `2023-02-08T00:00:00Z`.
+    Seconds,
+    /// RFC 3339 to the millisecond, e.g. `2023-02-08T11:11:54.250Z`.
+    Millis,
+    /// RFC 3339 to the microsecond.
+    Micros,
+    /// RFC 3339 to the nanosecond.
+    Nanos,
+}
+
+#[cfg(feature = "format")]
+impl Date {
+    /// Returns the date formatted as text in the given format.
+    pub fn format(&self, format: DateFormat) -> String {
+        let dt = self.datetime();
+        let seconds_format = match format {
+            DateFormat::Standard => {
+                if dt.hour() == 0 && dt.minute() == 0 && dt.second() == 0 {
+                    return dt.date_naive().to_string();
+                }
+                SecondsFormat::Secs
+            },
+            DateFormat::Seconds => SecondsFormat::Secs,
+            DateFormat::Millis => SecondsFormat::Millis,
+            DateFormat::Micros => SecondsFormat::Micros,
+            DateFormat::Nanos => SecondsFormat::Nanos,
+        };
+        dt.to_rfc3339_opts(seconds_format, true)
     }
 }
 
+#[cfg(feature = "format")]
 impl fmt::Display for Date {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let dt = self.datetime();
-        if dt.hour() == 0 && dt.minute() == 0 && dt.second() == 0 {
-            f.write_str(dt.date_naive().to_string().as_str())
-        } else {
-            f.write_str(dt.to_rfc3339_opts(SecondsFormat::Secs, true).as_str())
-        }
+        f.write_str(&self.format(DateFormat::Standard))
     }
 }
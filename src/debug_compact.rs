@@ -0,0 +1,319 @@
+import_stdlib!();
+
+use anyhow::{Error, Result};
+
+use crate::{error::{decode_hex, hex_reason}, string_util::hex_encode, with_tags, ByteString, CBORCase, CBORError, DuplicatePolicy, Map, Simple, Tag, TagValue, TagsStoreTrait, CBOR};
+
+/// A compact, single-line textual form of CBOR intended for log lines and
+/// golden-file tests.
+///
+/// Unlike diagnostic notation, the compact form is lossless and can be read
+/// back with [`CBOR::from_debug_compact`]:
+///
+/// - Integers are written in decimal, e.g. `42` or `-1`.
+/// - Floats are written with a decimal point or exponent, e.g. `1.5`, `1e300`,
+///   `NaN`, or `-inf`, so they are never confused with integers.
+/// - Byte strings carry their length, e.g. `h[2]'0102'`.
+/// - Text strings are quoted, with Rust escapes, e.g. `"a\nb"`.
+/// - Tagged values carry the tag's name, if known, e.g. `1#date(1675854714)`.
+/// - Arrays and maps are written `[1, 2]` and `{1: 2}`.
+impl CBOR {
+    /// Returns the compact debug form of this value, naming tags using the
+    /// global tags store.
+    pub fn to_debug_compact(&self) -> String {
+        with_tags!(|tags: &dyn TagsStoreTrait| {
+            self.to_debug_compact_opt(Some(tags))
+        })
+    }
+
+    /// Returns the compact debug form of this value, naming tags using the
+    /// given tags store, if any.
+    pub fn to_debug_compact_opt(&self, tags: Option<&dyn TagsStoreTrait>) -> String {
+        let mut result = String::new();
+        self.write_debug_compact(&mut result, tags);
+        result
+    }
+
+    /// Parses the compact debug form produced by [`CBOR::to_debug_compact`].
+    ///
+    /// Tag names are retained on the parsed tags, but are not checked against
+    /// any tags store. Returns [`CBORError::DuplicateMapKey`] if a map has
+    /// the same key more than once.
+    pub fn from_debug_compact(text: &str) -> Result<CBOR> {
+        let mut parser = Parser { text, pos: 0 };
+        let cbor = parser.parse_value()?;
+        parser.skip_whitespace();
+        if parser.pos < text.len() {
+            return Err(parser.error("unexpected trailing characters"));
+        }
+        Ok(cbor)
+    }
+
+    fn write_debug_compact(&self, out: &mut String, tags: Option<&dyn TagsStoreTrait>) {
+        match self.as_case() {
+            CBORCase::Unsigned(n) => *out += &n.to_string(),
+            CBORCase::Negative(n) => *out += &(-1 - *n as i128).to_string(),
+            CBORCase::ByteString(bytes) => *out += &format!("h[{}]'{}'", bytes.len(), hex_encode(bytes)),
+            CBORCase::Text(s) => *out += &format!("{:?}", s),
+            CBORCase::Array(array) => {
+                out.push('[');
+                for (index, item) in array.iter().enumerate() {
+                    if index > 0 {
+                        *out += ", ";
+                    }
+                    item.write_debug_compact(out, tags);
+                }
+                out.push(']');
+            },
+            CBORCase::Map(map) => {
+                out.push('{');
+                for (index, (key, value)) in map.iter().enumerate() {
+                    if index > 0 {
+                        *out += ", ";
+                    }
+                    key.write_debug_compact(out, tags);
+                    *out += ": ";
+                    value.write_debug_compact(out, tags);
+                }
+                out.push('}');
+            },
+            CBORCase::Tagged(tag, item) => {
+                *out += &tag.value().to_string();
+                let name = tag.name().or_else(|| tags.and_then(|tags| tags.assigned_name_for_tag(tag)));
+                if let Some(name) = name {
+                    out.push('#');
+                    if !name.is_empty() && name.chars().all(is_bare_name_char) {
+                        *out += &name;
+                    } else {
+                        *out += &format!("{:?}", name);
+                    }
+                }
+                out.push('(');
+                item.write_debug_compact(out, tags);
+                out.push(')');
+            },
+            CBORCase::Simple(Simple::False) => *out += "false",
+            CBORCase::Simple(Simple::True) => *out += "true",
+            CBORCase::Simple(Simple::Null) => *out += "null",
+            #[cfg(feature = "float")]
+            CBORCase::Simple(Simple::Float(f)) => *out += &format!("{:?}", f),
+        }
+    }
+}
+
+fn is_bare_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.'
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> Error {
+        Error::msg(CBORError::InvalidDebugCompact(self.pos, message.to_string()))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        self.skip_whitespace();
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("expected {:?}", expected)));
+        }
+        self.next();
+        Ok(())
+    }
+
+    /// Consumes characters while the predicate holds, returning them.
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let start = self.pos;
+        while self.peek().is_some_and(&predicate) {
+            self.next();
+        }
+        let text = self.text;
+        &text[start..self.pos]
+    }
+
+    fn parse_value(&mut self) -> Result<CBOR> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('[') => self.parse_array(),
+            Some('{') => self.parse_map(),
+            Some('"') => Ok(self.parse_string()?.into()),
+            Some('h') if self.text[self.pos..].starts_with("h[") => self.parse_byte_string(),
+            Some(c) if c.is_ascii_alphanumeric() || c == '-' => self.parse_scalar(),
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<CBOR> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.next();
+            return Ok(items.into());
+        }
+        loop {
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(items.into()),
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn parse_map(&mut self) -> Result<CBOR> {
+        self.expect('{')?;
+        let mut entries = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.next();
+        } else {
+            loop {
+                let key = self.parse_value()?;
+                self.expect(':')?;
+                let value = self.parse_value()?;
+                entries.push((key, value));
+                self.skip_whitespace();
+                match self.next() {
+                    Some(',') => continue,
+                    Some('}') => break,
+                    _ => return Err(self.error("expected ',' or '}'")),
+                }
+            }
+        }
+        Ok(Map::from_iter_with(entries, DuplicatePolicy::Error)?.into())
+    }
+
+    fn parse_string(&mut self) -> Result<String> {
+        self.expect('"')?;
+        let mut result = String::new();
+        loop {
+            match self.next() {
+                None => return Err(self.error("unterminated string")),
+                Some('"') => return Ok(result),
+                Some('\\') => {
+                    let c = match self.next() {
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('0') => '\0',
+                        Some(c @ ('\\' | '"' | '\'')) => c,
+                        Some('u') => {
+                            self.expect('{')?;
+                            let digits = self.take_while(|c| c.is_ascii_hexdigit());
+                            let c = u32::from_str_radix(digits, 16).ok().and_then(char::from_u32);
+                            let c = c.ok_or_else(|| self.error("invalid unicode escape"))?;
+                            self.expect('}')?;
+                            c
+                        },
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    result.push(c);
+                },
+                Some(c) => result.push(c),
+            }
+        }
+    }
+
+    fn parse_byte_string(&mut self) -> Result<CBOR> {
+        self.next();
+        self.expect('[')?;
+        let len: usize = self.take_while(|c| c.is_ascii_digit()).parse()
+            .map_err(|_| self.error("expected byte string length"))?;
+        self.expect(']')?;
+        self.expect('\'')?;
+        let start = self.pos;
+        let bytes = decode_hex(self.take_while(|c| c.is_ascii_hexdigit()))
+            .map_err(|err| match err {
+                CBORError::InvalidHex { position, source } => {
+                    Error::msg(CBORError::InvalidDebugCompact(start + position, format!("invalid hex: {}", hex_reason(&source))))
+                },
+                _ => self.error("invalid hex"),
+            })?;
+        self.expect('\'')?;
+        if bytes.len() != len {
+            return Err(self.error("byte string length does not match its content"));
+        }
+        Ok(ByteString::from(bytes).into())
+    }
+
+    /// Parses an integer, float, tagged value, or named simple value.
+    fn parse_scalar(&mut self) -> Result<CBOR> {
+        let start = self.pos;
+        let token = self.take_while(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '+');
+        match token {
+            "true" => return Ok(CBOR::r#true()),
+            "false" => return Ok(CBOR::r#false()),
+            "null" => return Ok(CBOR::null()),
+            _ => {},
+        }
+        if token.bytes().all(|c| c.is_ascii_digit()) && matches!(self.peek(), Some('#' | '(')) {
+            let value: TagValue = token.parse().map_err(|_| self.error("invalid tag value"))?;
+            return self.parse_tagged(value);
+        }
+        let is_float = token.contains(['.', 'e', 'E']) || token.ends_with("NaN") || token.ends_with("inf");
+        let result = match token {
+            #[cfg(feature = "float")]
+            _ if is_float => token.parse::<f64>().ok().map(|f| CBORCase::Simple(Simple::Float(f)).into()),
+            #[cfg(not(feature = "float"))]
+            _ if is_float => {
+                self.pos = start;
+                return Err(self.error("floats are not supported"));
+            },
+            _ if token.starts_with('-') => {
+                token.parse::<i128>().ok()
+                    .and_then(|n| u64::try_from(-1 - n).ok())
+                    .map(|n| CBORCase::Negative(n).into())
+            },
+            _ => token.parse::<u64>().ok().map(|n| CBORCase::Unsigned(n).into()),
+        };
+        match result {
+            Some(cbor) => Ok(cbor),
+            None => {
+                self.pos = start;
+                Err(self.error("invalid number"))
+            },
+        }
+    }
+
+    fn parse_tagged(&mut self, value: TagValue) -> Result<CBOR> {
+        let tag = if self.peek() == Some('#') {
+            self.next();
+            let name = if self.peek() == Some('"') {
+                self.parse_string()?
+            } else {
+                self.take_while(is_bare_name_char).to_string()
+            };
+            if name.is_empty() {
+                return Err(self.error("expected tag name"));
+            }
+            Tag::new(value, name)
+        } else {
+            Tag::with_value(value)
+        };
+        self.expect('(')?;
+        let item = self.parse_value()?;
+        self.expect(')')?;
+        Ok(CBOR::to_tagged_value(tag, item))
+    }
+}
@@ -1,10 +1,10 @@
 import_stdlib!();
 
-use anyhow::{bail, Result, Error};
-use half::f16;
-use unicode_normalization::is_nfc;
+use anyhow::{bail, Error, Result};
 
-use crate::{CBOR, Map, error::CBORError, float::{validate_canonical_f16, validate_canonical_f32, validate_canonical_f64}, CBORCase};
+use crate::{error::{with_details, with_path, within, DecodeOffset, ValuePath}, CBOR, PathElement, Map, error::CBORError, text::is_nfc, tokenizer::{read_token_from_head, Token}, CBORCase, CodecObserver, FloatKeyPolicy, Tag};
+#[cfg(feature = "float")]
+use crate::Simple;
 
 use super::varint::MajorType;
 
@@ -12,15 +12,233 @@ use super::varint::MajorType;
 ///
 /// Returns an error if the data is not well-formed deterministic CBOR.
 pub fn decode_cbor(data: impl AsRef<[u8]>) -> Result<CBOR> {
-    let data = data.as_ref();
-    let (cbor, len) = decode_cbor_internal(data)?;
+    decode_cbor_opt(data, &DecodeOptions::default())
+}
+
+/// Decode CBOR binary representation to symbolic representation, using the
+/// given options.
+pub fn decode_cbor_opt(data: impl AsRef<[u8]>, options: &DecodeOptions) -> Result<CBOR> {
+    let mut context = DecodeContext::new(options, None);
+    decode_cbor_with_context(data.as_ref(), &mut context)
+}
+
+/// Decode the single CBOR item at the start of the data, returning it along
+/// with the number of bytes it occupied.
+///
+/// Unlike [`decode_cbor`], any data following the item is not an error.
+pub fn decode_cbor_prefix(data: impl AsRef<[u8]>) -> Result<(CBOR, usize)> {
+    let options = DecodeOptions::default();
+    let mut context = DecodeContext::new(&options, None);
+    decode_cbor_internal(data.as_ref(), 0, 0, &mut context)
+}
+
+/// Decode CBOR binary representation to symbolic representation, reporting
+/// each decoded item to the given observer.
+pub fn decode_cbor_observed(data: impl AsRef<[u8]>, observer: &mut dyn CodecObserver) -> Result<CBOR> {
+    decode_cbor_opt_observed(data, &DecodeOptions::default(), observer)
+}
+
+/// Decode CBOR binary representation to symbolic representation, using the
+/// given options and reporting each decoded item to the given observer.
+pub fn decode_cbor_opt_observed(data: impl AsRef<[u8]>, options: &DecodeOptions, observer: &mut dyn CodecObserver) -> Result<CBOR> {
+    let mut context = DecodeContext::new(options, Some(observer));
+    decode_cbor_with_context(data.as_ref(), &mut context)
+}
+
+/// Options controlling how CBOR is decoded.
+///
+/// Options are set using the builder-style methods, starting from
+/// `DecodeOptions::default()`.
+#[derive(Debug, Clone, Default)]
+pub struct DecodeOptions {
+    #[cfg(feature = "spans")]
+    record_spans: bool,
+    max_text_bytes: Option<usize>,
+    max_text_chars: Option<usize>,
+    max_memory: Option<usize>,
+    max_depth: Option<usize>,
+    reject_nan: bool,
+    reject_infinity: bool,
+    float_key_policy: FloatKeyPolicy,
+    intern_keys: bool,
+    accept_non_nfc: bool,
+}
+
+impl DecodeOptions {
+    /// The estimated memory used by each decoded item, excluding the payload
+    /// of byte and text strings, when accounting against
+    /// [`DecodeOptions::max_memory`].
+    pub const ITEM_MEMORY_ESTIMATE: usize = 64;
+
+    /// Records the range of input bytes occupied by each decoded item,
+    /// retrievable using [`CBOR::encoded_span`].
+    ///
+    /// Requires the `spans` feature, without which values have no room to
+    /// store their span.
+    #[cfg(feature = "spans")]
+    pub fn record_spans(mut self, record_spans: bool) -> Self {
+        self.record_spans = record_spans;
+        self
+    }
+
+    /// The maximum length in bytes of any text string. Longer strings cause
+    /// decoding to fail with [`CBORError::TextTooLong`]. Defaults to no limit.
+    pub fn max_text_bytes(mut self, max_text_bytes: Option<usize>) -> Self {
+        self.max_text_bytes = max_text_bytes;
+        self
+    }
+
+    /// The maximum length in Unicode scalar values of any text string.
+    /// Longer strings cause decoding to fail with [`CBORError::TextTooLong`].
+    /// Defaults to no limit.
+    pub fn max_text_chars(mut self, max_text_chars: Option<usize>) -> Self {
+        self.max_text_chars = max_text_chars;
+        self
+    }
+
+    /// The maximum estimated memory, in bytes, that the decoded value may
+    /// occupy. Each item is charged [`DecodeOptions::ITEM_MEMORY_ESTIMATE`]
+    /// plus the length of its payload if it is a byte or text string.
+    /// Exceeding the budget causes decoding to fail with
+    /// [`CBORError::MemoryBudgetExceeded`]. Defaults to no limit.
+    pub fn max_memory(mut self, max_memory: Option<usize>) -> Self {
+        self.max_memory = max_memory;
+        self
+    }
+
+    /// The maximum depth of nesting, where the decoded value is at depth 0
+    /// and the items in an array, map, or tagged value are one deeper than
+    /// it. Deeper items cause decoding to fail with
+    /// [`CBORError::DepthLimitExceeded`]. Defaults to no limit.
+    ///
+    /// Decoding recurses for each level of nesting, so data from untrusted
+    /// sources should be decoded with a limit to avoid overflowing the stack.
+    pub fn max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Rejects NaN and infinite floating point values, as both
+    /// [`DecodeOptions::reject_nan`] and [`DecodeOptions::reject_infinity`].
+    pub fn reject_non_finite(mut self, reject_non_finite: bool) -> Self {
+        self.reject_nan = reject_non_finite;
+        self.reject_infinity = reject_non_finite;
+        self
+    }
+
+    /// Rejects NaN, failing decoding with [`CBORError::NonFiniteFloat`] and
+    /// the [`ValuePath`] of the offending value attached.
+    ///
+    /// This is independent of canonicalization: without it, the canonical NaN
+    /// is accepted. Negative zero needs no option, as dCBOR encodes it as the
+    /// integer 0.
+    pub fn reject_nan(mut self, reject_nan: bool) -> Self {
+        self.reject_nan = reject_nan;
+        self
+    }
+
+    /// Rejects positive and negative infinity, failing decoding as for
+    /// [`DecodeOptions::reject_nan`].
+    pub fn reject_infinity(mut self, reject_infinity: bool) -> Self {
+        self.reject_infinity = reject_infinity;
+        self
+    }
+
+    /// Which floating point map keys are permitted. Keys the policy does not
+    /// permit cause decoding to fail with [`CBORError::FloatMapKey`] or
+    /// [`CBORError::NanMapKey`], with the [`ValuePath`] of the map holding
+    /// them attached. Defaults to [`FloatKeyPolicy::Allow`].
+    pub fn float_key_policy(mut self, float_key_policy: FloatKeyPolicy) -> Self {
+        self.float_key_policy = float_key_policy;
+        self
+    }
+
+    /// Shares a single decoded value between all the map keys in the input
+    /// that are the same text string, such as the field names of an array of
+    /// similar records, rather than allocating each one separately.
+    ///
+    /// Interned keys are not charged against [`DecodeOptions::max_memory`]
+    /// after their first occurrence. With the `spans` feature, keys are not
+    /// interned when `DecodeOptions::record_spans` is set, as each occurrence
+    /// has its own span.
+    pub fn intern_keys(mut self, intern_keys: bool) -> Self {
+        self.intern_keys = intern_keys;
+        self
+    }
+
+    /// Accepts text strings that are not in Unicode Normalization Form C,
+    /// rather than failing with [`CBORError::NonCanonicalString`], reporting
+    /// each to [`CodecObserver::non_nfc_text_decoded`].
+    ///
+    /// The strings are decoded as they are, so the decoded value is not
+    /// canonical and its encoding differs from the input. This lets a
+    /// validator tell authors which strings to fix, as
+    /// [`CBOR::try_from_data_with_nfc_report`] does.
+    pub fn accept_non_nfc(mut self, accept_non_nfc: bool) -> Self {
+        self.accept_non_nfc = accept_non_nfc;
+        self
+    }
+
+    fn records_spans(&self) -> bool {
+        #[cfg(feature = "spans")]
+        return self.record_spans;
+        #[cfg(not(feature = "spans"))]
+        false
+    }
+}
+
+/// State carried through a single decoding pass.
+struct DecodeContext<'a> {
+    options: &'a DecodeOptions,
+    observer: Option<&'a mut dyn CodecObserver>,
+    memory_used: usize,
+    /// Text map keys decoded so far, by their encoding, if interning keys.
+    interned_keys: HashMap<Vec<u8>, CBOR>,
+}
+
+impl<'a> DecodeContext<'a> {
+    fn new(options: &'a DecodeOptions, observer: Option<&'a mut dyn CodecObserver>) -> Self {
+        Self { options, observer, memory_used: 0, interned_keys: HashMap::new() }
+    }
+
+    /// Charges the memory for an item with the given string payload length
+    /// against the budget.
+    fn charge_memory(&mut self, payload_len: usize) -> Result<()> {
+        self.memory_used = self.memory_used
+            .saturating_add(DecodeOptions::ITEM_MEMORY_ESTIMATE)
+            .saturating_add(payload_len);
+        if self.options.max_memory.is_some_and(|max| self.memory_used > max) {
+            bail!(CBORError::MemoryBudgetExceeded);
+        }
+        Ok(())
+    }
+}
+
+fn decode_cbor_with_context(data: &[u8], context: &mut DecodeContext<'_>) -> Result<CBOR> {
+    let (cbor, len) = decode_cbor_internal(data, 0, 0, context)?;
     let remaining = data.len() - len;
     if remaining > 0 {
-        bail!(CBORError::UnusedData(remaining));
+        return Err(located(Error::msg(CBORError::UnusedData(remaining)), len));
     }
     Ok(cbor)
 }
 
+/// Attaches the offset at which decoding failed beneath a [`CBORError`],
+/// unless an offset is already attached.
+///
+/// The offset goes beneath the `CBORError` so that the error still displays
+/// and downcasts as the `CBORError`, with the error's source beneath it.
+pub(crate) fn located(error: Error, offset: usize) -> Error {
+    if error.downcast_ref::<DecodeOffset>().is_some() {
+        return error;
+    }
+    let path = error.downcast_ref::<ValuePath>().cloned();
+    match error.downcast::<CBORError>() {
+        Ok(cbor_error) => with_details(cbor_error, path, Some(DecodeOffset(offset))),
+        Err(error) => error,
+    }
+}
+
 fn parse_header(header: u8) -> (MajorType, u8) {
     let major_type = match header >> 5 {
         0 => MajorType::Unsigned,
@@ -37,7 +255,7 @@ fn parse_header(header: u8) -> (MajorType, u8) {
     (major_type, header_value)
 }
 
-fn parse_header_varint(data: &[u8]) -> Result<(MajorType, u64, usize)> {
+pub(crate) fn parse_header_varint(data: &[u8]) -> Result<(MajorType, u64, usize)> {
     if data.is_empty() {
         bail!(CBORError::Underrun)
     }
@@ -95,90 +313,138 @@ fn parse_header_varint(data: &[u8]) -> Result<(MajorType, u64, usize)> {
     Ok((major_type, value, varint_len))
 }
 
-fn parse_bytes(data: &[u8], len: usize) -> Result<&[u8]> {
-    if data.len() < len {
-        bail!(CBORError::Underrun);
+/// Decodes the item at the start of `data`, which begins at `offset` in the
+/// overall input.
+fn decode_cbor_internal(data: &[u8], offset: usize, depth: usize, context: &mut DecodeContext<'_>) -> Result<(CBOR, usize)> {
+    #[cfg_attr(not(feature = "spans"), allow(unused_mut))]
+    let (mut cbor, len) = decode_cbor_item(data, offset, depth, context)
+        .map_err(|error| located(error, offset))?;
+    #[cfg(feature = "spans")]
+    if context.options.record_spans {
+        cbor.set_encoded_span(offset..offset + len);
+    }
+    if let Some(observer) = context.observer.as_mut() {
+        observer.item_decoded(&cbor, depth, len);
+    }
+    Ok((cbor, len))
+}
+
+/// Decodes the map key at the start of `data`, sharing a text key decoded
+/// earlier in the pass if interning keys.
+fn decode_map_key(data: &[u8], offset: usize, depth: usize, context: &mut DecodeContext<'_>) -> Result<(CBOR, usize)> {
+    if !context.options.intern_keys || context.options.records_spans() {
+        return decode_cbor_internal(data, offset, depth, context);
+    }
+    let encoded = match parse_header_varint(data) {
+        Ok((MajorType::Text, len, header_len)) => {
+            usize::try_from(len).ok()
+                .and_then(|len| len.checked_add(header_len))
+                .and_then(|end| data.get(..end))
+        },
+        _ => None,
+    };
+    let Some(encoded) = encoded else {
+        return decode_cbor_internal(data, offset, depth, context);
+    };
+    if let Some(key) = context.interned_keys.get(encoded) {
+        let key = key.clone();
+        if let Some(observer) = context.observer.as_mut() {
+            if let CBORCase::Text(text) = key.as_case() {
+                if context.options.accept_non_nfc && !is_nfc(text) {
+                    observer.non_nfc_text_decoded(text, offset..offset + encoded.len());
+                }
+            }
+            observer.item_decoded(&key, depth, encoded.len());
+        }
+        return Ok((key, encoded.len()));
     }
-    Ok(&data[0..len])
+    let (key, key_len) = decode_cbor_internal(data, offset, depth, context)?;
+    context.interned_keys.insert(encoded.to_vec(), key.clone());
+    Ok((key, key_len))
 }
 
-fn decode_cbor_internal(data: &[u8]) -> Result<(CBOR, usize)> {
+fn decode_cbor_item(data: &[u8], offset: usize, depth: usize, context: &mut DecodeContext<'_>) -> Result<(CBOR, usize)> {
     if data.is_empty() {
         bail!(CBORError::Underrun)
     }
+    if context.options.max_depth.is_some_and(|max| depth > max) {
+        bail!(CBORError::DepthLimitExceeded)
+    }
     let (major_type, value, header_varint_len) = parse_header_varint(data)?;
-    match major_type {
-        MajorType::Unsigned => Ok((CBORCase::Unsigned(value).into(), header_varint_len)),
-        MajorType::Negative => Ok((CBORCase::Negative(value).into(), header_varint_len)),
-        MajorType::ByteString => {
-            let data_len = value as usize;
-            let bytes = parse_bytes(&data[header_varint_len..], data_len)?.to_vec().into();
-            Ok((CBORCase::ByteString(bytes).into(), header_varint_len + data_len))
+    let payload_len = match major_type {
+        MajorType::ByteString | MajorType::Text => match usize::try_from(value) {
+            Ok(len) => len,
+            Err(_) => bail!(CBORError::OutOfRange),
         },
-        MajorType::Text => {
-            let data_len = value as usize;
-            let buf = parse_bytes(&data[header_varint_len..], data_len)?;
-            let string = str::from_utf8(buf).map_err(Error::msg)?;
-            if !is_nfc(string) {
-                bail!(CBORError::NonCanonicalString)
+        _ => 0,
+    };
+    context.charge_memory(payload_len)?;
+    if matches!(major_type, MajorType::Text) && context.options.max_text_bytes.is_some_and(|max| payload_len > max) {
+        bail!(CBORError::TextTooLong)
+    }
+    let (token, len) = read_token_from_head(data, major_type, value, header_varint_len, !context.options.accept_non_nfc)?;
+    match token {
+        Token::Unsigned(n) => Ok((CBORCase::Unsigned(n).into(), len)),
+        Token::Negative(n) => Ok((CBORCase::Negative(n).into(), len)),
+        Token::ByteString(bytes) => Ok((CBORCase::ByteString(bytes.to_vec().into()).into(), len)),
+        Token::Text(string) => {
+            if context.options.max_text_chars.is_some_and(|max| string.chars().count() > max) {
+                bail!(CBORError::TextTooLong)
             }
-            Ok((string.into(), header_varint_len + data_len))
+            if context.options.accept_non_nfc && !is_nfc(string) {
+                if let Some(observer) = context.observer.as_mut() {
+                    observer.non_nfc_text_decoded(string, offset..offset + len);
+                }
+            }
+            Ok((string.into(), len))
         },
-        MajorType::Array => {
-            let mut pos = header_varint_len;
+        Token::Array(count) => {
+            let Ok(count) = usize::try_from(count) else {
+                bail!(CBORError::OutOfRange);
+            };
+            let mut pos = len;
             let mut items = Vec::new();
-            for _ in 0..value {
-                let (item, item_len) = decode_cbor_internal(&data[pos..])?;
+            for index in 0..count {
+                let (item, item_len) = decode_cbor_internal(&data[pos..], offset + pos, depth + 1, context)
+                    .map_err(|error| within(error, || PathElement::Index(index)))?;
                 items.push(item);
                 pos += item_len;
             }
             Ok((items.into(), pos))
         },
-        MajorType::Map => {
-            let mut pos = header_varint_len;
+        Token::Map(count) => {
+            let mut pos = len;
             let mut map = Map::new();
-            for _ in 0..value {
-                let (key, key_len) = decode_cbor_internal(&data[pos..])?;
+            for _ in 0..count {
+                let key_pos = pos;
+                let (key, key_len) = decode_map_key(&data[pos..], offset + pos, depth + 1, context)?;
+                context.options.float_key_policy.check(&key)
+                    .map_err(|error| located(with_path(error), offset + key_pos))?;
                 pos += key_len;
-                let (value, value_len) = decode_cbor_internal(&data[pos..])?;
+                let (value, value_len) = decode_cbor_internal(&data[pos..], offset + pos, depth + 1, context)
+                    .map_err(|error| within(error, || PathElement::Key(key.clone())))?;
                 pos += value_len;
-                map.insert_next(key, value)?;
+                map.insert_next(key, value).map_err(|error| located(error, offset + key_pos))?;
             }
             Ok((map.into(), pos))
         },
-        MajorType::Tagged => {
-            let (item, item_len) = decode_cbor_internal(&data[header_varint_len..])?;
-            let tagged = CBOR::to_tagged_value(value, item);
-            Ok((tagged, header_varint_len + item_len))
+        Token::Tag(tag) => {
+            let (item, item_len) = decode_cbor_internal(&data[len..], offset + len, depth + 1, context)?;
+            if let Some(observer) = context.observer.as_mut() {
+                let start = offset + len;
+                observer.tag_decoded(&Tag::with_value(tag), &item, start..start + item_len)?;
+            }
+            let tagged = CBOR::to_tagged_value(tag, item);
+            Ok((tagged, len + item_len))
         },
-        MajorType::Simple => {
-            match header_varint_len {
-                3 => {
-                    let f = f16::from_bits(value as u16);
-                    validate_canonical_f16(f)?;
-                    Ok((f.into(), header_varint_len))
-                },
-                5 => {
-                    let f = f32::from_bits(value as u32);
-                    validate_canonical_f32(f)?;
-                    Ok((f.into(), header_varint_len))
-                },
-                9 => {
-                    let f = f64::from_bits(value);
-                    validate_canonical_f64(f)?;
-                    Ok((f.into(), header_varint_len))
-                },
-                _ => {
-                    match value {
-                        20 => Ok((CBOR::r#false(), header_varint_len)),
-                        21 => Ok((CBOR::r#true(), header_varint_len)),
-                        22 => Ok((CBOR::null(), header_varint_len)),
-                        _ => {
-                            bail!(CBORError::InvalidSimpleValue)
-                        },
-                    }
+        Token::Simple(simple) => {
+            #[cfg(feature = "float")]
+            if let Simple::Float(f) = simple {
+                if (context.options.reject_nan && f.is_nan()) || (context.options.reject_infinity && f.is_infinite()) {
+                    return Err(with_path(CBORError::NonFiniteFloat));
                 }
             }
-        }
+            Ok((simple.into(), len))
+        },
     }
 }
@@ -1,17 +1,154 @@
 import_stdlib!();
 
-use crate::{tags_store::TagsStoreTrait, with_tags, CBORCase, CBOR};
+use crate::{tags_store::TagsStoreTrait, with_tags, CBORCase, CBORTaggedDecodable, Date, DateFormat, CBOR, Tag, TAG_DATE, TAG_ENCODED_CBOR};
 
-use super::string_util::flanked;
+use super::string_util::{flanked, from_utf8, hex_encode, is_printable};
+
+/// Options controlling the output of [`CBOR::diagnostic_with_opts`].
+///
+/// Options are set using the builder-style methods, starting from
+/// `DiagFormatOpts::default()`.
+#[derive(Clone)]
+pub struct DiagFormatOpts<'a> {
+    pub(crate) annotate: bool,
+    decode_embedded: bool,
+    summarize: bool,
+    flat: bool,
+    indent_width: usize,
+    max_width: usize,
+    collapse_small: bool,
+    max_len: Option<usize>,
+    max_items: Option<usize>,
+    pub(crate) date_format: Option<DateFormat>,
+    pub(crate) tags: Option<&'a dyn TagsStoreTrait>,
+}
+
+impl Default for DiagFormatOpts<'_> {
+    fn default() -> Self {
+        Self {
+            annotate: false,
+            decode_embedded: false,
+            summarize: false,
+            flat: false,
+            indent_width: 4,
+            max_width: 20,
+            collapse_small: true,
+            max_len: None,
+            max_items: None,
+            date_format: None,
+            tags: None,
+        }
+    }
+}
+
+impl<'a> DiagFormatOpts<'a> {
+    /// Annotates the output, e.g. adding names of known tags as comments.
+    pub fn annotate(mut self, annotate: bool) -> Self {
+        self.annotate = annotate;
+        self
+    }
+
+    /// When annotating, shows the diagnostic notation of every byte string
+    /// that is itself valid dCBOR, e.g. `/ <<[1, 2]>> /`.
+    ///
+    /// By default only the content of tag 24 (encoded CBOR data item) is
+    /// decoded, as short byte strings such as `h'01'` are often valid dCBOR
+    /// by coincidence.
+    pub fn decode_embedded(mut self, decode_embedded: bool) -> Self {
+        self.decode_embedded = decode_embedded;
+        self
+    }
+
+    /// Replaces tagged values that have a registered summarizer with their
+    /// summaries.
+    pub fn summarize(mut self, summarize: bool) -> Self {
+        self.summarize = summarize;
+        self
+    }
+
+    /// Places the entire output on a single line.
+    pub fn flat(mut self, flat: bool) -> Self {
+        self.flat = flat;
+        self
+    }
+
+    /// The number of spaces each nesting level is indented by. Defaults to 4.
+    pub fn indent_width(mut self, indent_width: usize) -> Self {
+        self.indent_width = indent_width;
+        self
+    }
+
+    /// The length of content a container may have before it is broken across
+    /// multiple lines. Defaults to 20.
+    pub fn max_width(mut self, max_width: usize) -> Self {
+        self.max_width = max_width;
+        self
+    }
+
+    /// Whether containers that are short enough and contain no other
+    /// containers are placed on a single line. Defaults to `true`.
+    pub fn collapse_small(mut self, collapse_small: bool) -> Self {
+        self.collapse_small = collapse_small;
+        self
+    }
+
+    /// The number of bytes of a byte string, or characters of a text string,
+    /// shown before the rest is elided, e.g. `h'0001…(1.2 KB)'`. By default
+    /// nothing is elided.
+    ///
+    /// Output with elisions is for display only, and cannot be parsed as
+    /// diagnostic notation.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = Some(max_len);
+        self
+    }
+
+    /// The number of array items or map entries shown before the rest are
+    /// elided, e.g. `[1, 2, … 998 more]`. By default nothing is elided.
+    pub fn max_items(mut self, max_items: usize) -> Self {
+        self.max_items = Some(max_items);
+        self
+    }
+
+    /// The format of dates (tag 1) in summaries, e.g. to show fractional
+    /// seconds. This applies to annotated hex dumps as well as diagnostic
+    /// notation.
+    ///
+    /// When set, dates are summarized in this format even if no tags store
+    /// is given, and any summarizer registered for tag 1 is not used. By
+    /// default, dates are summarized by the registered summarizer, which
+    /// uses [`DateFormat::Standard`].
+    pub fn date_format(mut self, date_format: DateFormat) -> Self {
+        self.date_format = Some(date_format);
+        self
+    }
+
+    /// The tags store used to name tags and look up summarizers.
+    pub fn tags(mut self, tags: Option<&'a dyn TagsStoreTrait>) -> Self {
+        self.tags = tags;
+        self
+    }
+}
 
 /// Affordances for viewing CBOR in diagnostic notation.
 impl CBOR {
+    /// Returns a representation of this CBOR in diagnostic notation, formatted
+    /// according to the given options.
+    pub fn diagnostic_with_opts(&self, opts: &DiagFormatOpts<'_>) -> String {
+        self.diag_item(opts).format(opts)
+    }
+
     /// Returns a representation of this CBOR in diagnostic notation.
     ///
     /// Optionally annotates the output, e.g. formatting dates and adding names
     /// of known tags.
     pub fn diagnostic_opt(&self, annotate: bool, summarize: bool, flat: bool, tags: Option<&dyn TagsStoreTrait>) -> String {
-        self.diag_item(annotate, summarize, tags).format(annotate, flat)
+        let opts = DiagFormatOpts::default()
+            .annotate(annotate)
+            .summarize(summarize)
+            .flat(flat)
+            .tags(tags);
+        self.diagnostic_with_opts(&opts)
     }
 
     /// Returns a representation of this CBOR in diagnostic notation.
@@ -26,6 +163,21 @@ impl CBOR {
         })
     }
 
+    /// Returns a single-line representation of this CBOR in diagnostic
+    /// notation, with long strings and containers elided, so that large
+    /// values can be logged safely.
+    ///
+    /// At most `max_len` bytes of each byte string or characters of each text
+    /// string, and `max_items` items of each array or map, are shown. See
+    /// [`DiagFormatOpts::max_len`] and [`DiagFormatOpts::max_items`].
+    pub fn diagnostic_truncated(&self, max_len: usize, max_items: usize) -> String {
+        let opts = DiagFormatOpts::default()
+            .flat(true)
+            .max_len(max_len)
+            .max_items(max_items);
+        self.diagnostic_with_opts(&opts)
+    }
+
     pub fn diagnostic_flat(&self) -> String {
         with_tags!(|tags: &dyn TagsStoreTrait| {
             self.diagnostic_opt(false, false, true, Some(tags))
@@ -42,15 +194,34 @@ impl CBOR {
         self.diagnostic_opt(false, true, true, Some(tags))
     }
 
-    fn diag_item(&self, annotate: bool, summarize: bool, tags: Option<&dyn TagsStoreTrait>) -> DiagItem {
+    fn diag_item(&self, opts: &DiagFormatOpts<'_>) -> DiagItem {
         match self.as_case() {
-            CBORCase::Unsigned(_) | CBORCase::Negative(_) | CBORCase::ByteString(_) |
-            CBORCase::Text(_) | CBORCase::Simple(_) => DiagItem::Item(format!("{}", self)),
+            CBORCase::ByteString(b) => self.byte_string_item(b, opts.decode_embedded, opts),
+
+            CBORCase::Text(s) => {
+                let len = s.chars().count();
+                match opts.max_len {
+                    Some(max_len) if len > max_len => {
+                        let shown: String = s.chars().take(max_len).collect();
+                        let shown = format!("{}", CBOR::from(shown));
+                        let shown = &shown[..shown.len() - 1];
+                        DiagItem::Item(format!("{}…({} chars)\"", shown, len), None)
+                    },
+                    _ => DiagItem::Item(format!("{}", self), None),
+                }
+            },
+
+            CBORCase::Unsigned(_) | CBORCase::Negative(_) |
+            CBORCase::Simple(_) => DiagItem::Item(format!("{}", self), None),
 
             CBORCase::Array(a) => {
                 let begin = "[".to_string();
                 let end = "]".to_string();
-                let items = a.iter().map(|x| x.diag_item(annotate, summarize, tags)).collect();
+                let shown = opts.max_items.unwrap_or(usize::MAX).min(a.len());
+                let mut items: Vec<_> = a.iter().take(shown).map(|x| x.diag_item(opts)).collect();
+                if shown < a.len() {
+                    items.push(elided_items(a.len() - shown));
+                }
                 let is_pairs = false;
                 let comment = None;
                 DiagItem::Group(begin, end, items, is_pairs, comment)
@@ -58,32 +229,32 @@ impl CBOR {
             CBORCase::Map(m) => {
                 let begin = "{".to_string();
                 let end = "}".to_string();
-                let items = m.iter().flat_map(|(key, value)| vec![
-                    key.diag_item(annotate, summarize, tags),
-                    value.diag_item(annotate, summarize, tags)
+                let shown = opts.max_items.unwrap_or(usize::MAX).min(m.len());
+                let mut items: Vec<_> = m.iter().take(shown).flat_map(|(key, value)| vec![
+                    key.diag_item(opts),
+                    value.diag_item(opts)
                 ]).collect();
+                if shown < m.len() {
+                    items.push(elided_items(m.len() - shown));
+                }
                 let is_pairs = true;
                 let comment = None;
                 DiagItem::Group(begin, end, items, is_pairs, comment)
             },
             CBORCase::Tagged(tag, item) => {
-                if summarize {
-                    if let Some(tags) = tags {
-                        if let Some(summarizer) = tags.summarizer(tag.value()) {
-                            match summarizer(item.clone()) {
-                                Ok(summary) => return DiagItem::Item(summary),
-                                Err(error) => return DiagItem::Item(format!("<error: {}>", error)),
-                            }
-                        }
-                    }
+                if let Some(summary) = tagged_summary(tag, item, opts) {
+                    return DiagItem::Item(summary, None);
                 }
-                let diag_item = item.diag_item(annotate, summarize, tags);
+                let diag_item = match item.as_case() {
+                    CBORCase::ByteString(b) if tag.value() == TAG_ENCODED_CBOR => item.byte_string_item(b, true, opts),
+                    _ => item.diag_item(opts),
+                };
                 let begin = tag.value().to_string() + "(";
                 let end = ")".to_string();
                 let items = vec![diag_item];
                 let is_pairs = false;
-                let comment = if annotate {
-                    tags.as_ref().and_then(|x| x.assigned_name_for_tag(tag))
+                let comment = if opts.annotate {
+                    opts.tags.as_ref().and_then(|x| x.assigned_name_for_tag(tag))
                 } else {
                     None
                 };
@@ -91,36 +262,105 @@ impl CBOR {
             },
         }
     }
+
+    fn byte_string_item(&self, b: &[u8], decode_embedded: bool, opts: &DiagFormatOpts<'_>) -> DiagItem {
+        let comment = if opts.annotate { byte_string_comment(b, decode_embedded) } else { None };
+        match opts.max_len {
+            Some(max_len) if b.len() > max_len => {
+                let shown = hex_encode(&b[..max_len]);
+                DiagItem::Item(format!("h'{}…({})'", shown, format_size(b.len())), comment)
+            },
+            _ => DiagItem::Item(format!("{}", self), comment),
+        }
+    }
+}
+
+/// Returns the marker standing in for elided array items or map entries.
+/// Returns the summary of a tagged value, if summaries are requested and one
+/// is available, or a description of the error if summarizing it failed.
+pub(crate) fn tagged_summary(tag: &Tag, item: &CBOR, opts: &DiagFormatOpts<'_>) -> Option<String> {
+    if !opts.summarize {
+        return None;
+    }
+    let summary = if let (Some(date_format), TAG_DATE) = (opts.date_format, tag.value()) {
+        Date::from_untagged_cbor(item.clone()).map(|date| date.format(date_format))
+    } else {
+        let tags = opts.tags?;
+        let summarizer = tags.summarizer(tag.value())?;
+        summarizer(item.clone(), tags, opts)
+    };
+    Some(summary.unwrap_or_else(|error| format!("<error: {}>", error)))
+}
+
+fn elided_items(count: usize) -> DiagItem {
+    DiagItem::Item(format!("… {} more", count), None)
+}
+
+/// Returns a byte count in human-readable form, e.g. `1.2 KB`.
+fn format_size(len: usize) -> String {
+    const KB: usize = 1024;
+    const MB: usize = KB * 1024;
+    if len < KB {
+        format!("{} B", len)
+    } else if len < MB {
+        format!("{:.1} KB", len as f64 / KB as f64)
+    } else {
+        format!("{:.1} MB", len as f64 / MB as f64)
+    }
+}
+
+/// Returns a comment describing the content of a byte string: its
+/// diagnostic notation if `decode_embedded` is set and it is itself valid
+/// dCBOR, or its text if it is printable UTF-8.
+fn byte_string_comment(bytes: &[u8], decode_embedded: bool) -> Option<String> {
+    if bytes.is_empty() {
+        return None;
+    }
+    if decode_embedded {
+        if let Ok(cbor) = CBOR::try_from_data(bytes) {
+            return Some(flanked(&cbor.diagnostic_opt(false, false, true, None), "<<", ">>"));
+        }
+    }
+    let string = from_utf8(bytes).ok()?;
+    if string.chars().all(is_printable) {
+        Some(flanked(string, "\"", "\""))
+    } else {
+        None
+    }
 }
 
 #[derive(Debug)]
 enum DiagItem {
-    Item(String),
+    Item(String, Option<String>),
     Group(String, String, Vec<DiagItem>, bool, Option<String>),
 }
 
 impl DiagItem {
-    fn format(&self, annotate: bool, flat: bool) -> String {
-        self.format_opt(0, "", annotate, flat)
+    fn format(&self, opts: &DiagFormatOpts<'_>) -> String {
+        self.format_opt(0, "", opts)
     }
 
-    fn format_opt(&self, level: usize, separator: &str, annotate: bool, flat: bool) -> String {
+    fn format_opt(&self, level: usize, separator: &str, opts: &DiagFormatOpts<'_>) -> String {
         match self {
-            DiagItem::Item(string) => {
-                self.format_line(level, flat, string, separator, None)
+            DiagItem::Item(string, comment) => {
+                self.format_line(level, opts, string, separator, comment.as_deref())
             },
-            DiagItem::Group(_, _, _, _, _) => {
-                if !flat && (self.contains_group() || self.total_strings_len() > 20 || self.greatest_strings_len() > 20) {
-                    self.multiline_composition(level, separator, annotate)
+            DiagItem::Group(_, _, items, _, _) => {
+                let is_small = !self.contains_group() &&
+                    !self.contains_comment() &&
+                    self.total_strings_len() <= opts.max_width &&
+                    self.greatest_strings_len() <= opts.max_width;
+                if opts.flat || items.is_empty() || (is_small && opts.collapse_small) {
+                    self.single_line_composition(level, separator, opts)
                 } else {
-                    self.single_line_composition(level, separator, flat)
+                    self.multiline_composition(level, separator, opts)
                 }
             },
         }
     }
 
-    fn format_line(&self, level: usize, flat: bool, string: &str, separator: &str, comment: Option<&str>) -> String {
-        let indent = if flat { "".to_string() } else { " ".repeat(level * 4) };
+    fn format_line(&self, level: usize, opts: &DiagFormatOpts<'_>, string: &str, separator: &str, comment: Option<&str>) -> String {
+        let indent = if opts.flat { "".to_string() } else { " ".repeat(level * opts.indent_width) };
         let result = format!("{}{}{}", indent, string, separator);
         if let Some(comment) = comment {
             format!("{}   / {} /", result, comment)
@@ -129,20 +369,20 @@ impl DiagItem {
         }
     }
 
-    fn single_line_composition(&self, level: usize, separator: &str, flat: bool) -> String {
+    fn single_line_composition(&self, level: usize, separator: &str, opts: &DiagFormatOpts<'_>) -> String {
         let string: String;
         let comment: Option<&str>;
         match self {
-            DiagItem::Item(s) => {
+            DiagItem::Item(s, comm) => {
                 string = s.clone();
-                comment = None;
+                comment = comm.as_deref();
             },
             DiagItem::Group(begin, end, items, is_pairs, comm) => {
                 let components: Vec<String> = items.iter().map(|item| {
                     match item {
-                        DiagItem::Item(string) => string.clone(),
+                        DiagItem::Item(string, _) => string.clone(),
                         DiagItem::Group(_, _, _, _, _) => {
-                            item.single_line_composition(level + 1, separator, flat)
+                            item.single_line_composition(level + 1, separator, opts)
                         }
                     }
                 }).collect();
@@ -151,15 +391,15 @@ impl DiagItem {
                 comment = comm.as_ref().map(|x| x.as_str());
             },
         };
-        self.format_line(level, flat, &string, separator, comment)
+        self.format_line(level, opts, &string, separator, comment)
     }
 
-    fn multiline_composition(&self, level: usize, separator: &str, annotate: bool) -> String {
+    fn multiline_composition(&self, level: usize, separator: &str, opts: &DiagFormatOpts<'_>) -> String {
         match self {
-            DiagItem::Item(string) => string.to_owned(),
+            DiagItem::Item(string, _) => string.to_owned(),
             DiagItem::Group(begin, end, items, is_pairs, comment) => {
                 let mut lines: Vec<String> = vec![];
-                lines.push(self.format_line(level, false, begin, "", comment.as_ref().map(|x| x.as_str())));
+                lines.push(self.format_line(level, opts, begin, "", comment.as_ref().map(|x| x.as_str())));
                 for (index, item) in items.iter().enumerate() {
                     let separator = if index == items.len() - 1 {
                         ""
@@ -168,9 +408,9 @@ impl DiagItem {
                     } else {
                         ","
                     };
-                    lines.push(item.format_opt(level + 1, separator, annotate, false));
+                    lines.push(item.format_opt(level + 1, separator, opts));
                 }
-                lines.push(self.format_line(level, false, end, separator, None));
+                lines.push(self.format_line(level, opts, end, separator, None));
                 lines.join("\n")
             },
         }
@@ -178,7 +418,7 @@ impl DiagItem {
 
     fn total_strings_len(&self) -> usize {
         match self {
-            DiagItem::Item(string) => string.len(),
+            DiagItem::Item(string, _) => string.len(),
             DiagItem::Group(_, _, items, _, _) => {
                 items.iter().fold(0, |acc, item| { acc + item.total_strings_len() })
             },
@@ -187,7 +427,7 @@ impl DiagItem {
 
     fn greatest_strings_len(&self) -> usize {
         match self {
-            DiagItem::Item(string) => string.len(),
+            DiagItem::Item(string, _) => string.len(),
             DiagItem::Group(_, _, items, _, _) => {
                 items.iter().fold(0, |acc, item| { acc.max(item.total_strings_len()) })
             },
@@ -200,13 +440,22 @@ impl DiagItem {
 
     fn contains_group(&self) -> bool {
         match self {
-            DiagItem::Item(_) => false,
+            DiagItem::Item(_, _) => false,
             DiagItem::Group(_, _, items, _, _) => {
                 items.iter().any(|x| x.is_group())
             },
         }
     }
 
+    fn contains_comment(&self) -> bool {
+        match self {
+            DiagItem::Item(_, _) => false,
+            DiagItem::Group(_, _, items, _, _) => {
+                items.iter().any(|x| matches!(x, DiagItem::Item(_, Some(_))))
+            },
+        }
+    }
+
     fn joined(elements: &[String], item_separator: &str, pair_separator: Option<&str>) -> String {
         let pair_separator = pair_separator.unwrap_or(item_separator);
         let mut result = String::new();
@@ -0,0 +1,207 @@
+import_stdlib!();
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{string_util::hex_encode, CBORCase, CBOR};
+
+/// Limits on the size of the text produced by [`CBOR::display_bounded`].
+///
+/// The [`Display`](fmt::Display) implementation for `CBOR` writes the whole
+/// value, so a large value included in a log message or error can produce an
+/// enormous amount of text. Within the limits, the bounded form is the same as
+/// `Display`; beyond them, elided parts are replaced by `…`.
+///
+/// Options are set using the builder-style methods, starting from
+/// `DisplayLimits::default()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayLimits {
+    max_depth: usize,
+    max_items: usize,
+    max_len: usize,
+}
+
+impl Default for DisplayLimits {
+    fn default() -> Self {
+        Self { max_depth: 8, max_items: 16, max_len: 256 }
+    }
+}
+
+static ERROR_MAX_DEPTH: AtomicUsize = AtomicUsize::new(8);
+static ERROR_MAX_ITEMS: AtomicUsize = AtomicUsize::new(16);
+static ERROR_MAX_LEN: AtomicUsize = AtomicUsize::new(256);
+
+impl DisplayLimits {
+    /// The depth of nesting below which the contents of arrays, maps, and
+    /// tagged values are shown. Defaults to 8.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// The number of elements of each array, or entries of each map, that are
+    /// shown. Defaults to 16.
+    pub fn max_items(mut self, max_items: usize) -> Self {
+        self.max_items = max_items;
+        self
+    }
+
+    /// The length in bytes of the text, not counting the final `…` of text
+    /// that is cut short. Defaults to 256.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Returns the limits used when CBOR values are included in the messages
+    /// of errors returned by this crate.
+    pub fn for_errors() -> Self {
+        Self {
+            max_depth: ERROR_MAX_DEPTH.load(Ordering::Relaxed),
+            max_items: ERROR_MAX_ITEMS.load(Ordering::Relaxed),
+            max_len: ERROR_MAX_LEN.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Sets the limits used when CBOR values are included in the messages of
+    /// errors returned by this crate, for the whole process. Defaults to
+    /// `DisplayLimits::default()`.
+    pub fn set_for_errors(limits: DisplayLimits) {
+        ERROR_MAX_DEPTH.store(limits.max_depth, Ordering::Relaxed);
+        ERROR_MAX_ITEMS.store(limits.max_items, Ordering::Relaxed);
+        ERROR_MAX_LEN.store(limits.max_len, Ordering::Relaxed);
+    }
+}
+
+/// The text of a CBOR value within [`DisplayLimits`], returned by
+/// [`CBOR::display_bounded`].
+#[derive(Debug, Clone, Copy)]
+pub struct BoundedDisplay<'a> {
+    cbor: &'a CBOR,
+    limits: DisplayLimits,
+}
+
+impl CBOR {
+    /// Returns a value that displays this CBOR as [`Display`](fmt::Display)
+    /// does, within the given limits.
+    ///
+    /// The work done is bounded by the limits, not by the size of this value.
+    ///
+    /// ```
+    /// # use dcbor::{prelude::*, DisplayLimits};
+    /// let cbor = CBOR::from((0..100).collect::<Vec<u32>>());
+    /// let limits = DisplayLimits::default().max_items(3);
+    /// assert_eq!(cbor.display_bounded(limits).to_string(), "[0, 1, 2, …]");
+    /// ```
+    pub fn display_bounded(&self, limits: DisplayLimits) -> BoundedDisplay<'_> {
+        BoundedDisplay { cbor: self, limits }
+    }
+
+    /// Returns a value that displays this CBOR within the limits for errors.
+    pub(crate) fn display_for_error(&self) -> BoundedDisplay<'_> {
+        self.display_bounded(DisplayLimits::for_errors())
+    }
+}
+
+impl fmt::Display for BoundedDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut writer = LimitedWriter { f, remaining: self.limits.max_len, cut_short: false };
+        match write_bounded(self.cbor, &self.limits, 0, &mut writer) {
+            Ok(()) => Ok(()),
+            Err(_) if writer.cut_short => writer.f.write_str("…"),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// Writes to a formatter until the length limit is reached, then fails so
+/// that the rest of the value is not visited.
+struct LimitedWriter<'a, 'b> {
+    f: &'a mut fmt::Formatter<'b>,
+    remaining: usize,
+    cut_short: bool,
+}
+
+impl fmt::Write for LimitedWriter<'_, '_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if s.len() <= self.remaining {
+            self.remaining -= s.len();
+            return self.f.write_str(s);
+        }
+        let mut end = self.remaining;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.f.write_str(&s[..end])?;
+        self.remaining = 0;
+        self.cut_short = true;
+        Err(fmt::Error)
+    }
+}
+
+fn write_bounded(cbor: &CBOR, limits: &DisplayLimits, depth: usize, w: &mut LimitedWriter<'_, '_>) -> fmt::Result {
+    use fmt::Write;
+
+    let nested = depth >= limits.max_depth;
+    match cbor.as_case() {
+        CBORCase::ByteString(bytes) => {
+            w.write_str("h'")?;
+            // Two hex digits per byte, so no more than this can be shown.
+            let shown = bytes.len().min(w.remaining / 2 + 1);
+            w.write_str(&hex_encode(&bytes.data()[..shown]))?;
+            w.write_str("'")
+        },
+        CBORCase::Text(text) => {
+            w.write_str("\"")?;
+            for (i, part) in text.split('"').enumerate() {
+                if i > 0 {
+                    w.write_str("\\\"")?;
+                }
+                w.write_str(part)?;
+            }
+            w.write_str("\"")
+        },
+        CBORCase::Array(_) | CBORCase::Map(_) if nested => {
+            w.write_str(if matches!(cbor.as_case(), CBORCase::Array(_)) { "[…]" } else { "{…}" })
+        },
+        CBORCase::Array(items) => {
+            w.write_str("[")?;
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    w.write_str(", ")?;
+                }
+                if i == limits.max_items {
+                    w.write_str("…")?;
+                    break;
+                }
+                write_bounded(item, limits, depth + 1, w)?;
+            }
+            w.write_str("]")
+        },
+        CBORCase::Map(map) => {
+            w.write_str("{")?;
+            for (i, (key, value)) in map.iter().enumerate() {
+                if i > 0 {
+                    w.write_str(", ")?;
+                }
+                if i == limits.max_items {
+                    w.write_str("…")?;
+                    break;
+                }
+                write_bounded(key, limits, depth + 1, w)?;
+                w.write_str(": ")?;
+                write_bounded(value, limits, depth + 1, w)?;
+            }
+            w.write_str("}")
+        },
+        CBORCase::Tagged(tag, item) => {
+            write!(w, "{}(", tag)?;
+            if nested {
+                w.write_str("…")?;
+            } else {
+                write_bounded(item, limits, depth + 1, w)?;
+            }
+            w.write_str(")")
+        },
+        _ => write!(w, "{}", cbor),
+    }
+}
@@ -1,26 +1,30 @@
 import_stdlib!();
 
-use crate::{tags_store::TagsStoreTrait, with_tags, CBORCase, CBOR};
+use crate::{diag::tagged_summary, tags_store::TagsStoreTrait, with_tags, CBORCase, DiagFormatOpts, CBOR, TAG_ENCODED_CBOR};
 
-use super::{string_util::{sanitized, flanked}, varint::{EncodeVarInt, MajorType}};
+use super::{string_util::{sanitized, flanked, from_utf8, hex_encode}, varint::{EncodeVarInt, MajorType}};
 
 /// Affordances for viewing the encoded binary representation of CBOR as hexadecimal.
 impl CBOR {
-    /// Returns the encoded hexadecimal representation of this CBOR.
-    pub fn hex(&self) -> String {
-        hex::encode(self.to_cbor_data())
-    }
-
     /// Returns the encoded hexadecimal representation of this CBOR.
     ///
     /// Optionally annotates the output, e.g. breaking the output up into
-    /// semantically meaningful lines, formatting dates, and adding names of
-    /// known tags.
+    /// semantically meaningful lines and adding names of known tags.
     pub fn hex_opt(&self, annotate: bool, tags: Option<&dyn TagsStoreTrait>) -> String {
-        if !annotate {
+        self.hex_with_opts(&DiagFormatOpts::default().annotate(annotate).tags(tags))
+    }
+
+    /// Returns the encoded hexadecimal representation of this CBOR, annotated
+    /// if [`DiagFormatOpts::annotate`] is set.
+    ///
+    /// Tags are named using the options' tags store, and with
+    /// [`DiagFormatOpts::summarize`], tagged values are summarized beside
+    /// their tags, e.g. dates in the [`DiagFormatOpts::date_format`].
+    pub fn hex_with_opts(&self, opts: &DiagFormatOpts<'_>) -> String {
+        if !opts.annotate {
             return self.hex()
         }
-        let items = self.dump_items(0, tags);
+        let items = self.dump_lines(0, opts);
         let note_column = items.iter().fold(0, |largest, item| {
             largest.max(item.format_first_column().len())
         });
@@ -37,56 +41,76 @@ impl CBOR {
         })
     }
 
-    fn dump_items(&self, level: usize, tags: Option<&dyn TagsStoreTrait>) -> Vec<DumpItem> {
+    /// Returns the items of the annotated hex dump, naming tags using the
+    /// global tags store.
+    ///
+    /// This is the data from which [`CBOR::hex_annotated`] is formatted, so
+    /// tools can present it in other ways, such as an interactive hex view.
+    pub fn dump_items(&self) -> Vec<DumpItem> {
+        with_tags!(|tags: &dyn TagsStoreTrait| {
+            self.dump_items_opt(Some(tags))
+        })
+    }
+
+    /// Returns the items of the annotated hex dump, naming tags using the
+    /// given tags store, if any.
+    pub fn dump_items_opt(&self, tags: Option<&dyn TagsStoreTrait>) -> Vec<DumpItem> {
+        self.dump_items_with_opts(&DiagFormatOpts::default().tags(tags))
+    }
+
+    /// Returns the items of the annotated hex dump formatted by
+    /// [`CBOR::hex_with_opts`].
+    pub fn dump_items_with_opts(&self, opts: &DiagFormatOpts<'_>) -> Vec<DumpItem> {
+        let mut offset = 0;
+        self.dump_lines(0, opts).into_iter().map(|line| {
+            let bytes = line.data.concat();
+            let item = DumpItem {
+                offset,
+                bytes,
+                depth: line.level,
+                description: line.note,
+                tag_name: line.tag_name,
+                summary: line.summary,
+            };
+            offset += item.bytes.len();
+            item
+        }).collect()
+    }
+
+    fn dump_lines(&self, level: usize, opts: &DiagFormatOpts<'_>) -> Vec<DumpLine> {
         match self.as_case() {
-            CBORCase::Unsigned(n) => vec!(DumpItem::new(level, vec!(self.to_cbor_data()), Some(format!("unsigned({})", n)))),
-            CBORCase::Negative(n) => vec!(DumpItem::new(level, vec!(self.to_cbor_data()), Some(format!("negative({})", -1 - (*n as i128))))),
-            CBORCase::ByteString(d) => {
-                let mut items = vec![
-                    DumpItem::new(level, vec!(d.len().encode_varint(MajorType::ByteString)), Some(format!("bytes({})", d.len())))
-                ];
-                if !d.is_empty() {
-                    let mut note: Option<String> = None;
-                    if let Ok(a) = str::from_utf8(d) {
-                        if let Some(b) = sanitized(a) {
-                            note = Some(flanked(&b, "\"", "\""));
-                        }
-                    }
-                    items.push(DumpItem::new(level + 1, vec!(d.to_vec()), note));
-                }
-                items
-            },
+            CBORCase::Unsigned(n) => vec!(DumpLine::new(level, vec!(self.encoded()), Some(format!("unsigned({})", n)))),
+            CBORCase::Negative(n) => vec!(DumpLine::new(level, vec!(self.encoded()), Some(format!("negative({})", -1 - (*n as i128))))),
+            CBORCase::ByteString(d) => byte_string_lines(level, d, false),
             CBORCase::Text(s) => {
                 let header = s.len().encode_varint(MajorType::Text);
                 let header_data = vec![vec!(header[0]), header[1..].to_vec()];
                 let utf8_data = s.as_bytes().to_vec();
                 vec![
-                    DumpItem::new(level, header_data, Some(format!("text({})", utf8_data.len()))),
-                    DumpItem::new(level + 1, vec![utf8_data], Some(flanked(s, "\"", "\"")))
+                    DumpLine::new(level, header_data, Some(format!("text({})", utf8_data.len()))),
+                    DumpLine::new(level + 1, vec![utf8_data], Some(flanked(s, "\"", "\"")))
                 ]
             },
             CBORCase::Simple(v) => {
                 let data = v.cbor_data();
                 let note = format!("{}", v);
                 vec![
-                    DumpItem::new(level, vec![data], Some(note))
+                    DumpLine::new(level, vec![data], Some(note))
                 ]
             },
             CBORCase::Tagged(tag, item) => {
                 let header = tag.value().encode_varint(MajorType::Tagged);
                 let header_data = vec![vec!(header[0]), header[1..].to_vec()];
-                let mut note_components: Vec<String> = vec![format!("tag({})", tag.value())];
-                if let Some(tags) = tags {
-                    if let Some(name) = tags.assigned_name_for_tag(tag) {
-                        note_components.push(name);
-                    }
-                }
-                let tag_note = note_components.join(" ");
+                let mut line = DumpLine::new(level, header_data, Some(format!("tag({})", tag.value())));
+                line.tag_name = opts.tags.and_then(|tags| tags.assigned_name_for_tag(tag));
+                line.summary = tagged_summary(tag, item, opts);
+                let item_lines = match item.as_case() {
+                    CBORCase::ByteString(d) if tag.value() == TAG_ENCODED_CBOR => byte_string_lines(level + 1, d, true),
+                    _ => item.dump_lines(level + 1, opts),
+                };
                 vec![
-                    vec![
-                        DumpItem::new(level, header_data, Some(tag_note))
-                    ],
-                    item.dump_items(level + 1, tags)
+                    vec![line],
+                    item_lines
                 ].into_iter().flatten().collect()
             },
             CBORCase::Array(array) => {
@@ -94,9 +118,9 @@ impl CBOR {
                 let header_data = vec![vec!(header[0]), header[1..].to_vec()];
                 vec![
                     vec![
-                        DumpItem::new(level, header_data, Some(format!("array({})", array.len())))
+                        DumpLine::new(level, header_data, Some(format!("array({})", array.len())))
                     ],
-                    array.iter().flat_map(|x| x.dump_items(level + 1, tags)).collect()
+                    array.iter().flat_map(|x| x.dump_lines(level + 1, opts)).collect()
                 ].into_iter().flatten().collect()
             },
             CBORCase::Map(m) => {
@@ -104,13 +128,13 @@ impl CBOR {
                 let header_data = vec![vec!(header[0]), header[1..].to_vec()];
                 vec![
                     vec![
-                        DumpItem::new(level, header_data, Some(format!("map({})", m.len())))
+                        DumpLine::new(level, header_data, Some(format!("map({})", m.len())))
                     ],
                     m.iter().flat_map(|x| {
                         vec![
-                            x.0.dump_items(level + 1, tags),
-                            x.1.dump_items(level + 1, tags)
-                        ].into_iter().flatten().collect::<Vec<DumpItem>>()
+                            x.0.dump_lines(level + 1, opts),
+                            x.1.dump_lines(level + 1, opts)
+                        ].into_iter().flatten().collect::<Vec<DumpLine>>()
                     }).collect()
                 ].into_iter().flatten().collect()
             },
@@ -118,22 +142,80 @@ impl CBOR {
     }
 }
 
+/// Returns the lines of a byte string, noting its content as embedded dCBOR
+/// if `decode_embedded` is set and it decodes as such, or otherwise as text if
+/// it is UTF-8.
+fn byte_string_lines(level: usize, d: &[u8], decode_embedded: bool) -> Vec<DumpLine> {
+    let mut items = vec![
+        DumpLine::new(level, vec!(d.len().encode_varint(MajorType::ByteString)), Some(format!("bytes({})", d.len())))
+    ];
+    if !d.is_empty() {
+        let mut note: Option<String> = None;
+        if decode_embedded {
+            if let Ok(cbor) = CBOR::try_from_data(d) {
+                note = Some(flanked(&cbor.diagnostic_opt(false, false, true, None), "<<", ">>"));
+            }
+        }
+        if note.is_none() {
+            if let Ok(a) = from_utf8(d) {
+                if let Some(b) = sanitized(a) {
+                    note = Some(flanked(&b, "\"", "\""));
+                }
+            }
+        }
+        items.push(DumpLine::new(level + 1, vec!(d.to_vec()), note));
+    }
+    items
+}
+
+/// An item of the annotated hex dump of CBOR, as returned by
+/// [`CBOR::dump_items`].
+///
+/// Each item covers a contiguous run of the encoded bytes, such as the header
+/// of an array or the content of a byte string. Items are in encoding order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DumpItem {
+    /// The offset of the item's bytes in the encoded CBOR.
+    pub offset: usize,
+    /// The item's bytes.
+    pub bytes: Vec<u8>,
+    /// The nesting depth of the item, with the top level at zero.
+    pub depth: usize,
+    /// A description of the item, e.g. `array(2)` or `unsigned(1)`.
+    pub description: Option<String>,
+    /// The name of the tag, if the item is a tag header and the tag is known.
+    pub tag_name: Option<String>,
+    /// The summary of the tagged value, if the item is a tag header and
+    /// summaries were requested, e.g. the date of tag 1.
+    pub summary: Option<String>,
+}
+
 #[derive(Debug)]
-struct DumpItem {
+struct DumpLine {
     level: usize,
     data: Vec<Vec<u8>>,
     note: Option<String>,
+    tag_name: Option<String>,
+    summary: Option<String>,
 }
 
-impl DumpItem {
-    fn new(level: usize, data: Vec<Vec<u8>>, note: Option<String>) -> DumpItem {
-        DumpItem { level, data, note }
+impl DumpLine {
+    fn new(level: usize, data: Vec<Vec<u8>>, note: Option<String>) -> DumpLine {
+        DumpLine { level, data, note, tag_name: None, summary: None }
     }
 
     fn format(&self, note_column: usize) -> String {
         let column_1 = self.format_first_column();
+        let note = match (&self.note, &self.tag_name) {
+            (Some(note), Some(tag_name)) => Some(format!("{} {}", note, tag_name)),
+            (note, _) => note.clone(),
+        };
+        let note = match (note, &self.summary) {
+            (Some(note), Some(summary)) => Some(format!("{}: {}", note, summary)),
+            (note, _) => note,
+        };
         let (column_2, padding) = {
-            if let Some(note) = &self.note {
+            if let Some(note) = &note {
                 let padding_count = 1.max(39.min(note_column as i64) - (column_1.len() as i64) + 1);
                 let padding = " ".repeat(padding_count.try_into().unwrap());
                 let column_2 = format!("# {}", note);
@@ -148,7 +230,7 @@ impl DumpItem {
     fn format_first_column(&self) -> String {
         let indent = " ".repeat(self.level * 4);
         let hex: Vec<_> = self.data.iter()
-            .map(hex::encode)
+            .map(|data| hex_encode(data))
             .filter(|x| !x.is_empty())
             .collect();
         let hex = hex.join(" ");
@@ -0,0 +1,222 @@
+import_stdlib!();
+
+use anyhow::{bail, Result};
+
+#[cfg(feature = "float")]
+use crate::{error::{with_path, within}, float::canonical_float_case, PathElement, Simple};
+use crate::{text::{is_nfc, to_nfc, NfcPolicy}, varint::{EncodeVarInt, MajorType}, CBORCase, CBORError, CBOR};
+
+/// Options controlling how CBOR is encoded.
+///
+/// Options are set using the builder-style methods, starting from
+/// `EncodeOptions::default()`.
+#[derive(Debug, Clone, Default)]
+pub struct EncodeOptions {
+    nfc_policy: NfcPolicy,
+    reject_nan: bool,
+    reject_infinity: bool,
+}
+
+impl EncodeOptions {
+    /// How text strings not in Unicode Normalization Form C are handled.
+    /// Defaults to [`NfcPolicy::Normalize`].
+    pub fn nfc_policy(mut self, nfc_policy: NfcPolicy) -> Self {
+        self.nfc_policy = nfc_policy;
+        self
+    }
+
+    /// Rejects NaN, failing encoding with [`CBORError::NonFiniteFloat`] and
+    /// the [`ValuePath`](crate::ValuePath) of the offending value attached.
+    ///
+    /// This suits application profiles, such as financial ones, that forbid
+    /// non-finite values. It is independent of canonicalization: without it,
+    /// NaN is encoded as the canonical NaN. Negative zero needs no option, as
+    /// dCBOR encodes it as the integer 0.
+    pub fn reject_nan(mut self, reject_nan: bool) -> Self {
+        self.reject_nan = reject_nan;
+        self
+    }
+
+    /// Rejects positive and negative infinity, failing encoding as for
+    /// [`EncodeOptions::reject_nan`].
+    pub fn reject_infinity(mut self, reject_infinity: bool) -> Self {
+        self.reject_infinity = reject_infinity;
+        self
+    }
+}
+
+/// A destination for the bytes of an encoding, which are written in order as
+/// they are produced.
+///
+/// This is implemented for `Vec<u8>`, and for closures taking `&[u8]`, so
+/// that the bytes can be fed to a hasher, e.g. `|data: &[u8]|
+/// hasher.update(data)`.
+pub trait EncodeSink {
+    /// Writes the next bytes of the encoding.
+    fn write(&mut self, data: &[u8]);
+}
+
+impl EncodeSink for Vec<u8> {
+    fn write(&mut self, data: &[u8]) {
+        self.extend_from_slice(data);
+    }
+}
+
+impl<F: FnMut(&[u8])> EncodeSink for F {
+    fn write(&mut self, data: &[u8]) {
+        self(data)
+    }
+}
+
+/// A sink that writes each byte of an encoding to both an output and a
+/// hasher, so a digest or MAC of a large encoding is computed in the same pass
+/// that produces it.
+///
+/// ```
+/// # use dcbor::{prelude::*, HashingSink};
+/// let mut checksum = 0u8;
+/// let mut sink = HashingSink::new(Vec::new(), |data: &[u8]| {
+///     checksum = data.iter().fold(checksum, |sum, byte| sum.wrapping_add(*byte));
+/// });
+/// CBOR::from(vec![1, 2, 3]).encode_to(&mut sink);
+/// let (output, _) = sink.into_parts();
+/// assert_eq!(output, [0x83, 0x01, 0x02, 0x03]);
+/// assert_eq!(checksum, 0x89);
+/// ```
+#[derive(Debug)]
+pub struct HashingSink<O, H> {
+    output: O,
+    hasher: H,
+}
+
+impl<O: EncodeSink, H: EncodeSink> HashingSink<O, H> {
+    /// Makes a sink writing to the given output and hasher.
+    pub fn new(output: O, hasher: H) -> Self {
+        Self { output, hasher }
+    }
+
+    /// Returns the output written so far.
+    pub fn output(&self) -> &O {
+        &self.output
+    }
+
+    /// Returns the output and the hasher.
+    pub fn into_parts(self) -> (O, H) {
+        (self.output, self.hasher)
+    }
+}
+
+impl<O: EncodeSink, H: EncodeSink> EncodeSink for HashingSink<O, H> {
+    fn write(&mut self, data: &[u8]) {
+        self.output.write(data);
+        self.hasher.write(data);
+    }
+}
+
+impl CBOR {
+    /// Writes the encoding of this CBOR to the sink, a piece at a time.
+    ///
+    /// This writes the same bytes as [`CBOR::to_cbor_data`] returns, but
+    /// without holding the whole encoding in memory. The contents of byte
+    /// strings are written without being copied.
+    pub fn encode_to(&self, sink: &mut impl EncodeSink) {
+        self.encode_to_dyn(sink)
+    }
+
+    pub(crate) fn encode_to_dyn(&self, sink: &mut dyn EncodeSink) {
+        #[cfg(feature = "encoding-cache")]
+        if let Some(encoding) = self.cached_encoding() {
+            sink.write(encoding);
+            return;
+        }
+        match self.as_case() {
+            CBORCase::ByteString(x) => {
+                sink.write(&x.len().encode_varint(MajorType::ByteString));
+                sink.write(x.data());
+            },
+            CBORCase::Text(x) => {
+                let nfc = to_nfc(x);
+                sink.write(&nfc.len().encode_varint(MajorType::Text));
+                sink.write(nfc.as_bytes());
+            },
+            CBORCase::Array(x) => {
+                sink.write(&x.len().encode_varint(MajorType::Array));
+                for item in x {
+                    item.encode_to_dyn(sink);
+                }
+            },
+            CBORCase::Map(x) => x.encode_to(sink),
+            CBORCase::Tagged(tag, item) => {
+                sink.write(&tag.value().encode_varint(MajorType::Tagged));
+                item.encode_to_dyn(sink);
+            },
+            _ => sink.write(&self.encoded()),
+        }
+    }
+
+    /// Encodes this CBOR into binary representation, using the given options.
+    ///
+    /// Returns an error if the value cannot be encoded under the options,
+    /// e.g. it contains a string that is not in NFC and the policy is
+    /// [`NfcPolicy::Reject`].
+    pub fn try_to_cbor_data_opt(&self, options: &EncodeOptions) -> Result<Vec<u8>> {
+        if options.nfc_policy == NfcPolicy::Reject && !self.non_nfc_strings().is_empty() {
+            bail!(CBORError::NonCanonicalString);
+        }
+        #[cfg(feature = "float")]
+        if options.reject_nan || options.reject_infinity {
+            self.check_floats(options)?;
+        }
+        Ok(self.to_cbor_data())
+    }
+
+    #[cfg(feature = "float")]
+    fn check_floats(&self, options: &EncodeOptions) -> Result<()> {
+        match self.as_case() {
+            CBORCase::Simple(Simple::Float(f)) if (options.reject_nan && f.is_nan()) || (options.reject_infinity && f.is_infinite()) => {
+                return Err(with_path(CBORError::NonFiniteFloat));
+            },
+            CBORCase::Array(array) => {
+                for (index, item) in array.iter().enumerate() {
+                    item.check_floats(options).map_err(|error| within(error, || PathElement::Index(index)))?;
+                }
+            },
+            CBORCase::Map(map) => {
+                for (key, value) in map.iter() {
+                    key.check_floats(options)?;
+                    value.check_floats(options).map_err(|error| within(error, || PathElement::Key(key.clone())))?;
+                }
+            },
+            CBORCase::Tagged(_, item) => item.check_floats(options)?,
+            _ => {},
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if this value is held in canonical form, so that
+    /// decoding its encoding yields an identical value.
+    ///
+    /// Values are canonicalized as they are constructed, except that text
+    /// strings are only normalized to NFC when encoded. This returns `false`
+    /// if any float is held in a non-canonical form or any text string is
+    /// not in NFC.
+    pub fn is_canonical(&self) -> bool {
+        match self.as_case() {
+            #[cfg(feature = "float")]
+            CBORCase::Simple(Simple::Float(n)) => {
+                matches!(canonical_float_case(*n), CBORCase::Simple(Simple::Float(c)) if c.to_bits() == n.to_bits())
+            },
+            CBORCase::Text(s) => is_nfc(s),
+            CBORCase::Array(array) => array.iter().all(CBOR::is_canonical),
+            CBORCase::Map(map) => map.iter().all(|(key, value)| key.is_canonical() && value.is_canonical()),
+            CBORCase::Tagged(_, item) => item.is_canonical(),
+            _ => true,
+        }
+    }
+
+    /// Panics in debug builds if this value is not held in canonical form, as
+    /// determined by [`CBOR::is_canonical`].
+    pub fn debug_assert_canonical(&self) {
+        debug_assert!(self.is_canonical(), "CBOR value is not canonical: {:?}", self);
+    }
+}
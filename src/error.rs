@@ -1,6 +1,6 @@
 import_stdlib!();
 
-use crate::tag::Tag;
+use crate::{tag::{Tag, TagValue}, FieldKind, PathElement, TagRange};
 
 /// An error encountered while decoding or parsing CBOR.
 #[derive(Debug, ThisError)]
@@ -17,12 +17,27 @@ pub enum CBORError {
     #[error("an invalid CBOR simple value was encountered")]
     InvalidSimpleValue,
 
+    #[error("a non-finite floating point value (NaN or infinity) was encountered")]
+    NonFiniteFloat,
+
+    #[error("a floating point value was encountered, but floats are not supported")]
+    UnsupportedFloat,
+
     #[error("an invalidly-encoded UTF-8 string was encountered in the CBOR ({0:?})")]
-    InvalidString(str::Utf8Error),
+    InvalidString(#[source] str::Utf8Error),
 
     #[error("a CBOR string was not encoded in Unicode Canonical Normalization Form C")]
     NonCanonicalString,
 
+    #[error("a CBOR text string exceeded the maximum permitted length")]
+    TextTooLong,
+
+    #[error("the decoded CBOR exceeded the memory budget")]
+    MemoryBudgetExceeded,
+
+    #[error("the decoded CBOR is nested more deeply than the limit")]
+    DepthLimitExceeded,
+
     #[error("the decoded CBOR had {0} extra bytes at the end")]
     UnusedData(usize),
 
@@ -32,17 +47,351 @@ pub enum CBORError {
     #[error("the decoded CBOR map has a duplicate key")]
     DuplicateMapKey,
 
+    #[error("the CBOR map has a floating point key")]
+    FloatMapKey,
+
+    #[error("the CBOR map has a NaN key")]
+    NanMapKey,
+
     #[error("missing CBOR map key")]
     MissingMapKey,
 
+    #[error("field {0} missing")]
+    MissingField(u64),
+
+    #[error("field {0}: {1}")]
+    InvalidField(u64, #[source] anyhow::Error),
+
+    #[error("invalid fields: {}", problem_list(.0))]
+    InvalidFields(Vec<FieldProblem>),
+
     #[error("the CBOR numeric value could not be represented in the specified numeric type")]
     OutOfRange,
 
     #[error("the decoded CBOR value was not the expected type")]
     WrongType,
 
+    #[error("expected a CBOR array of {0} elements, but got {1}")]
+    WrongArrayLength(usize, usize),
+
+    #[error("the CBOR value does not correspond to a known enum variant")]
+    UnknownVariant,
+
     #[error("expected CBOR tag {0}, but got {1}")]
     WrongTag(Tag, Tag),
+
+    #[error("expected CBOR tag {}, but got {1}", tag_list(.0))]
+    WrongTagOf(Vec<Tag>, Tag),
+
+    #[error("tag {0} is already registered as {1:?}, and cannot also be registered as {2:?}")]
+    TagValueConflict(TagValue, String, String),
+
+    #[error("tag name {0:?} is already registered for tag {1}, and cannot also be registered for tag {2}")]
+    TagNameConflict(String, TagValue, TagValue),
+
+    #[error("tag {0} already has a different summarizer")]
+    SummarizerConflict(TagValue),
+
+    #[error("tag {0} is in the {1} range of tag values")]
+    ReservedTagValue(TagValue, TagRange),
+
+    #[error("tag {0} has no name")]
+    MissingTagName(TagValue),
+
+    #[error("invalid tags CSV on line {0}: {1}")]
+    InvalidTagsCsv(usize, String),
+
+    #[error("no tag values are left to allocate")]
+    TagRangeExhausted,
+
+    #[error("tag {0} cannot be applied to this value: {1}")]
+    InvalidTaggedValue(TagValue, String),
+
+    #[error("invalid JSON at offset {0}: {1}")]
+    InvalidJson(usize, String),
+
+    #[error("invalid path pattern at offset {0}: {1}")]
+    InvalidPathPattern(usize, String),
+
+    #[error("invalid decimal number: {0:?}")]
+    InvalidDecimal(String),
+
+    #[error("the CBOR value cannot be represented as canonical JSON: {0}")]
+    NotJson(String),
+
+    #[error("the CBOR value cannot be represented as a plain JavaScript value: {0}")]
+    NotJsValue(String),
+
+    #[error("invalid token sequence: {0}")]
+    InvalidTokenSequence(String),
+
+    #[error("invalid compact debug text at offset {0}: {1}")]
+    InvalidDebugCompact(usize, String),
+
+    #[error("the byte string chunk at position {0} is malformed or inconsistent with the other chunks")]
+    InvalidChunk(usize),
+
+    #[error("byte string chunk {0} is missing")]
+    MissingChunk(usize),
+
+    #[error("the chunk size must be greater than zero")]
+    InvalidChunkSize,
+
+    #[error("invalid hex at position {position}: {}", hex_reason(.source))]
+    InvalidHex { position: usize, #[source] source: hex::FromHexError },
+
+    #[error("invalid base64")]
+    InvalidBase64,
+
+    #[error("invalid base45")]
+    InvalidBase45,
+
+    #[error("invalid bytewords: {0}")]
+    InvalidBytewords(String),
+
+    #[error("invalid UR: {0}")]
+    InvalidUR(String),
+
+    #[error("unknown CBOR tag name: {0}")]
+    UnknownTagName(String),
+
+    #[error("no decoder is registered for CBOR tag {0}")]
+    NoDecoderForTag(Tag),
+
+    /// A failure reported by code outside this crate, such as a `TryFrom<CBOR>`
+    /// implementation. Create it with [`CBORError::custom`].
+    #[error("{0}")]
+    Custom(String),
+}
+
+impl CBORError {
+    /// Returns a [`CBORError::Custom`] error with the given message.
+    ///
+    /// This lets implementations of `TryFrom<CBOR>` and the other decoding
+    /// traits report their own failures, rather than using a variant such as
+    /// [`CBORError::WrongType`] that doesn't describe them.
+    pub fn custom(message: impl fmt::Display) -> Self {
+        CBORError::Custom(message.to_string())
+    }
+
+    /// Converts this error into an [`anyhow::Error`] that describes it with
+    /// the given context.
+    ///
+    /// Further context can be added with `anyhow::Context`, the
+    /// `CBORError` can still be retrieved with `downcast_ref::<CBORError>()`,
+    /// and its source, if any, remains in the error's `chain()`.
+    pub fn context<C>(self, context: C) -> anyhow::Error
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        anyhow::anyhow!(self).context(context)
+    }
+}
+
+/// A problem with one field of a map, as reported by
+/// [`CBORError::InvalidFields`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldProblem {
+    /// The map has no entry for the field with this name.
+    Missing(String),
+    /// The value of the field with this name is not of the expected kind.
+    WrongKind(String, FieldKind),
+    /// This key, in diagnostic notation, is not an integer that can be
+    /// allowed.
+    NotInteger(String),
+    /// This integer key is outside the reserved ranges.
+    NotReserved(i64),
+    /// This integer key is critical, but not understood.
+    NotUnderstood(i64),
+}
+
+impl fmt::Display for FieldProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldProblem::Missing(name) => write!(f, "field {:?} missing", name),
+            FieldProblem::WrongKind(name, kind) => write!(f, "field {:?}: expected {}", name, kind),
+            FieldProblem::NotInteger(key) => write!(f, "key {}: not an allowed integer", key),
+            FieldProblem::NotReserved(key) => write!(f, "key {}: not in a reserved range", key),
+            FieldProblem::NotUnderstood(key) => write!(f, "key {}: critical but not understood", key),
+        }
+    }
+}
+
+/// The offset of the byte in the input at which decoding failed.
+///
+/// This is attached to the errors returned when decoding CBOR data, beneath
+/// the [`CBORError`], so it can be retrieved with
+/// [`DisplayWithInput::decode_offset`] or `downcast_ref::<DecodeOffset>()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeOffset(pub usize);
+
+impl fmt::Display for DecodeOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at offset {}", self.0)
+    }
+}
+
+/// The path from the root of a CBOR tree to the value for which encoding or
+/// decoding failed, such as the float rejected by
+/// [`EncodeOptions::reject_nan`](crate::EncodeOptions::reject_nan).
+///
+/// This is attached to the error beneath the [`CBORError`], in the same way as
+/// [`DecodeOffset`], so it can be retrieved with
+/// `downcast_ref::<ValuePath>()`. It holds the path in the style of JSONPath,
+/// e.g. `$[2]["amount"]`, with map keys in diagnostic notation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValuePath(pub String);
+
+impl fmt::Display for ValuePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at {}", self.0)
+    }
+}
+
+/// Attaches the root [`ValuePath`] beneath the error, to be extended with
+/// [`within`] as it is returned from each enclosing array or map.
+pub(crate) fn with_path(error: CBORError) -> anyhow::Error {
+    with_details(error, Some(ValuePath("$".to_string())), None)
+}
+
+/// Attaches the path and offset beneath the error, followed by a copy of its
+/// source, so that the error's `chain()` still leads to the source.
+///
+/// An error whose source cannot be copied is returned without them, keeping
+/// its source.
+pub(crate) fn with_details(error: CBORError, path: Option<ValuePath>, offset: Option<DecodeOffset>) -> anyhow::Error {
+    if path.is_none() && offset.is_none() {
+        return anyhow::anyhow!(error);
+    }
+    let source = match &error {
+        CBORError::InvalidString(source) => Some(anyhow::anyhow!(*source)),
+        CBORError::InvalidHex { source, .. } => Some(anyhow::anyhow!(*source)),
+        CBORError::InvalidField(..) => return anyhow::anyhow!(error),
+        _ => None,
+    };
+    let beneath = attach(source, offset);
+    let beneath = attach(beneath, path);
+    match beneath {
+        Some(beneath) => beneath.context(error),
+        None => anyhow::anyhow!(error),
+    }
+}
+
+/// Attaches the detail, if any, above the error, or makes it the error if
+/// there is none.
+fn attach<C>(error: Option<anyhow::Error>, detail: Option<C>) -> Option<anyhow::Error>
+where
+    C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+{
+    match (error, detail) {
+        (Some(error), Some(detail)) => Some(error.context(detail)),
+        (None, Some(detail)) => Some(anyhow::Error::msg(detail)),
+        (error, None) => error,
+    }
+}
+
+/// Prepends the element to the [`ValuePath`] attached to the error, if any.
+pub(crate) fn within(mut error: anyhow::Error, element: impl FnOnce() -> PathElement) -> anyhow::Error {
+    if let Some(path) = error.downcast_mut::<ValuePath>() {
+        let step = match element() {
+            PathElement::Index(index) => format!("[{}]", index),
+            PathElement::Key(key) => format!("[{}]", key.display_for_error()),
+        };
+        path.0.insert_str(1, &step);
+    }
+    error
+}
+
+/// Formatting of errors together with the input they were returned for.
+pub trait DisplayWithInput {
+    /// Returns the offset of the byte in the input at which decoding failed,
+    /// if this error was returned when decoding CBOR data.
+    fn decode_offset(&self) -> Option<usize>;
+
+    /// Returns a description of this error, followed by the hex of the input
+    /// around the offset at which decoding failed, with a caret marking the
+    /// offending byte.
+    ///
+    /// For example:
+    ///
+    /// ```text
+    /// error: a CBOR numeric value was encoded in non-canonical form
+    ///  --> offset 2
+    ///   |
+    /// 0 | 82 01 19 00 05
+    ///   |       ^^
+    /// ```
+    ///
+    /// Errors without an offset are described on one line.
+    fn display_with_input(&self, data: &[u8]) -> String;
+}
+
+impl DisplayWithInput for anyhow::Error {
+    fn decode_offset(&self) -> Option<usize> {
+        self.downcast_ref::<DecodeOffset>().map(|offset| offset.0)
+    }
+
+    fn display_with_input(&self, data: &[u8]) -> String {
+        let Some(offset) = self.decode_offset() else {
+            return format!("error: {:#}", self);
+        };
+        const ROW_LEN: usize = 16;
+        let row = offset / ROW_LEN;
+        let rows = row.saturating_sub(1)..=row;
+        let gutter = (row * ROW_LEN).to_string().len();
+        let mut lines = vec![
+            format!("error: {}", self),
+            format!("{} --> offset {}", " ".repeat(gutter - 1), offset),
+            format!("{} |", " ".repeat(gutter)),
+        ];
+        for row in rows {
+            let start = row * ROW_LEN;
+            let end = (start + ROW_LEN).min(data.len());
+            let bytes = data.get(start..end).unwrap_or_default();
+            let hex: Vec<_> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+            lines.push(format!("{:>gutter$} | {}", start, hex.join(" ")).trim_end().to_string());
+        }
+        let caret = if offset < data.len() { "^^" } else { "^" };
+        lines.push(format!("{} | {}{}", " ".repeat(gutter), " ".repeat(offset % ROW_LEN * 3), caret));
+        lines.join("\n")
+    }
+}
+
+/// Decodes hexadecimal, reporting failures as [`CBORError::InvalidHex`].
+pub(crate) fn decode_hex(hex: &str) -> Result<Vec<u8>, CBORError> {
+    // The fast decoder doesn't describe the error, so invalid hex is decoded
+    // again to find it.
+    #[cfg(feature = "simd")]
+    if hex.len().is_multiple_of(2) {
+        let mut data = vec![0; hex.len() / 2];
+        if faster_hex::hex_decode(hex.as_bytes(), &mut data).is_ok() {
+            return Ok(data);
+        }
+    }
+    hex::decode(hex).map_err(|source| {
+        let position = match source {
+            hex::FromHexError::InvalidHexCharacter { index, .. } => index,
+            hex::FromHexError::OddLength => hex.len(),
+            hex::FromHexError::InvalidStringLength => 0,
+        };
+        CBORError::InvalidHex { position, source }
+    })
+}
+
+fn tag_list(tags: &[Tag]) -> String {
+    tags.iter().map(Tag::to_string).collect::<Vec<_>>().join(" or ")
+}
+
+pub(crate) fn hex_reason(error: &hex::FromHexError) -> String {
+    match error {
+        hex::FromHexError::InvalidHexCharacter { c, .. } => format!("invalid character {:?}", c),
+        hex::FromHexError::OddLength => "odd number of digits".to_string(),
+        hex::FromHexError::InvalidStringLength => "invalid length".to_string(),
+    }
+}
+
+fn problem_list(problems: &[FieldProblem]) -> String {
+    problems.iter().map(FieldProblem::to_string).collect::<Vec<_>>().join("; ")
 }
 
 impl From<str::Utf8Error> for CBORError {
@@ -50,3 +399,10 @@ impl From<str::Utf8Error> for CBORError {
         CBORError::InvalidString(err)
     }
 }
+
+#[cfg(feature = "std")]
+impl From<CBORError> for std::io::Error {
+    fn from(err: CBORError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+    }
+}
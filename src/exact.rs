@@ -9,6 +9,7 @@
 //! $ python3 ./utils/gyb.py -DCMAKE_SIZEOF_VOID_P=8 --line-directive '' -o output_file.swift stdlib/public/core/IntegerTypes.swift.gyb
 //! ```
 
+#[cfg(feature = "float")]
 use half::f16;
 
 pub trait ExactFrom {
@@ -24,6 +25,7 @@ pub trait ExactFrom {
     ///
     /// - Parameter source: The value to convert.
     #[allow(dead_code)]
+    #[cfg(feature = "float")]
     fn exact_from_f16(source: f16) -> Option<Self> where Self: Sized;
 
     /// Creates a target numeric value from the given `f32`, if it can be represented exactly.
@@ -36,6 +38,7 @@ pub trait ExactFrom {
     ///    assert_eq!(i64::exact_from_f32(21.5f32), None);
     ///
     /// - Parameter source: The value to convert.
+    #[cfg(feature = "float")]
     fn exact_from_f32(source: f32) -> Option<Self> where Self: Sized;
 
     /// Creates a target numeric value from the given `f64`, if it can be represented exactly.
@@ -48,6 +51,7 @@ pub trait ExactFrom {
     ///    assert_eq!(i64::exact_from_f64(21.5), None);
     ///
     /// - Parameter source: The value to convert.
+    #[cfg(feature = "float")]
     fn exact_from_f64(source: f64) -> Option<Self> where Self: Sized;
 
     /// Creates a target numeric value from the given `u64`, if it can be represented exactly.
@@ -86,7 +90,65 @@ pub trait ExactFrom {
     fn exact_from_i128(source: i128) -> Option<Self> where Self: Sized;
 }
 
+impl ExactFrom for i8 {
+    #[cfg(feature = "float")]
+    fn exact_from_f16(source: f16) -> Option<Self> {
+        Self::exact_from_f64(source.to_f64())
+    }
+
+    #[cfg(feature = "float")]
+    fn exact_from_f32(source: f32) -> Option<Self> {
+        Self::exact_from_f64(source as f64)
+    }
+
+    #[cfg(feature = "float")]
+    fn exact_from_f64(source: f64) -> Option<Self> {
+        if !source.is_finite() {
+            return None;
+        }
+
+        if source <= -129.0 || source >= 128.0 {
+            return None;
+        }
+
+        if source.fract() != 0.0 {
+            return None;
+        }
+
+        Some(source as i8)
+    }
+
+    fn exact_from_u64(source: u64) -> Option<Self> where Self: Sized {
+        if source > 127 {
+            return None;
+        }
+        Some(source as i8)
+    }
+
+    fn exact_from_i64(source: i64) -> Option<Self> where Self: Sized {
+        if !(-128..=127).contains(&source) {
+            return None;
+        }
+        Some(source as i8)
+    }
+
+    fn exact_from_u128(source: u128) -> Option<Self> where Self: Sized {
+        if source > 127 {
+            return None;
+        }
+        Some(source as i8)
+    }
+
+    fn exact_from_i128(source: i128) -> Option<Self> where Self: Sized {
+        if !(-128..=127).contains(&source) {
+            return None;
+        }
+        Some(source as i8)
+    }
+}
+
 impl ExactFrom for i16 {
+    #[cfg(feature = "float")]
     fn exact_from_f16(source: f16) -> Option<Self> {
         let source = source.to_f64();
 
@@ -105,6 +167,7 @@ impl ExactFrom for i16 {
         Some(source as i16)
     }
 
+    #[cfg(feature = "float")]
     fn exact_from_f32(source: f32) -> Option<Self> {
         if !source.is_finite() {
             return None;
@@ -121,6 +184,7 @@ impl ExactFrom for i16 {
         Some(source as i16)
     }
 
+    #[cfg(feature = "float")]
     fn exact_from_f64(source: f64) -> Option<Self> {
         if !source.is_finite() {
             return None;
@@ -167,6 +231,7 @@ impl ExactFrom for i16 {
 }
 
 impl ExactFrom for i32 {
+    #[cfg(feature = "float")]
     fn exact_from_f16(source: f16) -> Option<Self> {
         let source = source.to_f64();
 
@@ -184,6 +249,7 @@ impl ExactFrom for i32 {
         Some(source as i32)
     }
 
+    #[cfg(feature = "float")]
     fn exact_from_f32(source: f32) -> Option<Self> {
         if !source.is_finite() {
             return None;
@@ -200,6 +266,7 @@ impl ExactFrom for i32 {
         Some(source as i32)
     }
 
+    #[cfg(feature = "float")]
     fn exact_from_f64(source: f64) -> Option<Self> {
         if !source.is_finite() {
             return None;
@@ -246,6 +313,7 @@ impl ExactFrom for i32 {
 }
 
 impl ExactFrom for i64 {
+    #[cfg(feature = "float")]
     fn exact_from_f16(source: f16) -> Option<Self> {
         let source = source.to_f64();
 
@@ -263,6 +331,7 @@ impl ExactFrom for i64 {
         Some(source as i64)
     }
 
+    #[cfg(feature = "float")]
     fn exact_from_f32(source: f32) -> Option<Self> {
         if !source.is_finite() {
             return None;
@@ -279,6 +348,7 @@ impl ExactFrom for i64 {
         Some(source as i64)
     }
 
+    #[cfg(feature = "float")]
     fn exact_from_f64(source: f64) -> Option<Self> {
         if !source.is_finite() {
             return None;
@@ -322,6 +392,7 @@ impl ExactFrom for i64 {
 }
 
 impl ExactFrom for i128 {
+    #[cfg(feature = "float")]
     fn exact_from_f16(source: f16) -> Option<Self> {
         let source = source.to_f64();
 
@@ -338,6 +409,7 @@ impl ExactFrom for i128 {
         Some(source as i128)
     }
 
+    #[cfg(feature = "float")]
     fn exact_from_f32(source: f32) -> Option<Self> {
         if !source.is_finite() {
             return None;
@@ -352,6 +424,7 @@ impl ExactFrom for i128 {
         Some(source as i128)
     }
 
+    #[cfg(feature = "float")]
     fn exact_from_f64(source: f64) -> Option<Self> {
         if !source.is_finite() {
             return None;
@@ -386,7 +459,65 @@ impl ExactFrom for i128 {
     }
 }
 
+impl ExactFrom for u8 {
+    #[cfg(feature = "float")]
+    fn exact_from_f16(source: f16) -> Option<Self> {
+        Self::exact_from_f64(source.to_f64())
+    }
+
+    #[cfg(feature = "float")]
+    fn exact_from_f32(source: f32) -> Option<Self> {
+        Self::exact_from_f64(source as f64)
+    }
+
+    #[cfg(feature = "float")]
+    fn exact_from_f64(source: f64) -> Option<Self> {
+        if !source.is_finite() {
+            return None;
+        }
+
+        if source <= -1.0 || source >= 256.0 {
+            return None;
+        }
+
+        if source.fract() != 0.0 {
+            return None;
+        }
+
+        Some(source as u8)
+    }
+
+    fn exact_from_u64(source: u64) -> Option<Self> where Self: Sized {
+        if source > 255 {
+            return None;
+        }
+        Some(source as u8)
+    }
+
+    fn exact_from_i64(source: i64) -> Option<Self> where Self: Sized {
+        if !(0..=255).contains(&source) {
+            return None;
+        }
+        Some(source as u8)
+    }
+
+    fn exact_from_u128(source: u128) -> Option<Self> where Self: Sized {
+        if source > 255 {
+            return None;
+        }
+        Some(source as u8)
+    }
+
+    fn exact_from_i128(source: i128) -> Option<Self> where Self: Sized {
+        if !(0..=255).contains(&source) {
+            return None;
+        }
+        Some(source as u8)
+    }
+}
+
 impl ExactFrom for u16 {
+    #[cfg(feature = "float")]
     fn exact_from_f16(source: f16) -> Option<Self> {
         let source = source.to_f64();
 
@@ -408,6 +539,7 @@ impl ExactFrom for u16 {
         Some(source as u16)
     }
 
+    #[cfg(feature = "float")]
     fn exact_from_f32(source: f32) -> Option<Self> {
         if !source.is_finite() {
             return None;
@@ -424,6 +556,7 @@ impl ExactFrom for u16 {
         Some(source as u16)
     }
 
+    #[cfg(feature = "float")]
     fn exact_from_f64(source: f64) -> Option<Self> {
         if !source.is_finite() {
             return None;
@@ -470,6 +603,7 @@ impl ExactFrom for u16 {
 }
 
 impl ExactFrom for u32 {
+    #[cfg(feature = "float")]
     fn exact_from_f16(source: f16) -> Option<Self> {
         let source = source.to_f64();
 
@@ -491,6 +625,7 @@ impl ExactFrom for u32 {
         Some(source as u32)
     }
 
+    #[cfg(feature = "float")]
     fn exact_from_f32(source: f32) -> Option<Self> {
         if !source.is_finite() {
             return None;
@@ -507,6 +642,7 @@ impl ExactFrom for u32 {
         Some(source as u32)
     }
 
+    #[cfg(feature = "float")]
     fn exact_from_f64(source: f64) -> Option<Self> {
         if !source.is_finite() {
             return None;
@@ -553,6 +689,7 @@ impl ExactFrom for u32 {
 }
 
 impl ExactFrom for u64 {
+    #[cfg(feature = "float")]
     fn exact_from_f16(source: f16) -> Option<Self> {
         let source = source.to_f64();
 
@@ -574,6 +711,7 @@ impl ExactFrom for u64 {
         Some(source as u64)
     }
 
+    #[cfg(feature = "float")]
     fn exact_from_f32(source: f32) -> Option<Self> {
         if !source.is_finite() {
             return None;
@@ -590,6 +728,7 @@ impl ExactFrom for u64 {
         Some(source as u64)
     }
 
+    #[cfg(feature = "float")]
     fn exact_from_f64(source: f64) -> Option<Self> {
         if !source.is_finite() {
             return None;
@@ -633,6 +772,7 @@ impl ExactFrom for u64 {
 }
 
 impl ExactFrom for u128 {
+    #[cfg(feature = "float")]
     fn exact_from_f16(source: f16) -> Option<Self> {
         let source = source.to_f64();
 
@@ -651,6 +791,7 @@ impl ExactFrom for u128 {
         Some(source as u128)
     }
 
+    #[cfg(feature = "float")]
     fn exact_from_f32(source: f32) -> Option<Self> {
         if !source.is_finite() {
             return None;
@@ -667,6 +808,7 @@ impl ExactFrom for u128 {
         Some(source as u128)
     }
 
+    #[cfg(feature = "float")]
     fn exact_from_f64(source: f64) -> Option<Self> {
         if !source.is_finite() {
             return None;
@@ -706,6 +848,76 @@ impl ExactFrom for u128 {
     }
 }
 
+// The pointer-sized integers convert through the 64-bit integers, then check
+// that the value fits the pointer width of the target.
+
+impl ExactFrom for isize {
+    #[cfg(feature = "float")]
+    fn exact_from_f16(source: f16) -> Option<Self> {
+        i64::exact_from_f16(source).and_then(|n| isize::try_from(n).ok())
+    }
+
+    #[cfg(feature = "float")]
+    fn exact_from_f32(source: f32) -> Option<Self> {
+        i64::exact_from_f32(source).and_then(|n| isize::try_from(n).ok())
+    }
+
+    #[cfg(feature = "float")]
+    fn exact_from_f64(source: f64) -> Option<Self> {
+        i64::exact_from_f64(source).and_then(|n| isize::try_from(n).ok())
+    }
+
+    fn exact_from_u64(source: u64) -> Option<Self> where Self: Sized {
+        isize::try_from(source).ok()
+    }
+
+    fn exact_from_i64(source: i64) -> Option<Self> where Self: Sized {
+        isize::try_from(source).ok()
+    }
+
+    fn exact_from_u128(source: u128) -> Option<Self> where Self: Sized {
+        isize::try_from(source).ok()
+    }
+
+    fn exact_from_i128(source: i128) -> Option<Self> where Self: Sized {
+        isize::try_from(source).ok()
+    }
+}
+
+impl ExactFrom for usize {
+    #[cfg(feature = "float")]
+    fn exact_from_f16(source: f16) -> Option<Self> {
+        u64::exact_from_f16(source).and_then(|n| usize::try_from(n).ok())
+    }
+
+    #[cfg(feature = "float")]
+    fn exact_from_f32(source: f32) -> Option<Self> {
+        u64::exact_from_f32(source).and_then(|n| usize::try_from(n).ok())
+    }
+
+    #[cfg(feature = "float")]
+    fn exact_from_f64(source: f64) -> Option<Self> {
+        u64::exact_from_f64(source).and_then(|n| usize::try_from(n).ok())
+    }
+
+    fn exact_from_u64(source: u64) -> Option<Self> where Self: Sized {
+        usize::try_from(source).ok()
+    }
+
+    fn exact_from_i64(source: i64) -> Option<Self> where Self: Sized {
+        usize::try_from(source).ok()
+    }
+
+    fn exact_from_u128(source: u128) -> Option<Self> where Self: Sized {
+        usize::try_from(source).ok()
+    }
+
+    fn exact_from_i128(source: i128) -> Option<Self> where Self: Sized {
+        usize::try_from(source).ok()
+    }
+}
+
+#[cfg(feature = "float")]
 impl ExactFrom for f16 {
     fn exact_from_f16(source: f16) -> Option<Self> {
         if source.is_nan() {
@@ -799,6 +1011,7 @@ impl ExactFrom for f16 {
     }
 }
 
+#[cfg(feature = "float")]
 impl ExactFrom for f32 {
     fn exact_from_f16(source: f16) -> Option<Self> {
         if source.is_nan() {
@@ -879,6 +1092,7 @@ impl ExactFrom for f32 {
     }
 }
 
+#[cfg(feature = "float")]
 impl ExactFrom for f64 {
     fn exact_from_f16(source: f16) -> Option<Self> {
         if source.is_nan() {
@@ -951,7 +1165,7 @@ impl ExactFrom for f64 {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "float"))]
 mod tests {
     use super::*;
 
@@ -0,0 +1,461 @@
+//! A C ABI for building, encoding, decoding, and formatting dCBOR, on which
+//! bindings for other languages can be built.
+//!
+//! Values are held behind opaque [`DcborValue`] handles, and bytes are passed
+//! in as pointer and length pairs and returned in [`DcborBuffer`]s. Every
+//! handle and buffer returned must be freed by the matching `_free`
+//! function. Functions that can fail return a [`DcborStatus`], and the
+//! message of the last error on the calling thread is available from
+//! [`dcbor_last_error`].
+//!
+//! Values are decoded from data, or built with the `dcbor_value_` functions
+//! from integers, booleans, null, floats (with the `float` feature), byte
+//! strings, text, and other values. Values nested more deeply than
+//! [`DCBOR_MAX_DEPTH`] are rejected, whether decoded or built, so no call
+//! can overflow the stack. Panics are caught before they reach the caller,
+//! and reported as [`DcborStatus::Panic`].
+//!
+//! The functions are exported from any `cdylib` or `staticlib` that links
+//! this crate with the `ffi` feature enabled.
+//!
+//! ```c
+//! DcborValue *value;
+//! if (dcbor_decode(data, len, &value) != 0) {
+//!     DcborBuffer message;
+//!     dcbor_last_error(&message);
+//!     /* report message.data, message.len */
+//!     dcbor_buffer_free(message);
+//!     return;
+//! }
+//! DcborBuffer diagnostic;
+//! dcbor_diagnostic(value, false, &diagnostic);
+//! dcbor_buffer_free(diagnostic);
+//! dcbor_value_free(value);
+//! ```
+
+use std::{cell::RefCell, panic, ptr, slice, str};
+
+use anyhow::Error;
+
+use crate::{CBORCase, CBORError, DecodeOptions, DuplicatePolicy, Map, CBOR};
+
+/// The maximum depth of nesting of values decoded or built through this
+/// interface, as for [`DecodeOptions::max_depth`].
+pub const DCBOR_MAX_DEPTH: usize = 128;
+
+/// An opaque handle to a CBOR value.
+pub struct DcborValue {
+    cbor: CBOR,
+    /// The depth of nesting within the value, 0 if it holds no other values.
+    depth: usize,
+}
+
+/// A buffer of bytes owned by this library, freed with
+/// [`dcbor_buffer_free`].
+///
+/// Text, such as diagnostic notation, is UTF-8 and not NUL-terminated.
+#[repr(C)]
+#[derive(Debug)]
+pub struct DcborBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+/// The result of a call that can fail.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DcborStatus {
+    /// The call succeeded.
+    Ok = 0,
+    /// A required pointer argument was null.
+    NullPointer = 1,
+    /// The data ended before the item was complete.
+    Underrun = 2,
+    /// The data is not well-formed CBOR, or uses parts of CBOR that dCBOR
+    /// does not support, such as indefinite lengths, or text is not valid
+    /// UTF-8.
+    Malformed = 3,
+    /// The data is well-formed CBOR, but not in the canonical form dCBOR
+    /// requires, or a map was given the same key twice.
+    NonCanonical = 4,
+    /// The data has bytes following the item.
+    UnusedData = 5,
+    /// A limit on the size of the data or the depth of nesting was exceeded.
+    LimitExceeded = 6,
+    /// Any other error.
+    Other = 7,
+    /// The library panicked. No output was written.
+    Panic = 8,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+fn set_last_error(message: String) {
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+}
+
+/// Records the error as the last error on this thread, returning its status.
+fn fail(error: Error) -> DcborStatus {
+    let status = match error.downcast_ref::<CBORError>() {
+        Some(CBORError::Underrun) => DcborStatus::Underrun,
+        Some(
+            CBORError::UnsupportedHeaderValue(_) |
+            CBORError::InvalidSimpleValue |
+            CBORError::UnsupportedFloat |
+            CBORError::InvalidString(_)
+        ) => DcborStatus::Malformed,
+        Some(
+            CBORError::NonCanonicalNumeric |
+            CBORError::NonCanonicalString |
+            CBORError::NonFiniteFloat |
+            CBORError::MisorderedMapKey |
+            CBORError::DuplicateMapKey
+        ) => DcborStatus::NonCanonical,
+        Some(CBORError::UnusedData(_)) => DcborStatus::UnusedData,
+        Some(
+            CBORError::TextTooLong |
+            CBORError::MemoryBudgetExceeded |
+            CBORError::DepthLimitExceeded
+        ) => DcborStatus::LimitExceeded,
+        _ => DcborStatus::Other,
+    };
+    set_last_error(error.to_string());
+    status
+}
+
+fn null_pointer() -> DcborStatus {
+    set_last_error("a required pointer argument was null".to_string());
+    DcborStatus::NullPointer
+}
+
+/// Runs the body of an exported function, so that a panic is reported as
+/// [`DcborStatus::Panic`] rather than unwinding into the caller.
+fn guarded(body: impl FnOnce() -> DcborStatus) -> DcborStatus {
+    panic::catch_unwind(panic::AssertUnwindSafe(body)).unwrap_or_else(|_| {
+        set_last_error("dcbor panicked".to_string());
+        DcborStatus::Panic
+    })
+}
+
+fn buffer(bytes: Vec<u8>) -> DcborBuffer {
+    let bytes = bytes.into_boxed_slice();
+    let len = bytes.len();
+    DcborBuffer { data: Box::into_raw(bytes).cast::<u8>(), len }
+}
+
+/// Returns the bytes at `data`, or `None` if `data` is null and `len` is not
+/// zero.
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> Option<&'a [u8]> {
+    match (data.is_null(), len) {
+        (_, 0) => Some(&[]),
+        (true, _) => None,
+        (false, _) => Some(slice::from_raw_parts(data, len)),
+    }
+}
+
+/// Returns the values behind `count` handles at `handles`, or `None` if any
+/// pointer is null.
+unsafe fn values<'a>(handles: *const *const DcborValue, count: usize) -> Option<Vec<&'a DcborValue>> {
+    match (handles.is_null(), count) {
+        (_, 0) => Some(Vec::new()),
+        (true, _) => None,
+        (false, _) => slice::from_raw_parts(handles, count).iter().map(|handle| handle.as_ref()).collect(),
+    }
+}
+
+/// Writes a new handle for the value to `out`, unless it is nested too
+/// deeply.
+unsafe fn new_value(cbor: CBOR, depth: usize, out: *mut *mut DcborValue) -> DcborStatus {
+    if depth > DCBOR_MAX_DEPTH {
+        return fail(Error::msg(CBORError::DepthLimitExceeded));
+    }
+    *out = Box::into_raw(Box::new(DcborValue { cbor, depth }));
+    DcborStatus::Ok
+}
+
+/// Writes a new handle for a value holding no other values.
+unsafe fn leaf(cbor: CBOR, out: *mut *mut DcborValue) -> DcborStatus {
+    guarded(|| {
+        if out.is_null() {
+            return null_pointer();
+        }
+        new_value(cbor, 0, out)
+    })
+}
+
+/// Returns the depth of nesting within the value.
+fn nesting_depth(cbor: &CBOR) -> usize {
+    let children = match cbor.as_case() {
+        CBORCase::Array(array) => array.iter().map(nesting_depth).max(),
+        CBORCase::Map(map) => map.iter().map(|(key, value)| nesting_depth(key).max(nesting_depth(value))).max(),
+        CBORCase::Tagged(_, item) => Some(nesting_depth(item)),
+        _ => return 0,
+    };
+    children.map_or(0, |depth| depth + 1)
+}
+
+/// Decodes dCBOR data into a new value.
+///
+/// Data nested more deeply than [`DCBOR_MAX_DEPTH`] is rejected with
+/// [`DcborStatus::LimitExceeded`].
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, or may be null if `len` is
+/// zero. `out` must be valid for writing a pointer. On success, the value
+/// written to `out` must be freed with [`dcbor_value_free`].
+#[no_mangle]
+pub unsafe extern "C" fn dcbor_decode(data: *const u8, len: usize, out: *mut *mut DcborValue) -> DcborStatus {
+    guarded(|| {
+        let Some(data) = bytes(data, len).filter(|_| !out.is_null()) else {
+            return null_pointer();
+        };
+        let options = DecodeOptions::default().max_depth(Some(DCBOR_MAX_DEPTH));
+        match CBOR::try_from_data_opt(data, &options) {
+            Ok(cbor) => {
+                let depth = nesting_depth(&cbor);
+                new_value(cbor, depth, out)
+            },
+            Err(error) => fail(error),
+        }
+    })
+}
+
+/// Writes the dCBOR encoding of the value to `out`.
+///
+/// # Safety
+///
+/// `value` must be a live handle returned by this library, and `out` must be
+/// valid for writing a [`DcborBuffer`]. On success, the buffer must be freed
+/// with [`dcbor_buffer_free`].
+#[no_mangle]
+pub unsafe extern "C" fn dcbor_encode(value: *const DcborValue, out: *mut DcborBuffer) -> DcborStatus {
+    guarded(|| {
+        if value.is_null() || out.is_null() {
+            return null_pointer();
+        }
+        *out = buffer((*value).cbor.to_cbor_data());
+        DcborStatus::Ok
+    })
+}
+
+/// Writes the diagnostic notation of the value to `out`, with annotations if
+/// `annotate` is true, using the global tags store.
+///
+/// # Safety
+///
+/// As for [`dcbor_encode`].
+#[no_mangle]
+pub unsafe extern "C" fn dcbor_diagnostic(value: *const DcborValue, annotate: bool, out: *mut DcborBuffer) -> DcborStatus {
+    guarded(|| {
+        if value.is_null() || out.is_null() {
+            return null_pointer();
+        }
+        let cbor = &(*value).cbor;
+        let diagnostic = if annotate { cbor.diagnostic_annotated() } else { cbor.diagnostic() };
+        *out = buffer(diagnostic.into_bytes());
+        DcborStatus::Ok
+    })
+}
+
+/// Writes a new value holding an unsigned integer to `out`.
+///
+/// # Safety
+///
+/// `out` must be valid for writing a pointer. On success, the value written
+/// to `out` must be freed with [`dcbor_value_free`]. The same applies to the
+/// other `dcbor_value_` functions.
+#[no_mangle]
+pub unsafe extern "C" fn dcbor_value_unsigned(value: u64, out: *mut *mut DcborValue) -> DcborStatus {
+    leaf(CBOR::from(value), out)
+}
+
+/// Writes a new value holding a signed integer to `out`.
+///
+/// # Safety
+///
+/// As for [`dcbor_value_unsigned`].
+#[no_mangle]
+pub unsafe extern "C" fn dcbor_value_signed(value: i64, out: *mut *mut DcborValue) -> DcborStatus {
+    leaf(CBOR::from(value), out)
+}
+
+/// Writes a new value holding a floating point number to `out`. Floats with
+/// no fractional part are reduced to integers, as dCBOR requires.
+///
+/// # Safety
+///
+/// As for [`dcbor_value_unsigned`].
+#[cfg(feature = "float")]
+#[no_mangle]
+pub unsafe extern "C" fn dcbor_value_double(value: f64, out: *mut *mut DcborValue) -> DcborStatus {
+    leaf(CBOR::from(value), out)
+}
+
+/// Writes a new value holding `true` or `false` to `out`.
+///
+/// # Safety
+///
+/// As for [`dcbor_value_unsigned`].
+#[no_mangle]
+pub unsafe extern "C" fn dcbor_value_bool(value: bool, out: *mut *mut DcborValue) -> DcborStatus {
+    leaf(CBOR::from(value), out)
+}
+
+/// Writes a new value holding `null` to `out`.
+///
+/// # Safety
+///
+/// As for [`dcbor_value_unsigned`].
+#[no_mangle]
+pub unsafe extern "C" fn dcbor_value_null(out: *mut *mut DcborValue) -> DcborStatus {
+    leaf(CBOR::null(), out)
+}
+
+/// Writes a new value holding a copy of the byte string at `data` to `out`.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, or may be null if `len` is
+/// zero. Otherwise as for [`dcbor_value_unsigned`].
+#[no_mangle]
+pub unsafe extern "C" fn dcbor_value_bytes(data: *const u8, len: usize, out: *mut *mut DcborValue) -> DcborStatus {
+    guarded(|| match bytes(data, len) {
+        Some(data) => leaf(CBOR::to_byte_string(data), out),
+        None => null_pointer(),
+    })
+}
+
+/// Writes a new value holding the UTF-8 text at `data` to `out`, normalized
+/// to NFC as dCBOR requires. Invalid UTF-8 fails with
+/// [`DcborStatus::Malformed`].
+///
+/// # Safety
+///
+/// As for [`dcbor_value_bytes`].
+#[no_mangle]
+pub unsafe extern "C" fn dcbor_value_text(data: *const u8, len: usize, out: *mut *mut DcborValue) -> DcborStatus {
+    guarded(|| match bytes(data, len).map(str::from_utf8) {
+        Some(Ok(text)) => leaf(CBOR::from(text), out),
+        Some(Err(error)) => fail(Error::msg(CBORError::InvalidString(error))),
+        None => null_pointer(),
+    })
+}
+
+/// Writes a new array holding the `count` values at `items` to `out`. The
+/// values are not consumed, and must still be freed.
+///
+/// # Safety
+///
+/// `items` must point to `count` live handles returned by this library, or
+/// may be null if `count` is zero. Otherwise as for [`dcbor_value_unsigned`].
+#[no_mangle]
+pub unsafe extern "C" fn dcbor_value_array(items: *const *const DcborValue, count: usize, out: *mut *mut DcborValue) -> DcborStatus {
+    guarded(|| {
+        let Some(items) = values(items, count).filter(|_| !out.is_null()) else {
+            return null_pointer();
+        };
+        let depth = items.iter().map(|item| item.depth + 1).max().unwrap_or(0);
+        let array: Vec<CBOR> = items.iter().map(|item| item.cbor.clone()).collect();
+        new_value(array.into(), depth, out)
+    })
+}
+
+/// Writes a new map to `out` holding `count` entries, whose keys are at
+/// `keys` and values at `values`. The same key given twice fails with
+/// [`DcborStatus::NonCanonical`]. The keys and values are not consumed, and
+/// must still be freed.
+///
+/// # Safety
+///
+/// `keys` and `values` must each point to `count` live handles returned by
+/// this library, or may be null if `count` is zero. Otherwise as for
+/// [`dcbor_value_unsigned`].
+#[no_mangle]
+pub unsafe extern "C" fn dcbor_value_map(
+    keys: *const *const DcborValue,
+    values: *const *const DcborValue,
+    count: usize,
+    out: *mut *mut DcborValue,
+) -> DcborStatus {
+    guarded(|| {
+        let (Some(keys), Some(values)) = (self::values(keys, count), self::values(values, count)) else {
+            return null_pointer();
+        };
+        if out.is_null() {
+            return null_pointer();
+        }
+        let depth = keys.iter().chain(&values).map(|item| item.depth + 1).max().unwrap_or(0);
+        let entries = keys.iter().zip(&values).map(|(key, value)| (key.cbor.clone(), value.cbor.clone()));
+        match Map::from_iter_with(entries, DuplicatePolicy::Error) {
+            Ok(map) => new_value(map.into(), depth, out),
+            Err(error) => fail(error),
+        }
+    })
+}
+
+/// Writes a new value to `out` holding `content` tagged with `tag`. The
+/// content is not consumed, and must still be freed.
+///
+/// # Safety
+///
+/// `content` must be a live handle returned by this library. Otherwise as
+/// for [`dcbor_value_unsigned`].
+#[no_mangle]
+pub unsafe extern "C" fn dcbor_value_tagged(tag: u64, content: *const DcborValue, out: *mut *mut DcborValue) -> DcborStatus {
+    guarded(|| {
+        let Some(content) = content.as_ref().filter(|_| !out.is_null()) else {
+            return null_pointer();
+        };
+        new_value(CBOR::to_tagged_value(tag, content.cbor.clone()), content.depth + 1, out)
+    })
+}
+
+/// Writes the message of the last error on the calling thread to `out`, or
+/// an empty buffer if there has been none.
+///
+/// # Safety
+///
+/// `out` must be valid for writing a [`DcborBuffer`], which must be freed
+/// with [`dcbor_buffer_free`].
+#[no_mangle]
+pub unsafe extern "C" fn dcbor_last_error(out: *mut DcborBuffer) -> DcborStatus {
+    guarded(|| {
+        if out.is_null() {
+            return null_pointer();
+        }
+        let message = LAST_ERROR.with(|last| last.borrow().clone());
+        *out = buffer(message.into_bytes());
+        DcborStatus::Ok
+    })
+}
+
+/// Frees a value. Does nothing if `value` is null.
+///
+/// # Safety
+///
+/// `value` must be null or a live handle returned by this library, which
+/// must not be used again.
+#[no_mangle]
+pub unsafe extern "C" fn dcbor_value_free(value: *mut DcborValue) {
+    if !value.is_null() {
+        // A panic while dropping cannot be reported, but must not unwind
+        // into the caller.
+        let _ = panic::catch_unwind(panic::AssertUnwindSafe(|| drop(Box::from_raw(value))));
+    }
+}
+
+/// Frees a buffer. Does nothing if its data is null.
+///
+/// # Safety
+///
+/// `buffer` must have been returned by this library, or have null data, and
+/// must not be used again.
+#[no_mangle]
+pub unsafe extern "C" fn dcbor_buffer_free(buffer: DcborBuffer) {
+    if !buffer.data.is_null() {
+        let _ = panic::catch_unwind(|| drop(Box::from_raw(ptr::slice_from_raw_parts_mut(buffer.data, buffer.len))));
+    }
+}
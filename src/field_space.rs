@@ -0,0 +1,137 @@
+import_stdlib!();
+
+use anyhow::{bail, Result};
+
+use crate::{CBORError, FieldProblem, Map};
+
+/// Whether the keys in a range reserved by a [`FieldSpace`] must be
+/// understood by receivers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldCriticality {
+    /// A receiver must reject a map containing a key in the range that it
+    /// does not understand.
+    Critical,
+    /// A receiver ignores keys in the range that it does not understand.
+    Optional,
+}
+
+/// The integer map keys reserved by a protocol, for validating decoded maps.
+///
+/// A protocol reserves ranges of keys as critical or optional, in the manner
+/// of the COSE `crit` header parameter: a receiver must reject a map
+/// containing a critical key it does not understand, but may ignore optional
+/// keys it does not understand. Keys outside every reserved range, and keys
+/// that are not integers, are not allowed.
+///
+/// ```
+/// # use dcbor::{FieldCriticality, FieldSpace, Map};
+/// let space = FieldSpace::new()
+///     .reserve(1..=15, FieldCriticality::Critical)
+///     .reserve(16.., FieldCriticality::Optional)
+///     .understand([1, 2]);
+///
+/// let mut map = Map::new();
+/// map.insert(1, "a");
+/// map.insert(100, "b");
+/// assert!(space.validate(&map).is_ok());
+///
+/// map.insert(3, "c");
+/// assert_eq!(
+///     space.validate(&map).unwrap_err().to_string(),
+///     "invalid fields: key 3: critical but not understood"
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FieldSpace {
+    ranges: Vec<(i64, i64, FieldCriticality)>,
+    understood: BTreeSet<i64>,
+}
+
+impl FieldSpace {
+    /// Makes a new field space with no keys reserved.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reserves the given range of keys.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is empty or overlaps a range already reserved.
+    pub fn reserve(mut self, range: impl ops::RangeBounds<i64>, criticality: FieldCriticality) -> Self {
+        let start = match range.start_bound() {
+            ops::Bound::Included(&start) => Some(start),
+            ops::Bound::Excluded(&start) => start.checked_add(1),
+            ops::Bound::Unbounded => Some(i64::MIN),
+        };
+        let end = match range.end_bound() {
+            ops::Bound::Included(&end) => Some(end),
+            ops::Bound::Excluded(&end) => end.checked_sub(1),
+            ops::Bound::Unbounded => Some(i64::MAX),
+        };
+        let (start, end) = match (start, end) {
+            (Some(start), Some(end)) if start <= end => (start, end),
+            _ => panic!("reserved key range is empty"),
+        };
+        assert!(
+            self.ranges.iter().all(|&(s, e, _)| end < s || start > e),
+            "reserved key range {}..={} overlaps a range already reserved", start, end
+        );
+        self.ranges.push((start, end, criticality));
+        self
+    }
+
+    /// Declares that the given keys are understood by this implementation.
+    pub fn understand(mut self, keys: impl IntoIterator<Item = i64>) -> Self {
+        self.understood.extend(keys);
+        self
+    }
+
+    /// Returns the criticality of the range reserving the given key, or
+    /// `None` if the key is not reserved.
+    pub fn criticality(&self, key: i64) -> Option<FieldCriticality> {
+        self.ranges.iter()
+            .find(|&&(start, end, _)| (start..=end).contains(&key))
+            .map(|&(_, _, criticality)| criticality)
+    }
+
+    /// Returns `true` if the given key is understood by this implementation.
+    pub fn is_understood(&self, key: i64) -> bool {
+        self.understood.contains(&key)
+    }
+
+    /// Checks that the map only contains keys allowed by this field space.
+    ///
+    /// Returns [`CBORError::InvalidFields`] with a
+    /// [`FieldProblem`] for every key that is not an
+    /// integer, is outside the reserved ranges, or is critical but not
+    /// understood.
+    pub fn validate(&self, map: &Map) -> Result<()> {
+        let mut problems = Vec::new();
+        for key in map.keys() {
+            let Ok(n) = i64::try_from(key.clone()) else {
+                problems.push(FieldProblem::NotInteger(key.display_for_error().to_string()));
+                continue;
+            };
+            match self.criticality(n) {
+                None => problems.push(FieldProblem::NotReserved(n)),
+                Some(FieldCriticality::Critical) if !self.is_understood(n) => problems.push(FieldProblem::NotUnderstood(n)),
+                Some(_) => {},
+            }
+        }
+        if !problems.is_empty() {
+            bail!(CBORError::InvalidFields(problems));
+        }
+        Ok(())
+    }
+
+    /// Returns the keys in the map that this implementation ignores: the
+    /// optional keys it does not understand, in the order they appear in the
+    /// map.
+    pub fn ignored_keys(&self, map: &Map) -> Vec<i64> {
+        map.keys()
+            .filter_map(|key| i64::try_from(key.clone()).ok())
+            .filter(|&n| self.criticality(n) == Some(FieldCriticality::Optional) && !self.is_understood(n))
+            .collect()
+    }
+}
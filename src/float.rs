@@ -11,19 +11,31 @@ static CBOR_NAN: [u8; 3] = [0xf9, 0x7e, 0x00];
 
 impl From<f64> for CBOR {
     fn from(value: f64) -> Self {
-        let n = value;
-        if n < 0.0f64 {
-            if let Some(n) = i128::exact_from_f64(n) {
-                if let Some(i) = u64::exact_from_i128(-1 - n) {
-                    return CBORCase::Negative(i).into();
-                }
+        canonical_float_case(value).into()
+    }
+}
+
+/// Returns the case in which a floating point value is held: as an integer if
+/// it has no fractional part and is in range, and otherwise as a float, with
+/// any NaN replaced by the canonical NaN.
+///
+/// Every float is held this way, however it is constructed, so that it
+/// compares equal to the result of decoding its encoding.
+pub(crate) fn canonical_float_case(n: f64) -> CBORCase {
+    if n < 0.0f64 {
+        if let Some(n) = i128::exact_from_f64(n) {
+            if let Some(i) = u64::exact_from_i128(-1 - n) {
+                return CBORCase::Negative(i);
             }
         }
-        if let Some(i) = u64::exact_from_f64(n) {
-            return i.into();
-        }
-        CBORCase::Simple(Simple::Float(n)).into()
     }
+    if let Some(i) = u64::exact_from_f64(n) {
+        return CBORCase::Unsigned(i);
+    }
+    if n.is_nan() {
+        return CBORCase::Simple(Simple::Float(f64::NAN));
+    }
+    CBORCase::Simple(Simple::Float(n))
 }
 
 pub fn f64_cbor_data(value: f64) -> Vec<u8> {
@@ -36,7 +48,7 @@ pub fn f64_cbor_data(value: f64) -> Vec<u8> {
         if let Some(n) = i128::exact_from_f64(n) {
             if let Some(i) = u64::exact_from_i128(-1 - n) {
                 let cbor: CBOR = CBORCase::Negative(i).into();
-                return cbor.to_cbor_data();
+                return cbor.encoded();
             }
         }
     }
@@ -60,6 +72,71 @@ pub(crate) fn validate_canonical_f64(n: f64) -> Result<()> {
     Ok(())
 }
 
+impl CBOR {
+    /// Creates CBOR from a floating point value, failing with
+    /// [`CBORError::NonFiniteFloat`] if it is NaN or infinite.
+    ///
+    /// Unlike `From<f64>`, which silently encodes any NaN as the canonical
+    /// NaN, this suits application profiles that forbid non-finite values.
+    pub fn try_from_float(value: f64) -> Result<CBOR> {
+        if !value.is_finite() {
+            bail!(CBORError::NonFiniteFloat);
+        }
+        Ok(value.into())
+    }
+}
+
+/// Conversion between numbers and decimal text.
+///
+/// Parsing rounds to the nearest `f64`, and formatting produces the shortest
+/// text that parses back to the same value, so both are exact and give the
+/// same results on every platform.
+impl CBOR {
+    /// Creates CBOR from a decimal number such as `1.2`, `-7`, or `6.02e23`,
+    /// parsed to the nearest `f64` and encoded canonically, so that a number
+    /// with no fractional part in the range of the integers is encoded as an
+    /// integer.
+    ///
+    /// Returns [`CBORError::InvalidDecimal`] if the text is not a decimal
+    /// number, which includes `NaN` and `inf`, or [`CBORError::OutOfRange`] if
+    /// the number is too large for an `f64`.
+    pub fn from_decimal_str(text: &str) -> Result<CBOR> {
+        let is_decimal = text.bytes().any(|c| c.is_ascii_digit()) &&
+            text.bytes().all(|c| c.is_ascii_digit() || matches!(c, b'+' | b'-' | b'.' | b'e' | b'E'));
+        let value: f64 = match text.parse() {
+            Ok(value) if is_decimal => value,
+            _ => bail!(CBORError::InvalidDecimal(text.to_string())),
+        };
+        if !value.is_finite() {
+            bail!(CBORError::OutOfRange);
+        }
+        Ok(value.into())
+    }
+
+    /// Returns this number as decimal text: integers in full, and floats in
+    /// the shortest form that parses back to the same value, using an
+    /// exponent for very large and very small magnitudes, e.g. `1.5`, `1e20`,
+    /// or `2.5e-8`.
+    ///
+    /// Returns [`CBORError::WrongType`] if this is not a number, or
+    /// [`CBORError::NonFiniteFloat`] if it is NaN or infinite.
+    pub fn to_decimal_string(&self) -> Result<String> {
+        match self.as_case() {
+            CBORCase::Unsigned(n) => Ok(n.to_string()),
+            CBORCase::Negative(n) => Ok((-1 - *n as i128).to_string()),
+            CBORCase::Simple(Simple::Float(f)) if !f.is_finite() => bail!(CBORError::NonFiniteFloat),
+            CBORCase::Simple(Simple::Float(f)) => Ok(format!("{:?}", f)),
+            _ => bail!(CBORError::WrongType),
+        }
+    }
+}
+
+impl From<&f64> for CBOR {
+    fn from(value: &f64) -> Self {
+        (*value).into()
+    }
+}
+
 impl TryFrom<CBOR> for f64 {
     type Error = Error;
     
@@ -109,7 +186,7 @@ pub fn f32_cbor_data(value: f32) -> Vec<u8> {
     if n < 0.0f32 {
         if let Some(i) = u64::exact_from_f32(-1f32 - n) {
             let cbor: CBOR = CBORCase::Negative(i).into();
-            return cbor.to_cbor_data();
+            return cbor.encoded();
         }
     }
     if let Some(i) = u32::exact_from_f32(n) {
@@ -132,6 +209,12 @@ pub(crate) fn validate_canonical_f32(n: f32) -> Result<()> {
     Ok(())
 }
 
+impl From<&f32> for CBOR {
+    fn from(value: &f32) -> Self {
+        (*value).into()
+    }
+}
+
 impl TryFrom<CBOR> for f32 {
     type Error = Error;
 
@@ -183,7 +266,7 @@ pub fn f16_cbor_data(value: f16) -> Vec<u8> {
     if n < 0.0 {
         if let Some(i) = u64::exact_from_f64(-1f64 - n) {
             let cbor: CBOR = CBORCase::Negative(i).into();
-            return cbor.to_cbor_data();
+            return cbor.encoded();
         }
     }
     if let Some(i) = u16::exact_from_f64(n) {
@@ -195,6 +278,12 @@ pub fn f16_cbor_data(value: f16) -> Vec<u8> {
     value.to_bits().encode_int(MajorType::Simple)
 }
 
+impl From<&f16> for CBOR {
+    fn from(value: &f16) -> Self {
+        (*value).into()
+    }
+}
+
 impl TryFrom<CBOR> for f16 {
     type Error = Error;
 
@@ -240,3 +329,103 @@ pub(crate) fn validate_canonical_f16(n: f16) -> Result<()> {
     }
     Ok(())
 }
+
+/// The form in which a floating point value is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatEncoding {
+    /// Encoded as an unsigned or negative integer.
+    Integer,
+    /// Encoded as a half-precision float.
+    F16,
+    /// Encoded as a single-precision float.
+    F32,
+    /// Encoded as a double-precision float.
+    F64,
+}
+
+/// A step taken by the encoder to reduce a floating point value to its
+/// canonical form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatReduction {
+    /// Negative zero was encoded as the integer zero, losing its sign.
+    NegativeZeroToZero,
+    /// A value with no fractional part was encoded as an integer.
+    ToInteger,
+    /// The value was encoded at single precision, without loss.
+    ToF32,
+    /// The value was encoded at half precision, without loss.
+    ToF16,
+    /// A NaN was replaced by the canonical half-precision NaN, discarding its
+    /// sign and payload.
+    CanonicalNaN,
+}
+
+/// A report of how a floating point value is treated by the encoder, as
+/// returned by [`CBOR::analyze_float`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloatAnalysis {
+    /// The value analyzed.
+    pub value: f64,
+    /// Whether the value is negative zero.
+    pub is_negative_zero: bool,
+    /// Whether the value is subnormal at double precision.
+    pub is_subnormal: bool,
+    /// Whether the value is subnormal at the precision it is encoded with.
+    pub is_encoded_subnormal: bool,
+    /// Whether the value is exactly representable at single precision.
+    pub fits_f32: bool,
+    /// Whether the value is exactly representable at half precision.
+    pub fits_f16: bool,
+    /// The form the value is encoded in.
+    pub encoding: FloatEncoding,
+    /// The reductions applied by the encoder, in the order they apply.
+    pub reductions: Vec<FloatReduction>,
+}
+
+impl CBOR {
+    /// Reports how the given floating point value is encoded, including any
+    /// reductions that alter its representation.
+    ///
+    /// Numeric-sensitive applications can use this to audit exactly how
+    /// dCBOR's numeric reduction affects their values.
+    pub fn analyze_float(value: f64) -> FloatAnalysis {
+        let data = CBOR::from(value).encoded();
+        let encoding = match data[0] {
+            0xf9 => FloatEncoding::F16,
+            0xfa => FloatEncoding::F32,
+            0xfb => FloatEncoding::F64,
+            _ => FloatEncoding::Integer,
+        };
+        let is_negative_zero = value == 0.0 && value.is_sign_negative();
+        let mut reductions = vec![];
+        if value.is_nan() {
+            reductions.push(FloatReduction::CanonicalNaN);
+        } else {
+            if is_negative_zero {
+                reductions.push(FloatReduction::NegativeZeroToZero);
+            }
+            match encoding {
+                FloatEncoding::Integer => reductions.push(FloatReduction::ToInteger),
+                FloatEncoding::F16 => reductions.push(FloatReduction::ToF16),
+                FloatEncoding::F32 => reductions.push(FloatReduction::ToF32),
+                FloatEncoding::F64 => {},
+            }
+        }
+        let is_encoded_subnormal = match encoding {
+            FloatEncoding::Integer => false,
+            FloatEncoding::F16 => f16::from_f64(value).classify() == num::FpCategory::Subnormal,
+            FloatEncoding::F32 => (value as f32).is_subnormal(),
+            FloatEncoding::F64 => value.is_subnormal(),
+        };
+        FloatAnalysis {
+            value,
+            is_negative_zero,
+            is_subnormal: value.is_subnormal(),
+            is_encoded_subnormal,
+            fits_f32: value.is_nan() || value as f32 as f64 == value,
+            fits_f16: value.is_nan() || f16::from_f64(value).to_f64() == value,
+            encoding,
+            reductions,
+        }
+    }
+}
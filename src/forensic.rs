@@ -0,0 +1,282 @@
+import_stdlib!();
+
+#[cfg(feature = "float")]
+use half::f16;
+#[cfg(feature = "float")]
+use crate::float::{validate_canonical_f16, validate_canonical_f32, validate_canonical_f64};
+use crate::{
+    string_util::from_utf8,
+    text::is_nfc,
+    CBORError, CBORKind, CBOR,
+};
+
+/// An item found by [`CBOR::forensic_scan`].
+///
+/// An event describes the head of an item and, for strings, its payload.
+/// The items nested in an array, map, or tag follow it as separate events.
+#[derive(Debug)]
+pub struct ForensicEvent {
+    /// The major type of the item.
+    pub kind: CBORKind,
+    /// The argument of the item's head: the value of an integer, the length
+    /// of a string, the number of elements in an array or entries in a map,
+    /// the tag value, or the simple value or bits of a float.
+    ///
+    /// `None` for an indefinite-length string, array, or map, and for the
+    /// "break" that ends one, which has the kind [`CBORKind::Simple`].
+    pub argument: Option<u64>,
+    /// The offset of the item in the input.
+    pub offset: usize,
+    /// The number of bytes occupied by the head and any string payload,
+    /// excluding nested items.
+    pub len: usize,
+    /// The nesting level of the item, with top-level items at depth zero.
+    pub depth: usize,
+    /// The ways in which the item breaks the rules of dCBOR, in the order
+    /// they were found.
+    pub issues: Vec<CBORError>,
+}
+
+/// The result of [`CBOR::forensic_scan`].
+#[derive(Debug)]
+pub struct ForensicReport {
+    /// The items found, in the order they appear in the input.
+    pub events: Vec<ForensicEvent>,
+    /// The error that ended the scan early, with the offset at which it was
+    /// found, if the input could not be scanned to the end.
+    pub error: Option<(usize, CBORError)>,
+}
+
+impl ForensicReport {
+    /// Returns `true` if the input was scanned to the end and no item broke
+    /// the rules of dCBOR.
+    pub fn is_valid(&self) -> bool {
+        self.error.is_none() && self.events.iter().all(|event| event.issues.is_empty())
+    }
+}
+
+/// A container or tag whose nested items are being scanned.
+struct Frame {
+    kind: CBORKind,
+    /// The number of nested items left, counting keys and values
+    /// separately, or `None` if the container has indefinite length.
+    remaining: Option<u64>,
+    /// The number of nested items scanned so far.
+    count: u64,
+    /// The event index and offset of the map key being scanned.
+    key: (usize, usize),
+    /// The range of the previous key of a map.
+    previous_key: Option<ops::Range<usize>>,
+}
+
+impl CBOR {
+    /// Scans data that may not be valid dCBOR, reporting each item found
+    /// along with the ways it breaks the rules of dCBOR.
+    ///
+    /// This is for recovery and forensic tools that need to inspect malformed
+    /// data, rather than getting the single error that
+    /// [`CBOR::try_from_data`] returns. Problems that leave the structure of
+    /// the data intact, such as non-canonical numbers, invalid or
+    /// non-normalized text, misordered or duplicate map keys,
+    /// indefinite-length items, unassigned simple values, and data following
+    /// the first item, are recorded as issues on the items and scanning
+    /// continues, with any data following the first item being scanned as
+    /// further top-level items. Scanning stops at the first error that makes
+    /// the structure impossible to follow, such as truncated data.
+    pub fn forensic_scan(data: impl AsRef<[u8]>) -> ForensicReport {
+        let data = data.as_ref();
+        let mut events = Vec::new();
+        let error = scan(data, &mut events).err();
+        ForensicReport { events, error }
+    }
+}
+
+fn scan(data: &[u8], events: &mut Vec<ForensicEvent>) -> Result<(), (usize, CBORError)> {
+    if data.is_empty() {
+        return Err((0, CBORError::Underrun));
+    }
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut pos = 0;
+    let mut top_level_items = 0;
+    while pos < data.len() || !stack.is_empty() {
+        let depth = stack.len();
+        let mut event = ForensicEvent { kind: CBORKind::Simple, argument: None, offset: pos, len: 0, depth, issues: Vec::new() };
+        if depth == 0 {
+            if top_level_items == 1 {
+                event.issues.push(CBORError::UnusedData(data.len() - pos));
+            }
+            top_level_items += 1;
+        }
+        let header = *data.get(pos).ok_or((pos, CBORError::Underrun))?;
+        if header == 0xff {
+            match stack.last() {
+                Some(frame) if frame.remaining.is_none() => {
+                    if frame.kind == CBORKind::Map && !frame.count.is_multiple_of(2) {
+                        return Err((pos, CBORError::Underrun));
+                    }
+                },
+                _ => return Err((pos, CBORError::UnsupportedHeaderValue(31))),
+            }
+            event.len = 1;
+            event.depth -= 1;
+            events.push(event);
+            stack.pop();
+            pos += 1;
+            complete_item(data, &mut stack, events, pos);
+            continue;
+        }
+
+        let (kind, argument, head_len) = scan_head(data, pos, &mut event.issues)?;
+        event.kind = kind;
+        event.argument = argument;
+        event.len = head_len;
+
+        if let Some(frame) = stack.last_mut() {
+            if matches!(frame.kind, CBORKind::ByteString | CBORKind::Text) && (kind != frame.kind || argument.is_none()) {
+                return Err((pos, CBORError::WrongType));
+            }
+            if frame.kind == CBORKind::Map && frame.count.is_multiple_of(2) {
+                frame.key = (events.len(), pos);
+            }
+        }
+
+        match (kind, argument) {
+            (CBORKind::ByteString | CBORKind::Text, Some(len)) => {
+                let payload = usize::try_from(len).ok()
+                    .and_then(|len| data.get(pos + head_len..)?.get(..len))
+                    .ok_or((pos, CBORError::Underrun))?;
+                if kind == CBORKind::Text {
+                    match from_utf8(payload) {
+                        Ok(text) if !is_nfc(text) => event.issues.push(CBORError::NonCanonicalString),
+                        Ok(_) => { },
+                        Err(err) => event.issues.push(CBORError::InvalidString(err)),
+                    }
+                }
+                event.len += payload.len();
+                pos += event.len;
+                events.push(event);
+                complete_item(data, &mut stack, events, pos);
+            },
+            (CBORKind::ByteString | CBORKind::Text | CBORKind::Array | CBORKind::Map | CBORKind::Tagged, _) => {
+                let remaining = match (kind, argument) {
+                    (CBORKind::Map, Some(n)) => Some(n.saturating_mul(2)),
+                    (CBORKind::Tagged, _) => Some(1),
+                    _ => argument,
+                };
+                pos += head_len;
+                events.push(event);
+                stack.push(Frame { kind, remaining, count: 0, key: (0, 0), previous_key: None });
+                if remaining == Some(0) {
+                    stack.pop();
+                    complete_item(data, &mut stack, events, pos);
+                }
+            },
+            _ => {
+                pos += head_len;
+                events.push(event);
+                complete_item(data, &mut stack, events, pos);
+            },
+        }
+    }
+    Ok(())
+}
+
+/// Parses the head of the item at `pos`, returning its kind, its argument,
+/// and its length, and recording any issues with it.
+fn scan_head(data: &[u8], pos: usize, issues: &mut Vec<CBORError>) -> Result<(CBORKind, Option<u64>, usize), (usize, CBORError)> {
+    let header = data[pos];
+    let kind = match header >> 5 {
+        0 => CBORKind::Unsigned,
+        1 => CBORKind::Negative,
+        2 => CBORKind::ByteString,
+        3 => CBORKind::Text,
+        4 => CBORKind::Array,
+        5 => CBORKind::Map,
+        6 => CBORKind::Tagged,
+        _ => CBORKind::Simple,
+    };
+    let additional = header & 31;
+    let arg_len = match additional {
+        0..=23 => 0,
+        24 => 1,
+        25 => 2,
+        26 => 4,
+        27 => 8,
+        31 if matches!(kind, CBORKind::ByteString | CBORKind::Text | CBORKind::Array | CBORKind::Map) => {
+            issues.push(CBORError::UnsupportedHeaderValue(31));
+            return Ok((kind, None, 1));
+        },
+        _ => return Err((pos, CBORError::UnsupportedHeaderValue(additional))),
+    };
+    let bytes = data.get(pos + 1..pos + 1 + arg_len).ok_or((pos, CBORError::Underrun))?;
+    let argument = if arg_len == 0 {
+        additional as u64
+    } else {
+        bytes.iter().fold(0u64, |value, byte| (value << 8) | *byte as u64)
+    };
+    if kind == CBORKind::Simple {
+        let canonical: anyhow::Result<()> = match additional {
+            20..=22 => Ok(()),
+            #[cfg(feature = "float")]
+            25 => validate_canonical_f16(f16::from_bits(argument as u16)),
+            #[cfg(feature = "float")]
+            26 => validate_canonical_f32(f32::from_bits(argument as u32)),
+            #[cfg(feature = "float")]
+            27 => validate_canonical_f64(f64::from_bits(argument)),
+            #[cfg(not(feature = "float"))]
+            25..=27 => {
+                issues.push(CBORError::UnsupportedFloat);
+                Ok(())
+            },
+            _ => {
+                issues.push(CBORError::InvalidSimpleValue);
+                Ok(())
+            },
+        };
+        if canonical.is_err() {
+            issues.push(CBORError::NonCanonicalNumeric);
+        }
+    } else {
+        let minimal = match arg_len {
+            0 => true,
+            1 => argument >= 24,
+            2 => argument > u8::MAX as u64,
+            4 => argument > u16::MAX as u64,
+            _ => argument > u32::MAX as u64,
+        };
+        if !minimal {
+            issues.push(CBORError::NonCanonicalNumeric);
+        }
+    }
+    Ok((kind, Some(argument), 1 + arg_len))
+}
+
+/// Records that an item ending at `end` has been scanned, checking the order
+/// of map keys and closing any containers the item completes.
+fn complete_item(data: &[u8], stack: &mut Vec<Frame>, events: &mut [ForensicEvent], end: usize) {
+    while let Some(frame) = stack.last_mut() {
+        if frame.kind == CBORKind::Map && frame.count.is_multiple_of(2) {
+            let (key_event, key_start) = frame.key;
+            if let Some(previous) = &frame.previous_key {
+                let key = &data[key_start..end];
+                match data[previous.clone()].cmp(key) {
+                    cmp::Ordering::Equal => events[key_event].issues.push(CBORError::DuplicateMapKey),
+                    cmp::Ordering::Greater => events[key_event].issues.push(CBORError::MisorderedMapKey),
+                    cmp::Ordering::Less => { },
+                }
+            }
+            frame.previous_key = Some(key_start..end);
+        }
+        frame.count += 1;
+        match &mut frame.remaining {
+            Some(remaining) => {
+                *remaining -= 1;
+                if *remaining > 0 {
+                    return;
+                }
+            },
+            None => return,
+        }
+        stack.pop();
+    }
+}
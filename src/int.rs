@@ -1,6 +1,6 @@
 import_stdlib!();
 
-use crate::{CBOR, CBORError};
+use crate::{CBOR, CBORError, ExactFrom};
 
 use super::{CBORCase, varint::{EncodeVarInt, MajorType}};
 
@@ -32,17 +32,32 @@ macro_rules! impl_cbor {
             }
         }
 
+        impl From<&$type> for CBOR {
+            fn from(value: &$type) -> Self {
+                (*value).into()
+            }
+        }
+
+        impl_try_from_cbor!($type);
+    };
+}
+
+/// Implements `TryFrom<CBOR>` for an integer type, failing with
+/// `CBORError::OutOfRange` if the value does not fit exactly.
+macro_rules! impl_try_from_cbor {
+    ($type: ty) => {
         impl TryFrom<CBOR> for $type {
             type Error = Error;
 
             fn try_from(cbor: CBOR) -> Result<Self> {
-                match cbor.into_case() {
-                    CBORCase::Unsigned(n) => Self::from_u64(n, <$type>::MAX as u64, |x| x as $type),
-                    CBORCase::Negative(n) => {
-                        let a = Self::from_u64(n, <$type>::MAX as u64, |x| x as $type)? as i128;
-                        Ok((-1 - a) as $type)
-                    }
+                let value = match cbor.as_case() {
+                    CBORCase::Unsigned(n) => <$type>::exact_from_u64(*n),
+                    CBORCase::Negative(n) => <$type>::exact_from_i128(-1 - *n as i128),
                     _ => bail!(CBORError::WrongType),
+                };
+                match value {
+                    Some(value) => Ok(value),
+                    None => bail!(CBORError::OutOfRange),
                 }
             }
         }
@@ -58,26 +73,42 @@ impl_cbor!(i8);
 impl_cbor!(i16);
 impl_cbor!(i32);
 impl_cbor!(i64);
+impl_cbor!(isize);
+
+// 128-bit integers can hold values dCBOR integers cannot, so convert only from
+// CBOR.
+impl_try_from_cbor!(u128);
+impl_try_from_cbor!(i128);
 
 pub trait From64 {
     fn cbor_data(&self) -> Vec<u8>;
+}
 
-    fn from_u64<F>(n: u64, max: u64, f: F) -> Result<Self>
-    where F: Fn(u64) -> Self, Self: Sized
-    {
-        if n > max {
-            bail!(CBORError::OutOfRange)
-        }
-        Ok(f(n))
+/// Access to the full range of CBOR integers, including negative integers
+/// below `i64::MIN`.
+impl CBOR {
+    /// Creates the negative integer `-1 - n`.
+    ///
+    /// This reaches every negative integer dCBOR can encode, down to
+    /// -18446744073709551616.
+    pub fn from_negative_raw(n: u64) -> CBOR {
+        CBORCase::Negative(n).into()
     }
 
-    #[allow(dead_code)]
-    fn from_i64<F>(n: i64, min: i64, max: i64, f: F) -> Result<Self>
-    where F: Fn(i64) -> Self, Self: Sized
-    {
-        if n > max || n > min {
-            bail!(CBORError::OutOfRange)
+    /// Returns `n` if this is the negative integer `-1 - n`, as it is
+    /// encoded, and `None` otherwise.
+    pub fn as_negative_raw(&self) -> Option<u64> {
+        match self.as_case() {
+            CBORCase::Negative(n) => Some(*n),
+            _ => None,
         }
-        Ok(f(n))
+    }
+
+    /// Extracts the value of an unsigned or negative integer, which always
+    /// fits in an `i128`.
+    ///
+    /// Returns `Err` if the value is not an integer.
+    pub fn try_into_i128(self) -> Result<i128> {
+        i128::try_from(self)
     }
 }
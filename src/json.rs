@@ -0,0 +1,511 @@
+import_stdlib!();
+
+use anyhow::{Error, Result};
+
+use crate::{numeric::{decrement, trimmed}, text::to_nfc, CBORCase, CBORError, DuplicatePolicy, Map, Simple, CBOR, TAG_NEGATIVE_BIGNUM, TAG_POSITIVE_BIGNUM};
+
+/// How JSON numbers are mapped to CBOR by [`from_json_canonical`].
+///
+/// Without the `float` feature, numbers that are not integers are rejected
+/// under every policy, and `Float` is the same as `IntegersOnly`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NumberPolicy {
+    /// Integers become CBOR integers, or bignums (tags 2 and 3) if they are
+    /// outside the 64-bit range. Other numbers become the nearest `f64`.
+    #[default]
+    Preserve,
+    /// Every number becomes the nearest `f64`. As always, floats with no
+    /// fractional part are then encoded as integers.
+    Float,
+    /// Only integers in the 64-bit range are accepted; any other number is
+    /// an error.
+    IntegersOnly,
+}
+
+/// Converts JSON text to dCBOR, rejecting duplicate object keys.
+///
+/// This provides a single, well-defined path from JSON to deterministic
+/// bytes suitable for signing: the encoding of the result depends only on the
+/// JSON data model content of the input, not on its formatting, key order, or
+/// escaping. Objects become maps with text keys, and numbers are mapped
+/// according to the given policy.
+///
+/// Returns an error if the input is not valid JSON, contains a number the
+/// policy does not accept or that overflows `f64`, contains an object with a
+/// duplicate key, or is nested more deeply than
+/// [`JsonOptions::DEFAULT_MAX_DEPTH`].
+pub fn from_json_canonical(json: &str, policy: NumberPolicy) -> Result<CBOR> {
+    from_json_canonical_with(json, policy, DuplicatePolicy::Error)
+}
+
+/// Converts JSON text to dCBOR as [`from_json_canonical`] does, handling
+/// duplicate object keys according to the given policy.
+pub fn from_json_canonical_with(json: &str, policy: NumberPolicy, duplicates: DuplicatePolicy) -> Result<CBOR> {
+    from_json_canonical_opt(json, &JsonOptions::default().number_policy(policy).duplicates(duplicates))
+}
+
+/// Converts JSON text to dCBOR as [`from_json_canonical`] does, with the
+/// given options.
+pub fn from_json_canonical_opt(json: &str, options: &JsonOptions) -> Result<CBOR> {
+    let mut parser = JsonParser { text: json, pos: 0, options };
+    let cbor = parser.parse_value(0)?;
+    parser.skip_whitespace();
+    if parser.pos < json.len() {
+        return Err(parser.error("unexpected trailing characters"));
+    }
+    Ok(cbor)
+}
+
+/// Options controlling how JSON is converted by [`from_json_canonical_opt`].
+///
+/// Options are set using the builder-style methods, starting from
+/// `JsonOptions::default()`, which has the behavior of
+/// [`from_json_canonical`] with [`NumberPolicy::Preserve`].
+#[derive(Debug, Clone)]
+pub struct JsonOptions {
+    number_policy: NumberPolicy,
+    duplicates: DuplicatePolicy,
+    max_depth: Option<usize>,
+}
+
+impl JsonOptions {
+    /// The default for [`JsonOptions::max_depth`].
+    pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+    /// How numbers are mapped to CBOR. Defaults to
+    /// [`NumberPolicy::Preserve`].
+    pub fn number_policy(mut self, number_policy: NumberPolicy) -> Self {
+        self.number_policy = number_policy;
+        self
+    }
+
+    /// How duplicate object keys are handled. Defaults to
+    /// [`DuplicatePolicy::Error`].
+    pub fn duplicates(mut self, duplicates: DuplicatePolicy) -> Self {
+        self.duplicates = duplicates;
+        self
+    }
+
+    /// The maximum depth of nesting, where the converted value is at depth 0
+    /// and the members of an array or object are one deeper than it. Deeper
+    /// values cause the conversion to fail with [`CBORError::InvalidJson`].
+    /// Defaults to [`JsonOptions::DEFAULT_MAX_DEPTH`].
+    ///
+    /// The parser recurses for each level of nesting, so removing the limit
+    /// lets untrusted input overflow the stack.
+    pub fn max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+}
+
+impl Default for JsonOptions {
+    fn default() -> Self {
+        Self {
+            number_policy: NumberPolicy::default(),
+            duplicates: DuplicatePolicy::Error,
+            max_depth: Some(Self::DEFAULT_MAX_DEPTH),
+        }
+    }
+}
+
+struct JsonParser<'a> {
+    text: &'a str,
+    pos: usize,
+    options: &'a JsonOptions,
+}
+
+impl<'a> JsonParser<'a> {
+    fn error(&self, message: &str) -> Error {
+        Error::msg(CBORError::InvalidJson(self.pos, message.to_string()))
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<()> {
+        self.skip_whitespace();
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("expected '{}'", expected as char)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<CBOR> {
+        self.skip_whitespace();
+        if self.options.max_depth.is_some_and(|max| depth > max) {
+            return Err(self.error("nesting too deep"));
+        }
+        match self.peek() {
+            Some(b'{') => self.parse_object(depth),
+            Some(b'[') => self.parse_array(depth),
+            Some(b'"') => Ok(self.parse_string()?.into()),
+            Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            Some(b't') => self.parse_literal("true", CBOR::r#true()),
+            Some(b'f') => self.parse_literal("false", CBOR::r#false()),
+            Some(b'n') => self.parse_literal("null", CBOR::null()),
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn parse_literal(&mut self, literal: &str, value: CBOR) -> Result<CBOR> {
+        if !self.text[self.pos..].starts_with(literal) {
+            return Err(self.error("expected a value"));
+        }
+        self.pos += literal.len();
+        Ok(value)
+    }
+
+    fn parse_object(&mut self, depth: usize) -> Result<CBOR> {
+        self.expect(b'{')?;
+        let mut entries = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+        } else {
+            loop {
+                self.skip_whitespace();
+                let key = self.parse_string()?;
+                self.expect(b':')?;
+                let value = self.parse_value(depth + 1)?;
+                entries.push((key, value));
+                self.skip_whitespace();
+                match self.peek() {
+                    Some(b',') => self.pos += 1,
+                    Some(b'}') => {
+                        self.pos += 1;
+                        break;
+                    },
+                    _ => return Err(self.error("expected ',' or '}'")),
+                }
+            }
+        }
+        Ok(Map::from_iter_with(entries, self.options.duplicates)?.into())
+    }
+
+    fn parse_array(&mut self, depth: usize) -> Result<CBOR> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(items.into());
+        }
+        loop {
+            items.push(self.parse_value(depth + 1)?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(items.into());
+                },
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String> {
+        if self.peek() != Some(b'"') {
+            return Err(self.error("expected a string"));
+        }
+        self.pos += 1;
+        let mut result = String::new();
+        loop {
+            let rest = &self.text[self.pos..];
+            let c = match rest.chars().next() {
+                Some(c) => c,
+                None => return Err(self.error("unterminated string")),
+            };
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(result),
+                '\\' => result.push(self.parse_escape()?),
+                c if (c as u32) < 0x20 => return Err(self.error("unescaped control character in string")),
+                c => result.push(c),
+            }
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<char> {
+        let c = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+        self.pos += 1;
+        let c = match c {
+            b'"' => '"',
+            b'\\' => '\\',
+            b'/' => '/',
+            b'b' => '\u{08}',
+            b'f' => '\u{0c}',
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'u' => {
+                let high = self.parse_hex4()?;
+                let code = if (0xd800..0xdc00).contains(&high) {
+                    if !self.text[self.pos..].starts_with("\\u") {
+                        return Err(self.error("unpaired surrogate"));
+                    }
+                    self.pos += 2;
+                    let low = self.parse_hex4()?;
+                    if !(0xdc00..0xe000).contains(&low) {
+                        return Err(self.error("unpaired surrogate"));
+                    }
+                    0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+                } else {
+                    high
+                };
+                char::from_u32(code).ok_or_else(|| self.error("unpaired surrogate"))?
+            },
+            _ => return Err(self.error("invalid escape")),
+        };
+        Ok(c)
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32> {
+        let digits = self.text.get(self.pos..self.pos + 4).ok_or_else(|| self.error("invalid unicode escape"))?;
+        if !digits.bytes().all(|c| c.is_ascii_hexdigit()) {
+            return Err(self.error("invalid unicode escape"));
+        }
+        self.pos += 4;
+        Ok(u32::from_str_radix(digits, 16).unwrap())
+    }
+
+    fn parse_number(&mut self) -> Result<CBOR> {
+        let start = self.pos;
+        let bytes = self.text.as_bytes();
+        let digits = |pos: &mut usize| {
+            let digits_start = *pos;
+            while bytes.get(*pos).is_some_and(u8::is_ascii_digit) {
+                *pos += 1;
+            }
+            *pos - digits_start
+        };
+        let mut pos = self.pos;
+        let negative = bytes[pos] == b'-';
+        if negative {
+            pos += 1;
+        }
+        let int_start = pos;
+        let int_len = digits(&mut pos);
+        if int_len == 0 || (int_len > 1 && bytes[int_start] == b'0') {
+            return Err(self.error("invalid number"));
+        }
+        let mut is_integer = true;
+        if bytes.get(pos) == Some(&b'.') {
+            pos += 1;
+            is_integer = false;
+            if digits(&mut pos) == 0 {
+                return Err(self.error("invalid number"));
+            }
+        }
+        if matches!(bytes.get(pos), Some(b'e' | b'E')) {
+            pos += 1;
+            is_integer = false;
+            if matches!(bytes.get(pos), Some(b'+' | b'-')) {
+                pos += 1;
+            }
+            if digits(&mut pos) == 0 {
+                return Err(self.error("invalid number"));
+            }
+        }
+        self.pos = pos;
+        let magnitude = &self.text[int_start..int_start + int_len];
+
+        if is_integer && (cfg!(not(feature = "float")) || self.options.number_policy != NumberPolicy::Float) {
+            if let Ok(n) = magnitude.parse::<u128>() {
+                if !negative {
+                    if let Ok(n) = u64::try_from(n) {
+                        return Ok(n.into());
+                    }
+                } else if n == 0 {
+                    return Ok(0.into());
+                } else if let Ok(n) = u64::try_from(n - 1) {
+                    return Ok(CBORCase::Negative(n).into());
+                }
+            }
+            if self.options.number_policy == NumberPolicy::Preserve {
+                return Ok(bignum(negative, magnitude));
+            }
+        }
+        #[cfg(feature = "float")]
+        if self.options.number_policy != NumberPolicy::IntegersOnly {
+            let f: f64 = self.text[start..pos].parse().map_err(|_| self.error("invalid number"))?;
+            if !f.is_finite() {
+                self.pos = start;
+                return Err(self.error("number is out of range"));
+            }
+            return Ok(f.into());
+        }
+        self.pos = start;
+        Err(self.error("number is not a 64-bit integer"))
+    }
+}
+
+/// Returns the bignum for the integer with the given sign and decimal
+/// magnitude.
+fn bignum(negative: bool, decimal: &str) -> CBOR {
+    // Big-endian bytes, built by multiplying by ten and adding each digit.
+    let mut bytes: Vec<u8> = Vec::new();
+    for digit in decimal.bytes() {
+        let mut carry = (digit - b'0') as u32;
+        for byte in bytes.iter_mut().rev() {
+            let value = *byte as u32 * 10 + carry;
+            *byte = value as u8;
+            carry = value >> 8;
+        }
+        while carry > 0 {
+            bytes.insert(0, carry as u8);
+            carry >>= 8;
+        }
+    }
+    let tag = if negative {
+        // Negative bignums hold `-1 - n`.
+        decrement(&mut bytes);
+        TAG_NEGATIVE_BIGNUM
+    } else {
+        TAG_POSITIVE_BIGNUM
+    };
+    CBOR::to_tagged_value(tag, CBOR::to_byte_string(trimmed(&bytes)))
+}
+
+impl CBOR {
+    /// Returns this value as canonical JSON, as specified by RFC 8785 (the
+    /// JSON Canonicalization Scheme).
+    ///
+    /// Canonical JSON has no whitespace, object members sorted by the UTF-16
+    /// code units of their names, minimal string escaping, and numbers
+    /// formatted as ECMAScript formats them, so the output depends only on
+    /// the value.
+    ///
+    /// Only values that map cleanly to JSON are accepted. Returns
+    /// [`CBORError::NotJson`] if the value contains a byte string, a tag, a
+    /// map with a key that is not text, a NaN or infinite float, or an
+    /// integer that a double cannot hold exactly, since JSON numbers are read
+    /// as doubles.
+    pub fn to_json_canonical(&self) -> Result<String> {
+        let mut json = String::new();
+        self.write_json_canonical(&mut json)?;
+        Ok(json)
+    }
+
+    fn write_json_canonical(&self, json: &mut String) -> Result<()> {
+        match self.as_case() {
+            CBORCase::Unsigned(n) => write_json_integer(*n as i128, json)?,
+            CBORCase::Negative(n) => write_json_integer(-1 - *n as i128, json)?,
+            CBORCase::ByteString(_) => return Err(not_json("byte strings are not supported")),
+            CBORCase::Text(s) => write_json_string(&to_nfc(s), json),
+            CBORCase::Array(array) => {
+                json.push('[');
+                for (index, item) in array.iter().enumerate() {
+                    if index > 0 {
+                        json.push(',');
+                    }
+                    item.write_json_canonical(json)?;
+                }
+                json.push(']');
+            },
+            CBORCase::Map(map) => {
+                let mut members = Vec::with_capacity(map.len());
+                for (key, value) in map.iter() {
+                    match key.as_case() {
+                        CBORCase::Text(name) => members.push((to_nfc(name), value)),
+                        _ => return Err(not_json(format!("map key {} is not text", key.display_for_error()))),
+                    }
+                }
+                members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+                json.push('{');
+                for (index, (name, value)) in members.into_iter().enumerate() {
+                    if index > 0 {
+                        json.push(',');
+                    }
+                    write_json_string(&name, json);
+                    json.push(':');
+                    value.write_json_canonical(json)?;
+                }
+                json.push('}');
+            },
+            CBORCase::Tagged(tag, _) => return Err(not_json(format!("tag {} is not supported", tag.value()))),
+            CBORCase::Simple(simple) => match simple {
+                Simple::False => json.push_str("false"),
+                Simple::True => json.push_str("true"),
+                Simple::Null => json.push_str("null"),
+                #[cfg(feature = "float")]
+                Simple::Float(f) => {
+                    if !f.is_finite() {
+                        return Err(not_json(format!("{} is not a JSON number", f)));
+                    }
+                    json.push_str(&format_ecmascript_number(*f));
+                },
+            },
+        }
+        Ok(())
+    }
+}
+
+fn not_json(reason: impl Into<String>) -> Error {
+    Error::msg(CBORError::NotJson(reason.into()))
+}
+
+/// Writes an integer as the JSON number for the double holding it exactly.
+fn write_json_integer(n: i128, json: &mut String) -> Result<()> {
+    let f = n as f64;
+    if f as i128 != n {
+        return Err(not_json(format!("integer {} cannot be held exactly by a double", n)));
+    }
+    json.push_str(&format_ecmascript_number(f));
+    Ok(())
+}
+
+/// Writes a JSON string literal, escaping only what RFC 8785 requires.
+fn write_json_string(s: &str, json: &mut String) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\u{08}' => json.push_str("\\b"),
+            '\t' => json.push_str("\\t"),
+            '\n' => json.push_str("\\n"),
+            '\u{0c}' => json.push_str("\\f"),
+            '\r' => json.push_str("\\r"),
+            c if c < ' ' => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+/// Formats a finite double as ECMAScript's `Number.prototype.toString` does.
+fn format_ecmascript_number(f: f64) -> String {
+    if f == 0.0 {
+        return "0".to_string();
+    }
+    // The shortest digits that round-trip, and the exponent `n` such that the
+    // value is `0.digits × 10^n`.
+    let scientific = format!("{:e}", f.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    let n = exponent.parse::<i32>().unwrap() + 1;
+    let sign = if f < 0.0 { "-" } else { "" };
+    let body = if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat(-n as usize), digits)
+    } else {
+        let exponent_sign = if n - 1 < 0 { "-" } else { "+" };
+        let fraction = if k > 1 { format!(".{}", &digits[1..]) } else { String::new() };
+        format!("{}{}e{}{}", &digits[..1], fraction, exponent_sign, (n - 1).abs())
+    };
+    format!("{}{}", sign, body)
+}
@@ -0,0 +1,106 @@
+import_stdlib!();
+
+use crate::{ByteString, CBORCase, Map, Simple, CBOR};
+
+/// The major type of a CBOR value, without its content.
+///
+/// This allows cheap dispatch on the type of a value, e.g. for routing,
+/// without borrowing or cloning its content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CBORKind {
+    Unsigned,
+    Negative,
+    ByteString,
+    Text,
+    Array,
+    Map,
+    Tagged,
+    Simple,
+}
+
+impl fmt::Display for CBORKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CBORKind::Unsigned => "unsigned",
+            CBORKind::Negative => "negative",
+            CBORKind::ByteString => "byte string",
+            CBORKind::Text => "text",
+            CBORKind::Array => "array",
+            CBORKind::Map => "map",
+            CBORKind::Tagged => "tagged",
+            CBORKind::Simple => "simple",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl CBOR {
+    /// Returns the major type of this value.
+    pub fn kind(&self) -> CBORKind {
+        match self.as_case() {
+            CBORCase::Unsigned(_) => CBORKind::Unsigned,
+            CBORCase::Negative(_) => CBORKind::Negative,
+            CBORCase::ByteString(_) => CBORKind::ByteString,
+            CBORCase::Text(_) => CBORKind::Text,
+            CBORCase::Array(_) => CBORKind::Array,
+            CBORCase::Map(_) => CBORKind::Map,
+            CBORCase::Tagged(_, _) => CBORKind::Tagged,
+            CBORCase::Simple(_) => CBORKind::Simple,
+        }
+    }
+
+    /// Returns the empty or zero value of the given kind: `0`, `-1`, `h''`,
+    /// `""`, `[]`, `{}`, or `null`.
+    ///
+    /// Returns `None` for [`CBORKind::Tagged`], as there is no default tag.
+    ///
+    /// ```
+    /// # use dcbor::{prelude::*, CBORKind};
+    /// assert_eq!(CBOR::default_for_kind(CBORKind::Map), Some(Map::new().into()));
+    /// assert_eq!(CBOR::default_for_kind(CBORKind::Tagged), None);
+    /// ```
+    pub fn default_for_kind(kind: CBORKind) -> Option<CBOR> {
+        let case = match kind {
+            CBORKind::Unsigned => CBORCase::Unsigned(0),
+            CBORKind::Negative => CBORCase::Negative(0),
+            CBORKind::ByteString => CBORCase::ByteString(ByteString::default()),
+            CBORKind::Text => CBORCase::Text(String::new()),
+            CBORKind::Array => CBORCase::Array(Vec::new()),
+            CBORKind::Map => CBORCase::Map(Map::default()),
+            CBORKind::Tagged => return None,
+            CBORKind::Simple => CBORCase::Simple(Simple::Null),
+        };
+        Some(case.into())
+    }
+
+    /// Returns `true` if this is an unsigned or negative integer.
+    pub fn is_integer(&self) -> bool {
+        matches!(self.kind(), CBORKind::Unsigned | CBORKind::Negative)
+    }
+
+    /// Returns `true` if this is a floating point value.
+    ///
+    /// Note that floating point values with no fractional part are encoded
+    /// as integers, so are not floats.
+    pub fn is_float(&self) -> bool {
+        #[cfg(feature = "float")]
+        return matches!(self.as_case(), CBORCase::Simple(Simple::Float(_)));
+        #[cfg(not(feature = "float"))]
+        false
+    }
+
+    /// Returns `true` if this is an integer or floating point value.
+    pub fn is_number(&self) -> bool {
+        self.is_integer() || self.is_float()
+    }
+
+    /// Returns `true` if this is `true` or `false`.
+    pub fn is_bool(&self) -> bool {
+        matches!(self.as_case(), CBORCase::Simple(Simple::True | Simple::False))
+    }
+
+    /// Returns `true` if this is `null`.
+    pub fn is_null(&self) -> bool {
+        matches!(self.as_case(), CBORCase::Simple(Simple::Null))
+    }
+}
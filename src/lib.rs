@@ -1,4 +1,4 @@
-#![doc(html_root_url = "https://docs.rs/dcbor/0.16.1")]
+#![doc(html_root_url = "https://docs.rs/dcbor/0.17.0")]
 #![warn(rust_2018_idioms)]
 #![cfg_attr(not(feature = "std"), no_std)]
 
@@ -20,7 +20,7 @@
 //!
 //! ```toml
 //! [dependencies]
-//! dcbor = "0.16.1"
+//! dcbor = "0.17.0"
 //! ```
 //!
 //! # Features
@@ -32,22 +32,143 @@
 //!
 //! ```toml
 //! [dependencies.dcbor]
-//! version = "0.16.1"
+//! version = "0.17.0"
 //! features = ["multithreaded"]
 //! ```
 //!
 //! ## `no_std`
 //!
 //! The `dcbor` library is `no_std` compatible. To use it in a `no_std` environment, disable the
-//! default features in your `Cargo.toml` and enable the `no_std` feature:
+//! default features in your `Cargo.toml` and enable the `no_std` feature, and the `float`
+//! feature if you need floating point values:
 //!
 //! ```toml
 //! [dependencies.dcbor]
-//! version = "0.16.1"
+//! version = "0.17.0"
 //! default-features = false
-//! features = ["no_std"]
+//! features = ["no_std", "float"]
 //! ```
 //!
+//! ## `format`
+//!
+//! The `format` feature, enabled by default, provides the human-readable
+//! forms of CBOR: diagnostic notation and summaries (`CBOR::diagnostic`,
+//! `DiagFormatOpts`, and tag summarizers), annotated hex dumps
+//! (`CBOR::hex_annotated`), the compact debug notation, and date formatting
+//! (`Date::format` and `Display` for `Date`). Deeply embedded users who only
+//! need to encode and decode can disable it for a smaller build. `Display`
+//! for `CBOR` and for errors, and `CBOR::hex`, remain available.
+//!
+//! ```toml
+//! [dependencies.dcbor]
+//! version = "0.17.0"
+//! default-features = false
+//! features = ["std", "float"]
+//! ```
+//!
+//! ## `float`
+//!
+//! The `float` feature, enabled by default, provides floating point values:
+//! `Simple::Float`, conversions between `CBOR` and `f64`, `f32`, and `f16`,
+//! and the analysis of float encodings. Protocols that forbid floats can
+//! disable it, removing float encoding and decoding entirely and the `half`
+//! dependency with them. Without it, decoding a float fails with
+//! `CBORError::UnsupportedFloat`, JSON and compact debug text containing
+//! numbers that are not integers are rejected, and dates have a resolution of
+//! one second.
+//!
+//! Floats were previously always available. Builds that disable the default
+//! features, including `no_std` builds, must now enable `float` to keep them:
+//! code using float conversions fails to compile without it, and decoding
+//! data that contains floats fails at run time. `Simple` is now
+//! `#[non_exhaustive]`, so that matches on it compile whether or not another
+//! crate in the build enables `float`.
+//!
+//! ## Insertion order
+//!
+//! The `insertion-order` feature makes each `Map` record the order in which
+//! its keys were inserted, available from `Map::insertion_order`. Maps are
+//! still encoded in canonical order.
+//!
+//! ## Encoding cache
+//!
+//! The `encoding-cache` feature makes `CBOR::to_cbor_data` keep the encoding
+//! it returns with the value, so that encoding the same value again, or
+//! computing its hex or a digest of it, copies the encoding rather than
+//! serializing the whole tree. Only the values on which `to_cbor_data` is
+//! called are cached, not the items inside them, and a cached encoding is
+//! freed with its value. Values that are encoded once gain nothing and hold
+//! twice the memory, so the feature is off by default.
+//!
+//! ## Spans
+//!
+//! The `spans` feature adds `DecodeOptions::record_spans`, which records the
+//! range of input bytes each decoded item occupied, retrievable using
+//! `CBOR::encoded_span`. It is off by default, as it adds a range to every
+//! value whether or not spans are recorded.
+//!
+//! ## `ciborium`
+//!
+//! The `ciborium` feature adds conversions between `CBOR` and
+//! `ciborium::Value`, so codebases using both can convert values directly
+//! rather than by way of their encodings.
+//!
+//! ## `subtle`
+//!
+//! The `subtle` feature implements `subtle::ConstantTimeEq` for `ByteString`
+//! and `CBOR`, for comparing secrets without timing side channels. For
+//! `CBOR`, only the comparison of the encodings is constant time, as the time
+//! taken to encode a value depends on its data. No other comparison in this
+//! crate is constant time: this includes `==` and ordering on all types,
+//! looking up keys in a `Map`, and validating decoded data.
+//!
+//! ## `zeroize`
+//!
+//! The `zeroize` feature implements `Zeroize` for `CBOR`, `ByteString`, and
+//! `Map`, and adds methods such as `CBOR::into_zeroizing_bytes` for taking
+//! secrets out of decoded values in buffers that are zeroized when dropped.
+//!
+//! ## `wasm`
+//!
+//! The `wasm` feature adds conversions between CBOR byte strings and
+//! `js_sys::Uint8Array`, and `CBOR::to_js_value` for converting values to
+//! plain JavaScript values, for applications compiled to WebAssembly with
+//! `wasm-bindgen`.
+//!
+//! ## `ffi`
+//!
+//! The `ffi` feature adds the `ffi` module, a C ABI for building, decoding,
+//! encoding, and formatting dCBOR through opaque handles, byte buffers, and
+//! status codes, on which bindings for other languages can be built.
+//!
+//! ## `simd`
+//!
+//! The `simd` feature validates UTF-8 in decoded text strings with
+//! `simdutf8`, and encodes and decodes hex with `faster-hex`, using SIMD
+//! instructions where the target supports them. Results are the same as
+//! without it; only large strings and byte strings are noticeably faster.
+//!
+//! ## `text-encoding`
+//!
+//! The `text-encoding` feature adds encoding values as unpadded base64url and
+//! as base45, e.g. `CBOR::to_base64url`, for web and QR code transports that
+//! carry text, and encoding byte strings as padded base64.
+//!
+//! ## `ur`
+//!
+//! The `ur` feature adds encoding `CBOR` as Bytewords and as single-part URs
+//! (Uniform Resources), and decoding them, e.g. `CBOR::to_ur_string("seed")`.
+//!
+//! ## `reexport-stdlib`
+//!
+//! The `reexport-stdlib` feature, enabled by default, keeps re-exporting the
+//! standard library items used by this crate, such as `Vec`, `String`, and
+//! `HashMap`, from the crate root, as earlier versions always did. Crates
+//! that glob import `dcbor::*` alongside other crates can disable it to avoid
+//! ambiguous names. Builds with `default-features = false` must enable it to
+//! keep the re-exports. Items should be imported from `std` rather than
+//! `dcbor`, as the re-exports may be removed in a later version.
+//!
 //! # Specification
 //!
 //! The current specification of the norms and practices guiding the creation of
@@ -77,6 +198,7 @@
 //! use dcbor::prelude::*;
 //! let data = hex_literal::hex!("831903e81907d0190bb8");
 //! let cbor = CBOR::try_from_data(&data).unwrap();
+//! # #[cfg(feature = "format")]
 //! assert_eq!(cbor.diagnostic(), "[1000, 2000, 3000]");
 //! let array: Vec::<u32> = cbor.try_into().unwrap();
 //! assert_eq!(format!("{:?}", array), "[1000, 2000, 3000]");
@@ -91,36 +213,97 @@
 #[macro_use]
 mod stdlib;
 
+/// The result type returned by fallible functions in this crate, whose error
+/// is an `anyhow::Error`.
+///
+/// It is not part of the [`prelude`], so glob importing the prelude leaves
+/// the standard `Result` in place.
+pub type Result<T> = anyhow::Result<T>;
+
 mod cbor;
 pub use cbor::*;
 
+#[cfg(feature = "ciborium")]
+mod ciborium_value;
+
+#[cfg(feature = "ur")]
+mod ur;
+#[cfg(feature = "ur")]
+pub use ur::{bytewords_decode, bytewords_encode, BytewordsStyle};
+
+#[cfg(feature = "wasm")]
+mod wasm;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "text-encoding")]
+mod text_encoding;
+
+mod json;
+pub use json::{from_json_canonical, from_json_canonical_opt, from_json_canonical_with, JsonOptions, NumberPolicy};
+
+mod kind;
+pub use kind::CBORKind;
+
+mod display_limits;
+pub use display_limits::{BoundedDisplay, DisplayLimits};
+
 mod byte_string;
 pub use byte_string::ByteString;
 
 mod bool_value;
 
+mod chunk;
+
+#[cfg(feature = "float")]
 mod float;
+#[cfg(feature = "float")]
+pub use float::{FloatAnalysis, FloatEncoding, FloatReduction};
 
 mod array;
+pub use array::sort_canonical;
 
 mod error;
-pub use error::CBORError;
+pub use error::{CBORError, DecodeOffset, DisplayWithInput, FieldProblem, ValuePath};
 
 mod date;
 pub use date::Date;
+#[cfg(feature = "format")]
+pub use date::DateFormat;
 
+#[cfg(feature = "format")]
+mod debug_compact;
+#[cfg(feature = "format")]
 mod diag;
+#[cfg(feature = "format")]
+pub use diag::DiagFormatOpts;
+#[cfg(feature = "format")]
 mod dump;
+#[cfg(feature = "format")]
+pub use dump::DumpItem;
+mod forensic;
+pub use forensic::{ForensicEvent, ForensicReport};
 
 mod tags_store;
-pub use tags_store::{TagsStoreTrait, TagsStore, CBORSummarizer};
+pub use tags_store::{TagsStoreTrait, TagsStore, CBORTaggedDecoder, TagConflictPolicy, TagConflictHandler};
+#[cfg(feature = "format")]
+pub use tags_store::CBORSummarizer;
 
 mod tag;
 pub use tag::{Tag, TagValue};
 
+mod tag_allocator;
+pub use tag_allocator::{TagAllocator, TagRange, TagWarningHandler};
+
+mod tag_policy;
+pub use tag_policy::TagPolicy;
+
 mod tags;
 pub use tags::*;
 
+mod tagging;
+
 mod cbor_codable;
 pub use cbor_codable::{CBOREncodable, CBORDecodable, CBORCodable};
 
@@ -135,11 +318,57 @@ mod cbor_tagged_codable;
 pub use cbor_tagged_codable::CBORTaggedCodable;
 
 mod decode;
+pub use decode::DecodeOptions;
+mod encode;
+pub use encode::{EncodeOptions, EncodeSink, HashingSink};
+mod token_writer;
+pub use token_writer::TokenWriter;
+mod tokenizer;
+pub use tokenizer::{Token, Tokenizer};
+mod const_cbor;
+
+pub mod text;
+
+pub mod testkit;
+
+pub mod container;
+
+mod observer;
+pub use observer::CodecObserver;
+
+mod variant;
+pub use variant::{EnumCodec, UnitVariants};
+
+mod walk;
+pub use walk::{EdgeType, TreeIter, Visitor, WalkElement};
+
+mod redact;
+pub use redact::{PathElement, RedactionPolicy};
+mod path_matcher;
+pub use path_matcher::{PathMatch, PathMatcher, PathPattern};
+
+mod stats;
+pub use stats::{Stats, MajorTypeCounts};
 
 mod int;
 
+mod numeric_array;
+pub use numeric_array::NumericElement;
+mod numeric;
+
 mod map;
-pub use map::{Map, MapIter};
+pub use map::{Map, MapIter, MapIntoIter, MapKeys, MapValues, MapRange, DuplicatePolicy, FloatKeyPolicy};
+#[cfg(feature = "insertion-order")]
+pub use map::OrderedEntries;
+
+mod record;
+pub use record::Record;
+
+mod struct_fields;
+pub use struct_fields::FieldKind;
+
+mod field_space;
+pub use field_space::{FieldCriticality, FieldSpace};
 
 mod string;
 
@@ -153,3 +382,13 @@ mod exact;
 use exact::ExactFrom;
 
 pub mod prelude;
+
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(feature = "std")]
+    pub use std::{vec, vec::Vec};
+    #[cfg(not(feature = "std"))]
+    pub use crate::stdlib::without_std::{vec, Vec};
+    pub use anyhow::{Error, Result};
+    pub use crate::const_cbor::{const_cbor_encode, const_cbor_len};
+}
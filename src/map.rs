@@ -2,20 +2,70 @@ import_stdlib!();
 
 use anyhow::{bail, Error, Result};
 
-use crate::{CBOR, CBORError, CBORCase};
+use crate::{error::{with_path, within}, string_util::hex_encode, text::{is_nfc, to_nfc}, CBOR, CBORError, CBORCase, EncodeSink, PathElement, Simple};
 
 use super::varint::{EncodeVarInt, MajorType};
 
 /// A CBOR map.
 ///
 /// Keys are kept sorted by encoded CBOR form in ascending lexicographic order.
+/// All iteration over a `Map` (including [`Map::iter`], [`Map::keys`],
+/// [`Map::values`], [`Map::first`], and [`Map::last`]) follows this canonical
+/// order, which is the same order in which the entries are encoded.
+///
+/// With the `insertion-order` feature, the map also records the order in
+/// which its keys were first inserted, available from
+/// `Map::insertion_order`. This does not affect the encoding.
 #[derive(Clone)]
-pub struct Map(BTreeMap<MapKey, MapValue>);
+pub struct Map(
+    BTreeMap<MapKey, MapValue>,
+    #[cfg(feature = "insertion-order")] usize,
+);
 
 impl Map {
     /// Makes a new, empty CBOR `Map`.
     pub fn new() -> Map {
-        Map(BTreeMap::new())
+        Map(
+            BTreeMap::new(),
+            #[cfg(feature = "insertion-order")] 0,
+        )
+    }
+
+    /// Makes a map from entries whose keys are already in canonical order,
+    /// such as the entries of another map, building its B-tree in one pass
+    /// rather than inserting the entries one at a time.
+    ///
+    /// Returns [`CBORError::MisorderedMapKey`] or
+    /// [`CBORError::DuplicateMapKey`] if a key does not sort strictly after
+    /// the key before it.
+    pub fn from_presorted<K, V>(entries: impl IntoIterator<Item = (K, V)>) -> Result<Map>
+    where
+        K: Into<CBOR>, V: Into<CBOR>
+    {
+        let mut sorted: Vec<(MapKey, MapValue)> = Vec::new();
+        for (key, value) in entries {
+            let key = key.into();
+            let map_key = MapKey::new(key.encoded());
+            if let Some((last, _)) = sorted.last() {
+                match last.cmp(&map_key) {
+                    cmp::Ordering::Less => {},
+                    cmp::Ordering::Equal => bail!(CBORError::DuplicateMapKey),
+                    cmp::Ordering::Greater => bail!(CBORError::MisorderedMapKey),
+                }
+            }
+            #[cfg(feature = "insertion-order")]
+            let entry = MapValue { key, value: value.into(), ordinal: sorted.len() + 1 };
+            #[cfg(not(feature = "insertion-order"))]
+            let entry = MapValue::new(key, value.into());
+            sorted.push((map_key, entry));
+        }
+        #[cfg(feature = "insertion-order")]
+        let count = sorted.len();
+        // Collecting sorted entries bulk-builds the tree.
+        Ok(Map(
+            sorted.into_iter().collect(),
+            #[cfg(feature = "insertion-order")] count,
+        ))
     }
 
     /// Returns the number of entries in the map.
@@ -23,6 +73,7 @@ impl Map {
         self.0.len()
     }
 
+    /// Returns `true` if the map contains no entries.
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
@@ -34,11 +85,99 @@ impl Map {
         MapIter::new(self.0.values())
     }
 
+    /// Gets an iterator over the keys of the CBOR map, in canonical order.
+    pub fn keys(&self) -> MapKeys<'_> {
+        MapKeys(self.0.values())
+    }
+
+    /// Gets an iterator over the values of the CBOR map, in the canonical
+    /// order of their keys.
+    pub fn values(&self) -> MapValues<'_> {
+        MapValues(self.0.values())
+    }
+
+    /// Gets an iterator over the entries whose keys fall within the given
+    /// range, in canonical order.
+    ///
+    /// Range bounds are compared by their binary-encoded CBOR, the same
+    /// ordering used to sort the map. Note that this ordering sorts shorter
+    /// text strings before longer ones, so a range of text keys does not
+    /// correspond to a range of Unicode strings.
+    ///
+    /// Panics if the start of the range is greater than its end, or if the
+    /// start and end are equal and both excluded.
+    pub fn range<K, R>(&self, range: R) -> MapRange<'_>
+    where
+        K: Into<CBOR> + Clone, R: ops::RangeBounds<K>
+    {
+        fn encoded<K: Into<CBOR> + Clone>(bound: ops::Bound<&K>) -> ops::Bound<MapKey> {
+            match bound {
+                ops::Bound::Included(key) => ops::Bound::Included(MapKey::new(key.clone().into().encoded())),
+                ops::Bound::Excluded(key) => ops::Bound::Excluded(MapKey::new(key.clone().into().encoded())),
+                ops::Bound::Unbounded => ops::Bound::Unbounded,
+            }
+        }
+        let bounds = (encoded(range.start_bound()), encoded(range.end_bound()));
+        MapRange(self.0.range(bounds))
+    }
+
+    /// Returns the entry with the first key in canonical order, if any.
+    pub fn first(&self) -> Option<(&CBOR, &CBOR)> {
+        self.0.first_key_value().map(|(_, entry)| (&entry.key, &entry.value))
+    }
+
+    /// Returns the entry with the last key in canonical order, if any.
+    pub fn last(&self) -> Option<(&CBOR, &CBOR)> {
+        self.0.last_key_value().map(|(_, entry)| (&entry.key, &entry.value))
+    }
+
     /// Inserts a key-value pair into the map.
     pub fn insert(&mut self, key: impl Into<CBOR>, value: impl Into<CBOR>) {
         let key = key.into();
         let value = value.into();
-        self.0.insert(MapKey::new(key.to_cbor_data()), MapValue::new(key, value));
+        self.insert_entry(MapKey::new(key.encoded()), key, value);
+    }
+
+    fn insert_entry(&mut self, map_key: MapKey, key: CBOR, value: CBOR) {
+        #[cfg(feature = "insertion-order")]
+        {
+            // A replaced entry keeps its original position.
+            let ordinal = match self.0.get(&map_key) {
+                Some(entry) => entry.ordinal,
+                None => {
+                    self.1 += 1;
+                    self.1
+                },
+            };
+            self.0.insert(map_key, MapValue { key, value, ordinal });
+        }
+        #[cfg(not(feature = "insertion-order"))]
+        self.0.insert(map_key, MapValue::new(key, value));
+    }
+
+    /// Returns the entries of the map in the order their keys were first
+    /// inserted.
+    ///
+    /// Replacing the value of an existing key does not change its position.
+    /// Maps that were decoded are in canonical order. Encoding always uses
+    /// canonical order, so this is useful for presentation only, such as
+    /// showing fields in the order an author wrote them.
+    #[cfg(feature = "insertion-order")]
+    pub fn insertion_order(&self) -> OrderedEntries<'_> {
+        let mut entries: Vec<_> = self.0.values().collect();
+        entries.sort_by_key(|entry| entry.ordinal);
+        OrderedEntries(entries.into_iter())
+    }
+
+    /// Keeps only the entries for which the predicate returns `true`,
+    /// visiting them in canonical order.
+    ///
+    /// The predicate may change the values it visits, but not the keys.
+    pub fn retain<F>(&mut self, mut predicate: F)
+    where
+        F: FnMut(&CBOR, &mut CBOR) -> bool
+    {
+        self.0.retain(|_, entry| predicate(&entry.key, &mut entry.value));
     }
 
     pub(crate) fn insert_next(&mut self, key: CBOR, value: CBOR) -> Result<()> {
@@ -48,14 +187,14 @@ impl Map {
                 Ok(())
             },
             Some(entry) => {
-                let new_key = MapKey::new(key.to_cbor_data());
+                let new_key = MapKey::new(key.encoded());
                 if self.0.contains_key(&new_key) {
                     bail!(CBORError::DuplicateMapKey)
                 }
                 if entry.0 >= &new_key {
                     bail!(CBORError::MisorderedMapKey)
                 }
-                self.0.insert(new_key, MapValue::new(key, value));
+                self.insert_entry(new_key, key, value);
                 Ok(())
             }
         }
@@ -68,7 +207,7 @@ impl Map {
     where
         K: Into<CBOR>, V: TryFrom<CBOR>
     {
-        match self.0.get(&MapKey::new(key.into().to_cbor_data())) {
+        match self.0.get(&MapKey::new(key.into().encoded())) {
             Some(value) => V::try_from(value.value.clone()).ok(),
             None => None
         }
@@ -88,6 +227,310 @@ impl Map {
     }
 }
 
+/// Lookups by the key types most common in deterministic protocols.
+///
+/// These encode the key directly, without constructing a CBOR value for it.
+impl Map {
+    /// Get a value from the map, given an integer key.
+    pub fn get_by_int<V>(&self, key: i64) -> Option<V> where V: TryFrom<CBOR> {
+        let key_data = if key < 0 {
+            ((-1 - key) as u64).encode_varint(MajorType::Negative)
+        } else {
+            (key as u64).encode_varint(MajorType::Unsigned)
+        };
+        self.get_by_key_data(key_data)
+    }
+
+    /// Get a value from the map, given a text key.
+    pub fn get_by_str<V>(&self, key: &str) -> Option<V> where V: TryFrom<CBOR> {
+        let key: Cow<'_, str> = if is_nfc(key) { Cow::Borrowed(key) } else { Cow::Owned(to_nfc(key)) };
+        let mut key_data = key.len().encode_varint(MajorType::Text);
+        key_data.extend(key.as_bytes());
+        self.get_by_key_data(key_data)
+    }
+
+    /// Get a value from the map, given a byte string key.
+    pub fn get_by_bytes<V>(&self, key: &[u8]) -> Option<V> where V: TryFrom<CBOR> {
+        let mut key_data = key.len().encode_varint(MajorType::ByteString);
+        key_data.extend(key);
+        self.get_by_key_data(key_data)
+    }
+
+    /// Get a value from the map, given an integer key.
+    ///
+    /// Returns an error if the key is not present or the value cannot be
+    /// converted to `V`.
+    pub fn extract_by_int<V>(&self, key: i64) -> Result<V> where V: TryFrom<CBOR> {
+        match self.get_by_int(key) {
+            Some(value) => Ok(value),
+            None => bail!(CBORError::MissingMapKey),
+        }
+    }
+
+    /// Get a value from the map, given a text key.
+    ///
+    /// Returns an error if the key is not present or the value cannot be
+    /// converted to `V`.
+    pub fn extract_by_str<V>(&self, key: &str) -> Result<V> where V: TryFrom<CBOR> {
+        match self.get_by_str(key) {
+            Some(value) => Ok(value),
+            None => bail!(CBORError::MissingMapKey),
+        }
+    }
+
+    /// Get a value from the map, given a byte string key.
+    ///
+    /// Returns an error if the key is not present or the value cannot be
+    /// converted to `V`.
+    pub fn extract_by_bytes<V>(&self, key: &[u8]) -> Result<V> where V: TryFrom<CBOR> {
+        match self.get_by_bytes(key) {
+            Some(value) => Ok(value),
+            None => bail!(CBORError::MissingMapKey),
+        }
+    }
+
+    /// Get a value from the map, given the binary-encoded CBOR of its key.
+    ///
+    /// This allows keys whose encodings are known in advance, such as fixed
+    /// protocol field keys, to be looked up without constructing a CBOR key
+    /// each time. The encoding must be the canonical dCBOR encoding of the
+    /// key, or no value will be found.
+    pub fn get_with_encoded_key(&self, key_data: &[u8]) -> Option<&CBOR> {
+        self.0.get(key_data).map(|value| &value.value)
+    }
+
+    fn get_by_key_data<V>(&self, key_data: Vec<u8>) -> Option<V> where V: TryFrom<CBOR> {
+        self.get_with_encoded_key(&key_data).and_then(|value| V::try_from(value.clone()).ok())
+    }
+}
+
+impl Map {
+    /// Converts the map to a map keyed by strings, such as a
+    /// `HashMap<String, CBOR>` or `BTreeMap<String, CBOR>`, for use with
+    /// configuration or JSON-like layers.
+    ///
+    /// Text keys are used as they are, and integer and boolean keys are
+    /// converted to their diagnostic notation, e.g. `1` or `true`. Unless
+    /// `lossy` is set, returns an error if any other kind of key is present,
+    /// or if two keys convert to the same string, e.g. `1` and `"1"`.
+    ///
+    /// If `lossy` is set, every key is converted to its diagnostic notation
+    /// (keeping text keys unquoted), and where two keys convert to the same
+    /// string, the entry that is last in canonical order is kept.
+    pub fn to_string_keyed_map<M>(&self, lossy: bool) -> Result<M>
+    where
+        M: FromIterator<(String, CBOR)>
+    {
+        let mut entries = BTreeMap::new();
+        for (key, value) in self.iter() {
+            let string = match key.as_case() {
+                CBORCase::Text(text) => text.clone(),
+                CBORCase::Unsigned(_) | CBORCase::Negative(_) |
+                CBORCase::Simple(Simple::True | Simple::False) => key.to_string(),
+                _ if lossy => key.to_string(),
+                _ => bail!(CBORError::WrongType),
+            };
+            if entries.insert(string, value.clone()).is_some() && !lossy {
+                bail!(CBORError::DuplicateMapKey);
+            }
+        }
+        Ok(entries.into_iter().collect())
+    }
+
+    /// Converts every entry of this map to a typed key-value pair, in the
+    /// canonical order of the keys.
+    ///
+    /// Returns an error if any key or value cannot be converted, noting the
+    /// key of the entry that failed.
+    ///
+    /// ```
+    /// # use dcbor::prelude::*;
+    /// let mut map = Map::new();
+    /// map.insert(2, "b");
+    /// map.insert(1, "a");
+    /// let entries: Vec<(u32, String)> = map.try_into_vec().unwrap();
+    /// assert_eq!(entries, [(1, "a".to_string()), (2, "b".to_string())]);
+    /// ```
+    pub fn try_into_vec<K, V>(self) -> Result<Vec<(K, V)>>
+    where
+        K: TryFrom<CBOR, Error = Error>,
+        V: TryFrom<CBOR, Error = Error>,
+    {
+        self.iter().map(|(key, value)| decode_entry(key, value)).collect()
+    }
+}
+
+/// How [`Map::from_iter_with`] handles a key that appears more than once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// Fail with `CBORError::DuplicateMapKey`.
+    Error,
+    /// Keep the value of the first occurrence of the key.
+    KeepFirst,
+    /// Keep the value of the last occurrence of the key, as [`Map::insert`]
+    /// does.
+    #[default]
+    KeepLast,
+}
+
+impl Map {
+    /// Creates a map from an iterator of key-value pairs, resolving duplicate
+    /// keys according to the given policy.
+    ///
+    /// Keys are compared by their binary-encoded CBOR.
+    pub fn from_iter_with<I, K, V>(iter: I, policy: DuplicatePolicy) -> Result<Map>
+    where
+        I: IntoIterator<Item = (K, V)>, K: Into<CBOR>, V: Into<CBOR>
+    {
+        let mut map = Map::new();
+        for (key, value) in iter {
+            let key = key.into();
+            let map_key = MapKey::new(key.encoded());
+            if map.0.contains_key(&map_key) {
+                match policy {
+                    DuplicatePolicy::Error => bail!(CBORError::DuplicateMapKey),
+                    DuplicatePolicy::KeepFirst => continue,
+                    DuplicatePolicy::KeepLast => { },
+                }
+            }
+            map.insert_entry(map_key, key, value.into());
+        }
+        Ok(map)
+    }
+}
+
+/// Which floating point map keys are permitted.
+///
+/// dCBOR allows keys of any type, but some application profiles forbid float
+/// keys, as values that are equal numerically may not be equal as keys. Keys
+/// with integral values are always integers in dCBOR, so are never affected.
+///
+/// See [`Map::validate_keys`] and
+/// [`DecodeOptions::float_key_policy`](crate::DecodeOptions::float_key_policy).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FloatKeyPolicy {
+    /// Permit any float key.
+    #[default]
+    Allow,
+    /// Fail with `CBORError::NanMapKey` for a NaN key.
+    RejectNan,
+    /// Fail with `CBORError::FloatMapKey` for any float key, or
+    /// `CBORError::NanMapKey` for a NaN key.
+    Reject,
+}
+
+impl FloatKeyPolicy {
+    /// Returns an error if the key is not permitted by this policy.
+    pub(crate) fn check(self, key: &CBOR) -> Result<(), CBORError> {
+        match (self, key.as_case()) {
+            #[cfg(feature = "float")]
+            (FloatKeyPolicy::RejectNan | FloatKeyPolicy::Reject, CBORCase::Simple(Simple::Float(f))) if f.is_nan() => {
+                Err(CBORError::NanMapKey)
+            },
+            #[cfg(feature = "float")]
+            (FloatKeyPolicy::Reject, CBORCase::Simple(Simple::Float(_))) => Err(CBORError::FloatMapKey),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_within(self, cbor: &CBOR) -> Result<()> {
+        match cbor.as_case() {
+            CBORCase::Array(array) => {
+                for (index, item) in array.iter().enumerate() {
+                    self.check_within(item).map_err(|error| within(error, || PathElement::Index(index)))?;
+                }
+            },
+            CBORCase::Map(map) => map.validate_keys(self)?,
+            CBORCase::Tagged(_, item) => self.check_within(item)?,
+            _ => {},
+        }
+        Ok(())
+    }
+}
+
+impl Map {
+    /// Checks the keys of this map, and of every map nested within it, against
+    /// the policy.
+    ///
+    /// Returns `CBORError::FloatMapKey` or `CBORError::NanMapKey` for the first
+    /// key the policy does not permit, with the
+    /// [`ValuePath`](crate::ValuePath) of the map holding it attached.
+    ///
+    /// ```
+    /// # use dcbor::{prelude::*, FloatKeyPolicy};
+    /// let mut map = Map::new();
+    /// map.insert(1, "one");
+    /// # #[cfg(feature = "float")]
+    /// map.insert(1.5, "one and a half");
+    /// assert!(map.validate_keys(FloatKeyPolicy::RejectNan).is_ok());
+    /// # #[cfg(feature = "float")]
+    /// assert!(map.validate_keys(FloatKeyPolicy::Reject).is_err());
+    /// ```
+    pub fn validate_keys(&self, policy: FloatKeyPolicy) -> Result<()> {
+        if policy == FloatKeyPolicy::Allow {
+            return Ok(());
+        }
+        for (key, value) in self.iter() {
+            policy.check(key).map_err(with_path)?;
+            policy.check_within(key)?;
+            policy.check_within(value).map_err(|error| within(error, || PathElement::Key(key.clone())))?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects key-value pairs into a map. If a key appears more than once, the
+/// last value is kept ([`DuplicatePolicy::KeepLast`]).
+impl<K, V> FromIterator<(K, V)> for Map where K: Into<CBOR>, V: Into<CBOR> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Map::new();
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+/// Inserts key-value pairs into the map, as [`Map::insert`] does.
+impl<K, V> Extend<(K, V)> for Map where K: Into<CBOR>, V: Into<CBOR> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+/// Consumes the map, yielding its entries in canonical order.
+impl IntoIterator for Map {
+    type Item = (CBOR, CBOR);
+    type IntoIter = MapIntoIter;
+
+    fn into_iter(self) -> MapIntoIter {
+        MapIntoIter(self.0.into_values())
+    }
+}
+
+impl<'a> IntoIterator for &'a Map {
+    type Item = (&'a CBOR, &'a CBOR);
+    type IntoIter = MapIter<'a>;
+
+    fn into_iter(self) -> MapIter<'a> {
+        self.iter()
+    }
+}
+
+/// Zeroizes the encoded keys and the entries of this map, then empties it.
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for Map {
+    fn zeroize(&mut self) {
+        for (mut key, mut entry) in core::mem::take(&mut self.0) {
+            key.0.zeroize();
+            entry.key.zeroize();
+            entry.value.zeroize();
+        }
+    }
+}
+
 impl Default for Map {
     fn default() -> Self {
         Self::new()
@@ -100,16 +543,14 @@ impl PartialEq for Map {
     }
 }
 
-impl Eq for Map {
-    fn assert_receiver_is_total_eq(&self) {}
-}
+impl Eq for Map { }
 
 impl Map {
     pub fn cbor_data(&self) -> Vec<u8> {
         let pairs: Vec<(Vec<u8>, Vec<u8>)> = self.0.iter().map(|x| {
             let a: Vec<u8> = x.0.0.to_owned();
             let cbor: &CBOR = &x.1.value;
-            let b: Vec<u8> = cbor.to_cbor_data();
+            let b: Vec<u8> = cbor.encoded();
             (a, b)
         }).collect();
         let mut buf = pairs.len().encode_varint(MajorType::Map);
@@ -119,11 +560,27 @@ impl Map {
         }
         buf
     }
+
+    /// Writes the encoding of this map to the sink, without holding the
+    /// whole encoding in memory.
+    pub(crate) fn encode_to(&self, sink: &mut dyn EncodeSink) {
+        sink.write(&self.0.len().encode_varint(MajorType::Map));
+        for (key, entry) in self.0.iter() {
+            sink.write(&key.0);
+            entry.value.encode_to_dyn(sink);
+        }
+    }
 }
 
 impl From<Map> for CBOR {
     fn from(value: Map) -> Self {
-        CBORCase::Map(value.clone()).into()
+        CBORCase::Map(value).into()
+    }
+}
+
+impl From<&Map> for CBOR {
+    fn from(value: &Map) -> Self {
+        value.clone().into()
     }
 }
 
@@ -153,14 +610,157 @@ impl<'a> Iterator for MapIter<'a> {
         let entry = self.0.next()?;
         Some((&entry.key, &entry.value))
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for MapIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let entry = self.0.next_back()?;
+        Some((&entry.key, &entry.value))
+    }
+}
+
+impl ExactSizeIterator for MapIter<'_> { }
+
+/// An owning iterator over the entries of a CBOR map, returned by
+/// `Map::into_iter`.
+///
+/// This iterator always returns the entries in lexicographic order by the key's
+/// binary-encoded CBOR value.
+#[derive(Debug)]
+pub struct MapIntoIter(BTreeMapIntoValues<MapKey, MapValue>);
+
+impl Iterator for MapIntoIter {
+    type Item = (CBOR, CBOR);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|entry| (entry.key, entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for MapIntoIter {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|entry| (entry.key, entry.value))
+    }
+}
+
+impl ExactSizeIterator for MapIntoIter { }
+
+/// An iterator over a range of entries of a CBOR map.
+///
+/// This iterator always returns the entries in lexicographic order by the key's
+/// binary-encoded CBOR value.
+#[derive(Debug)]
+pub struct MapRange<'a>(BTreeMapRange<'a, MapKey, MapValue>);
+
+impl<'a> Iterator for MapRange<'a> {
+    type Item = (&'a CBOR, &'a CBOR);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (_, entry) = self.0.next()?;
+        Some((&entry.key, &entry.value))
+    }
 }
 
+impl DoubleEndedIterator for MapRange<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (_, entry) = self.0.next_back()?;
+        Some((&entry.key, &entry.value))
+    }
+}
+
+/// An iterator over the keys of a CBOR map.
+///
+/// This iterator always returns the keys in lexicographic order by their
+/// binary-encoded CBOR value.
+#[derive(Debug)]
+pub struct MapKeys<'a>(BTreeMapValues<'a, MapKey, MapValue>);
+
+impl<'a> Iterator for MapKeys<'a> {
+    type Item = &'a CBOR;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|entry| &entry.key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for MapKeys<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|entry| &entry.key)
+    }
+}
+
+impl ExactSizeIterator for MapKeys<'_> { }
+
+/// An iterator over the values of a CBOR map.
+///
+/// This iterator always returns the values in lexicographic order by the
+/// binary-encoded CBOR value of their keys.
+#[derive(Debug)]
+pub struct MapValues<'a>(BTreeMapValues<'a, MapKey, MapValue>);
+
+impl<'a> Iterator for MapValues<'a> {
+    type Item = &'a CBOR;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|entry| &entry.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for MapValues<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|entry| &entry.value)
+    }
+}
+
+impl ExactSizeIterator for MapValues<'_> { }
+
+/// An iterator over the entries of a [`Map`] in insertion order, returned by
+/// [`Map::insertion_order`].
+#[cfg(feature = "insertion-order")]
+#[derive(Debug)]
+pub struct OrderedEntries<'a>(<Vec<&'a MapValue> as IntoIterator>::IntoIter);
+
+#[cfg(feature = "insertion-order")]
+impl<'a> Iterator for OrderedEntries<'a> {
+    type Item = (&'a CBOR, &'a CBOR);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|entry| (&entry.key, &entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+#[cfg(feature = "insertion-order")]
+impl ExactSizeIterator for OrderedEntries<'_> { }
+
 #[derive(Clone)]
 struct MapValue {
     key: CBOR,
     value: CBOR,
+    #[cfg(feature = "insertion-order")]
+    ordinal: usize,
 }
 
+#[cfg(not(feature = "insertion-order"))]
 impl MapValue {
     fn new(key: CBOR, value: CBOR) -> MapValue {
         MapValue { key, value }
@@ -194,9 +794,7 @@ impl PartialEq for MapKey {
     }
 }
 
-impl Eq for MapKey {
-    fn assert_receiver_is_total_eq(&self) {}
-}
+impl Eq for MapKey { }
 
 impl PartialOrd for MapKey {
     fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
@@ -210,27 +808,60 @@ impl Ord for MapKey {
     }
 }
 
+// Keys are ordered by their bytes, so they can be looked up by encoding.
+impl Borrow<[u8]> for MapKey {
+    fn borrow(&self) -> &[u8] {
+        &self.0
+    }
+}
+
 impl fmt::Debug for MapKey {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_fmt(format_args!("0x{}", hex::encode(&self.0)))
+        f.write_fmt(format_args!("0x{}", hex_encode(&self.0)))
     }
 }
 
-/// Convert a container to a CBOR Map where the container's items are
-/// pairs of CBOREncodable values.
-impl<T, K, V> From<T> for Map where T: IntoIterator<Item=(K, V)>, K: Into<CBOR>, V: Into<CBOR> {
-    fn from(container: T) -> Self {
-        let mut map = Map::new();
-        for (k, v) in container {
-            map.insert(k.into(), v.into());
-        }
-        map
-    }
+/// Convert containers of pairs of CBOR-encodable values to CBOR maps.
+///
+/// Other iterators of pairs can be collected into a `Map`.
+macro_rules! impl_map_from {
+    ($({$($generics:tt)*} $container:ty);* $(;)?) => {
+        $(
+            impl<K, V, $($generics)*> From<$container> for Map where K: Into<CBOR>, V: Into<CBOR> {
+                fn from(container: $container) -> Self {
+                    container.into_iter().collect()
+                }
+            }
+        )*
+    };
 }
 
-impl<K, V> From<HashMap<K, V>> for CBOR where K: Into<CBOR>, V: Into<CBOR> {
-    fn from(container: HashMap<K, V>) -> Self {
-        CBORCase::Map(Map::from(container.into_iter())).into()
+impl_map_from!(
+    {S} HashMap<K, V, S>;
+    {} BTreeMap<K, V>;
+    {} Vec<(K, V)>;
+    {} VecDeque<(K, V)>;
+    {} LinkedList<(K, V)>;
+    {} BinaryHeap<(K, V)>;
+    {const N: usize} [(K, V); N];
+);
+
+/// Decodes a map entry, noting which key failed in any error.
+fn decode_entry<K, V>(key: &CBOR, value: &CBOR) -> Result<(K, V)>
+where
+    K: TryFrom<CBOR, Error = Error>,
+    V: TryFrom<CBOR, Error = Error>,
+{
+    let k = K::try_from(key.clone())
+        .map_err(|error| error.context(format!("map key {}", key.display_for_error())))?;
+    let v = V::try_from(value.clone())
+        .map_err(|error| within(error, || PathElement::Key(key.clone())).context(format!("map value for key {}", key.display_for_error())))?;
+    Ok((k, v))
+}
+
+impl<K, V, S> From<HashMap<K, V, S>> for CBOR where K: Into<CBOR>, V: Into<CBOR> {
+    fn from(container: HashMap<K, V, S>) -> Self {
+        CBORCase::Map(Map::from(container)).into()
     }
 }
 
@@ -246,7 +877,8 @@ where
             CBORCase::Map(map) => {
                 let mut container = <HashMap<K, V>>::new();
                 for (k, v) in map.iter() {
-                    container.insert(k.clone().try_into()?, v.clone().try_into()?);
+                    let (key, value) = decode_entry(k, v)?;
+                    container.insert(key, value);
                 }
                 Ok(container)
             },
@@ -261,7 +893,7 @@ where
     V: Into<CBOR>,
 {
     fn from(container: BTreeMap<K, V>) -> Self {
-        CBORCase::Map(Map::from(container.into_iter())).into()
+        CBORCase::Map(Map::from(container)).into()
     }
 }
 
@@ -277,8 +909,7 @@ where
             CBORCase::Map(map) => {
                 let mut container = <BTreeMap<K, V>>::new();
                 for (k, v) in map.iter() {
-                    let key = k.clone().try_into()?;
-                    let value = v.clone().try_into()?;
+                    let (key, value) = decode_entry(k, v)?;
                     container.insert(key, value);
                 }
                 Ok(container)
@@ -0,0 +1,110 @@
+import_stdlib!();
+
+#[cfg(feature = "float")]
+use crate::Simple;
+use crate::{ByteString, CBORCase, CBOR, TAG_NEGATIVE_BIGNUM, TAG_POSITIVE_BIGNUM};
+
+/// A number extracted from CBOR, in a form that can be compared exactly
+/// across representations.
+enum Number {
+    /// An integer. If `negative` is `true` its value is `-1 - magnitude`,
+    /// matching the CBOR encoding of negative integers; otherwise it is
+    /// `magnitude`. The magnitude is big-endian with no leading zero bytes.
+    Integer { negative: bool, magnitude: Vec<u8> },
+    /// A floating point value that is not an integer.
+    #[cfg(feature = "float")]
+    Float(f64),
+}
+
+impl Number {
+    fn from_cbor(cbor: &CBOR) -> Option<Number> {
+        match cbor.as_case() {
+            CBORCase::Unsigned(n) => Some(Self::integer(false, &n.to_be_bytes())),
+            CBORCase::Negative(n) => Some(Self::integer(true, &n.to_be_bytes())),
+            #[cfg(feature = "float")]
+            CBORCase::Simple(Simple::Float(f)) => Some(Self::from_f64(*f)),
+            CBORCase::Tagged(tag, item) => {
+                let negative = match tag.value() {
+                    TAG_POSITIVE_BIGNUM => false,
+                    TAG_NEGATIVE_BIGNUM => true,
+                    _ => return None,
+                };
+                let bytes = ByteString::try_from(item.clone()).ok()?;
+                Some(Self::integer(negative, bytes.data()))
+            },
+            _ => None,
+        }
+    }
+
+    fn integer(negative: bool, magnitude: &[u8]) -> Number {
+        Number::Integer { negative, magnitude: trimmed(magnitude).to_vec() }
+    }
+
+    #[cfg(feature = "float")]
+    fn from_f64(f: f64) -> Number {
+        if !f.is_finite() || f.fract() != 0.0 {
+            return Number::Float(f);
+        }
+        // The absolute value as an integer is `mantissa * 2^exponent`.
+        let bits = f.abs().to_bits();
+        let biased_exponent = ((bits >> 52) & 0x7ff) as i32;
+        if biased_exponent == 0 {
+            // Zero; integral subnormals do not exist.
+            return Self::integer(false, &[]);
+        }
+        let mantissa = (bits & ((1 << 52) - 1)) | (1 << 52);
+        let exponent = biased_exponent - 1075;
+        let mut magnitude = if exponent >= 0 {
+            let shift = exponent as u32;
+            let mut bytes = trimmed(&((mantissa as u128) << (shift % 8)).to_be_bytes()).to_vec();
+            bytes.resize(bytes.len() + (shift / 8) as usize, 0);
+            bytes
+        } else {
+            (mantissa >> -exponent).to_be_bytes().to_vec()
+        };
+        let negative = f < 0.0;
+        if negative {
+            decrement(&mut magnitude);
+        }
+        Self::integer(negative, &magnitude)
+    }
+}
+
+pub(crate) fn trimmed(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+/// Subtracts one from a nonzero big-endian magnitude.
+pub(crate) fn decrement(bytes: &mut [u8]) {
+    for byte in bytes.iter_mut().rev() {
+        let (result, borrow) = byte.overflowing_sub(1);
+        *byte = result;
+        if !borrow {
+            break;
+        }
+    }
+}
+
+impl CBOR {
+    /// Returns `true` if this value and `other` are both numbers with the
+    /// same exact value, regardless of how each is represented.
+    ///
+    /// Unsigned and negative integers, floating point values, and bignums
+    /// (byte strings tagged 2 or 3) are compared by their mathematical value
+    /// without rounding, so e.g. `1` equals `2(h'01')` and `1.0`, but
+    /// `u64::MAX` does not equal the nearest `f64`. NaN is not equal to any
+    /// value, and positive and negative zero are equal.
+    ///
+    /// Returns `false` if either value is not a number.
+    pub fn numerically_equal(&self, other: &CBOR) -> bool {
+        match (Number::from_cbor(self), Number::from_cbor(other)) {
+            (Some(Number::Integer { negative: n1, magnitude: m1 }), Some(Number::Integer { negative: n2, magnitude: m2 })) => {
+                n1 == n2 && m1 == m2
+            },
+            #[cfg(feature = "float")]
+            (Some(Number::Float(f1)), Some(Number::Float(f2))) => f1 == f2,
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,139 @@
+import_stdlib!();
+
+use anyhow::{bail, Error, Result};
+
+#[cfg(feature = "float")]
+use half::f16;
+#[cfg(feature = "float")]
+use crate::{float::{f64_cbor_data, validate_canonical_f16, validate_canonical_f32, validate_canonical_f64}, ExactFrom};
+use crate::{
+    decode::{located, parse_header_varint},
+    int::From64,
+    varint::{EncodeVarInt, MajorType},
+    CBORError, CBOR,
+};
+
+/// A numeric type with a fast path for encoding and decoding arrays of it.
+///
+/// See [`CBOR::encode_numeric_array`] and [`CBOR::decode_numeric_array`].
+pub trait NumericElement: Copy + Sized {
+    /// Appends the encoding of the value to the buffer.
+    fn encode_element(self, buf: &mut Vec<u8>);
+
+    /// Decodes a value from the start of the data, returning it along with
+    /// the number of bytes it occupied.
+    fn decode_element(data: &[u8]) -> Result<(Self, usize)>;
+}
+
+impl NumericElement for u64 {
+    fn encode_element(self, buf: &mut Vec<u8>) {
+        buf.extend(self.encode_varint(MajorType::Unsigned));
+    }
+
+    fn decode_element(data: &[u8]) -> Result<(Self, usize)> {
+        match parse_header_varint(data)? {
+            (MajorType::Unsigned, n, len) => Ok((n, len)),
+            (MajorType::Negative, _, _) => bail!(CBORError::OutOfRange),
+            _ => bail!(CBORError::WrongType),
+        }
+    }
+}
+
+impl NumericElement for i64 {
+    fn encode_element(self, buf: &mut Vec<u8>) {
+        buf.extend(self.cbor_data());
+    }
+
+    fn decode_element(data: &[u8]) -> Result<(Self, usize)> {
+        match parse_header_varint(data)? {
+            (MajorType::Unsigned, n, len) if n <= i64::MAX as u64 => Ok((n as i64, len)),
+            (MajorType::Negative, n, len) if n <= i64::MAX as u64 => Ok((-1 - n as i64, len)),
+            (MajorType::Unsigned | MajorType::Negative, _, _) => bail!(CBORError::OutOfRange),
+            _ => bail!(CBORError::WrongType),
+        }
+    }
+}
+
+#[cfg(feature = "float")]
+impl NumericElement for f64 {
+    fn encode_element(self, buf: &mut Vec<u8>) {
+        buf.extend(f64_cbor_data(self));
+    }
+
+    fn decode_element(data: &[u8]) -> Result<(Self, usize)> {
+        match parse_header_varint(data)? {
+            (MajorType::Unsigned, n, len) => match f64::exact_from_u64(n) {
+                Some(f) => Ok((f, len)),
+                None => bail!(CBORError::OutOfRange),
+            },
+            (MajorType::Negative, n, len) => match f64::exact_from_u64(n) {
+                Some(f) => Ok((-1.0 - f, len)),
+                None => bail!(CBORError::OutOfRange),
+            },
+            (MajorType::Simple, n, 3) => {
+                let f = f16::from_bits(n as u16);
+                validate_canonical_f16(f)?;
+                Ok((f.into(), 3))
+            },
+            (MajorType::Simple, n, 5) => {
+                let f = f32::from_bits(n as u32);
+                validate_canonical_f32(f)?;
+                Ok((f.into(), 5))
+            },
+            (MajorType::Simple, n, 9) => {
+                let f = f64::from_bits(n);
+                validate_canonical_f64(f)?;
+                Ok((f, 9))
+            },
+            _ => bail!(CBORError::WrongType),
+        }
+    }
+}
+
+/// Fast paths for arrays of numbers.
+impl CBOR {
+    /// Returns the encoding of the values as a CBOR array.
+    ///
+    /// This produces the same bytes as encoding `CBOR::from(values.to_vec())`,
+    /// but encodes each element directly rather than first making a `CBOR`
+    /// value for it, which is much faster for large arrays.
+    pub fn encode_numeric_array<T: NumericElement>(values: &[T]) -> Vec<u8> {
+        let mut buf = values.len().encode_varint(MajorType::Array);
+        buf.reserve(values.len());
+        for value in values {
+            value.encode_element(&mut buf);
+        }
+        buf
+    }
+
+    /// Decodes data holding a CBOR array of numbers.
+    ///
+    /// This returns the same values as decoding with [`CBOR::try_from_data`]
+    /// and converting to `Vec<T>`, but decodes each element directly rather
+    /// than first making a `CBOR` value for it, which is much faster for large
+    /// arrays. Elements must be integers or floats that `T` can represent
+    /// exactly; negative integers are out of range for `u64`.
+    pub fn decode_numeric_array<T: NumericElement>(data: impl AsRef<[u8]>) -> Result<Vec<T>> {
+        let data = data.as_ref();
+        let (count, mut pos) = match parse_header_varint(data).map_err(|error| located(error, 0))? {
+            (MajorType::Array, count, len) => (count, len),
+            _ => return Err(located(Error::msg(CBORError::WrongType), 0)),
+        };
+        let Ok(count) = usize::try_from(count) else {
+            return Err(located(Error::msg(CBORError::OutOfRange), 0));
+        };
+        // Each element occupies at least one byte, which bounds the
+        // allocation whatever count the data claims.
+        let mut values = Vec::with_capacity(count.min(data.len() - pos));
+        for index in 0..count {
+            let (value, len) = T::decode_element(&data[pos..])
+                .map_err(|error| located(error, pos).context(format!("array element {}", index)))?;
+            values.push(value);
+            pos += len;
+        }
+        if pos < data.len() {
+            return Err(located(Error::msg(CBORError::UnusedData(data.len() - pos)), pos));
+        }
+        Ok(values)
+    }
+}
@@ -0,0 +1,98 @@
+import_stdlib!();
+
+use anyhow::Result;
+
+use crate::{decode::{decode_cbor_observed, decode_cbor_opt_observed, DecodeOptions}, CBORCase, Tag, CBOR};
+
+use super::varint::{EncodeVarInt, MajorType};
+
+/// A type that receives events as CBOR is encoded or decoded.
+///
+/// This allows collecting metrics about the shape of payloads (item counts,
+/// nesting depth, container sizes, bytes consumed) in the same pass that
+/// does the encoding or decoding, as well as capturing or vetoing tags as
+/// they are decoded.
+///
+/// All methods have empty default implementations, so implementors only
+/// need to override the events they are interested in.
+pub trait CodecObserver {
+    /// Called after each item has been decoded.
+    ///
+    /// `depth` is the nesting level of the item, with the top-level item at
+    /// depth zero. `len` is the number of bytes the item occupied in the
+    /// input, including any nested items.
+    fn item_decoded(&mut self, _item: &CBOR, _depth: usize, _len: usize) { }
+
+    /// Called after the content of each tag has been decoded, before the
+    /// tagged item is.
+    ///
+    /// `content_span` is the range of input bytes occupied by the content.
+    /// Returning an error vetoes the tag: decoding stops and fails with that
+    /// error.
+    fn tag_decoded(&mut self, _tag: &Tag, _content: &CBOR, _content_span: ops::Range<usize>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called for each text string that is not in Unicode Normalization Form
+    /// C, as it is decoded.
+    ///
+    /// Such strings are only decoded, rather than failing decoding, with
+    /// [`DecodeOptions::accept_non_nfc`] enabled. `span` is the range of
+    /// input bytes occupied by the string.
+    fn non_nfc_text_decoded(&mut self, _text: &str, _span: ops::Range<usize>) { }
+
+    /// Called after each item has been encoded.
+    ///
+    /// `depth` is the nesting level of the item, with the top-level item at
+    /// depth zero. `len` is the number of bytes the item occupies in the
+    /// output, including any nested items.
+    fn item_encoded(&mut self, _item: &CBOR, _depth: usize, _len: usize) { }
+}
+
+impl CBOR {
+    /// Decodes the given data into CBOR symbolic representation, reporting
+    /// each decoded item to the given observer.
+    pub fn try_from_data_observed(data: impl AsRef<[u8]>, observer: &mut dyn CodecObserver) -> Result<CBOR> {
+        decode_cbor_observed(data, observer)
+    }
+
+    /// Decodes the given data into CBOR symbolic representation, using the
+    /// given options and reporting each decoded item to the given observer.
+    pub fn try_from_data_opt_observed(data: impl AsRef<[u8]>, options: &DecodeOptions, observer: &mut dyn CodecObserver) -> Result<CBOR> {
+        decode_cbor_opt_observed(data, options, observer)
+    }
+
+    /// Encodes this CBOR into binary representation, reporting each encoded
+    /// item to the given observer.
+    pub fn to_cbor_data_observed(&self, observer: &mut dyn CodecObserver) -> Vec<u8> {
+        self.encode_observed(0, observer)
+    }
+
+    fn encode_observed(&self, depth: usize, observer: &mut dyn CodecObserver) -> Vec<u8> {
+        let buf = match self.as_case() {
+            CBORCase::Array(a) => {
+                let mut buf = a.len().encode_varint(MajorType::Array);
+                for item in a {
+                    buf.extend(item.encode_observed(depth + 1, observer));
+                }
+                buf
+            },
+            CBORCase::Map(m) => {
+                let mut buf = m.len().encode_varint(MajorType::Map);
+                for (key, value) in m.iter() {
+                    buf.extend(key.encode_observed(depth + 1, observer));
+                    buf.extend(value.encode_observed(depth + 1, observer));
+                }
+                buf
+            },
+            CBORCase::Tagged(tag, item) => {
+                let mut buf = tag.value().encode_varint(MajorType::Tagged);
+                buf.extend(item.encode_observed(depth + 1, observer));
+                buf
+            },
+            _ => self.encoded(),
+        };
+        observer.item_encoded(self, depth, buf.len());
+        buf
+    }
+}
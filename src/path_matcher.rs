@@ -0,0 +1,378 @@
+import_stdlib!();
+
+use anyhow::{Error, Result};
+
+use crate::{decode::located, CBORError, PathElement, Token, Tokenizer, CBOR};
+
+/// A pattern selecting values in a CBOR tree by their paths, in the style of
+/// JSONPath.
+///
+/// A pattern starts with `$`, the root, followed by steps:
+///
+/// - `.name` or `["name"]`: the value for a text key.
+/// - `[2]`: the element at an index of an array, or the value for an integer
+///   key of a map. Negative integers, e.g. `[-1]`, select map keys only.
+/// - `.*` or `[*]`: every element of an array or value of a map.
+///
+/// As elsewhere, tags are not steps: a path continues through a tagged value
+/// to its content. The paths held by a [`ValuePath`](crate::ValuePath) are
+/// patterns that select the value they locate, if its map keys are text or
+/// integers.
+///
+/// ```
+/// # use dcbor::PathPattern;
+/// let pattern: PathPattern = "$.records[*].id".parse().unwrap();
+/// assert_eq!(pattern.to_string(), r#"$["records"][*]["id"]"#);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathPattern(Vec<Step>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    /// An array index, or the map key that is the same integer, with its
+    /// encoding.
+    Index(usize, Vec<u8>),
+    /// A map key, by its encoding.
+    Key(Vec<u8>),
+    /// Every element or value.
+    Any,
+}
+
+impl Step {
+    fn matches_index(&self, index: usize) -> bool {
+        match self {
+            Step::Index(i, _) => *i == index,
+            Step::Key(_) => false,
+            Step::Any => true,
+        }
+    }
+
+    fn matches_key(&self, key: &[u8]) -> bool {
+        match self {
+            Step::Index(_, encoded) | Step::Key(encoded) => encoded == key,
+            Step::Any => true,
+        }
+    }
+}
+
+impl PathPattern {
+    /// Parses a pattern.
+    ///
+    /// Returns [`CBORError::InvalidPathPattern`] if the text is not a
+    /// pattern.
+    pub fn parse(text: &str) -> Result<PathPattern> {
+        let error = |offset: usize, message: &str| Error::msg(CBORError::InvalidPathPattern(offset, message.to_string()));
+        let Some(mut rest) = text.strip_prefix('$') else {
+            return Err(error(0, "expected `$`"));
+        };
+        let mut steps = Vec::new();
+        while !rest.is_empty() {
+            let offset = text.len() - rest.len();
+            if let Some(after) = rest.strip_prefix(".*") {
+                steps.push(Step::Any);
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix('.') {
+                let len = after.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(after.len());
+                if len == 0 || after.starts_with(|c: char| c.is_ascii_digit()) {
+                    return Err(error(offset + 1, "expected a name"));
+                }
+                steps.push(Step::Key(CBOR::from(&after[..len]).to_cbor_data()));
+                rest = &after[len..];
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let (step, len) = parse_bracketed(after).map_err(|(position, message)| error(offset + 1 + position, message))?;
+                steps.push(step);
+                rest = &after[len..];
+            } else {
+                return Err(error(offset, "expected `.` or `[`"));
+            }
+        }
+        Ok(PathPattern(steps))
+    }
+}
+
+/// Parses the step following a `[`, returning it along with the length of
+/// the text it occupies, including the closing `]`.
+fn parse_bracketed(text: &str) -> Result<(Step, usize), (usize, &'static str)> {
+    let (step, len) = if let Some(after) = text.strip_prefix('"') {
+        let mut key = String::new();
+        let mut chars = after.char_indices();
+        let end = loop {
+            match chars.next() {
+                Some((i, '"')) => break i,
+                Some((_, '\\')) => match chars.next() {
+                    Some((_, c @ ('"' | '\\'))) => key.push(c),
+                    _ => return Err((1 + after.len(), "invalid escape")),
+                },
+                Some((_, c)) => key.push(c),
+                None => return Err((text.len(), "unterminated string")),
+            }
+        };
+        (Step::Key(CBOR::from(key).to_cbor_data()), end + 2)
+    } else if text.starts_with('*') {
+        (Step::Any, 1)
+    } else {
+        let len = text.find(|c: char| !(c.is_ascii_digit() || c == '-')).unwrap_or(text.len());
+        let number = &text[..len];
+        let step = if let Ok(index) = number.parse::<usize>() {
+            Step::Index(index, CBOR::from(index).to_cbor_data())
+        } else if let Ok(key) = number.parse::<i64>() {
+            Step::Key(CBOR::from(key).to_cbor_data())
+        } else {
+            return Err((0, "expected a string, integer, or `*`"));
+        };
+        (step, len)
+    };
+    if !text[len..].starts_with(']') {
+        return Err((len, "expected `]`"));
+    }
+    Ok((step, len + 1))
+}
+
+impl str::FromStr for PathPattern {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        PathPattern::parse(s)
+    }
+}
+
+impl fmt::Display for PathPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("$")?;
+        for step in &self.0 {
+            match step {
+                Step::Index(index, _) => write!(f, "[{}]", index)?,
+                // Keys were encoded from text or integers, which display as
+                // they were written.
+                Step::Key(encoded) => match CBOR::try_from_data(encoded) {
+                    Ok(key) => write!(f, "[{}]", key)?,
+                    Err(_) => f.write_str("[?]")?,
+                },
+                Step::Any => f.write_str("[*]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A value selected by a [`PathMatcher`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathMatch {
+    /// The index of the pattern that selected the value, in the order the
+    /// patterns were added.
+    pub pattern: usize,
+    /// The path from the root to the value.
+    pub path: Vec<PathElement>,
+    /// The selected value.
+    pub value: CBOR,
+    /// The range of bytes the value occupies in the data.
+    pub span: ops::Range<usize>,
+}
+
+/// Selects the values at the paths matching a set of [`PathPattern`]s from
+/// dCBOR data, in a single pass over the data.
+///
+/// Only the selected values are decoded as `CBOR`. The rest of the data is
+/// read token by token with a [`Tokenizer`], so a large document can be
+/// searched without building its tree. The whole of the data is still
+/// checked to be valid dCBOR, with the same errors as
+/// [`CBOR::try_from_data`], although the error may be reported after some
+/// values have been. Nesting is limited by [`PathMatcher::max_depth`].
+///
+/// ```
+/// # use dcbor::{prelude::*, PathMatcher};
+/// let mut first = Map::new();
+/// first.insert("id", 1);
+/// first.insert("name", "Alice");
+/// let mut second = Map::new();
+/// second.insert("id", 2);
+/// let mut document = Map::new();
+/// document.insert("records", vec![first, second]);
+/// let data = CBOR::from(document).to_cbor_data();
+///
+/// let matcher = PathMatcher::new().pattern("$.records[*].id".parse().unwrap());
+/// let ids: Vec<_> = matcher.collect(&data).unwrap().into_iter().map(|m| m.value).collect();
+/// assert_eq!(ids, [CBOR::from(1), CBOR::from(2)]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PathMatcher {
+    patterns: Vec<PathPattern>,
+    max_depth: Option<usize>,
+}
+
+impl PathMatcher {
+    /// The default for [`PathMatcher::max_depth`].
+    pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+    /// Makes a new matcher with no patterns.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The maximum depth of nesting, where the root is at depth 0 and the
+    /// items in an array, map, or tagged value are one deeper than it.
+    /// Deeper items cause the scan to fail with
+    /// [`CBORError::DepthLimitExceeded`]. Defaults to
+    /// [`PathMatcher::DEFAULT_MAX_DEPTH`].
+    ///
+    /// The scan recurses for each level of nesting, so removing the limit
+    /// lets untrusted data overflow the stack.
+    pub fn max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Adds a pattern, whose index in [`PathMatch::pattern`] is the number of
+    /// patterns added before it.
+    pub fn pattern(mut self, pattern: PathPattern) -> Self {
+        self.patterns.push(pattern);
+        self
+    }
+
+    /// Reads the data, calling `on_match` with each value whose path matches
+    /// a pattern, as soon as the end of the value has been read.
+    ///
+    /// A value inside another selected value is therefore reported before
+    /// it. A value matching several patterns is reported once for each.
+    /// Returns the first error returned by `on_match`, or found in the data.
+    pub fn scan(&self, data: impl AsRef<[u8]>, mut on_match: impl FnMut(PathMatch) -> Result<()>) -> Result<()> {
+        let data = data.as_ref();
+        let mut scan = Scan {
+            data,
+            tokens: Tokenizer::new(data),
+            patterns: &self.patterns,
+            max_depth: self.max_depth,
+            path: Vec::new(),
+            on_match: &mut on_match,
+        };
+        let states: Vec<State> = (0..self.patterns.len()).map(|pattern| State { pattern, step: 0 }).collect();
+        scan.item(&states, 0)?;
+        let len = scan.tokens.position();
+        if len < data.len() {
+            return Err(located(Error::msg(CBORError::UnusedData(data.len() - len)), len));
+        }
+        Ok(())
+    }
+
+    /// Reads the data, returning the values whose paths match a pattern, in
+    /// the order [`PathMatcher::scan`] reports them.
+    pub fn collect(&self, data: impl AsRef<[u8]>) -> Result<Vec<PathMatch>> {
+        let mut matches = Vec::new();
+        self.scan(data, |m| {
+            matches.push(m);
+            Ok(())
+        })?;
+        Ok(matches)
+    }
+}
+
+impl Default for PathMatcher {
+    fn default() -> Self {
+        Self {
+            patterns: Vec::new(),
+            max_depth: Some(Self::DEFAULT_MAX_DEPTH),
+        }
+    }
+}
+
+/// A pattern whose steps before `step` match the path to the current item.
+#[derive(Debug, Clone, Copy)]
+struct State {
+    pattern: usize,
+    step: usize,
+}
+
+/// A step of the path to the current item, with map keys held as the range
+/// of their encoding in the data until they are needed.
+enum PathStep {
+    Index(usize),
+    Key(ops::Range<usize>),
+}
+
+struct Scan<'a, 'f> {
+    data: &'a [u8],
+    tokens: Tokenizer<'a>,
+    patterns: &'a [PathPattern],
+    max_depth: Option<usize>,
+    path: Vec<PathStep>,
+    on_match: &'f mut dyn FnMut(PathMatch) -> Result<()>,
+}
+
+impl Scan<'_, '_> {
+    /// Reads the next item, reporting it and the items within it that match,
+    /// and returns the range of bytes it occupies.
+    fn item(&mut self, states: &[State], depth: usize) -> Result<ops::Range<usize>> {
+        let start = self.tokens.position();
+        if self.max_depth.is_some_and(|max| depth > max) {
+            return Err(located(Error::msg(CBORError::DepthLimitExceeded), start));
+        }
+        let Some((token, _)) = self.tokens.next_token()? else {
+            return Err(located(Error::msg(CBORError::Underrun), start));
+        };
+        let (matched, live): (Vec<State>, Vec<State>) = states.iter()
+            .partition(|state| state.step == self.patterns[state.pattern].0.len());
+        match token {
+            Token::Array(len) => {
+                let Ok(len) = usize::try_from(len) else {
+                    return Err(located(Error::msg(CBORError::OutOfRange), start));
+                };
+                for index in 0..len {
+                    let children = self.advance(&live, |step| step.matches_index(index));
+                    self.path.push(PathStep::Index(index));
+                    self.item(&children, depth + 1)?;
+                    self.path.pop();
+                }
+            },
+            Token::Map(len) => {
+                let mut previous: Option<ops::Range<usize>> = None;
+                for _ in 0..len {
+                    let key = self.item(&[], depth + 1)?;
+                    if let Some(previous) = previous {
+                        match self.data[previous].cmp(&self.data[key.clone()]) {
+                            cmp::Ordering::Less => {},
+                            cmp::Ordering::Equal => return Err(located(Error::msg(CBORError::DuplicateMapKey), key.start)),
+                            cmp::Ordering::Greater => return Err(located(Error::msg(CBORError::MisorderedMapKey), key.start)),
+                        }
+                    }
+                    let key_data = &self.data[key.clone()];
+                    let children = self.advance(&live, |step| step.matches_key(key_data));
+                    self.path.push(PathStep::Key(key.clone()));
+                    self.item(&children, depth + 1)?;
+                    self.path.pop();
+                    previous = Some(key);
+                }
+            },
+            // The content of a tagged value is at the same path, so only the
+            // patterns not already matched by the tagged value continue.
+            Token::Tag(_) => {
+                self.item(&live, depth + 1)?;
+            },
+            _ => {},
+        }
+        let end = self.tokens.position();
+        if !matched.is_empty() {
+            let value = CBOR::try_from_data(&self.data[start..end]).map_err(|error| located(error, start))?;
+            let path = self.path_elements()?;
+            for state in matched {
+                (self.on_match)(PathMatch { pattern: state.pattern, path: path.clone(), value: value.clone(), span: start..end })?;
+            }
+        }
+        Ok(start..end)
+    }
+
+    /// Returns the states of the patterns whose next step is satisfied, for
+    /// the item reached by that step.
+    fn advance(&self, states: &[State], satisfies: impl Fn(&Step) -> bool) -> Vec<State> {
+        states.iter()
+            .filter(|state| satisfies(&self.patterns[state.pattern].0[state.step]))
+            .map(|state| State { pattern: state.pattern, step: state.step + 1 })
+            .collect()
+    }
+
+    fn path_elements(&self) -> Result<Vec<PathElement>> {
+        self.path.iter().map(|step| match step {
+            PathStep::Index(index) => Ok(PathElement::Index(*index)),
+            PathStep::Key(range) => Ok(PathElement::Key(CBOR::try_from_data(&self.data[range.clone()])?)),
+        }).collect()
+    }
+}
@@ -1,22 +1,64 @@
-pub use crate::{
-    ByteString,
-    CBOR,
-    CBORCase,
-    CBORCodable,
-    CBORDecodable,
-    CBOREncodable,
-    CBORError,
-    CBORTagged,
-    CBORTaggedCodable,
-    CBORTaggedDecodable,
-    CBORTaggedEncodable,
-    CBORSummarizer,
-    Map,
-    Tag,
-    TagValue,
-    TagsStore,
-    TagsStoreTrait,
-    with_tags,
-    with_tags_mut,
-    tags_for_values,
-};
+//! Commonly used items, for glob import.
+//!
+//! `use dcbor::prelude::*` imports everything below. Crates that want finer
+//! control can import one of the layers instead:
+//!
+//! - [`core`]: the CBOR value types and options.
+//! - [`traits`]: the conversion and tags store traits.
+//! - [`formatting`]: the tags store and the items used to format CBOR.
+//!
+//! The prelude does not re-export any standard library types, or
+//! [`Result`](crate::Result), so it does not shadow the standard `Result`.
+//! It does import the layers themselves, so after `use dcbor::prelude::*`
+//! the `core` crate is reached as `::core`.
+
+pub use self::core::*;
+pub use self::traits::*;
+pub use self::formatting::*;
+
+/// The CBOR value types and options.
+pub mod core {
+    pub use crate::{
+        ByteString,
+        CBOR,
+        CBORCase,
+        CBORError,
+        CBORKind,
+        DecodeOptions,
+        EncodeOptions,
+        Map,
+        Tag,
+        TagValue,
+    };
+}
+
+/// The conversion and tags store traits.
+pub mod traits {
+    pub use crate::{
+        CBORCodable,
+        CBORDecodable,
+        CBOREncodable,
+        CBORTagged,
+        CBORTaggedCodable,
+        CBORTaggedDecodable,
+        CBORTaggedEncodable,
+        CodecObserver,
+        DisplayWithInput,
+        TagsStoreTrait,
+    };
+}
+
+/// The tags store and the items used to format CBOR.
+pub mod formatting {
+    #[cfg(feature = "format")]
+    pub use crate::{
+        CBORSummarizer,
+        DiagFormatOpts,
+    };
+    pub use crate::{
+        TagsStore,
+        with_tags,
+        with_tags_mut,
+        tags_for_values,
+    };
+}
@@ -0,0 +1,113 @@
+import_stdlib!();
+
+use anyhow::{bail, Error, Result};
+
+use crate::{CBORCase, CBORError, Map, CBOR};
+
+/// A map whose keys are integer field numbers, as used by record-like
+/// protocol structures.
+///
+/// Field accessors report errors in terms of the field involved, e.g.
+/// "field 2 missing".
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Record(Map);
+
+impl Record {
+    /// Creates a new, empty record.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the value of a field, replacing any existing value.
+    pub fn set(&mut self, field: u64, value: impl Into<CBOR>) {
+        self.0.insert(field, value);
+    }
+
+    /// Sets the value of a field, returning the record.
+    pub fn with(mut self, field: u64, value: impl Into<CBOR>) -> Self {
+        self.set(field, value);
+        self
+    }
+
+    /// Sets the value of a field if a value is given.
+    pub fn set_optional(&mut self, field: u64, value: Option<impl Into<CBOR>>) {
+        if let Some(value) = value {
+            self.set(field, value);
+        }
+    }
+
+    /// Returns `true` if the field is present.
+    pub fn contains(&self, field: u64) -> bool {
+        self.0.get::<_, CBOR>(field).is_some()
+    }
+
+    /// Returns the value of a field that must be present.
+    ///
+    /// Returns [`CBORError::MissingField`] if the field is missing, or
+    /// [`CBORError::InvalidField`] if it cannot be converted to `T`.
+    pub fn get_required<T>(&self, field: u64) -> Result<T>
+    where
+        T: TryFrom<CBOR>, T::Error: Into<anyhow::Error>
+    {
+        match self.get_optional(field)? {
+            Some(value) => Ok(value),
+            None => bail!(CBORError::MissingField(field)),
+        }
+    }
+
+    /// Returns the value of a field if it is present.
+    ///
+    /// Returns [`CBORError::InvalidField`] if the field is present but cannot
+    /// be converted to `T`, with the conversion error as its source.
+    pub fn get_optional<T>(&self, field: u64) -> Result<Option<T>>
+    where
+        T: TryFrom<CBOR>, T::Error: Into<anyhow::Error>
+    {
+        match self.0.get::<_, CBOR>(field) {
+            Some(value) => match T::try_from(value) {
+                Ok(value) => Ok(Some(value)),
+                Err(err) => bail!(CBORError::InvalidField(field, err.into())),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the underlying map.
+    pub fn as_map(&self) -> &Map {
+        &self.0
+    }
+
+    /// Returns the underlying map, consuming the record.
+    pub fn into_map(self) -> Map {
+        self.0
+    }
+}
+
+impl From<Map> for Record {
+    fn from(map: Map) -> Self {
+        Self(map)
+    }
+}
+
+impl From<Record> for CBOR {
+    fn from(record: Record) -> Self {
+        record.0.into()
+    }
+}
+
+impl From<&Record> for CBOR {
+    fn from(record: &Record) -> Self {
+        (&record.0).into()
+    }
+}
+
+impl TryFrom<CBOR> for Record {
+    type Error = Error;
+
+    fn try_from(cbor: CBOR) -> Result<Self> {
+        match cbor.into_case() {
+            CBORCase::Map(map) => Ok(Self(map)),
+            _ => bail!(CBORError::WrongType),
+        }
+    }
+}
@@ -0,0 +1,154 @@
+import_stdlib!();
+
+use crate::{CBORCase, Map, TagValue, CBOR, TAG_REDACTED};
+
+/// One step on the path from the root of a CBOR tree to a value within it.
+///
+/// Tags are not steps: a path continues through a tagged value to its
+/// content.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathElement {
+    /// The element of an array at the given index.
+    Index(usize),
+    /// The value of a map for the given key.
+    Key(CBOR),
+}
+
+impl From<usize> for PathElement {
+    fn from(index: usize) -> Self {
+        PathElement::Index(index)
+    }
+}
+
+impl From<&str> for PathElement {
+    fn from(key: &str) -> Self {
+        PathElement::Key(key.into())
+    }
+}
+
+/// The values to redact from a CBOR tree, by path, tag, or map key.
+///
+/// See [`CBOR::redacted`].
+#[derive(Debug, Clone, Default)]
+pub struct RedactionPolicy {
+    paths: Vec<Vec<PathElement>>,
+    tags: Vec<TagValue>,
+    keys: Vec<CBOR>,
+}
+
+impl RedactionPolicy {
+    /// Makes a new policy redacting nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Redacts the value at the given path from the root.
+    pub fn path<P: Into<PathElement>>(mut self, path: impl IntoIterator<Item = P>) -> Self {
+        self.paths.push(path.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Redacts every value tagged with the given tag, including the tag.
+    pub fn tag(mut self, tag: TagValue) -> Self {
+        self.tags.push(tag);
+        self
+    }
+
+    /// Redacts the value of every map entry with the given key, at any level.
+    pub fn key(mut self, key: impl Into<CBOR>) -> Self {
+        self.keys.push(key.into());
+        self
+    }
+
+    fn redacts(&self, path: &[PathElement], cbor: &CBOR) -> bool {
+        if self.paths.iter().any(|p| p == path) {
+            return true;
+        }
+        if let Some(PathElement::Key(key)) = path.last() {
+            if self.keys.contains(key) {
+                return true;
+            }
+        }
+        matches!(cbor.as_case(), CBORCase::Tagged(tag, _) if self.tags.contains(&tag.value()))
+    }
+}
+
+/// Redaction of values, keeping the structure of the tree.
+impl CBOR {
+    /// Returns a copy of this value with the values selected by the policy
+    /// replaced by digests of their encodings.
+    ///
+    /// Each redacted value is replaced by a byte string holding
+    /// `digest(encoding)`, tagged with [`TAG_REDACTED`]. Arrays keep their
+    /// length and maps keep their keys, so the structure of the tree is
+    /// visible, and a receiver given the redacted copy can check it against
+    /// the original with [`CBOR::is_redaction_of`]. Values already redacted
+    /// are left as they are.
+    pub fn redacted(&self, policy: &RedactionPolicy, digest: impl Fn(&[u8]) -> Vec<u8>) -> CBOR {
+        self.redacted_at(&mut Vec::new(), policy, &digest)
+    }
+
+    /// Returns `true` if this value is `original` with some values redacted as
+    /// [`CBOR::redacted`] redacts them, using the same digest.
+    pub fn is_redaction_of(&self, original: &CBOR, digest: impl Fn(&[u8]) -> Vec<u8>) -> bool {
+        self.is_redaction_of_dyn(original, &digest)
+    }
+
+    fn redacted_at(&self, path: &mut Vec<PathElement>, policy: &RedactionPolicy, digest: &dyn Fn(&[u8]) -> Vec<u8>) -> CBOR {
+        if self.is_redacted() {
+            return self.clone();
+        }
+        if policy.redacts(path, self) {
+            return CBOR::to_tagged_value(TAG_REDACTED, CBOR::to_byte_string(digest(&self.to_cbor_data())));
+        }
+        match self.as_case() {
+            CBORCase::Array(array) => {
+                let mut result = Vec::with_capacity(array.len());
+                for (index, item) in array.iter().enumerate() {
+                    path.push(PathElement::Index(index));
+                    result.push(item.redacted_at(path, policy, digest));
+                    path.pop();
+                }
+                result.into()
+            },
+            CBORCase::Map(map) => {
+                let mut result = Map::new();
+                for (key, value) in map.iter() {
+                    path.push(PathElement::Key(key.clone()));
+                    result.insert(key.clone(), value.redacted_at(path, policy, digest));
+                    path.pop();
+                }
+                result.into()
+            },
+            CBORCase::Tagged(tag, item) => {
+                CBOR::to_tagged_value(tag.clone(), item.redacted_at(path, policy, digest))
+            },
+            _ => self.clone(),
+        }
+    }
+
+    fn is_redaction_of_dyn(&self, original: &CBOR, digest: &dyn Fn(&[u8]) -> Vec<u8>) -> bool {
+        if self == original {
+            return true;
+        }
+        match (self.as_case(), original.as_case()) {
+            (CBORCase::Tagged(tag, item), _) if tag.value() == TAG_REDACTED => {
+                matches!(item.as_case(), CBORCase::ByteString(d) if d.data() == digest(&original.to_cbor_data()))
+            },
+            (CBORCase::Array(a), CBORCase::Array(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.is_redaction_of_dyn(b, digest))
+            },
+            (CBORCase::Map(a), CBORCase::Map(b)) => {
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|((ka, va), (kb, vb))| {
+                    ka == kb && va.is_redaction_of_dyn(vb, digest)
+                })
+            },
+            (CBORCase::Tagged(ta, a), CBORCase::Tagged(tb, b)) => ta == tb && a.is_redaction_of_dyn(b, digest),
+            _ => false,
+        }
+    }
+
+    fn is_redacted(&self) -> bool {
+        matches!(self.as_case(), CBORCase::Tagged(tag, _) if tag.value() == TAG_REDACTED)
+    }
+}
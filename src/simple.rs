@@ -2,12 +2,18 @@ import_stdlib!();
 
 use anyhow::{bail, Error, Result};
 
-use crate::{float::f64_cbor_data, CBORCase, CBORError, CBOR};
+#[cfg(feature = "float")]
+use crate::float::f64_cbor_data;
+use crate::{CBORCase, CBORError, CBOR};
 
 use super::varint::{EncodeVarInt, MajorType};
 
 /// A CBOR simple value.
+///
+/// The enum is non-exhaustive, as its [`Simple::Float`] variant exists only
+/// with the `float` feature, which any crate in a build may enable.
 #[derive(Clone)]
+#[non_exhaustive]
 pub enum Simple {
     /// The boolean value `false`.
     False,
@@ -16,6 +22,9 @@ pub enum Simple {
     /// The value representing `null` (`None`).
     Null,
     /// A floating point value.
+    ///
+    /// Only with the `float` feature.
+    #[cfg(feature = "float")]
     Float(f64),
 }
 
@@ -30,6 +39,7 @@ impl Simple {
             Self::False => 20u8.encode_varint(MajorType::Simple),
             Self::True => 21u8.encode_varint(MajorType::Simple),
             Self::Null => 22u8.encode_varint(MajorType::Simple),
+            #[cfg(feature = "float")]
             Self::Float(v) => f64_cbor_data(*v),
         }
     }
@@ -37,7 +47,13 @@ impl Simple {
 
 impl From<Simple> for CBOR {
     fn from(value: Simple) -> Self {
-        CBORCase::Simple(value.clone()).into()
+        CBORCase::Simple(value).into()
+    }
+}
+
+impl From<&Simple> for CBOR {
+    fn from(value: &Simple) -> Self {
+        value.clone().into()
     }
 }
 
@@ -58,6 +74,7 @@ impl PartialEq for Simple {
             (Self::False, Self::False) => true,
             (Self::True, Self::True) => true,
             (Self::Null, Self::Null) => true,
+            #[cfg(feature = "float")]
             (Self::Float(v1), Self::Float(v2)) => v1 == v2,
             _ => false,
         }
@@ -70,6 +87,7 @@ impl fmt::Debug for Simple {
             Self::False => "false".to_owned(),
             Self::True => "true".to_owned(),
             Self::Null => "null".to_owned(),
+            #[cfg(feature = "float")]
             Self::Float(v) => format!("{:?}", v),
         };
         f.write_str(&s)
@@ -82,6 +100,7 @@ impl fmt::Display for Simple {
             Self::False => "false".to_owned(),
             Self::True => "true".to_owned(),
             Self::Null => "null".to_owned(),
+            #[cfg(feature = "float")]
             Self::Float(v) => format!("{:?}", v),
         };
         f.write_str(&s)
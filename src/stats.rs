@@ -0,0 +1,103 @@
+import_stdlib!();
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{CBORCase, CBOR};
+
+use super::varint::{EncodeVarInt, MajorType};
+
+/// Size and shape statistics for a CBOR value, as returned by
+/// [`CBOR::stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// The total number of items, including the top-level item and all
+    /// nested items, map keys, and tagged contents.
+    pub total_items: usize,
+    /// The deepest nesting level reached, with the top-level item at depth
+    /// zero.
+    pub max_depth: usize,
+    /// The length of the binary-encoded value.
+    pub byte_len: usize,
+    /// The number of items of each major type.
+    pub counts_per_major_type: MajorTypeCounts,
+    /// The length in bytes of the largest text or byte string.
+    pub largest_string: usize,
+    /// The number of elements in the largest array.
+    pub largest_array: usize,
+    /// The number of entries in the largest map.
+    pub largest_map: usize,
+}
+
+/// The number of items of each CBOR major type.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MajorTypeCounts {
+    pub unsigned: usize,
+    pub negative: usize,
+    pub byte_string: usize,
+    pub text: usize,
+    pub array: usize,
+    pub map: usize,
+    pub tagged: usize,
+    pub simple: usize,
+}
+
+impl CBOR {
+    /// Returns size and shape statistics for this value, computed in a single
+    /// pass over the tree.
+    pub fn stats(&self) -> Stats {
+        let mut stats = Stats::default();
+        stats.byte_len = self.accumulate_stats(0, &mut stats);
+        stats
+    }
+
+    /// Adds this item and its children to `stats`, returning the item's
+    /// encoded length.
+    fn accumulate_stats(&self, depth: usize, stats: &mut Stats) -> usize {
+        stats.total_items += 1;
+        stats.max_depth = stats.max_depth.max(depth);
+        let counts = &mut stats.counts_per_major_type;
+        match self.as_case() {
+            CBORCase::Unsigned(_) => {
+                counts.unsigned += 1;
+                self.encoded().len()
+            },
+            CBORCase::Negative(_) => {
+                counts.negative += 1;
+                self.encoded().len()
+            },
+            CBORCase::ByteString(b) => {
+                counts.byte_string += 1;
+                stats.largest_string = stats.largest_string.max(b.len());
+                b.len().encode_varint(MajorType::ByteString).len() + b.len()
+            },
+            CBORCase::Text(t) => {
+                counts.text += 1;
+                let payload_len: usize = t.nfc().map(char::len_utf8).sum();
+                stats.largest_string = stats.largest_string.max(payload_len);
+                payload_len.encode_varint(MajorType::Text).len() + payload_len
+            },
+            CBORCase::Array(a) => {
+                counts.array += 1;
+                stats.largest_array = stats.largest_array.max(a.len());
+                let header_len = a.len().encode_varint(MajorType::Array).len();
+                a.iter().fold(header_len, |len, item| len + item.accumulate_stats(depth + 1, stats))
+            },
+            CBORCase::Map(m) => {
+                counts.map += 1;
+                stats.largest_map = stats.largest_map.max(m.len());
+                let header_len = m.len().encode_varint(MajorType::Map).len();
+                m.iter().fold(header_len, |len, (key, value)| {
+                    len + key.accumulate_stats(depth + 1, stats) + value.accumulate_stats(depth + 1, stats)
+                })
+            },
+            CBORCase::Tagged(tag, item) => {
+                counts.tagged += 1;
+                tag.value().encode_varint(MajorType::Tagged).len() + item.accumulate_stats(depth + 1, stats)
+            },
+            CBORCase::Simple(_) => {
+                counts.simple += 1;
+                self.encoded().len()
+            },
+        }
+    }
+}
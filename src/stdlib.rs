@@ -1,19 +1,27 @@
 #[cfg(feature = "std")]
 pub mod with_std {
-    pub use std::{fmt, str::FromStr};
+    pub use std::fmt;
+    #[allow(unused_imports)] // Only re-exported, with the `reexport-stdlib` feature.
+    pub use std::str::FromStr;
 
+    pub use std::any::Any;
     pub use std::array::TryFromSliceError;
-    pub use std::borrow::ToOwned;
+    pub use std::borrow::{Borrow, Cow, ToOwned};
     pub use std::boxed::Box;
     pub use std::cmp::{self};
-    pub use std::collections::{BTreeMap, btree_map::Values as BTreeMapValues, VecDeque, HashSet, HashMap};
+    pub use std::collections::{BTreeMap, BTreeSet, BinaryHeap, btree_map::Values as BTreeMapValues, btree_map::IntoValues as BTreeMapIntoValues, btree_map::Range as BTreeMapRange, LinkedList, VecDeque, HashSet, HashMap};
     pub use std::format;
     pub use std::hash::{self};
+    #[allow(unused_imports)] // Unused without the `float` feature.
+    pub use std::num::{self};
     pub use std::ops::{self, Deref};
+    #[allow(unused_imports)] // Unused with the `multithreaded` feature.
     pub use std::rc::{self};
     pub use std::str::{self};
     pub use std::string::{String, ToString};
-    pub use std::sync::{self, Arc, Once, Mutex, MutexGuard};
+    #[allow(unused_imports)] // Only re-exported, with the `reexport-stdlib` feature.
+    pub use std::sync::{self};
+    pub use std::sync::{Arc, Once, Mutex, MutexGuard};
     pub use std::time::Duration;
     pub use std::vec::Vec;
     pub use thiserror::Error as ThisError;
@@ -24,36 +32,53 @@ pub mod with_std {
 pub mod without_std {
     extern crate alloc;
 
-    pub use alloc::borrow::ToOwned;
+    pub use alloc::borrow::{Borrow, Cow, ToOwned};
     pub use alloc::boxed::Box;
-    pub use alloc::collections::{BTreeMap, btree_map::Values as BTreeMapValues, VecDeque};
+    pub use alloc::collections::{BTreeMap, BTreeSet, BinaryHeap, btree_map::Values as BTreeMapValues, btree_map::IntoValues as BTreeMapIntoValues, btree_map::Range as BTreeMapRange, LinkedList, VecDeque};
     pub use alloc::fmt::{self};
     pub use alloc::format;
+    #[allow(unused_imports)] // Unused with the `multithreaded` feature.
     pub use alloc::rc::{self};
     pub use alloc::str::{self};
     pub use alloc::string::{String, ToString};
-    pub use alloc::sync::{self, Arc};
+    #[allow(unused_imports)] // Only re-exported, with the `reexport-stdlib` feature.
+    pub use alloc::sync::{self};
+    pub use alloc::sync::Arc;
     pub use alloc::vec;
     pub use alloc::vec::Vec;
+    pub use core::any::Any;
     pub use core::array::TryFromSliceError;
     pub use core::cmp::{self};
     pub use core::hash::{self};
+    #[allow(unused_imports)] // Unused without the `float` feature.
+    pub use core::num::{self};
     pub use core::ops::{self, Deref};
     pub use core::time::Duration;
     pub use hashbrown::{HashSet, HashMap};
     pub use spin::{Once, Mutex, MutexGuard};
     pub use thiserror_no_std::Error as ThisError;
 
+    #[allow(dead_code)] // Only re-exported, with the `reexport-stdlib` feature.
     pub trait StdError: fmt::Debug + fmt::Display { }
 }
 
+/// Imports the standard library items used throughout the crate.
+///
+/// With the `reexport-stdlib` feature the items are also re-exported from
+/// the crate root, as they always were before the feature was added.
 macro_rules! import_stdlib {
     () => {
         #[allow(unused_imports)]
-        #[cfg(feature = "std")]
+        #[cfg(all(feature = "std", feature = "reexport-stdlib"))]
         pub use $crate::stdlib::with_std::*;
         #[allow(unused_imports)]
-        #[cfg(not(feature = "std"))]
+        #[cfg(all(feature = "std", not(feature = "reexport-stdlib")))]
+        use $crate::stdlib::with_std::*;
+        #[allow(unused_imports)]
+        #[cfg(all(not(feature = "std"), feature = "reexport-stdlib"))]
         pub use $crate::stdlib::without_std::*;
+        #[allow(unused_imports)]
+        #[cfg(all(not(feature = "std"), not(feature = "reexport-stdlib")))]
+        use $crate::stdlib::without_std::*;
     };
 }
@@ -12,7 +12,13 @@ impl From<&str> for CBOR {
 
 impl From<String> for CBOR {
     fn from(value: String) -> Self {
-        CBORCase::Text(value.clone()).into()
+        CBORCase::Text(value).into()
+    }
+}
+
+impl From<&String> for CBOR {
+    fn from(value: &String) -> Self {
+        value.as_str().into()
     }
 }
 
@@ -4,10 +4,33 @@ pub fn flanked(s: &str, left: &str, right: &str) -> String {
     left.to_owned() + s + right
 }
 
+/// Converts bytes to a string if they are valid UTF-8, validating them with
+/// SIMD instructions if the `simd` feature is enabled.
+pub fn from_utf8(bytes: &[u8]) -> Result<&str, str::Utf8Error> {
+    // The fast validator doesn't describe the error, so invalid strings are
+    // validated again to find it.
+    #[cfg(feature = "simd")]
+    if let Ok(string) = simdutf8::basic::from_utf8(bytes) {
+        return Ok(string);
+    }
+    str::from_utf8(bytes)
+}
+
+/// Returns the bytes in lowercase hexadecimal, using SIMD instructions if the
+/// `simd` feature is enabled.
+pub fn hex_encode(bytes: &[u8]) -> String {
+    #[cfg(feature = "simd")]
+    return faster_hex::hex_string(bytes);
+    #[cfg(not(feature = "simd"))]
+    hex::encode(bytes)
+}
+
+#[cfg(feature = "format")]
 pub fn is_printable(c: char) -> bool {
     !c.is_ascii() || (32..=126).contains(&(c as u32))
 }
 
+#[cfg(feature = "format")]
 pub fn sanitized(string: &str) -> Option<String> {
     let mut has_printable = false;
     let chars: Vec<_> = string.chars().map(|c| {
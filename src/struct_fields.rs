@@ -0,0 +1,97 @@
+import_stdlib!();
+
+use anyhow::{bail, Result};
+
+use crate::{CBORCase, CBORError, FieldProblem, Map, Simple, TagValue, CBOR};
+
+/// The kind of value expected in a field extracted by
+/// [`Map::to_struct_fields`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    /// Any value.
+    Any,
+    /// An unsigned integer.
+    Unsigned,
+    /// An unsigned or negative integer.
+    Integer,
+    /// An integer or floating point value.
+    Number,
+    /// `true` or `false`.
+    Bool,
+    /// A text string.
+    Text,
+    /// A byte string.
+    ByteString,
+    /// An array.
+    Array,
+    /// A map.
+    Map,
+    /// A value tagged with the given tag.
+    Tagged(TagValue),
+}
+
+impl FieldKind {
+    /// Returns `true` if the value is of this kind.
+    pub fn matches(&self, cbor: &CBOR) -> bool {
+        match (self, cbor.as_case()) {
+            (FieldKind::Any, _) => true,
+            (FieldKind::Unsigned, CBORCase::Unsigned(_)) => true,
+            (FieldKind::Integer, CBORCase::Unsigned(_) | CBORCase::Negative(_)) => true,
+            (FieldKind::Number, CBORCase::Unsigned(_) | CBORCase::Negative(_)) => true,
+            #[cfg(feature = "float")]
+            (FieldKind::Number, CBORCase::Simple(Simple::Float(_))) => true,
+            (FieldKind::Bool, CBORCase::Simple(Simple::True | Simple::False)) => true,
+            (FieldKind::Text, CBORCase::Text(_)) => true,
+            (FieldKind::ByteString, CBORCase::ByteString(_)) => true,
+            (FieldKind::Array, CBORCase::Array(_)) => true,
+            (FieldKind::Map, CBORCase::Map(_)) => true,
+            (FieldKind::Tagged(value), CBORCase::Tagged(tag, _)) => tag.value() == *value,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for FieldKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldKind::Any => write!(f, "any value"),
+            FieldKind::Unsigned => write!(f, "unsigned integer"),
+            FieldKind::Integer => write!(f, "integer"),
+            FieldKind::Number => write!(f, "number"),
+            FieldKind::Bool => write!(f, "boolean"),
+            FieldKind::Text => write!(f, "text"),
+            FieldKind::ByteString => write!(f, "byte string"),
+            FieldKind::Array => write!(f, "array"),
+            FieldKind::Map => write!(f, "map"),
+            FieldKind::Tagged(value) => write!(f, "tag {}", value),
+        }
+    }
+}
+
+impl Map {
+    /// Extracts the values of the given text-keyed fields, checking each is
+    /// present and of the expected kind.
+    ///
+    /// Returns the values in the order the fields are given, ready to be
+    /// converted to their Rust types. If any fields are missing or of the
+    /// wrong kind, returns [`CBORError::InvalidFields`] with a
+    /// [`FieldProblem`] for every such field, described as e.g.
+    /// `invalid fields: field "id" missing; field "name": expected text`.
+    ///
+    /// Fields in the map that are not listed are ignored.
+    pub fn to_struct_fields(&self, fields: &[(&str, FieldKind)]) -> Result<Vec<CBOR>> {
+        let mut values = Vec::with_capacity(fields.len());
+        let mut problems = Vec::new();
+        for (name, kind) in fields {
+            match self.get::<_, CBOR>(*name) {
+                Some(value) if kind.matches(&value) => values.push(value),
+                Some(_) => problems.push(FieldProblem::WrongKind(name.to_string(), *kind)),
+                None => problems.push(FieldProblem::Missing(name.to_string())),
+            }
+        }
+        if !problems.is_empty() {
+            bail!(CBORError::InvalidFields(problems));
+        }
+        Ok(values)
+    }
+}
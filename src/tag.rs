@@ -1,5 +1,9 @@
 import_stdlib!();
 
+use anyhow::{bail, Error, Result};
+
+use crate::{with_tags, CBORError, TagsStoreTrait};
+
 #[derive(Debug, Clone)]
 enum TagName {
     Static(&'static str),
@@ -46,6 +50,33 @@ impl Tag {
     }
 }
 
+impl Tag {
+    /// Parses a tag from either its numeric value, e.g. `"1"`, or a name
+    /// assigned in the given tags store, e.g. `"date"`.
+    ///
+    /// A numeric value is given the name assigned to it in the store, if any.
+    /// Returns an error if the string is neither a number nor an assigned
+    /// name.
+    pub fn parse_with(s: &str, tags: &dyn TagsStoreTrait) -> Result<Tag> {
+        if let Ok(value) = s.parse::<TagValue>() {
+            return Ok(tags.tag_for_value(value).unwrap_or_else(|| Tag::with_value(value)));
+        }
+        match tags.tag_for_name(s) {
+            Some(tag) => Ok(tag),
+            None => bail!(CBORError::UnknownTagName(s.to_string())),
+        }
+    }
+}
+
+/// Parses a tag using the global tags store. See [`Tag::parse_with`].
+impl str::FromStr for Tag {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        with_tags!(|tags: &dyn TagsStoreTrait| Tag::parse_with(s, tags))
+    }
+}
+
 impl PartialEq for Tag {
     fn eq(&self, other: &Self) -> bool {
         self.value == other.value
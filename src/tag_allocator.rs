@@ -0,0 +1,230 @@
+import_stdlib!();
+
+use anyhow::{bail, Result};
+
+use crate::{CBORError, Tag, TagValue, TagsStore};
+
+/// The ranges of tag values defined by the IANA CBOR tags registry
+/// ([RFC 8949 §9.2](https://www.rfc-editor.org/rfc/rfc8949#section-9.2)).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagRange {
+    /// Tags 0 to 23, registered by IETF standards action.
+    StandardsAction,
+    /// Tags 24 to 32767, registered with a published specification.
+    SpecificationRequired,
+    /// Tags 32768 and above, registered first come, first served.
+    FirstComeFirstServed,
+    /// Tags 65535, 4294967295, and 18446744073709551615, which are reserved
+    /// and must not be used.
+    Invalid,
+}
+
+impl TagRange {
+    /// Returns the range the tag value falls in.
+    pub fn of(value: TagValue) -> TagRange {
+        match value {
+            0..=23 => TagRange::StandardsAction,
+            24..=32767 => TagRange::SpecificationRequired,
+            0xffff | 0xffff_ffff | u64::MAX => TagRange::Invalid,
+            _ => TagRange::FirstComeFirstServed,
+        }
+    }
+}
+
+impl fmt::Display for TagRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TagRange::StandardsAction => "standards action",
+            TagRange::SpecificationRequired => "specification required",
+            TagRange::FirstComeFirstServed => "first come, first served",
+            TagRange::Invalid => "invalid",
+        })
+    }
+}
+
+/// A function called with the details of a questionable tag allocation, as
+/// used by [`TagAllocator::with_warning_handler`].
+pub type TagWarningHandler = Arc<dyn Fn(&CBORError) + Send + Sync>;
+
+/// Allocates tag values for a crate's own types from a range of values, and
+/// registers their names.
+///
+/// Crates that define tags without registering them with IANA should
+/// allocate them from [`TagAllocator::private`], so they don't collide with
+/// registered tags or with other crates choosing values arbitrarily.
+///
+/// ```
+/// # use dcbor::{TagAllocator, TagsStore, TagsStoreTrait};
+/// let mut allocator = TagAllocator::private();
+/// let point = allocator.allocate("point").unwrap();
+/// let line = allocator.allocate("line").unwrap();
+/// assert_eq!(line.value(), point.value() + 1);
+///
+/// let mut tags = TagsStore::default();
+/// allocator.register_in(&mut tags).unwrap();
+/// assert_eq!(tags.tag_for_name("line"), Some(line));
+/// ```
+#[derive(Clone)]
+pub struct TagAllocator {
+    range: ops::RangeInclusive<TagValue>,
+    next: Option<TagValue>,
+    tags: Vec<Tag>,
+    warning_handler: Option<TagWarningHandler>,
+}
+
+impl TagAllocator {
+    /// The first-come-first-served range of the IANA registry, excluding the
+    /// invalid value 18446744073709551615.
+    pub const FIRST_COME_FIRST_SERVED: ops::RangeInclusive<TagValue> = 32768..=u64::MAX - 1;
+
+    /// The range used by [`TagAllocator::private`].
+    ///
+    /// The IANA registry defines no private-use range, so by convention this
+    /// crate treats the upper half of the first-come-first-served range, in
+    /// which no tags are registered, as private. The top of that half is
+    /// kept back as [`TagAllocator::DCBOR_PRIVATE`].
+    pub const PRIVATE: ops::RangeInclusive<TagValue> = 0x8000_0000_0000_0000..=0xffff_ffff_ffff_feff;
+
+    /// The private tag values used by this crate for its own tags, such as
+    /// [`TAG_BYTE_STRING_CHUNK`](crate::TAG_BYTE_STRING_CHUNK), just above
+    /// [`TagAllocator::PRIVATE`], so they cannot collide with the tags of
+    /// crates that allocate from it.
+    pub const DCBOR_PRIVATE: ops::RangeInclusive<TagValue> = 0xffff_ffff_ffff_ff00..=u64::MAX - 1;
+
+    /// Makes a new allocator for the given range of tag values.
+    ///
+    /// Allocating a value in the standards action or specification required
+    /// ranges calls the warning handler, if any, with
+    /// [`CBORError::ReservedTagValue`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is empty.
+    pub fn new(range: ops::RangeInclusive<TagValue>) -> Self {
+        assert!(!range.is_empty(), "TagAllocator::new called with an empty range");
+        Self {
+            next: Some(*range.start()),
+            range,
+            tags: Vec::new(),
+            warning_handler: None,
+        }
+    }
+
+    /// Makes a new allocator for tags that are to be registered with IANA
+    /// first come, first served, starting at the given value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is below the first-come-first-served range.
+    pub fn first_come_first_served(start: TagValue) -> Self {
+        assert!(
+            start >= *Self::FIRST_COME_FIRST_SERVED.start(),
+            "TagAllocator::first_come_first_served called with a value below 32768"
+        );
+        Self::new(start..=*Self::FIRST_COME_FIRST_SERVED.end())
+    }
+
+    /// Makes a new allocator for tags that are used privately and never
+    /// registered, allocated from [`TagAllocator::PRIVATE`].
+    pub fn private() -> Self {
+        Self::new(Self::PRIVATE)
+    }
+
+    /// Sets the handler called when a value is allocated in a range reserved
+    /// for registered standard tags.
+    pub fn with_warning_handler(mut self, handler: TagWarningHandler) -> Self {
+        self.warning_handler = Some(handler);
+        self
+    }
+
+    /// Returns the range of values this allocator allocates from.
+    pub fn range(&self) -> &ops::RangeInclusive<TagValue> {
+        &self.range
+    }
+
+    /// Returns the tags allocated so far, in the order they were allocated.
+    pub fn tags(&self) -> &[Tag] {
+        &self.tags
+    }
+
+    /// Allocates the next unallocated value in the range to a tag with the
+    /// given name.
+    ///
+    /// Invalid tag values are skipped. Returns
+    /// [`CBORError::TagRangeExhausted`] if no values are left, or
+    /// [`CBORError::TagNameConflict`] if the name has already been allocated.
+    pub fn allocate(&mut self, name: impl Into<String>) -> Result<Tag> {
+        let Some(mut value) = self.next else {
+            bail!(CBORError::TagRangeExhausted);
+        };
+        while TagRange::of(value) == TagRange::Invalid || self.is_allocated(value) {
+            if value == *self.range.end() {
+                self.next = None;
+                bail!(CBORError::TagRangeExhausted);
+            }
+            value += 1;
+        }
+        let tag = self.allocate_value(value, name)?;
+        self.next = if value < *self.range.end() { Some(value + 1) } else { None };
+        Ok(tag)
+    }
+
+    /// Allocates the given value to a tag with the given name.
+    ///
+    /// Returns [`CBORError::OutOfRange`] if the value is outside the range,
+    /// [`CBORError::ReservedTagValue`] if it is an invalid tag value, or
+    /// [`CBORError::TagValueConflict`] or [`CBORError::TagNameConflict`] if
+    /// the value or name has already been allocated.
+    pub fn allocate_value(&mut self, value: TagValue, name: impl Into<String>) -> Result<Tag> {
+        let name = name.into();
+        if !self.range.contains(&value) {
+            bail!(CBORError::OutOfRange);
+        }
+        let range = TagRange::of(value);
+        if range == TagRange::Invalid {
+            bail!(CBORError::ReservedTagValue(value, range));
+        }
+        for tag in &self.tags {
+            let existing_name = tag.name().unwrap();
+            if tag.value() == value {
+                bail!(CBORError::TagValueConflict(value, existing_name, name));
+            }
+            if existing_name == name {
+                bail!(CBORError::TagNameConflict(name, tag.value(), value));
+            }
+        }
+        if matches!(range, TagRange::StandardsAction | TagRange::SpecificationRequired) {
+            if let Some(handler) = &self.warning_handler {
+                handler(&CBORError::ReservedTagValue(value, range));
+            }
+        }
+        let tag = Tag::new(value, name);
+        self.tags.push(tag.clone());
+        Ok(tag)
+    }
+
+    /// Registers the names of the allocated tags in the given store.
+    ///
+    /// Conflicts with tags already in the store are handled according to its
+    /// [`TagConflictPolicy`](crate::TagConflictPolicy).
+    pub fn register_in(&self, tags_store: &mut TagsStore) -> Result<()> {
+        for tag in &self.tags {
+            tags_store.try_insert(tag.clone())?;
+        }
+        Ok(())
+    }
+
+    fn is_allocated(&self, value: TagValue) -> bool {
+        self.tags.iter().any(|tag| tag.value() == value)
+    }
+}
+
+impl fmt::Debug for TagAllocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TagAllocator")
+            .field("range", &self.range)
+            .field("next", &self.next)
+            .field("tags", &self.tags)
+            .finish_non_exhaustive()
+    }
+}
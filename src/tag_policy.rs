@@ -0,0 +1,91 @@
+import_stdlib!();
+
+use anyhow::{bail, Result};
+
+use crate::{
+    CBORCase, CBORError, CBORKind, Tag, TagValue, CBOR, TAG_BIGFLOAT, TAG_DATE, TAG_DATE_TIME_STRING,
+    TAG_DECIMAL_FRACTION, TAG_NEGATIVE_BIGNUM, TAG_POSITIVE_BIGNUM, TAG_SELF_DESCRIBED_CBOR,
+};
+
+/// The rules checked by [`CBOR::try_to_tagged_value_with`] before tagging a
+/// value.
+///
+/// The standard tags 0 to 5 must have content of the types defined for them
+/// by [RFC 8949 §3.4](https://www.rfc-editor.org/rfc/rfc8949#section-3.4).
+/// The self-described CBOR tag 55799 is rejected unless explicitly allowed,
+/// as it is meant only to mark the start of a file or stream. Options are
+/// set using the builder-style methods, starting from
+/// `TagPolicy::default()`.
+#[derive(Debug, Clone, Default)]
+pub struct TagPolicy {
+    allow_self_described: bool,
+}
+
+impl TagPolicy {
+    /// Whether the self-described CBOR tag 55799 may be used. Defaults to
+    /// `false`.
+    pub fn allow_self_described(mut self, allow: bool) -> Self {
+        self.allow_self_described = allow;
+        self
+    }
+
+    /// Checks that the tag may be applied to the given content.
+    ///
+    /// Returns [`CBORError::InvalidTaggedValue`] if it may not.
+    pub fn check(&self, tag: TagValue, item: &CBOR) -> Result<()> {
+        let expected = match tag {
+            TAG_DATE_TIME_STRING => (item.kind() != CBORKind::Text).then_some("a text string"),
+            TAG_DATE => (!item.is_number()).then_some("a number"),
+            TAG_POSITIVE_BIGNUM | TAG_NEGATIVE_BIGNUM => (item.kind() != CBORKind::ByteString).then_some("a byte string"),
+            TAG_DECIMAL_FRACTION | TAG_BIGFLOAT => (!is_exponent_and_mantissa(item))
+                .then_some("an array of an integer exponent and an integer or bignum mantissa"),
+            TAG_SELF_DESCRIBED_CBOR if !self.allow_self_described => {
+                bail!(CBORError::InvalidTaggedValue(tag, "the self-described CBOR tag is not allowed".to_string()))
+            },
+            _ => None,
+        };
+        if let Some(expected) = expected {
+            bail!(CBORError::InvalidTaggedValue(tag, format!("the content must be {}", expected)));
+        }
+        Ok(())
+    }
+}
+
+fn is_bignum(item: &CBOR) -> bool {
+    match item.as_case() {
+        CBORCase::Tagged(tag, content) => {
+            matches!(tag.value(), TAG_POSITIVE_BIGNUM | TAG_NEGATIVE_BIGNUM) && content.kind() == CBORKind::ByteString
+        },
+        _ => false,
+    }
+}
+
+fn is_exponent_and_mantissa(item: &CBOR) -> bool {
+    match item.as_case() {
+        CBORCase::Array(items) if items.len() == 2 => {
+            items[0].is_integer() && (items[1].is_integer() || is_bignum(&items[1]))
+        },
+        _ => false,
+    }
+}
+
+impl CBOR {
+    /// Create a new CBOR value representing a tagged value, checking it
+    /// against the default [`TagPolicy`].
+    ///
+    /// Returns [`CBORError::InvalidTaggedValue`] if the content does not
+    /// have the type required by a standard tag, or if the tag is the
+    /// self-described CBOR tag.
+    pub fn try_to_tagged_value(tag: impl Into<Tag>, item: impl Into<CBOR>) -> Result<CBOR> {
+        Self::try_to_tagged_value_with(tag, item, &TagPolicy::default())
+    }
+
+    /// Create a new CBOR value representing a tagged value, checking it
+    /// against the given policy.
+    pub fn try_to_tagged_value_with(tag: impl Into<Tag>, item: impl Into<CBOR>, policy: &TagPolicy) -> Result<CBOR> {
+        let tag = tag.into();
+        let item = item.into();
+        policy.check(tag.value(), &item)?;
+        Ok(Self::to_tagged_value(tag, item))
+    }
+}
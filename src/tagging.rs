@@ -0,0 +1,182 @@
+import_stdlib!();
+
+use crate::{CBORCase, DuplicatePolicy, Map, Tag, TagValue, CBOR};
+
+/// Utilities for working with tags throughout a CBOR tree.
+impl CBOR {
+    /// Returns `true` if this value equals `other` once all tags have been
+    /// removed from both, at every level.
+    ///
+    /// This compares semantic payloads where one side wraps values in
+    /// additional annotation tags.
+    ///
+    /// Removing tags from map keys can leave two keys of a map equal, as in
+    /// `{1("k"): 1, "k": 2}`. Neither entry is dropped in favour of the other:
+    /// a value with such a map is never equal to another.
+    pub fn eq_ignoring_tags(&self, other: &CBOR) -> bool {
+        let is_ignored = |_: &Tag| true;
+        matches!((self.without_tags(&is_ignored), other.without_tags(&is_ignored)), (Some(a), Some(b)) if a == b)
+    }
+
+    /// Returns `true` if this value equals `other` once the given tags have
+    /// been removed from both, at every level. Other tags must match.
+    ///
+    /// As with [`CBOR::eq_ignoring_tags`], a value with a map whose keys are
+    /// equal once the tags are removed is never equal to another.
+    pub fn eq_ignoring_tags_in(&self, other: &CBOR, tags: &[TagValue]) -> bool {
+        let is_ignored = |tag: &Tag| tags.contains(&tag.value());
+        matches!((self.without_tags(&is_ignored), other.without_tags(&is_ignored)), (Some(a), Some(b)) if a == b)
+    }
+
+    /// Returns this value wrapped in the given tag, unless it is already
+    /// wrapped in that tag, in which case it is returned unchanged.
+    ///
+    /// Tagging with this is idempotent, so values passing through several
+    /// annotating layers do not end up tagged more than once.
+    pub fn tagged_if_needed(self, tag: impl Into<Tag>) -> CBOR {
+        let tag = tag.into();
+        if matches!(self.as_case(), CBORCase::Tagged(t, _) if *t == tag) {
+            self
+        } else {
+            CBOR::to_tagged_value(tag, self)
+        }
+    }
+
+    /// Returns the content of this value with every outer layer of the given
+    /// tag removed.
+    ///
+    /// Only consecutive layers of the given tag are removed, starting at the
+    /// outermost. A value not wrapped in the tag is returned unchanged.
+    pub fn untag_all(self, tag: impl Into<Tag>) -> CBOR {
+        let tag = tag.into();
+        let mut cbor = self;
+        loop {
+            match cbor.as_case() {
+                CBORCase::Tagged(t, item) if *t == tag => {
+                    let item = item.clone();
+                    cbor = item;
+                },
+                _ => return cbor,
+            }
+        }
+    }
+
+    /// Returns the tags wrapping this value, outermost first, and the content
+    /// inside the innermost of them, without cloning either.
+    ///
+    /// A value that is not tagged has no tags and is its own content.
+    ///
+    /// ```
+    /// # use dcbor::prelude::*;
+    /// let cbor = CBOR::to_tagged_value(200, CBOR::to_tagged_value(201, "payload"));
+    /// let (tags, content) = cbor.untag_chain();
+    /// assert_eq!(tags.iter().map(|tag| tag.value()).collect::<Vec<_>>(), [200, 201]);
+    /// assert_eq!(*content, CBOR::from("payload"));
+    /// ```
+    pub fn untag_chain(&self) -> (Vec<&Tag>, &CBOR) {
+        let mut tags = Vec::new();
+        let mut cbor = self;
+        while let CBORCase::Tagged(tag, item) = cbor.as_case() {
+            tags.push(tag);
+            cbor = item;
+        }
+        (tags, cbor)
+    }
+
+    /// Returns a copy of this value with every tag, at every level, rewritten
+    /// by `f`.
+    ///
+    /// `f` is called with each tag in the tree. Returning `Some` replaces the
+    /// tag with the one returned, and returning `None` leaves it unchanged.
+    /// Subtrees in which no tag changes are shared with this value rather
+    /// than copied.
+    ///
+    /// If rewriting the tags in map keys makes two keys equal, the entry that
+    /// sorted last in this value is kept.
+    pub fn map_tags(&self, f: impl Fn(&Tag) -> Option<Tag>) -> CBOR {
+        self.with_mapped_tags(&f).unwrap_or_else(|| self.clone())
+    }
+
+    /// Returns a copy of this value with every use of the tag `from`, at every
+    /// level, replaced by the tag `to`.
+    ///
+    /// This is useful for migrating stored data from a deprecated tag. See
+    /// [`CBOR::map_tags`].
+    pub fn replace_tag(&self, from: impl Into<Tag>, to: impl Into<Tag>) -> CBOR {
+        let from = from.into();
+        let to = to.into();
+        self.map_tags(|tag| (*tag == from).then(|| to.clone()))
+    }
+
+    /// Returns a copy of this value with its tags rewritten by `f`, or `None`
+    /// if no tag changes.
+    fn with_mapped_tags(&self, f: &dyn Fn(&Tag) -> Option<Tag>) -> Option<CBOR> {
+        match self.as_case() {
+            CBORCase::Tagged(tag, item) => {
+                let mapped_tag = f(tag);
+                let mapped_item = item.with_mapped_tags(f);
+                if mapped_tag.is_none() && mapped_item.is_none() {
+                    return None;
+                }
+                Some(CBOR::to_tagged_value(
+                    mapped_tag.unwrap_or_else(|| tag.clone()),
+                    mapped_item.unwrap_or_else(|| item.clone()),
+                ))
+            },
+            CBORCase::Array(array) => {
+                let mapped: Vec<_> = array.iter().map(|item| item.with_mapped_tags(f)).collect();
+                if mapped.iter().all(Option::is_none) {
+                    return None;
+                }
+                let array: Vec<_> = array.iter().zip(mapped)
+                    .map(|(item, mapped)| mapped.unwrap_or_else(|| item.clone()))
+                    .collect();
+                Some(array.into())
+            },
+            CBORCase::Map(map) => {
+                let mapped: Vec<_> = map.iter()
+                    .map(|(key, value)| (key.with_mapped_tags(f), value.with_mapped_tags(f)))
+                    .collect();
+                if mapped.iter().all(|(key, value)| key.is_none() && value.is_none()) {
+                    return None;
+                }
+                let mut result = Map::new();
+                for ((key, value), (mapped_key, mapped_value)) in map.iter().zip(mapped) {
+                    result.insert(
+                        mapped_key.unwrap_or_else(|| key.clone()),
+                        mapped_value.unwrap_or_else(|| value.clone()),
+                    );
+                }
+                Some(result.into())
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns a copy of this value with the content of each tag matching the
+    /// predicate substituted for the tagged value itself, or `None` if that
+    /// leaves two keys of a map equal.
+    fn without_tags(&self, is_removed: &dyn Fn(&Tag) -> bool) -> Option<CBOR> {
+        let result = match self.as_case() {
+            CBORCase::Tagged(tag, item) => {
+                let item = item.without_tags(is_removed)?;
+                if is_removed(tag) {
+                    item
+                } else {
+                    CBOR::to_tagged_value(tag.clone(), item)
+                }
+            },
+            CBORCase::Array(array) => {
+                array.iter().map(|item| item.without_tags(is_removed)).collect::<Option<Vec<_>>>()?.into()
+            },
+            CBORCase::Map(map) => {
+                let entries = map.iter()
+                    .map(|(key, value)| Some((key.without_tags(is_removed)?, value.without_tags(is_removed)?)))
+                    .collect::<Option<Vec<_>>>()?;
+                Map::from_iter_with(entries, DuplicatePolicy::Error).ok()?.into()
+            },
+            _ => self.clone(),
+        };
+        Some(result)
+    }
+}
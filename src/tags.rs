@@ -1,6 +1,10 @@
 import_stdlib!();
 
-use crate::{CBORTaggedDecodable, Date, Tag, TagValue, TagsStore, TagsStoreTrait};
+use anyhow::{bail, Result};
+
+use crate::{CBORCase, CBORError, Tag, TagValue, TagsStore, TagsStoreTrait, CBOR};
+#[cfg(feature = "format")]
+use crate::{CBORTaggedDecodable, Date};
 
 pub struct LazyTagsStore {
     init: Once,
@@ -61,17 +65,106 @@ macro_rules! with_tags_mut {
     };
 }
 
+/// Returns a copy of the global tags store.
+///
+/// The copy is independent of the global store, so it can be inspected at
+/// leisure or passed to functions taking a tags store without holding the
+/// global store's lock.
+pub fn tags_snapshot() -> TagsStore {
+    with_tags!(|tags: &TagsStore| tags.clone())
+}
+
+/// Decodes the given tagged CBOR data into whichever type is registered for
+/// its outer tag in the global tags store.
+///
+/// Types are registered using [`TagsStore::register_decoder`], e.g. via
+/// [`with_tags_mut!`]. See [`TagsStore::decode_any`].
+pub fn decode_any(data: impl AsRef<[u8]>) -> Result<Box<dyn Any>> {
+    match CBOR::try_from_data(data)?.into_case() {
+        CBORCase::Tagged(tag, item) => {
+            // The decoder is cloned out so the global store is not locked
+            // while it runs.
+            let decoder = with_tags!(|tags: &TagsStore| tags.decoder(tag.value()).cloned());
+            match decoder {
+                Some(decoder) => decoder(item),
+                None => bail!(CBORError::NoDecoderForTag(tag)),
+            }
+        },
+        _ => bail!(CBORError::WrongType),
+    }
+}
+
+// Standard tags from the IANA "Concise Binary Object Representation (CBOR)
+// Tags" registry.
+// https://www.iana.org/assignments/cbor-tags/cbor-tags.xhtml
+
+pub const TAG_DATE_TIME_STRING: TagValue = 0;
 pub const TAG_DATE: TagValue = 1;
+pub const TAG_POSITIVE_BIGNUM: TagValue = 2;
+pub const TAG_NEGATIVE_BIGNUM: TagValue = 3;
+pub const TAG_DECIMAL_FRACTION: TagValue = 4;
+pub const TAG_BIGFLOAT: TagValue = 5;
+pub const TAG_COSE_SIGN1: TagValue = 18;
+pub const TAG_EXPECTED_BASE64URL: TagValue = 21;
+pub const TAG_EXPECTED_BASE64: TagValue = 22;
+pub const TAG_EXPECTED_BASE16: TagValue = 23;
+pub const TAG_ENCODED_CBOR: TagValue = 24;
+pub const TAG_URI: TagValue = 32;
+pub const TAG_BASE64URL: TagValue = 33;
+pub const TAG_BASE64: TagValue = 34;
+pub const TAG_REGEXP: TagValue = 35;
+pub const TAG_MIME_MESSAGE: TagValue = 36;
+pub const TAG_UUID: TagValue = 37;
+pub const TAG_COSE_SIGN: TagValue = 98;
+pub const TAG_EPOCH_DAYS: TagValue = 100;
+pub const TAG_SET: TagValue = 258;
+pub const TAG_SELF_DESCRIBED_CBOR: TagValue = 55799;
+
+// Tags defined by this crate, which are not registered with IANA and so are
+// taken from `TagAllocator::DCBOR_PRIVATE`.
+
+/// Tags one chunk of a byte string split by
+/// [`CBOR::to_byte_string_chunks`].
+pub const TAG_BYTE_STRING_CHUNK: TagValue = 0xffff_ffff_ffff_ff00;
+
+/// Tags the digest of a value that has been redacted. See
+/// [`CBOR::redacted`].
+pub const TAG_REDACTED: TagValue = 0xffff_ffff_ffff_ff01;
 
 pub fn register_tags_in(tags_store: &mut TagsStore) {
     let tags = vec![
+        (TAG_DATE_TIME_STRING, "date-time-string"),
         (TAG_DATE, "date"),
+        (TAG_POSITIVE_BIGNUM, "positive-bignum"),
+        (TAG_NEGATIVE_BIGNUM, "negative-bignum"),
+        (TAG_DECIMAL_FRACTION, "decimal-fraction"),
+        (TAG_BIGFLOAT, "bigfloat"),
+        (TAG_COSE_SIGN1, "COSE_Sign1"),
+        (TAG_EXPECTED_BASE64URL, "expected-base64url"),
+        (TAG_EXPECTED_BASE64, "expected-base64"),
+        (TAG_EXPECTED_BASE16, "expected-base16"),
+        (TAG_ENCODED_CBOR, "encoded-cbor"),
+        (TAG_URI, "uri"),
+        (TAG_BASE64URL, "base64url"),
+        (TAG_BASE64, "base64"),
+        (TAG_REGEXP, "regexp"),
+        (TAG_MIME_MESSAGE, "mime-message"),
+        (TAG_UUID, "uuid"),
+        (TAG_COSE_SIGN, "COSE_Sign"),
+        (TAG_EPOCH_DAYS, "epoch-days"),
+        (TAG_SET, "set"),
+        (TAG_SELF_DESCRIBED_CBOR, "self-described-cbor"),
+        (TAG_BYTE_STRING_CHUNK, "byte-string-chunk"),
+        (TAG_REDACTED, "redacted"),
     ];
     for tag in tags.into_iter() {
         tags_store.insert(Tag::new(tag.0, tag.1));
     }
-    tags_store.set_summarizer(TAG_DATE, Arc::new(|untagged_cbor| {
-        Ok(format!("{}", Date::from_untagged_cbor(untagged_cbor)?))
+    // The date summarizer always replaces any other, as it did before
+    // conflicting registrations were detected.
+    #[cfg(feature = "format")]
+    tags_store.replace_summarizer(TAG_DATE, Arc::new(|untagged_cbor, _, opts| {
+        Ok(Date::from_untagged_cbor(untagged_cbor)?.format(opts.date_format.unwrap_or_default()))
     }));
 }
 
@@ -1,8 +1,47 @@
 import_stdlib!();
 
-use crate::{Tag, TagValue, CBOR};
+use anyhow::{bail, Error, Result};
 
-pub type CBORSummarizer = Arc<dyn Fn(CBOR) -> anyhow::Result<String> + Send + Sync>;
+use crate::{CBORCase, CBORError, CBORTaggedDecodable, Map, Tag, TagValue, CBOR};
+#[cfg(feature = "format")]
+use crate::DiagFormatOpts;
+
+/// A function that summarizes the untagged content of a tagged CBOR value,
+/// as used by [`CBOR::summary`].
+///
+/// It is given the tags store in use and the options of the output being
+/// produced, so that it can summarize nested values in the same way, e.g.
+/// by calling [`CBOR::diagnostic_with_opts`] on them.
+#[cfg(feature = "format")]
+pub type CBORSummarizer = Arc<dyn Fn(CBOR, &dyn TagsStoreTrait, &DiagFormatOpts<'_>) -> anyhow::Result<String> + Send + Sync>;
+
+/// A function that decodes the untagged content of a tagged CBOR value into
+/// a registered type, as used by [`TagsStore::decode_any`].
+pub type CBORTaggedDecoder = Arc<dyn Fn(CBOR) -> anyhow::Result<Box<dyn Any>> + Send + Sync>;
+
+/// A function called with the details of a conflicting registration, as
+/// used by [`TagConflictPolicy::Warn`].
+pub type TagConflictHandler = Arc<dyn Fn(&CBORError) + Send + Sync>;
+
+/// How a [`TagsStore`] handles a registration that conflicts with an
+/// existing one: a tag value registered with a different name, a name
+/// registered for a different tag value, or a tag given a different
+/// summarizer.
+///
+/// Registering the same tag and name again is not a conflict.
+#[derive(Clone, Default)]
+pub enum TagConflictPolicy {
+    /// The new registration replaces the existing one.
+    #[default]
+    LastWins,
+    /// The new registration is refused. [`TagsStore::try_insert`] and
+    /// [`TagsStore::try_set_summarizer`] return an error, while
+    /// [`TagsStore::insert`] and [`TagsStore::set_summarizer`] panic.
+    Error,
+    /// The handler is called, then the new registration replaces the
+    /// existing one.
+    Warn(TagConflictHandler),
+}
 
 /// A type that can map between tags and their names.
 pub trait TagsStoreTrait {
@@ -11,7 +50,16 @@ pub trait TagsStoreTrait {
     fn tag_for_value(&self, value: u64) -> Option<Tag>;
     fn tag_for_name(&self, name: &str) -> Option<Tag>;
     fn name_for_value(&self, value: u64) -> String;
-    fn summarizer(&self, tag: TagValue) -> Option<&CBORSummarizer>;
+
+    /// Returns the summarizer registered for the tag, if any.
+    ///
+    /// This has a default body returning `None`, so implementations written
+    /// without the `format` feature, which cannot name [`CBORSummarizer`],
+    /// still compile when another crate in the build enables it.
+    #[cfg(feature = "format")]
+    fn summarizer(&self, _tag: TagValue) -> Option<&CBORSummarizer> {
+        None
+    }
 
     fn name_for_tag_opt<T>(tag: &Tag, tags: Option<&T>) -> String where T: TagsStoreTrait, Self: Sized {
         match tags {
@@ -26,42 +74,412 @@ pub trait TagsStoreTrait {
 pub struct TagsStore {
     tags_by_value: HashMap<u64, Tag>,
     tags_by_name: HashMap<String, Tag>,
+    #[cfg(feature = "format")]
     summarizers: HashMap<u64, CBORSummarizer>,
+    decoders: HashMap<u64, CBORTaggedDecoder>,
+    conflict_policy: TagConflictPolicy,
 }
 
 impl TagsStore {
+    /// Makes a store assigning the names of the given tags.
+    ///
+    /// Panics if a tag has no name. Where two tags share a value or a name,
+    /// the later one is kept.
     pub fn new<T>(tags: T) -> Self where T: IntoIterator<Item=Tag> {
-        let mut tags_by_value = HashMap::new();
-        let mut tags_by_name = HashMap::new();
-        for tag in tags {
-            Self::_insert(tag, &mut tags_by_value, &mut tags_by_name);
-        }
-        Self {
-            tags_by_value,
-            tags_by_name,
+        let mut store = Self {
+            tags_by_value: HashMap::new(),
+            tags_by_name: HashMap::new(),
+            #[cfg(feature = "format")]
             summarizers: HashMap::new(),
+            decoders: HashMap::new(),
+            conflict_policy: TagConflictPolicy::default(),
+        };
+        for tag in tags {
+            store.insert(tag);
         }
+        store
     }
 
+    /// Sets how registrations that conflict with existing ones are handled.
+    pub fn set_conflict_policy(&mut self, policy: TagConflictPolicy) {
+        self.conflict_policy = policy;
+    }
+
+    /// Assigns the tag's name to its value.
+    ///
+    /// Panics if the tag has no name, or if the registration conflicts with
+    /// an existing one and the conflict policy is
+    /// [`TagConflictPolicy::Error`].
     pub fn insert(&mut self, tag: Tag) {
-        Self::_insert(tag, &mut self.tags_by_value, &mut self.tags_by_name);
+        if let Err(err) = self.try_insert(tag) {
+            panic!("{}", err);
+        }
+    }
+
+    /// Assigns the tag's name to its value, returning an error if the
+    /// registration conflicts with an existing one and the conflict policy is
+    /// [`TagConflictPolicy::Error`].
+    ///
+    /// A registration that is not refused replaces any existing assignment
+    /// of the tag's value or name, so each value has at most one name and
+    /// each name at most one value. Returns [`CBORError::MissingTagName`] if
+    /// the tag has no name.
+    pub fn try_insert(&mut self, tag: Tag) -> Result<()> {
+        let name = match tag.name() {
+            Some(name) if !name.is_empty() => name,
+            _ => bail!(CBORError::MissingTagName(tag.value())),
+        };
+        let existing_name = self.tags_by_value.get(&tag.value()).and_then(Tag::name).filter(|existing| *existing != name);
+        if let Some(existing_name) = &existing_name {
+            self.check_conflict(CBORError::TagValueConflict(tag.value(), existing_name.clone(), name.clone()))?;
+        }
+        let existing_value = self.tags_by_name.get(&name).map(Tag::value).filter(|existing| *existing != tag.value());
+        if let Some(existing_value) = existing_value {
+            self.check_conflict(CBORError::TagNameConflict(name.clone(), existing_value, tag.value()))?;
+        }
+        // Only once neither conflict is refused are the assignments replaced.
+        if let Some(existing_name) = existing_name {
+            self.tags_by_name.remove(&existing_name);
+        }
+        if let Some(existing_value) = existing_value {
+            self.tags_by_value.remove(&existing_value);
+        }
+        self.tags_by_value.insert(tag.value(), tag.clone());
+        self.tags_by_name.insert(name, tag);
+        Ok(())
     }
 
+    /// Sets the summarizer for a tag.
+    ///
+    /// Panics if the tag already has a different summarizer and the conflict
+    /// policy is [`TagConflictPolicy::Error`].
+    #[cfg(feature = "format")]
     pub fn set_summarizer(&mut self, tag: TagValue, summarizer: CBORSummarizer) {
+        if let Err(err) = self.try_set_summarizer(tag, summarizer) {
+            panic!("{}", err);
+        }
+    }
+
+    /// Sets the summarizer for a tag, returning an error if the tag already
+    /// has a different summarizer and the conflict policy is
+    /// [`TagConflictPolicy::Error`].
+    #[cfg(feature = "format")]
+    pub fn try_set_summarizer(&mut self, tag: TagValue, summarizer: CBORSummarizer) -> Result<()> {
+        if let Some(existing) = self.summarizers.get(&tag) {
+            if !Arc::ptr_eq(existing, &summarizer) {
+                self.check_conflict(CBORError::SummarizerConflict(tag))?;
+            }
+        }
+        self.summarizers.insert(tag, summarizer);
+        Ok(())
+    }
+
+    /// Sets the summarizer for a tag whatever the conflict policy, as the
+    /// standard registrations of [`register_tags_in`](crate::register_tags_in)
+    /// do.
+    #[cfg(feature = "format")]
+    pub(crate) fn replace_summarizer(&mut self, tag: TagValue, summarizer: CBORSummarizer) {
         self.summarizers.insert(tag, summarizer);
     }
 
-    fn _insert(tag: Tag, tags_by_value: &mut HashMap<u64, Tag>, tags_by_name: &mut HashMap<String, Tag>) {
-        let name = tag.name().unwrap();
-        assert!(!name.is_empty());
-        tags_by_value.insert(tag.value(), tag.clone());
-        tags_by_name.insert(name, tag);
+    fn check_conflict(&self, conflict: CBORError) -> Result<()> {
+        match &self.conflict_policy {
+            TagConflictPolicy::LastWins => Ok(()),
+            TagConflictPolicy::Error => bail!(conflict),
+            TagConflictPolicy::Warn(handler) => {
+                handler(&conflict);
+                Ok(())
+            },
+        }
+    }
+
+    /// Registers `T` as the type decoded by [`TagsStore::decode_any`] for
+    /// each of its tags, replacing any type previously registered for them.
+    pub fn register_decoder<T>(&mut self) where T: CBORTaggedDecodable + 'static {
+        let decoder: CBORTaggedDecoder = Arc::new(|untagged_cbor| {
+            Ok(Box::new(T::from_untagged_cbor(untagged_cbor)?))
+        });
+        for tag in T::cbor_tags() {
+            self.decoders.insert(tag.value(), decoder.clone());
+        }
+    }
+
+    /// Returns the decoder registered for the given tag, if any.
+    pub fn decoder(&self, tag: TagValue) -> Option<&CBORTaggedDecoder> {
+        self.decoders.get(&tag)
+    }
+
+    /// Decodes the given tagged CBOR into whichever type is registered for
+    /// its outer tag.
+    ///
+    /// The result can be recovered as a concrete type with
+    /// `Box::downcast`. Returns an error if the CBOR is not tagged, or no
+    /// type is registered for its tag.
+    pub fn decode_any(&self, cbor: CBOR) -> Result<Box<dyn Any>> {
+        match cbor.into_case() {
+            CBORCase::Tagged(tag, item) => {
+                match self.decoder(tag.value()) {
+                    Some(decoder) => decoder(item),
+                    None => bail!(CBORError::NoDecoderForTag(tag)),
+                }
+            },
+            _ => bail!(CBORError::WrongType),
+        }
+    }
+}
+
+/// Introspection and removal of registered tags.
+impl TagsStore {
+    /// Returns the number of tags with assigned names.
+    pub fn len(&self) -> usize {
+        self.tags_by_value.len()
+    }
+
+    /// Returns `true` if no tags have assigned names.
+    pub fn is_empty(&self) -> bool {
+        self.tags_by_value.is_empty()
+    }
+
+    /// Returns all tags with assigned names, ordered by tag value.
+    pub fn tags(&self) -> Vec<Tag> {
+        let mut tags: Vec<Tag> = self.tags_by_value.values().cloned().collect();
+        tags.sort_by_key(Tag::value);
+        tags
+    }
+
+    /// Returns the values of all tags that have a summarizer, in ascending
+    /// order. Summarizers may be set for tags with no assigned name.
+    #[cfg(feature = "format")]
+    pub fn summarized_tag_values(&self) -> Vec<TagValue> {
+        let mut values: Vec<TagValue> = self.summarizers.keys().copied().collect();
+        values.sort();
+        values
+    }
+
+    /// Removes the tag with the given value along with its name,
+    /// summarizer, and decoder, returning the tag if it had an assigned name.
+    pub fn remove(&mut self, value: TagValue) -> Option<Tag> {
+        #[cfg(feature = "format")]
+        self.summarizers.remove(&value);
+        self.decoders.remove(&value);
+        let tag = self.tags_by_value.remove(&value)?;
+        if let Some(name) = tag.name() {
+            self.tags_by_name.remove(&name);
+        }
+        Some(tag)
+    }
+
+    /// Removes the summarizer for the given tag, returning it if present.
+    #[cfg(feature = "format")]
+    pub fn remove_summarizer(&mut self, value: TagValue) -> Option<CBORSummarizer> {
+        self.summarizers.remove(&value)
+    }
+}
+
+/// Import and export of tag name assignments.
+impl TagsStore {
+    /// Adds the tag name assignments found in a CSV snapshot, returning the
+    /// number of tags added.
+    ///
+    /// Each record holds a tag value and its name. Fields may be quoted, as
+    /// specified by RFC 4180; unquoted fields are trimmed of surrounding
+    /// whitespace, while quoted fields are read exactly as written. By
+    /// default the name is the second field and any further fields are
+    /// ignored. A leading header record, recognized by a first field that is
+    /// not a tag value, may instead name the columns: the name is then taken
+    /// from the column headed `Name`, or failing that `Semantics`, so the CSV
+    /// of the IANA CBOR Tags registry can be loaded directly, ignoring its
+    /// `Data Item` column.
+    ///
+    /// Records for a range of values, such as `6-15`, and records whose name
+    /// is `Unassigned` describe unassigned tags and are skipped, as are blank
+    /// lines and lines beginning with `#`.
+    ///
+    /// The whole snapshot is read before any tag is added. Returns
+    /// [`CBORError::InvalidTagsCsv`] if it is malformed, in which case the
+    /// store is unchanged. Conflicting registrations are handled according to
+    /// the store's conflict policy, and refusing one also leaves the store
+    /// unchanged.
+    pub fn import_csv(&mut self, csv: &str) -> Result<usize> {
+        let error = |line: usize, message: &str| Error::msg(CBORError::InvalidTagsCsv(line, message.to_string()));
+        let mut records = csv_records(csv)?.into_iter().peekable();
+        let mut name_column = 1;
+        if let Some((line, header)) = records.peek() {
+            if header[0].parse::<TagValue>().is_err() && value_range(&header[0]).is_none() {
+                let column = |title: &str| header.iter().position(|field| field.eq_ignore_ascii_case(title));
+                name_column = column("Name").or_else(|| column("Semantics"))
+                    .ok_or_else(|| error(*line, "the header has no Name or Semantics column"))?;
+                records.next();
+            }
+        }
+        let mut tags = Vec::new();
+        for (line, fields) in records {
+            let value = &fields[0];
+            let name = fields.get(name_column).map(String::as_str).unwrap_or_default();
+            if value_range(value).is_some() || name.eq_ignore_ascii_case("Unassigned") {
+                continue;
+            }
+            let value = value.parse::<TagValue>().map_err(|_| error(line, &format!("invalid tag value {:?}", value)))?;
+            if name.is_empty() {
+                return Err(error(line, "missing tag name"));
+            }
+            tags.push(Tag::new(value, name));
+        }
+        self.insert_all(tags)
+    }
+
+    /// Inserts the tags, returning how many there were, or leaving the store
+    /// unchanged if any is refused.
+    fn insert_all(&mut self, tags: Vec<Tag>) -> Result<usize> {
+        let mut store = self.clone();
+        for tag in &tags {
+            store.try_insert(tag.clone())?;
+        }
+        *self = store;
+        Ok(tags.len())
+    }
+
+    /// Returns a CSV snapshot of the tag name assignments, one `value,name`
+    /// record per tag, ordered by tag value. Names are quoted if they contain
+    /// commas, quotes, line breaks, or surrounding whitespace.
+    pub fn export_csv(&self) -> String {
+        self.tags().into_iter().filter_map(|tag| {
+            tag.name().map(|name| format!("{},{}\n", tag.value(), csv_field(&name)))
+        }).collect()
+    }
+
+    /// Adds the tag name assignments found in a CBOR snapshot, returning the
+    /// number of tags added.
+    ///
+    /// The snapshot is a map from unsigned tag values to text names, as
+    /// produced by [`TagsStore::export_cbor`]. As with
+    /// [`TagsStore::import_csv`], the store is unchanged if the snapshot is
+    /// malformed or a conflicting registration is refused.
+    pub fn import_cbor(&mut self, cbor: &CBOR) -> Result<usize> {
+        let map = cbor.clone().try_into_map()?;
+        let mut tags = Vec::new();
+        for (value, name) in map.iter() {
+            let value: TagValue = value.clone().try_into()?;
+            let name: String = name.clone().try_into()?;
+            if name.is_empty() {
+                bail!(CBORError::MissingTagName(value));
+            }
+            tags.push(Tag::new(value, name));
+        }
+        self.insert_all(tags)
+    }
+
+    /// Returns a CBOR snapshot of the tag name assignments: a map from tag
+    /// values to their names.
+    pub fn export_cbor(&self) -> CBOR {
+        let mut map = Map::new();
+        for (value, tag) in self.tags_by_value.iter() {
+            if let Some(name) = tag.name() {
+                map.insert(*value, name);
+            }
+        }
+        map.into()
+    }
+}
+
+/// Splits CSV text into records of fields, as specified by RFC 4180, along
+/// with the line on which each record starts. Unquoted fields are trimmed,
+/// and only whitespace may follow a quoted field. Blank lines and lines
+/// beginning with `#` are skipped.
+fn csv_records(csv: &str) -> Result<Vec<(usize, Vec<String>)>> {
+    let mut records = Vec::new();
+    let mut chars = csv.chars().peekable();
+    let mut line = 1;
+    while chars.peek().is_some() {
+        let start_line = line;
+        if chars.peek() == Some(&'#') {
+            for c in chars.by_ref() {
+                if c == '\n' {
+                    break;
+                }
+            }
+            line += 1;
+            continue;
+        }
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        // Whether the field was quoted and its closing quote has been read.
+        let mut closed = false;
+        loop {
+            match chars.next() {
+                None if quoted => bail!(CBORError::InvalidTagsCsv(start_line, "unterminated quoted field".to_string())),
+                None => break,
+                Some('"') if quoted => {
+                    // A doubled quote is a literal quote.
+                    if chars.next_if_eq(&'"').is_some() {
+                        field.push('"');
+                    } else {
+                        quoted = false;
+                        closed = true;
+                    }
+                },
+                Some(',') if !quoted => {
+                    fields.push(csv_field_value(core::mem::take(&mut field), closed));
+                    closed = false;
+                },
+                Some('\n') if !quoted => {
+                    line += 1;
+                    break;
+                },
+                Some(c) if closed => {
+                    if !c.is_whitespace() {
+                        bail!(CBORError::InvalidTagsCsv(line, "unexpected text after a quoted field".to_string()));
+                    }
+                },
+                Some('"') if field.trim().is_empty() => {
+                    field.clear();
+                    quoted = true;
+                },
+                Some(c) => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                },
+            }
+        }
+        fields.push(csv_field_value(field, closed));
+        if fields.len() > 1 || !fields[0].is_empty() {
+            records.push((start_line, fields));
+        }
+    }
+    Ok(records)
+}
+
+/// Returns a field's value: trimmed if it was unquoted, and as written if it
+/// was quoted.
+fn csv_field_value(field: String, quoted: bool) -> String {
+    if quoted {
+        field
+    } else {
+        field.trim().to_string()
+    }
+}
+
+/// Returns the bounds of a range of tag values written as `first-last`.
+fn value_range(field: &str) -> Option<(TagValue, TagValue)> {
+    let (first, last) = field.split_once('-')?;
+    Some((first.trim().parse().ok()?, last.trim().parse().ok()?))
+}
+
+/// Returns the field, quoted if it would not otherwise be read back intact.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) || field.trim() != field {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
     }
 }
 
 impl TagsStoreTrait for TagsStore {
     fn assigned_name_for_tag(&self, tag: &Tag) -> Option<String> {
-        self.tag_for_value(tag.value()).map(|tag| tag.name().unwrap())
+        self.tag_for_value(tag.value()).and_then(|tag| tag.name())
     }
 
     fn name_for_tag(&self, tag: &Tag) -> String {
@@ -82,6 +500,7 @@ impl TagsStoreTrait for TagsStore {
             .unwrap_or_else(|| value.to_string())
     }
 
+    #[cfg(feature = "format")]
     fn summarizer(&self, tag: TagValue) -> Option<&CBORSummarizer> {
         self.summarizers.get(&tag)
     }
@@ -0,0 +1,58 @@
+//! Assertions for testing types that convert to and from CBOR.
+//!
+//! These let downstream crates check their CBOR conversions with one-line
+//! tests, in the same way this crate tests its own types. Each function panics
+//! with a descriptive message if a check fails.
+
+import_stdlib!();
+
+#[cfg(all(feature = "std", feature = "format"))]
+pub mod conformance;
+
+use crate::{CBORTaggedDecodable, CBORTaggedEncodable, CBOR};
+
+/// Asserts that `value` encodes to the CBOR data given in hex, and that
+/// decoding that data yields an equal value.
+pub fn assert_roundtrip<T>(value: T, expected_hex: &str)
+where
+    T: Into<CBOR> + TryFrom<CBOR> + Clone + PartialEq + fmt::Debug,
+    T::Error: fmt::Debug,
+{
+    let cbor: CBOR = value.clone().into();
+    assert_eq!(cbor.hex(), expected_hex, "encoding of {:?}", value);
+    let decoded_cbor = CBOR::try_from_hex(expected_hex)
+        .unwrap_or_else(|error| panic!("decoding {}: {:?}", expected_hex, error));
+    assert_eq!(decoded_cbor, cbor, "decoded CBOR of {:?}", value);
+    let decoded = T::try_from(decoded_cbor)
+        .unwrap_or_else(|error| panic!("converting decoded CBOR of {:?}: {:?}", value, error));
+    assert_eq!(decoded, value, "round trip of {:?}", value);
+}
+
+/// Asserts that `value` round-trips as [`assert_roundtrip`] checks, and that
+/// its tagged and untagged forms agree with its CBOR conversions.
+pub fn assert_roundtrip_tagged<T>(value: T, expected_hex: &str)
+where
+    T: CBORTaggedEncodable + CBORTaggedDecodable + Into<CBOR> + Clone + PartialEq + fmt::Debug,
+    <T as TryFrom<CBOR>>::Error: fmt::Debug,
+{
+    assert_roundtrip(value.clone(), expected_hex);
+    let tagged = value.tagged_cbor();
+    assert_eq!(tagged.hex(), expected_hex, "tagged encoding of {:?}", value);
+    let untagged = value.untagged_cbor();
+    let from_tagged = T::from_tagged_cbor(tagged)
+        .unwrap_or_else(|error| panic!("decoding tagged CBOR of {:?}: {:?}", value, error));
+    assert_eq!(from_tagged, value, "tagged round trip of {:?}", value);
+    let from_untagged = T::from_untagged_cbor(untagged)
+        .unwrap_or_else(|error| panic!("decoding untagged CBOR of {:?}: {:?}", value, error));
+    assert_eq!(from_untagged, value, "untagged round trip of {:?}", value);
+}
+
+/// Asserts the `Debug`, `Display`, and diagnostic notation forms of the CBOR
+/// for `value`.
+#[cfg(feature = "format")]
+pub fn assert_formats(value: impl Into<CBOR>, expected_debug: &str, expected_display: &str, expected_diagnostic: &str) {
+    let cbor = value.into();
+    assert_eq!(format!("{:?}", cbor), expected_debug, "Debug form");
+    assert_eq!(format!("{}", cbor), expected_display, "Display form");
+    assert_eq!(cbor.diagnostic(), expected_diagnostic, "diagnostic notation");
+}
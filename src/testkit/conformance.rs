@@ -0,0 +1,210 @@
+//! Testing against files of test vectors, in a format simple enough for
+//! other dCBOR implementations, such as the Swift and TypeScript reference
+//! implementations, to produce or consume.
+//!
+//! This crate does not ship vectors generated by those implementations. The
+//! vectors in its `tests/conformance` directory are local: they were written
+//! by hand for this crate, following RFC 8949 and the dCBOR specification,
+//! so they check this crate against its reading of those documents rather
+//! than against another implementation's output. Vectors exported by another
+//! implementation can be run in the same way with [`run_directory`].
+//!
+//! A vector file is a JSON array of objects, each describing one input:
+//!
+//! ```json
+//! [
+//!     { "name": "small-int", "hex": "0a", "diagnostic": "10" },
+//!     { "name": "map", "hex": "a201610261", "encoding": "a201610261" },
+//!     { "name": "non-canonical", "hex": "1800", "valid": false }
+//! ]
+//! ```
+//!
+//! - `name`: identifies the vector in reports.
+//! - `hex`: the input data.
+//! - `valid`: whether the input is valid dCBOR. Defaults to `true`.
+//! - `encoding`: for valid inputs, the hex of the value re-encoded.
+//!   Defaults to `hex`.
+//! - `diagnostic`: for valid inputs, the expected single-line diagnostic
+//!   notation, as returned by [`CBOR::diagnostic_flat`]. Not checked if
+//!   absent.
+//!
+//! ```
+//! use dcbor::testkit::conformance::run_vectors;
+//!
+//! let report = run_vectors(r#"[{ "name": "one", "hex": "01", "diagnostic": "1" }]"#, "inline").unwrap();
+//! assert!(report.is_success());
+//! assert_eq!(report.passed, 1);
+//! ```
+
+import_stdlib!();
+
+use std::path::Path;
+
+use anyhow::{bail, Result};
+
+use crate::{from_json_canonical, CBORError, NumberPolicy, CBOR};
+
+/// The ways in which this crate can disagree with a test vector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MismatchKind {
+    /// The vector describes valid dCBOR, but decoding it failed.
+    UnexpectedError,
+    /// The vector describes invalid dCBOR, but decoding it succeeded.
+    UnexpectedSuccess,
+    /// Re-encoding the decoded value produced different bytes.
+    Encoding,
+    /// The diagnostic notation of the decoded value differs.
+    Diagnostic,
+    /// The vector itself could not be understood.
+    InvalidVector,
+}
+
+impl fmt::Display for MismatchKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MismatchKind::UnexpectedError => "unexpected error",
+            MismatchKind::UnexpectedSuccess => "unexpected success",
+            MismatchKind::Encoding => "encoding",
+            MismatchKind::Diagnostic => "diagnostic",
+            MismatchKind::InvalidVector => "invalid vector",
+        })
+    }
+}
+
+/// A test vector this crate disagrees with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The file or other source the vector came from.
+    pub source: String,
+    /// The name of the vector, or its index if it has none.
+    pub name: String,
+    pub kind: MismatchKind,
+    /// What was expected and what was found.
+    pub detail: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}: {}", self.source, self.name, self.kind, self.detail)
+    }
+}
+
+/// The result of running test vectors.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConformanceReport {
+    /// The number of vectors this crate agrees with.
+    pub passed: usize,
+    /// The vectors this crate disagrees with, in the order they were run.
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl ConformanceReport {
+    /// Returns `true` if this crate agrees with every vector.
+    pub fn is_success(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+
+    /// Returns the number of mismatches of each kind, for kinds that
+    /// occurred.
+    pub fn counts(&self) -> BTreeMap<MismatchKind, usize> {
+        let mut counts = BTreeMap::new();
+        for mismatch in &self.mismatches {
+            *counts.entry(mismatch.kind).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    fn merge(&mut self, other: ConformanceReport) {
+        self.passed += other.passed;
+        self.mismatches.extend(other.mismatches);
+    }
+}
+
+impl fmt::Display for ConformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} passed, {} failed", self.passed, self.mismatches.len())?;
+        for (kind, count) in self.counts() {
+            write!(f, "\n  {}: {}", kind, count)?;
+        }
+        for mismatch in &self.mismatches {
+            write!(f, "\n{}", mismatch)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs the vectors in every `.json` file in the directory, in order of
+/// file name.
+///
+/// Returns an error if the directory or a file cannot be read, or a file is
+/// not a JSON array. Problems with individual vectors are reported as
+/// [`MismatchKind::InvalidVector`].
+pub fn run_directory(dir: impl AsRef<Path>) -> Result<ConformanceReport> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|extension| extension == "json") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    let mut report = ConformanceReport::default();
+    for path in paths {
+        let json = std::fs::read_to_string(&path)?;
+        report.merge(run_vectors(&json, path.display().to_string())?);
+    }
+    Ok(report)
+}
+
+/// Runs the vectors in the JSON text, attributing mismatches to `source`.
+///
+/// Returns an error if the text is not a JSON array.
+pub fn run_vectors(json: &str, source: impl Into<String>) -> Result<ConformanceReport> {
+    let source = source.into();
+    let vectors = match from_json_canonical(json, NumberPolicy::Preserve)?.try_into_array() {
+        Ok(vectors) => vectors,
+        Err(_) => bail!(CBORError::custom("test vectors must be a JSON array")),
+    };
+    let mut report = ConformanceReport::default();
+    for (index, vector) in vectors.into_iter().enumerate() {
+        let name = vector.clone().try_into_map().ok()
+            .and_then(|map| map.get_by_str::<String>("name"))
+            .unwrap_or_else(|| format!("#{}", index));
+        match run_vector(&vector) {
+            Ok(()) => report.passed += 1,
+            Err((kind, detail)) => report.mismatches.push(Mismatch { source: source.clone(), name, kind, detail }),
+        }
+    }
+    Ok(report)
+}
+
+fn run_vector(vector: &CBOR) -> Result<(), (MismatchKind, String)> {
+    let invalid = |detail: &str| (MismatchKind::InvalidVector, detail.to_string());
+    let map = vector.clone().try_into_map().map_err(|_| invalid("not an object"))?;
+    let input_hex: String = map.get_by_str("hex").ok_or_else(|| invalid("missing \"hex\""))?;
+    let input = hex::decode(&input_hex).map_err(|_| invalid("\"hex\" is not hex"))?;
+    let valid = match map.get_by_str::<CBOR>("valid") {
+        None => true,
+        Some(valid) => bool::try_from(valid).map_err(|_| invalid("\"valid\" is not a boolean"))?,
+    };
+    let expected_encoding: String = map.get_by_str("encoding").unwrap_or(input_hex);
+    let expected_diagnostic: Option<String> = map.get_by_str("diagnostic");
+
+    let cbor = match (CBOR::try_from_data(&input), valid) {
+        (Ok(cbor), true) => cbor,
+        (Ok(cbor), false) => return Err((MismatchKind::UnexpectedSuccess, format!("decoded as {}", cbor.diagnostic_flat()))),
+        (Err(error), true) => return Err((MismatchKind::UnexpectedError, error.to_string())),
+        (Err(_), false) => return Ok(()),
+    };
+    let encoding = cbor.hex();
+    if !encoding.eq_ignore_ascii_case(&expected_encoding) {
+        return Err((MismatchKind::Encoding, format!("expected {}, got {}", expected_encoding, encoding)));
+    }
+    if let Some(expected_diagnostic) = expected_diagnostic {
+        let diagnostic = cbor.diagnostic_flat();
+        if diagnostic != expected_diagnostic {
+            return Err((MismatchKind::Diagnostic, format!("expected {}, got {}", expected_diagnostic, diagnostic)));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,153 @@
+//! Unicode normalization helpers for CBOR text strings.
+//!
+//! dCBOR requires text strings to be in Unicode Normalization Form C (NFC).
+//! By default strings are normalized when they are encoded, and decoding
+//! rejects any string that is not already in NFC.
+
+import_stdlib!();
+
+use unicode_normalization::UnicodeNormalization;
+
+use anyhow::Result;
+
+use crate::{CBORCase, CodecObserver, DecodeOptions, CBOR};
+
+/// Returns `true` if the given string is in Unicode Normalization Form C.
+pub fn is_nfc(s: &str) -> bool {
+    // Every character below U+0300, the first combining mark, is unchanged by
+    // normalization and cannot combine with what precedes it. These are
+    // exactly the characters encoded with no byte of 0xCC or above, which can
+    // be checked many bytes at a time.
+    s.bytes().all(|b| b < 0xcc) || unicode_normalization::is_nfc(s)
+}
+
+/// Returns the given string in Unicode Normalization Form C.
+pub fn to_nfc(s: &str) -> String {
+    s.nfc().collect()
+}
+
+/// The length of a text string, measured both in bytes of its UTF-8
+/// encoding and in Unicode scalar values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextLength {
+    /// The number of bytes in the UTF-8 encoding of the string.
+    pub bytes: usize,
+    /// The number of Unicode scalar values (Rust `char`s) in the string.
+    pub chars: usize,
+}
+
+/// Returns the length of the given string in bytes and in Unicode scalar
+/// values.
+pub fn length(s: &str) -> TextLength {
+    TextLength { bytes: s.len(), chars: s.chars().count() }
+}
+
+/// How text strings that are not in Unicode Normalization Form C are
+/// handled when encoding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NfcPolicy {
+    /// Normalize the string to NFC when it is encoded.
+    #[default]
+    Normalize,
+    /// Fail encoding with [`CBORError::NonCanonicalString`](crate::CBORError::NonCanonicalString).
+    Reject,
+}
+
+/// The text strings in some CBOR data that are not in Unicode Normalization
+/// Form C, as returned by [`CBOR::try_from_data_with_nfc_report`].
+///
+/// This is a [`CodecObserver`], so it can also be passed to
+/// [`CBOR::try_from_data_opt_observed`] with
+/// [`DecodeOptions::accept_non_nfc`] enabled.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NfcReport {
+    /// The strings that normalization would change, in the order they were
+    /// decoded.
+    pub non_nfc: Vec<NonNfcText>,
+}
+
+impl NfcReport {
+    /// Returns `true` if normalization would change none of the strings.
+    pub fn is_nfc(&self) -> bool {
+        self.non_nfc.is_empty()
+    }
+}
+
+impl CodecObserver for NfcReport {
+    fn non_nfc_text_decoded(&mut self, text: &str, span: ops::Range<usize>) {
+        self.non_nfc.push(NonNfcText { span, text: text.to_string() });
+    }
+}
+
+/// A text string that is not in Unicode Normalization Form C, as reported by
+/// [`NfcReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonNfcText {
+    /// The range of input bytes occupied by the string, including its head.
+    pub span: ops::Range<usize>,
+    /// The string as it was encoded.
+    pub text: String,
+}
+
+impl NonNfcText {
+    /// Returns the string in Unicode Normalization Form C.
+    pub fn normalized(&self) -> String {
+        to_nfc(&self.text)
+    }
+}
+
+impl CBOR {
+    /// Decodes the given data as for [`CBOR::try_from_data`], except that
+    /// text strings not in Unicode Normalization Form C are accepted, and
+    /// reported in the returned [`NfcReport`].
+    ///
+    /// The strings are found in the same pass that decodes the data. The
+    /// decoded value holds them as they were encoded.
+    pub fn try_from_data_with_nfc_report(data: impl AsRef<[u8]>) -> Result<(CBOR, NfcReport)> {
+        let mut report = NfcReport::default();
+        let options = DecodeOptions::default().accept_non_nfc(true);
+        let cbor = CBOR::try_from_data_opt_observed(data, &options, &mut report)?;
+        Ok((cbor, report))
+    }
+
+    /// If this is a text string, returns its length in bytes and in Unicode
+    /// scalar values, otherwise returns `None`.
+    pub fn text_length(&self) -> Option<TextLength> {
+        match self.as_case() {
+            CBORCase::Text(s) => Some(length(s)),
+            _ => None,
+        }
+    }
+
+    /// Returns the text strings in this value, including map keys and
+    /// tagged content, that would be changed by normalization when
+    /// encoded.
+    ///
+    /// Values decoded from binary only contain such strings if decoded with
+    /// [`DecodeOptions::accept_non_nfc`], in which case
+    /// [`CBOR::try_from_data_with_nfc_report`] also gives their positions.
+    pub fn non_nfc_strings(&self) -> Vec<&str> {
+        let mut result = Vec::new();
+        self.collect_non_nfc_strings(&mut result);
+        result
+    }
+
+    fn collect_non_nfc_strings<'a>(&'a self, result: &mut Vec<&'a str>) {
+        match self.as_case() {
+            CBORCase::Text(s) if !is_nfc(s) => result.push(s),
+            CBORCase::Array(a) => {
+                for item in a {
+                    item.collect_non_nfc_strings(result);
+                }
+            },
+            CBORCase::Map(m) => {
+                for (key, value) in m.iter() {
+                    key.collect_non_nfc_strings(result);
+                    value.collect_non_nfc_strings(result);
+                }
+            },
+            CBORCase::Tagged(_, item) => item.collect_non_nfc_strings(result),
+            _ => {},
+        }
+    }
+}
@@ -0,0 +1,69 @@
+import_stdlib!();
+
+use anyhow::{bail, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+use crate::{CBORError, CBOR};
+
+const BASE45_ALPHABET: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// Text encodings of the canonical bytes, for transports that carry text,
+/// with the `text-encoding` feature.
+impl CBOR {
+    /// Returns the encoding of this value in unpadded base64url (RFC 4648
+    /// §5), for use in URLs, JSON, and other web transports.
+    pub fn to_base64url(&self) -> String {
+        URL_SAFE_NO_PAD.encode(self.to_cbor_data())
+    }
+
+    /// Decodes a value from its encoding in unpadded base64url.
+    ///
+    /// Padding is rejected, so each value has exactly one base64url form.
+    pub fn try_from_base64url(text: &str) -> Result<CBOR> {
+        let data = match URL_SAFE_NO_PAD.decode(text) {
+            Ok(data) => data,
+            Err(_) => bail!(CBORError::InvalidBase64),
+        };
+        CBOR::try_from_data(data)
+    }
+
+    /// Returns the encoding of this value in base45 (RFC 9285).
+    ///
+    /// Base45 uses only characters in the QR code alphanumeric mode, so it
+    /// makes smaller QR codes than base64.
+    pub fn to_base45(&self) -> String {
+        let data = self.to_cbor_data();
+        let mut text = String::with_capacity(data.len().div_ceil(2) * 3);
+        for chunk in data.chunks(2) {
+            let (mut n, digits) = match chunk {
+                [a, b] => ((*a as usize) << 8 | *b as usize, 3),
+                _ => (chunk[0] as usize, 2),
+            };
+            for _ in 0..digits {
+                text.push(BASE45_ALPHABET[n % 45] as char);
+                n /= 45;
+            }
+        }
+        text
+    }
+
+    /// Decodes a value from its encoding in base45.
+    pub fn try_from_base45(text: &str) -> Result<CBOR> {
+        let mut data = Vec::with_capacity(text.len() / 3 * 2 + 1);
+        for chunk in text.as_bytes().chunks(3) {
+            let mut n = 0usize;
+            for &c in chunk.iter().rev() {
+                let Some(digit) = BASE45_ALPHABET.iter().position(|&a| a == c) else {
+                    bail!(CBORError::InvalidBase45);
+                };
+                n = n * 45 + digit;
+            }
+            match chunk.len() {
+                3 if n <= 0xffff => data.extend_from_slice(&(n as u16).to_be_bytes()),
+                2 if n <= 0xff => data.push(n as u8),
+                _ => bail!(CBORError::InvalidBase45),
+            }
+        }
+        CBOR::try_from_data(data)
+    }
+}
@@ -0,0 +1,218 @@
+import_stdlib!();
+
+use anyhow::{bail, Result};
+
+#[cfg(feature = "float")]
+use crate::float::f64_cbor_data;
+use crate::{text::{is_nfc, to_nfc}, CBORError, EncodeSink, Simple, TagValue, CBOR};
+
+use super::varint::{EncodeVarInt, MajorType};
+
+/// Writes dCBOR one token at a time, without building a `CBOR` value.
+///
+/// Each method writes the head of an item, together with its payload for
+/// strings and scalars. The items of an array, the keys and values of a map,
+/// and the content of a tag are written by the calls that follow its head.
+/// Every token is written in its shortest form, and text is normalized to
+/// NFC, as when encoding a `CBOR` value.
+///
+/// The writer checks that the tokens form exactly one complete item and that
+/// map keys are written in canonical order. The first problem found is
+/// reported by [`TokenWriter::finish`]; until then, writes continue so that
+/// calls can be chained.
+///
+/// ```
+/// # use dcbor::{prelude::*, TokenWriter};
+/// let mut writer = TokenWriter::new();
+/// writer
+///     .write_map_header(2)
+///     .write_unsigned(1).write_text("one")
+///     .write_unsigned(2).write_array_header(2)
+///         .write_bool(true).write_null();
+/// let data = writer.finish().unwrap();
+/// # #[cfg(feature = "format")]
+/// assert_eq!(CBOR::try_from_data(&data).unwrap().diagnostic_flat(), r#"{1: "one", 2: [true, null]}"#);
+/// ```
+pub struct TokenWriter<S: EncodeSink = Vec<u8>> {
+    sink: S,
+    stack: Vec<Container>,
+    complete: bool,
+    error: Option<CBORError>,
+}
+
+/// An array, map, or tag whose content is being written.
+struct Container {
+    /// The number of items left to write, counting keys and values
+    /// separately.
+    remaining: u64,
+    is_map: bool,
+    /// The number of items written so far.
+    count: u64,
+    /// The encoding of the map key being written.
+    key: Option<Vec<u8>>,
+    /// The encoding of the previous map key.
+    previous_key: Option<Vec<u8>>,
+}
+
+impl TokenWriter {
+    /// Makes a new writer that writes to a buffer, returned by
+    /// [`TokenWriter::finish`].
+    pub fn new() -> Self {
+        Self::with_sink(Vec::new())
+    }
+}
+
+impl Default for TokenWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: EncodeSink> TokenWriter<S> {
+    /// Makes a new writer that writes to the given sink.
+    ///
+    /// Only the keys of the map currently being written are held in memory,
+    /// to check their order.
+    pub fn with_sink(sink: S) -> Self {
+        Self { sink, stack: Vec::new(), complete: false, error: None }
+    }
+
+    /// Writes an unsigned integer.
+    pub fn write_unsigned(&mut self, value: u64) -> &mut Self {
+        self.write_item(&value.encode_varint(MajorType::Unsigned), None)
+    }
+
+    /// Writes the negative integer `-1 - n`.
+    pub fn write_negative(&mut self, n: u64) -> &mut Self {
+        self.write_item(&n.encode_varint(MajorType::Negative), None)
+    }
+
+    /// Writes a signed integer.
+    pub fn write_int(&mut self, value: i64) -> &mut Self {
+        if value < 0 {
+            self.write_negative(!value as u64)
+        } else {
+            self.write_unsigned(value as u64)
+        }
+    }
+
+    /// Writes a byte string.
+    pub fn write_bytes(&mut self, bytes: impl AsRef<[u8]>) -> &mut Self {
+        let bytes = bytes.as_ref();
+        let mut buf = bytes.len().encode_varint(MajorType::ByteString);
+        buf.extend(bytes);
+        self.write_item(&buf, None)
+    }
+
+    /// Writes a text string, normalized to NFC.
+    pub fn write_text(&mut self, text: &str) -> &mut Self {
+        let text: Cow<'_, str> = if is_nfc(text) { Cow::Borrowed(text) } else { Cow::Owned(to_nfc(text)) };
+        let mut buf = text.len().encode_varint(MajorType::Text);
+        buf.extend(text.as_bytes());
+        self.write_item(&buf, None)
+    }
+
+    /// Writes the head of an array of `len` items, which are written next.
+    pub fn write_array_header(&mut self, len: usize) -> &mut Self {
+        self.write_item(&len.encode_varint(MajorType::Array), Some((len as u64, false)))
+    }
+
+    /// Writes the head of a map of `len` entries, whose keys and values are
+    /// written next, alternately and in canonical order of the keys.
+    pub fn write_map_header(&mut self, len: usize) -> &mut Self {
+        let items = (len as u64).saturating_mul(2);
+        self.write_item(&len.encode_varint(MajorType::Map), Some((items, true)))
+    }
+
+    /// Writes a tag, whose content is written next.
+    pub fn write_tag(&mut self, tag: TagValue) -> &mut Self {
+        self.write_item(&tag.encode_varint(MajorType::Tagged), Some((1, false)))
+    }
+
+    /// Writes `true` or `false`.
+    pub fn write_bool(&mut self, value: bool) -> &mut Self {
+        let simple = if value { Simple::True } else { Simple::False };
+        self.write_item(&simple.cbor_data(), None)
+    }
+
+    /// Writes `null`.
+    pub fn write_null(&mut self) -> &mut Self {
+        self.write_item(&Simple::Null.cbor_data(), None)
+    }
+
+    /// Writes a floating point value, in its canonical form.
+    #[cfg(feature = "float")]
+    pub fn write_float(&mut self, value: f64) -> &mut Self {
+        self.write_item(&f64_cbor_data(value), None)
+    }
+
+    /// Writes a complete item.
+    pub fn write_cbor(&mut self, cbor: &CBOR) -> &mut Self {
+        self.write_item(&cbor.to_cbor_data(), None)
+    }
+
+    /// Returns the sink, failing if the tokens written do not form exactly
+    /// one complete item with map keys in canonical order.
+    pub fn finish(self) -> Result<S> {
+        if let Some(error) = self.error {
+            bail!(error);
+        }
+        if !self.complete {
+            bail!(CBORError::InvalidTokenSequence("the item is incomplete".to_string()));
+        }
+        Ok(self.sink)
+    }
+
+    /// Writes the encoding of an item's head, and its payload if it has one,
+    /// then opens a container of the given size, if any.
+    fn write_item(&mut self, encoded: &[u8], container: Option<(u64, bool)>) -> &mut Self {
+        if self.complete && self.error.is_none() {
+            self.error = Some(CBORError::InvalidTokenSequence("tokens were written after the item was complete".to_string()));
+        }
+        if let Some(parent) = self.stack.last_mut() {
+            if parent.is_map && parent.count.is_multiple_of(2) {
+                parent.key = Some(Vec::new());
+            }
+        }
+        self.sink.write(encoded);
+        for container in self.stack.iter_mut() {
+            if let Some(key) = container.key.as_mut() {
+                key.extend(encoded);
+            }
+        }
+        match container {
+            Some((remaining, is_map)) if remaining > 0 => {
+                self.stack.push(Container { remaining, is_map, count: 0, key: None, previous_key: None });
+            },
+            _ => self.end_item(),
+        }
+        self
+    }
+
+    /// Records that an item has been written, checking the order of map
+    /// keys and closing any containers the item completes.
+    fn end_item(&mut self) {
+        while let Some(container) = self.stack.last_mut() {
+            if let Some(key) = container.key.take() {
+                if let Some(previous) = &container.previous_key {
+                    let error = match previous.cmp(&key) {
+                        cmp::Ordering::Equal => Some(CBORError::DuplicateMapKey),
+                        cmp::Ordering::Greater => Some(CBORError::MisorderedMapKey),
+                        cmp::Ordering::Less => None,
+                    };
+                    if self.error.is_none() {
+                        self.error = error;
+                    }
+                }
+                container.previous_key = Some(key);
+            }
+            container.count += 1;
+            container.remaining -= 1;
+            if container.remaining > 0 {
+                return;
+            }
+            self.stack.pop();
+        }
+        self.complete = true;
+    }
+}
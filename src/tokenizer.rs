@@ -0,0 +1,183 @@
+import_stdlib!();
+
+use anyhow::{bail, Result};
+
+#[cfg(feature = "float")]
+use half::f16;
+#[cfg(feature = "float")]
+use crate::float::{validate_canonical_f16, validate_canonical_f32, validate_canonical_f64};
+use crate::{
+    decode::{located, parse_header_varint},
+    string_util::from_utf8,
+    text::is_nfc,
+    CBORError, Simple, TagValue,
+};
+
+use super::varint::MajorType;
+
+/// A token of dCBOR: the head of an item, together with its payload for
+/// strings and scalars.
+///
+/// Strings borrow their payload from the data being tokenized.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token<'a> {
+    /// An unsigned integer.
+    Unsigned(u64),
+    /// The negative integer `-1 - n`.
+    Negative(u64),
+    /// A byte string.
+    ByteString(&'a [u8]),
+    /// A text string.
+    Text(&'a str),
+    /// The head of an array with the given number of items, which are the
+    /// tokens that follow.
+    Array(u64),
+    /// The head of a map with the given number of entries, whose keys and
+    /// values are the tokens that follow, alternately.
+    Map(u64),
+    /// A tag, whose content is the item that follows.
+    Tag(TagValue),
+    /// `false`, `true`, `null`, or a floating point value.
+    Simple(Simple),
+}
+
+/// A pull parser that reads the tokens of dCBOR data in order, without
+/// building `CBOR` values.
+///
+/// Each token is checked against the rules of dCBOR that apply to it alone:
+/// its head must be in shortest form, text must be valid UTF-8 in NFC, and
+/// floats must be in canonical form. Rules about how tokens fit together,
+/// such as the number of items in a container and the order of map keys,
+/// are left to the caller, which is free to stop reading at any point.
+///
+/// Errors carry the offset at which tokenizing failed, like those returned
+/// by [`CBOR::try_from_data`](crate::CBOR::try_from_data). After an error,
+/// the tokenizer returns no further tokens.
+///
+/// ```
+/// # use dcbor::{prelude::*, Token, Tokenizer};
+/// let data = CBOR::from(vec![1, -2]).to_cbor_data();
+/// let tokens: Vec<_> = Tokenizer::new(&data)
+///     .map(|token| token.unwrap().0)
+///     .collect();
+/// assert_eq!(tokens, [Token::Array(2), Token::Unsigned(1), Token::Negative(1)]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Tokenizer<'a> {
+    data: &'a [u8],
+    pos: usize,
+    failed: bool,
+}
+
+impl<'a> Tokenizer<'a> {
+    /// Makes a new tokenizer that reads the tokens of the given data.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0, failed: false }
+    }
+
+    /// Returns the offset of the next token in the data.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Reads the next token, returning it along with the range of bytes it
+    /// occupies, or `None` at the end of the data.
+    pub fn next_token(&mut self) -> Result<Option<(Token<'a>, ops::Range<usize>)>> {
+        if self.failed || self.pos == self.data.len() {
+            return Ok(None);
+        }
+        let start = self.pos;
+        match read_token(&self.data[start..]) {
+            Ok((token, len)) => {
+                self.pos += len;
+                Ok(Some((token, start..self.pos)))
+            },
+            Err(error) => {
+                self.failed = true;
+                Err(located(error, start))
+            },
+        }
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Result<(Token<'a>, ops::Range<usize>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_token().transpose()
+    }
+}
+
+/// Reads the token at the start of the data, returning it along with the
+/// number of bytes it occupies.
+fn read_token(data: &[u8]) -> Result<(Token<'_>, usize)> {
+    let (major_type, value, head_len) = parse_header_varint(data)?;
+    read_token_from_head(data, major_type, value, head_len, true)
+}
+
+/// Reads the token at the start of the data, whose head has already been
+/// parsed into its major type, argument, and length.
+///
+/// This allows the decoder to check the length of a string against its
+/// limits before the payload is read. Text strings not in NFC are rejected
+/// unless `require_nfc` is `false`.
+pub(crate) fn read_token_from_head(data: &[u8], major_type: MajorType, value: u64, head_len: usize, require_nfc: bool) -> Result<(Token<'_>, usize)> {
+    let token = match major_type {
+        MajorType::Unsigned => Token::Unsigned(value),
+        MajorType::Negative => Token::Negative(value),
+        MajorType::ByteString | MajorType::Text => {
+            let payload = usize::try_from(value).ok()
+                .and_then(|len| data[head_len..].get(..len));
+            let Some(payload) = payload else {
+                bail!(CBORError::Underrun);
+            };
+            let len = head_len + payload.len();
+            if matches!(major_type, MajorType::ByteString) {
+                return Ok((Token::ByteString(payload), len));
+            }
+            let string = match from_utf8(payload) {
+                Ok(string) => string,
+                Err(err) => bail!(CBORError::InvalidString(err)),
+            };
+            if require_nfc && !is_nfc(string) {
+                bail!(CBORError::NonCanonicalString)
+            }
+            return Ok((Token::Text(string), len));
+        },
+        MajorType::Array => Token::Array(value),
+        MajorType::Map => Token::Map(value),
+        MajorType::Tagged => Token::Tag(value),
+        MajorType::Simple => {
+            let simple = match head_len {
+                #[cfg(not(feature = "float"))]
+                3 | 5 | 9 => bail!(CBORError::UnsupportedFloat),
+                #[cfg(feature = "float")]
+                3 => {
+                    let f = f16::from_bits(value as u16);
+                    validate_canonical_f16(f)?;
+                    Simple::Float(f.to_f64())
+                },
+                #[cfg(feature = "float")]
+                5 => {
+                    let f = f32::from_bits(value as u32);
+                    validate_canonical_f32(f)?;
+                    Simple::Float(f as f64)
+                },
+                #[cfg(feature = "float")]
+                9 => {
+                    let f = f64::from_bits(value);
+                    validate_canonical_f64(f)?;
+                    Simple::Float(f)
+                },
+                _ => match value {
+                    20 => Simple::False,
+                    21 => Simple::True,
+                    22 => Simple::Null,
+                    _ => bail!(CBORError::InvalidSimpleValue),
+                },
+            };
+            Token::Simple(simple)
+        },
+    };
+    Ok((token, head_len))
+}
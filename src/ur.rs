@@ -0,0 +1,169 @@
+import_stdlib!();
+
+use anyhow::{bail, Result};
+
+use crate::{CBORError, CBOR};
+
+/// The style of a Bytewords encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BytewordsStyle {
+    /// Four-letter words separated by spaces, e.g. `able acid also`.
+    Standard,
+    /// Four-letter words separated by hyphens, for use in URIs.
+    Uri,
+    /// The first and last letter of each word, without separators. This is
+    /// the style used in URs.
+    Minimal,
+}
+
+const WORDS: [&str; 256] = [
+    "able", "acid", "also", "apex", "aqua", "arch", "atom", "aunt",
+    "away", "axis", "back", "bald", "barn", "belt", "beta", "bias",
+    "blue", "body", "brag", "brew", "bulb", "buzz", "calm", "cash",
+    "cats", "chef", "city", "claw", "code", "cola", "cook", "cost",
+    "crux", "curl", "cusp", "cyan", "dark", "data", "days", "deli",
+    "dice", "diet", "door", "down", "draw", "drop", "drum", "dull",
+    "duty", "each", "easy", "echo", "edge", "epic", "even", "exam",
+    "exit", "eyes", "fact", "fair", "fern", "figs", "film", "fish",
+    "fizz", "flap", "flew", "flux", "foxy", "free", "frog", "fuel",
+    "fund", "gala", "game", "gear", "gems", "gift", "girl", "glow",
+    "good", "gray", "grim", "guru", "gush", "gyro", "half", "hang",
+    "hard", "hawk", "heat", "help", "high", "hill", "holy", "hope",
+    "horn", "huts", "iced", "idea", "idle", "inch", "inky", "into",
+    "iris", "iron", "item", "jade", "jazz", "join", "jolt", "jowl",
+    "judo", "jugs", "jump", "junk", "jury", "keep", "keno", "kept",
+    "keys", "kick", "kiln", "king", "kite", "kiwi", "knob", "lamb",
+    "lava", "lazy", "leaf", "legs", "liar", "limp", "lion", "list",
+    "logo", "loud", "love", "luau", "luck", "lung", "main", "many",
+    "math", "maze", "memo", "menu", "meow", "mild", "mint", "miss",
+    "monk", "nail", "navy", "need", "news", "next", "noon", "note",
+    "numb", "obey", "oboe", "omit", "onyx", "open", "oval", "owls",
+    "paid", "part", "peck", "play", "plus", "poem", "pool", "pose",
+    "puff", "puma", "purr", "quad", "quiz", "race", "ramp", "real",
+    "redo", "rich", "road", "rock", "roof", "ruby", "ruin", "runs",
+    "rust", "safe", "saga", "scar", "sets", "silk", "skew", "slot",
+    "soap", "solo", "song", "stub", "surf", "swan", "taco", "task",
+    "taxi", "tent", "tied", "time", "tiny", "toil", "tomb", "toys",
+    "trip", "tuna", "twin", "ugly", "undo", "unit", "urge", "user",
+    "vast", "very", "veto", "vial", "vibe", "view", "visa", "void",
+    "vows", "wall", "wand", "warm", "wasp", "wave", "waxy", "webs",
+    "what", "when", "whiz", "wolf", "work", "yank", "yawn", "yell",
+    "yoga", "yurt", "zaps", "zero", "zest", "zinc", "zone", "zoom",
+];
+
+/// Encodes the given data as Bytewords in the given style, with its CRC-32
+/// checksum appended.
+pub fn bytewords_encode(data: impl AsRef<[u8]>, style: BytewordsStyle) -> String {
+    let data = data.as_ref();
+    let mut bytes = data.to_vec();
+    bytes.extend_from_slice(&crc32(data).to_be_bytes());
+    let words = bytes.iter().map(|&byte| WORDS[byte as usize]);
+    match style {
+        BytewordsStyle::Standard => words.collect::<Vec<_>>().join(" "),
+        BytewordsStyle::Uri => words.collect::<Vec<_>>().join("-"),
+        BytewordsStyle::Minimal => words.flat_map(|word| [&word[0..1], &word[3..4]]).collect(),
+    }
+}
+
+/// Decodes Bytewords in the given style, verifying and removing the CRC-32
+/// checksum.
+///
+/// Upper and lower case letters are accepted.
+pub fn bytewords_decode(text: &str, style: BytewordsStyle) -> Result<Vec<u8>> {
+    let text = text.to_ascii_lowercase();
+    let mut bytes = Vec::new();
+    match style {
+        BytewordsStyle::Standard | BytewordsStyle::Uri => {
+            let separator = if style == BytewordsStyle::Standard { ' ' } else { '-' };
+            for word in text.split(separator) {
+                match WORDS.iter().position(|w| *w == word) {
+                    Some(byte) => bytes.push(byte as u8),
+                    None => bail!(CBORError::InvalidBytewords(format!("unknown word {:?}", word))),
+                }
+            }
+        },
+        BytewordsStyle::Minimal => {
+            if !text.is_ascii() || !text.len().is_multiple_of(2) {
+                bail!(CBORError::InvalidBytewords("minimal bytewords must be an even number of letters".into()));
+            }
+            for pair in text.as_bytes().chunks(2) {
+                let byte = WORDS.iter().position(|w| w.as_bytes()[0] == pair[0] && w.as_bytes()[3] == pair[1]);
+                match byte {
+                    Some(byte) => bytes.push(byte as u8),
+                    None => bail!(CBORError::InvalidBytewords(format!("unknown word {:?}", str::from_utf8(pair).unwrap()))),
+                }
+            }
+        },
+    }
+    if bytes.len() < 4 {
+        bail!(CBORError::InvalidBytewords("too short to contain a checksum".into()));
+    }
+    let (data, checksum) = bytes.split_at(bytes.len() - 4);
+    if crc32(data).to_be_bytes() != checksum {
+        bail!(CBORError::InvalidBytewords("invalid checksum".into()));
+    }
+    Ok(data.to_vec())
+}
+
+/// Interop with the Bytewords and UR text encodings.
+impl CBOR {
+    /// Returns the encoding of this value as Bytewords in the given style.
+    pub fn to_bytewords(&self, style: BytewordsStyle) -> String {
+        bytewords_encode(self.to_cbor_data(), style)
+    }
+
+    /// Decodes a value from its encoding as Bytewords in the given style.
+    pub fn try_from_bytewords(text: &str, style: BytewordsStyle) -> Result<CBOR> {
+        CBOR::try_from_data(bytewords_decode(text, style)?)
+    }
+
+    /// Returns this value as a single-part UR of the given type, e.g.
+    /// `ur:seed/...`.
+    ///
+    /// Returns an error if the type is not made up of lower case letters,
+    /// digits, and hyphens.
+    pub fn to_ur_string(&self, ur_type: &str) -> Result<String> {
+        check_ur_type(ur_type)?;
+        Ok(format!("ur:{}/{}", ur_type, self.to_bytewords(BytewordsStyle::Minimal)))
+    }
+
+    /// Decodes a single-part UR, returning its type and value.
+    ///
+    /// Upper and lower case URs are accepted, and the type is returned in
+    /// lower case. Multi-part URs are not supported.
+    pub fn try_from_ur_string(ur: &str) -> Result<(String, CBOR)> {
+        let ur = ur.to_ascii_lowercase();
+        let Some(rest) = ur.strip_prefix("ur:") else {
+            bail!(CBORError::InvalidUR("missing \"ur:\" prefix".into()));
+        };
+        let Some((ur_type, body)) = rest.split_once('/') else {
+            bail!(CBORError::InvalidUR("missing type".into()));
+        };
+        check_ur_type(ur_type)?;
+        if body.contains('/') {
+            bail!(CBORError::InvalidUR("multi-part URs are not supported".into()));
+        }
+        let cbor = CBOR::try_from_bytewords(body, BytewordsStyle::Minimal)?;
+        Ok((ur_type.to_string(), cbor))
+    }
+}
+
+fn check_ur_type(ur_type: &str) -> Result<()> {
+    if ur_type.is_empty() || !ur_type.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') {
+        bail!(CBORError::InvalidUR(format!("invalid type {:?}", ur_type)));
+    }
+    Ok(())
+}
+
+/// The CRC-32 (ISO-HDLC) checksum used by Bytewords.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
@@ -0,0 +1,187 @@
+import_stdlib!();
+
+use core::marker::PhantomData;
+
+use anyhow::{bail, Result};
+
+use crate::{CBORError, Map, CBOR};
+
+/// Conventions for encoding Rust enums as CBOR.
+///
+/// - Enums with only unit variants are encoded as a single integer or text
+///   string per variant. Use [`EnumCodec`] to encode each variant as its
+///   discriminant or its name, or
+///   [`impl_cbor_unit_enum!`](crate::impl_cbor_unit_enum) to implement the
+///   conversions with values of your choosing.
+/// - Data-carrying variants are encoded as a map with a single entry, whose
+///   key identifies the variant and whose value is the variant's content. Use
+///   [`CBOR::to_variant`] and [`CBOR::try_into_variant`].
+/// - Where the variant identifies a distinct registered type, it may instead
+///   be encoded as a tagged value, with the tag identifying the variant.
+impl CBOR {
+    /// Creates a new CBOR value representing an enum variant: a map with the
+    /// single entry `{discriminant: content}`.
+    pub fn to_variant(discriminant: impl Into<CBOR>, content: impl Into<CBOR>) -> CBOR {
+        let mut map = Map::new();
+        map.insert(discriminant, content);
+        map.into()
+    }
+
+    /// Extracts the discriminant and content of an enum variant encoded by
+    /// [`CBOR::to_variant`].
+    ///
+    /// Returns `Err` if the value is not a map with exactly one entry.
+    pub fn try_into_variant(self) -> Result<(CBOR, CBOR)> {
+        let map = self.try_into_map()?;
+        if map.len() != 1 {
+            bail!(CBORError::WrongType)
+        }
+        let (discriminant, content) = map.iter().next().unwrap();
+        Ok((discriminant.clone(), content.clone()))
+    }
+}
+
+/// An enum with only unit variants, whose variants can be listed for
+/// encoding with an [`EnumCodec`].
+///
+/// Implement it with [`impl_unit_variants!`](crate::impl_unit_variants).
+pub trait UnitVariants: Sized + Copy + PartialEq + 'static {
+    /// Each variant, with its discriminant and name.
+    const VARIANTS: &'static [(Self, i64, &'static str)];
+}
+
+/// Encodes the variants of an enum with only unit variants as integers or
+/// text strings, following one of the conventions for encoding enums.
+///
+/// ```
+/// use dcbor::{prelude::*, EnumCodec};
+///
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// enum Color { Red = 1, Green = 2 }
+///
+/// dcbor::impl_unit_variants!(Color { Red, Green });
+///
+/// let codec = EnumCodec::<Color>::by_discriminant();
+/// assert_eq!(codec.encode(Color::Green), CBOR::from(2));
+/// assert_eq!(codec.decode(&CBOR::from(1)).unwrap(), Color::Red);
+///
+/// let codec = EnumCodec::<Color>::by_name();
+/// assert_eq!(codec.encode(Color::Green), CBOR::from("Green"));
+/// assert!(codec.decode(&CBOR::from(1)).is_err());
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct EnumCodec<T> {
+    by_name: bool,
+    variants: PhantomData<fn() -> T>,
+}
+
+impl<T: UnitVariants> EnumCodec<T> {
+    /// Returns a codec encoding each variant as its discriminant, an
+    /// integer.
+    pub fn by_discriminant() -> Self {
+        Self { by_name: false, variants: PhantomData }
+    }
+
+    /// Returns a codec encoding each variant as its name, a text string.
+    pub fn by_name() -> Self {
+        Self { by_name: true, variants: PhantomData }
+    }
+
+    /// Returns the encoding of the variant.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the variant is missing from [`UnitVariants::VARIANTS`],
+    /// which cannot happen when it is implemented with
+    /// [`impl_unit_variants!`](crate::impl_unit_variants).
+    pub fn encode(&self, value: T) -> CBOR {
+        let (_, discriminant, name) = T::VARIANTS.iter()
+            .find(|(variant, _, _)| *variant == value)
+            .expect("variant missing from UnitVariants::VARIANTS");
+        self.value_of(*discriminant, name)
+    }
+
+    /// Returns the variant with the given encoding.
+    ///
+    /// Returns [`CBORError::UnknownVariant`] if no variant has that
+    /// encoding.
+    pub fn decode(&self, cbor: &CBOR) -> Result<T> {
+        match T::VARIANTS.iter().find(|(_, discriminant, name)| self.value_of(*discriminant, name) == *cbor) {
+            Some((variant, _, _)) => Ok(*variant),
+            None => bail!(CBORError::UnknownVariant),
+        }
+    }
+
+    fn value_of(&self, discriminant: i64, name: &str) -> CBOR {
+        if self.by_name {
+            CBOR::from(name)
+        } else {
+            CBOR::from(discriminant)
+        }
+    }
+}
+
+/// Implements [`UnitVariants`](crate::UnitVariants) for an enum with only
+/// unit variants, listing each of them, so that the enum can be encoded with
+/// an [`EnumCodec`](crate::EnumCodec).
+///
+/// The discriminants are those of the variants, so the enum must be one that
+/// can be cast with `as i64`, and names are as written.
+#[macro_export]
+macro_rules! impl_unit_variants {
+    ($type:ident { $($variant:ident),+ $(,)? }) => {
+        impl $crate::UnitVariants for $type {
+            const VARIANTS: &'static [(Self, i64, &'static str)] = &[
+                $(($type::$variant, $type::$variant as i64, stringify!($variant))),+
+            ];
+        }
+    };
+}
+
+/// Implements conversion to and from CBOR for an enum with only unit
+/// variants, each of which is encoded as the given CBOR value (usually an
+/// integer or text string).
+///
+/// Decoding a value that does not correspond to any variant fails with
+/// `CBORError::UnknownVariant`.
+///
+/// ```
+/// use dcbor::prelude::*;
+///
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// enum Color { Red, Green }
+///
+/// dcbor::impl_cbor_unit_enum!(Color {
+///     Red => 0,
+///     Green => "green",
+/// });
+///
+/// assert_eq!(CBOR::from(Color::Green), CBOR::from("green"));
+/// assert_eq!(Color::try_from(CBOR::from(0)).unwrap(), Color::Red);
+/// assert!(Color::try_from(CBOR::from(1)).is_err());
+/// ```
+#[macro_export]
+macro_rules! impl_cbor_unit_enum {
+    ($type:ident { $($variant:ident => $value:expr),+ $(,)? }) => {
+        impl From<$type> for $crate::CBOR {
+            fn from(value: $type) -> Self {
+                match value {
+                    $($type::$variant => $crate::CBOR::from($value)),+
+                }
+            }
+        }
+
+        impl TryFrom<$crate::CBOR> for $type {
+            type Error = $crate::__private::Error;
+
+            fn try_from(cbor: $crate::CBOR) -> $crate::__private::Result<Self> {
+                $(
+                    if cbor == $crate::CBOR::from($value) {
+                        return Ok($type::$variant);
+                    }
+                )+
+                Err($crate::__private::Error::msg($crate::CBORError::UnknownVariant))
+            }
+        }
+    };
+}
@@ -0,0 +1,200 @@
+import_stdlib!();
+
+use crate::{CBORCase, Tag, CBOR};
+
+/// The relationship between an element visited by [`CBOR::walk`] and its
+/// parent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EdgeType {
+    /// The element is the root of the walk, and has no parent.
+    None,
+    /// The element is an item of an array, at the given index.
+    ArrayElement(usize),
+    /// The element is a key-value pair of a map.
+    MapKeyValue,
+    /// The element is the key of a map entry.
+    MapKey,
+    /// The element is the value of a map entry.
+    MapValue,
+    /// The element is the content of a tagged value with the given tag.
+    TaggedContent(Tag),
+}
+
+/// An element visited by [`CBOR::walk`].
+#[derive(Debug, Clone)]
+pub enum WalkElement<'a> {
+    /// A single CBOR value.
+    Single(&'a CBOR),
+    /// A key-value pair of a map, visited before its key and value are
+    /// visited individually.
+    KeyValue { key: &'a CBOR, value: &'a CBOR },
+}
+
+impl<'a> WalkElement<'a> {
+    /// Returns the value if this is a single element.
+    pub fn as_single(&self) -> Option<&'a CBOR> {
+        match self {
+            WalkElement::Single(cbor) => Some(cbor),
+            WalkElement::KeyValue { .. } => None,
+        }
+    }
+
+    /// Returns the key and value if this is a key-value pair.
+    pub fn as_key_value(&self) -> Option<(&'a CBOR, &'a CBOR)> {
+        match self {
+            WalkElement::Single(_) => None,
+            WalkElement::KeyValue { key, value } => Some((key, value)),
+        }
+    }
+}
+
+/// A function called for each element visited by [`CBOR::walk`].
+///
+/// It receives the element, its nesting level (with the root at level
+/// zero), its relationship to its parent, and the state passed down from
+/// its parent. It returns the state to pass down to the element's children,
+/// and whether to skip visiting them.
+///
+/// Visitors are `FnMut`, so they can accumulate results in captured
+/// variables directly.
+pub type Visitor<'a, State> = dyn FnMut(&WalkElement<'_>, usize, EdgeType, State) -> (State, bool) + 'a;
+
+impl CBOR {
+    /// Walks this CBOR value depth-first, calling the visitor for each
+    /// element.
+    ///
+    /// Array items, map entries, and tagged content are visited after their
+    /// parent. Each map entry is visited first as a
+    /// [`WalkElement::KeyValue`], then its key and value are visited
+    /// individually.
+    pub fn walk<State: Clone>(&self, state: State, visitor: &mut Visitor<'_, State>) {
+        self.walk_element(0, EdgeType::None, state, visitor);
+    }
+
+    /// Returns an iterator over the elements of this CBOR value, in the same
+    /// depth-first order that [`CBOR::walk`] visits them.
+    ///
+    /// Each item is an element, its nesting level, and its relationship to
+    /// its parent. Elements are produced lazily, so standard iterator
+    /// adapters such as `filter` and `take_while` can be used.
+    pub fn iter_tree(&self) -> TreeIter<'_> {
+        TreeIter { stack: vec![Pending::Single(self, 0, EdgeType::None)] }
+    }
+
+    fn walk_element<State: Clone>(&self, level: usize, edge: EdgeType, state: State, visitor: &mut Visitor<'_, State>) {
+        let (state, stop) = visitor(&WalkElement::Single(self), level, edge, state);
+        if stop {
+            return;
+        }
+        let level = level + 1;
+        match self.as_case() {
+            CBORCase::Array(array) => {
+                for (index, item) in array.iter().enumerate() {
+                    item.walk_element(level, EdgeType::ArrayElement(index), state.clone(), visitor);
+                }
+            },
+            CBORCase::Map(map) => {
+                for (key, value) in map.iter() {
+                    let element = WalkElement::KeyValue { key, value };
+                    let (state, stop) = visitor(&element, level, EdgeType::MapKeyValue, state.clone());
+                    if stop {
+                        continue;
+                    }
+                    key.walk_element(level, EdgeType::MapKey, state.clone(), visitor);
+                    value.walk_element(level, EdgeType::MapValue, state, visitor);
+                }
+            },
+            CBORCase::Tagged(tag, item) => {
+                item.walk_element(level, EdgeType::TaggedContent(tag.clone()), state, visitor);
+            },
+            _ => {},
+        }
+    }
+}
+
+/// Common walks, built on [`CBOR::iter_tree`].
+impl CBOR {
+    /// Returns the number of items in this value, including the value
+    /// itself and all nested items, map keys, and tagged contents.
+    pub fn count_items(&self) -> usize {
+        self.singles().count()
+    }
+
+    /// Returns the deepest nesting level in this value, with the value
+    /// itself at level zero.
+    pub fn max_depth(&self) -> usize {
+        self.iter_tree().map(|(_, level, _)| level).max().unwrap_or(0)
+    }
+
+    /// Returns the text strings in this value, including map keys, in the
+    /// order [`CBOR::walk`] visits them.
+    pub fn collect_text_strings(&self) -> Vec<&str> {
+        self.singles().filter_map(|cbor| match cbor.as_case() {
+            CBORCase::Text(text) => Some(text.as_str()),
+            _ => None,
+        }).collect()
+    }
+
+    /// Returns the byte strings in this value, including map keys, in the
+    /// order [`CBOR::walk`] visits them.
+    pub fn collect_byte_strings(&self) -> Vec<&[u8]> {
+        self.singles().filter_map(|cbor| match cbor.as_case() {
+            CBORCase::ByteString(bytes) => Some(bytes.data()),
+            _ => None,
+        }).collect()
+    }
+
+    fn singles(&self) -> impl Iterator<Item = &CBOR> {
+        self.iter_tree().filter_map(|(element, _, _)| element.as_single())
+    }
+}
+
+/// An iterator over the elements of a CBOR value, returned by
+/// [`CBOR::iter_tree`].
+#[derive(Debug, Clone)]
+pub struct TreeIter<'a> {
+    stack: Vec<Pending<'a>>,
+}
+
+/// An element waiting to be produced by a [`TreeIter`].
+#[derive(Debug, Clone)]
+enum Pending<'a> {
+    Single(&'a CBOR, usize, EdgeType),
+    KeyValue(&'a CBOR, &'a CBOR, usize),
+}
+
+impl<'a> Iterator for TreeIter<'a> {
+    type Item = (WalkElement<'a>, usize, EdgeType);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.stack.pop()? {
+            Pending::KeyValue(key, value, level) => {
+                Some((WalkElement::KeyValue { key, value }, level, EdgeType::MapKeyValue))
+            },
+            Pending::Single(cbor, level, edge) => {
+                // Children are pushed in reverse, so they are popped in order.
+                let child_level = level + 1;
+                match cbor.as_case() {
+                    CBORCase::Array(array) => {
+                        for (index, item) in array.iter().enumerate().rev() {
+                            self.stack.push(Pending::Single(item, child_level, EdgeType::ArrayElement(index)));
+                        }
+                    },
+                    CBORCase::Map(map) => {
+                        let entries: Vec<_> = map.iter().collect();
+                        for (key, value) in entries.into_iter().rev() {
+                            self.stack.push(Pending::Single(value, child_level, EdgeType::MapValue));
+                            self.stack.push(Pending::Single(key, child_level, EdgeType::MapKey));
+                            self.stack.push(Pending::KeyValue(key, value, child_level));
+                        }
+                    },
+                    CBORCase::Tagged(tag, item) => {
+                        self.stack.push(Pending::Single(item, child_level, EdgeType::TaggedContent(tag.clone())));
+                    },
+                    _ => {},
+                }
+                Some((WalkElement::Single(cbor), level, edge))
+            },
+        }
+    }
+}
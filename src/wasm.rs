@@ -0,0 +1,132 @@
+import_stdlib!();
+
+use anyhow::{bail, Error, Result};
+use js_sys::{Array, BigInt, Object, Reflect, Uint8Array};
+use wasm_bindgen::JsValue;
+
+use crate::{ByteString, CBORCase, CBORError, CBORKind, Simple, CBOR};
+
+/// Converts a JavaScript typed array to a CBOR byte string, copying its
+/// contents.
+impl From<Uint8Array> for CBOR {
+    fn from(array: Uint8Array) -> Self {
+        CBOR::from(&array)
+    }
+}
+
+impl From<&Uint8Array> for CBOR {
+    fn from(array: &Uint8Array) -> Self {
+        CBOR::to_byte_string(array.to_vec())
+    }
+}
+
+impl From<Uint8Array> for ByteString {
+    fn from(array: Uint8Array) -> Self {
+        ByteString::from(array.to_vec())
+    }
+}
+
+impl From<&ByteString> for Uint8Array {
+    fn from(bytes: &ByteString) -> Self {
+        Uint8Array::new_from_slice(bytes.data())
+    }
+}
+
+/// Converts a CBOR byte string to a JavaScript typed array, copying its
+/// contents.
+///
+/// Returns [`CBORError::WrongType`] if the value is not a byte string.
+impl TryFrom<CBOR> for Uint8Array {
+    type Error = Error;
+
+    fn try_from(cbor: CBOR) -> Result<Self> {
+        match cbor.as_case() {
+            CBORCase::ByteString(bytes) => Ok(Uint8Array::from(bytes)),
+            _ => bail!(CBORError::WrongType),
+        }
+    }
+}
+
+impl CBOR {
+    /// Converts this value to a plain JavaScript value.
+    ///
+    /// - Integers become numbers if they are safe integers, and `BigInt`s
+    ///   otherwise.
+    /// - Floats become numbers.
+    /// - Byte strings become `Uint8Array`s, and text strings become strings.
+    /// - Arrays become arrays.
+    /// - Maps whose keys are all text become plain objects, and other maps
+    ///   become `Map`s, with entries in canonical order.
+    /// - `true`, `false`, and `null` become themselves.
+    ///
+    /// Returns [`CBORError::NotJsValue`] if the value contains a tagged
+    /// value, which has no plain JavaScript counterpart.
+    pub fn to_js_value(&self) -> Result<JsValue> {
+        let value = match self.as_case() {
+            CBORCase::Unsigned(n) => integer_js_value(*n as i128),
+            CBORCase::Negative(n) => integer_js_value(-1 - *n as i128),
+            CBORCase::ByteString(bytes) => Uint8Array::from(bytes).into(),
+            CBORCase::Text(text) => JsValue::from_str(text),
+            CBORCase::Array(items) => {
+                let array = Array::new();
+                for item in items {
+                    array.push(&item.to_js_value()?);
+                }
+                array.into()
+            },
+            CBORCase::Map(map) => {
+                if map.iter().all(|(key, _)| key.kind() == CBORKind::Text) {
+                    let object = Object::new();
+                    for (key, value) in map.iter() {
+                        set_property(&object, &key.to_js_value()?, &value.to_js_value()?)?;
+                    }
+                    object.into()
+                } else {
+                    let js_map = js_sys::Map::new();
+                    for (key, value) in map.iter() {
+                        js_map.set(&key.to_js_value()?, &value.to_js_value()?);
+                    }
+                    js_map.into()
+                }
+            },
+            CBORCase::Tagged(tag, _) => bail!(CBORError::NotJsValue(format!("tag {} is not supported", tag.value()))),
+            CBORCase::Simple(Simple::False) => JsValue::FALSE,
+            CBORCase::Simple(Simple::True) => JsValue::TRUE,
+            CBORCase::Simple(Simple::Null) => JsValue::NULL,
+            #[cfg(feature = "float")]
+            CBORCase::Simple(Simple::Float(f)) => JsValue::from_f64(*f),
+        };
+        Ok(value)
+    }
+}
+
+/// The largest magnitude of an integer that a JavaScript number holds
+/// exactly, `Number.MAX_SAFE_INTEGER`.
+const MAX_SAFE_INTEGER: i128 = (1 << 53) - 1;
+
+fn integer_js_value(n: i128) -> JsValue {
+    if (-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&n) {
+        JsValue::from_f64(n as f64)
+    } else {
+        BigInt::from(n).into()
+    }
+}
+
+/// Defines `key` as an own data property of `object`.
+///
+/// Assigning with `Reflect::set` would run inherited setters, so a key such
+/// as `"__proto__"` would replace the object's prototype instead of becoming
+/// an entry.
+fn set_property(object: &Object, key: &JsValue, value: &JsValue) -> Result<()> {
+    let descriptor = Object::new();
+    for (name, field) in [("value", value), ("writable", &JsValue::TRUE), ("enumerable", &JsValue::TRUE), ("configurable", &JsValue::TRUE)] {
+        if let Err(error) = Reflect::set(&descriptor, &JsValue::from_str(name), field) {
+            bail!(CBORError::NotJsValue(format!("{:?}", error)));
+        }
+    }
+    match Reflect::define_property(object, key, &descriptor) {
+        Ok(true) => Ok(()),
+        Ok(false) => bail!(CBORError::NotJsValue(format!("cannot define property {:?}", key))),
+        Err(error) => bail!(CBORError::NotJsValue(format!("{:?}", error))),
+    }
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "format")]
+
 use dcbor::prelude::*;
 use indoc::indoc;
 
@@ -311,12 +313,12 @@ fn format_tagged() {
         r#"100("Hello")"#,
         r#"tagged(100, text("Hello"))"#,
         r#"100("Hello")"#,
-        r#"100("Hello")"#,
+        r#"100("Hello")   / epoch-days /"#,
         r#"100("Hello")"#,
         r#"100("Hello")"#,
         "d8646548656c6c6f",
         indoc! {r#"
-        d8 64               # tag(100)
+        d8 64               # tag(100) epoch-days
             65              # text(5)
                 48656c6c6f  # "Hello"
         "#}.trim()
@@ -354,6 +356,7 @@ fn format_date() {
     );
 }
 
+#[cfg(feature = "float")]
 #[test]
 fn format_fractional_date() {
     run(dcbor::Date::from_timestamp(0.5).into(),
@@ -399,6 +402,28 @@ fn format_structure() {
         ]
     )
     "}.trim();
+    let diagnostic_annotated = indoc! {r#"
+    49(
+        [
+            1,
+            h'536f6d65206d7973746572696573206172656e2774206d65616e7420746f20626520736f6c7665642e',   / "Some mysteries aren't meant to be solved." /
+            [
+                707(
+                    [
+                        1,
+                        h'2b9238e19eafbc154b49ec89edd4e0fb1368e97332c6913b4beb637d1875824f3e43bd7fb0c41fb574f08ce00247413d3ce2d9466e0ccfa4a89b92504982710a'
+                    ]
+                ),
+                707(
+                    [
+                        1,
+                        h'0f9c7af36804ffe5313c00115e5a31aa56814abaa77ff301da53d48613496e9c51a98b36d55f6fb5634fdb0123910cfa4904f1c60523df41013dc3749b377900'
+                    ]
+                )
+            ]
+        ]
+    )
+    "#}.trim();
     let diagnostic_flat = "49([1, h'536f6d65206d7973746572696573206172656e2774206d65616e7420746f20626520736f6c7665642e', [707([1, h'2b9238e19eafbc154b49ec89edd4e0fb1368e97332c6913b4beb637d1875824f3e43bd7fb0c41fb574f08ce00247413d3ce2d9466e0ccfa4a89b92504982710a']), 707([1, h'0f9c7af36804ffe5313c00115e5a31aa56814abaa77ff301da53d48613496e9c51a98b36d55f6fb5634fdb0123910cfa4904f1c60523df41013dc3749b377900'])]])";
     let hex = "d83183015829536f6d65206d7973746572696573206172656e2774206d65616e7420746f20626520736f6c7665642e82d902c3820158402b9238e19eafbc154b49ec89edd4e0fb1368e97332c6913b4beb637d1875824f3e43bd7fb0c41fb574f08ce00247413d3ce2d9466e0ccfa4a89b92504982710ad902c3820158400f9c7af36804ffe5313c00115e5a31aa56814abaa77ff301da53d48613496e9c51a98b36d55f6fb5634fdb0123910cfa4904f1c60523df41013dc3749b377900";
     let hex_annotated = indoc! {r#"
@@ -423,7 +448,7 @@ fn format_structure() {
         description,
         debug_description,
         diagnostic,
-        diagnostic,
+        diagnostic_annotated,
         diagnostic_flat,
         diagnostic_flat,
         hex,
@@ -567,3 +592,227 @@ fn format_key_order() {
         hex_annotated
     );
 }
+
+#[test]
+fn format_diagnostic_opts() {
+    let cbor = CBOR::from(vec![CBOR::from(vec![1, 2]), CBOR::from("abc")]);
+
+    let opts = DiagFormatOpts::default().indent_width(2);
+    assert_eq!(cbor.diagnostic_with_opts(&opts), indoc! {r#"
+        [
+          [1, 2],
+          "abc"
+        ]
+    "#}.trim());
+
+    let opts = DiagFormatOpts::default().indent_width(2).collapse_small(false);
+    assert_eq!(cbor.diagnostic_with_opts(&opts), indoc! {r#"
+        [
+          [
+            1,
+            2
+          ],
+          "abc"
+        ]
+    "#}.trim());
+
+    let array = CBOR::from(vec!["alpha", "bravo", "charlie"]);
+    assert_eq!(array.diagnostic(), indoc! {r#"
+        [
+            "alpha",
+            "bravo",
+            "charlie"
+        ]
+    "#}.trim());
+    let opts = DiagFormatOpts::default().max_width(40);
+    assert_eq!(array.diagnostic_with_opts(&opts), r#"["alpha", "bravo", "charlie"]"#);
+
+    let opts = DiagFormatOpts::default().flat(true);
+    assert_eq!(cbor.diagnostic_with_opts(&opts), r#"[[1, 2], "abc"]"#);
+}
+
+#[test]
+fn format_annotated_byte_strings() {
+    let embedded = CBOR::from(vec![1, 2]).to_cbor_data();
+    let cbor = CBOR::from(vec![
+        CBOR::to_byte_string(&embedded),
+        CBOR::to_tagged_value(24, CBOR::to_byte_string(&embedded)),
+        CBOR::to_byte_string("Hi"),
+        CBOR::to_byte_string([0x01]),
+        CBOR::to_byte_string([0xff, 0x00]),
+    ]);
+    assert_eq!(cbor.diagnostic_flat(), r#"[h'820102', 24(h'820102'), h'4869', h'01', h'ff00']"#);
+
+    // Only the content of tag 24 is decoded as embedded dCBOR by default, so
+    // bytes that happen to be valid dCBOR, like h'01', are not shown as such.
+    assert_eq!(cbor.diagnostic_annotated(), indoc! {r#"
+        [
+            h'820102',
+            24(
+                h'820102'   / <<[1, 2]>> /
+            ),
+            h'4869',   / "Hi" /
+            h'01',
+            h'ff00'
+        ]
+    "#}.trim());
+
+    let opts = DiagFormatOpts::default().annotate(true).decode_embedded(true);
+    assert_eq!(cbor.diagnostic_with_opts(&opts), indoc! {r#"
+        [
+            h'820102',   / <<[1, 2]>> /
+            24(
+                h'820102'   / <<[1, 2]>> /
+            ),
+            h'4869',   / "Hi" /
+            h'01',   / <<1>> /
+            h'ff00'
+        ]
+    "#}.trim());
+
+    assert_eq!(CBOR::to_tagged_value(24, CBOR::to_byte_string(&embedded)).hex_annotated(), indoc! {r#"
+        d8 18           # tag(24)
+            43          # bytes(3)
+                820102  # <<[1, 2]>>
+    "#}.trim());
+}
+
+#[cfg(feature = "float")]
+#[test]
+fn format_debug_compact() {
+    let mut map = Map::new();
+    map.insert(1, CBOR::to_byte_string([0x01, 0x02]));
+    map.insert("a\n\"b\"", vec![CBOR::from(-1), CBOR::from(1.5), CBOR::null()]);
+    map.insert(-18446744073709551616i128 as f64, CBOR::from(f64::NEG_INFINITY));
+    let cbor: CBOR = vec![
+        CBOR::from(map),
+        CBOR::to_tagged_value(Tag::new(700, "my tag"), "x"),
+        CBOR::to_tagged_value(701, true),
+        CBOR::from(1e300),
+    ].into();
+
+    let mut tags = TagsStore::default();
+    tags.insert(Tag::new(701, "flag"));
+    let compact = cbor.to_debug_compact_opt(Some(&tags));
+    assert_eq!(compact, r#"[{1: h[2]'0102', -18446744073709551616: -inf, "a\n\"b\"": [-1, 1.5, null]}, 700#"my tag"("x"), 701#flag(true), 1e300]"#);
+
+    let parsed = CBOR::from_debug_compact(&compact).unwrap();
+    assert_eq!(parsed, cbor);
+    // Parsed tags keep their names, so no tags store is needed to reproduce
+    // the text.
+    assert_eq!(parsed.to_debug_compact_opt(None), compact);
+
+    // Floats read back reduced as dCBOR requires, and byte lengths are
+    // checked.
+    assert!(CBOR::from_debug_compact("2.5").unwrap().is_float());
+    assert_eq!(CBOR::from_debug_compact("2.0").unwrap(), CBOR::from(2));
+    assert!(CBOR::from_debug_compact(" [ 1 ,2 ] ").is_ok());
+    let err = CBOR::from_debug_compact("h[3]'0102'").unwrap_err();
+    assert_eq!(err.to_string(), "invalid compact debug text at offset 10: byte string length does not match its content");
+    assert!(CBOR::from_debug_compact("[1, 2").is_err());
+    assert!(CBOR::from_debug_compact("1 2").is_err());
+    assert!(CBOR::from_debug_compact("-18446744073709551617").is_err());
+
+    // Maps may not repeat a key.
+    let err = CBOR::from_debug_compact(r#"{1: "a", 2: "b", 1: "c"}"#).unwrap_err();
+    assert!(matches!(err.downcast_ref::<CBORError>(), Some(CBORError::DuplicateMapKey)));
+}
+
+#[test]
+fn format_dump_items() {
+    let mut tags = TagsStore::default();
+    tags.insert(Tag::new(700, "my-tag"));
+    // [1, 700("ab")]
+    let cbor: CBOR = vec![CBOR::from(1), CBOR::to_tagged_value(700, "ab")].into();
+    let items = cbor.dump_items_opt(Some(&tags));
+    let summary: Vec<_> = items.iter()
+        .map(|item| (item.offset, hex::encode(&item.bytes), item.depth, item.description.clone().unwrap_or_default(), item.tag_name.clone()))
+        .collect();
+    assert_eq!(summary, vec![
+        (0, "82".to_string(), 0, "array(2)".to_string(), None),
+        (1, "01".to_string(), 1, "unsigned(1)".to_string(), None),
+        (2, "d902bc".to_string(), 1, "tag(700)".to_string(), Some("my-tag".to_string())),
+        (5, "62".to_string(), 2, "text(2)".to_string(), None),
+        (6, "6162".to_string(), 3, r#""ab""#.to_string(), None),
+    ]);
+    // The items cover the encoding exactly.
+    assert_eq!(items.iter().flat_map(|item| item.bytes.clone()).collect::<Vec<_>>(), cbor.to_cbor_data());
+}
+
+#[test]
+fn format_diagnostic_truncated() {
+    let numbers: Vec<u32> = (1..=1000).collect();
+    let mut map = Map::new();
+    for i in 0..5 {
+        map.insert(i, i * 10);
+    }
+    let cbor: CBOR = vec![
+        CBOR::from(numbers),
+        CBOR::to_byte_string(vec![0xab; 1200]),
+        CBOR::from("a\"bcdefgh"),
+        map.into(),
+        CBOR::from("ab"),
+    ].into();
+    assert_eq!(
+        cbor.diagnostic_truncated(3, 2),
+        r#"[[1, 2, … 998 more], h'ababab…(1.2 KB)', … 3 more]"#
+    );
+    assert_eq!(
+        cbor.diagnostic_truncated(3, 4),
+        r#"[[1, 2, 3, 4, … 996 more], h'ababab…(1.2 KB)', "a\"b…(9 chars)", {0: 0, 1: 10, 2: 20, 3: 30, … 1 more}, … 1 more]"#
+    );
+    // Small values are unaffected.
+    assert_eq!(CBOR::from(vec![1, 2]).diagnostic_truncated(3, 2), "[1, 2]");
+}
+
+#[cfg(feature = "float")]
+#[test]
+fn format_date_precision() {
+    use dcbor::{Date, DateFormat};
+    dcbor::register_tags();
+    let date = Date::from_timestamp(1675854714.25);
+    assert_eq!(date.to_string(), "2023-02-08T11:11:54Z");
+    assert_eq!(date.format(DateFormat::Millis), "2023-02-08T11:11:54.250Z");
+    assert_eq!(date.format(DateFormat::Micros), "2023-02-08T11:11:54.250000Z");
+    assert_eq!(Date::from_ymd(2023, 2, 8).format(DateFormat::Seconds), "2023-02-08T00:00:00Z");
+
+    let cbor: CBOR = vec![CBOR::from(date), CBOR::from(Date::from_ymd(2023, 2, 8))].into();
+    assert_eq!(cbor.summary(), "[2023-02-08T11:11:54Z, 2023-02-08]");
+    let opts = DiagFormatOpts::default()
+        .summarize(true)
+        .flat(true)
+        .date_format(DateFormat::Millis);
+    assert_eq!(cbor.diagnostic_with_opts(&opts), "[2023-02-08T11:11:54.250Z, 2023-02-08T00:00:00.000Z]");
+
+    // The format applies in the same way to indented output and to
+    // annotated hex dumps.
+    let tags = TagsStore::new([Tag::new(1, "date")]);
+    let opts = DiagFormatOpts::default()
+        .annotate(true)
+        .summarize(true)
+        .max_width(0)
+        .date_format(DateFormat::Millis)
+        .tags(Some(&tags));
+    assert_eq!(cbor.diagnostic_with_opts(&opts), indoc! {"
+        [
+            2023-02-08T11:11:54.250Z,
+            2023-02-08T00:00:00.000Z
+        ]"}.trim());
+    assert_eq!(cbor.hex_with_opts(&opts), indoc! {"
+        82                          # array(2)
+            c1                      # tag(1) date: 2023-02-08T11:11:54.250Z
+                fb41d8f8e0de900000  # 1675854714.25
+            c1                      # tag(1) date: 2023-02-08T00:00:00.000Z
+                1a63e2e600          # unsigned(1675814400)"}.trim());
+    let items = cbor.dump_items_with_opts(&opts);
+    assert_eq!(items[1].summary.as_deref(), Some("2023-02-08T11:11:54.250Z"));
+    assert_eq!(items[2].summary, None);
+
+    // The registered date summarizer follows the format too.
+    with_tags!(|tags: &TagsStore| {
+        let summarizer = tags.summarizer(1).unwrap();
+        let opts = DiagFormatOpts::default().date_format(DateFormat::Millis);
+        assert_eq!(summarizer(CBOR::from(1675854714.25), tags, &opts).unwrap(), "2023-02-08T11:11:54.250Z");
+        assert_eq!(summarizer(CBOR::from(1675854714.25), tags, &DiagFormatOpts::default()).unwrap(), "2023-02-08T11:11:54Z");
+    });
+}
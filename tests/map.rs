@@ -0,0 +1,398 @@
+use dcbor::{prelude::*, FieldKind};
+#[cfg(feature = "format")]
+use dcbor::{DuplicatePolicy, MapRange, Record};
+#[cfg(feature = "float")]
+use dcbor::{FloatKeyPolicy, ValuePath};
+
+fn sample_map() -> Map {
+    let mut m = Map::new();
+    m.insert("z", 4);
+    m.insert(10, 1);
+    m.insert(-1, 3);
+    m.insert(100, 2);
+    m
+}
+
+#[cfg(feature = "format")]
+#[test]
+fn map_canonical_order() {
+    let m = sample_map();
+    assert_eq!(m.len(), 4);
+    assert!(!m.is_empty());
+
+    let keys: Vec<String> = m.keys().map(|k| k.diagnostic()).collect();
+    assert_eq!(keys, ["10", "100", "-1", r#""z""#]);
+    let values: Vec<i32> = m.values().map(|v| v.clone().try_into().unwrap()).collect();
+    assert_eq!(values, [1, 2, 3, 4]);
+    let reversed: Vec<String> = m.keys().rev().map(|k| k.diagnostic()).collect();
+    assert_eq!(reversed, [r#""z""#, "-1", "100", "10"]);
+    assert_eq!(m.iter().len(), 4);
+
+    assert_eq!(m.first(), Some((&CBOR::from(10), &CBOR::from(1))));
+    assert_eq!(m.last(), Some((&CBOR::from("z"), &CBOR::from(4))));
+    assert_eq!(Map::new().first(), None);
+}
+
+#[cfg(feature = "format")]
+#[test]
+fn map_range() {
+    let mut m = Map::new();
+    for key in ["ns:a", "ns:b", "ns:c", "other"] {
+        m.insert(key, key.len());
+    }
+    m.insert(1, 1);
+
+    let keys = |range: MapRange<'_>| -> Vec<String> {
+        range.map(|(k, _)| k.diagnostic()).collect()
+    };
+    assert_eq!(keys(m.range("ns:a"..="ns:c")), [r#""ns:a""#, r#""ns:b""#, r#""ns:c""#]);
+    assert_eq!(keys(m.range("ns:b".."ns:c")), [r#""ns:b""#]);
+    assert_eq!(keys(m.range(.."ns:a")), ["1"]);
+    assert_eq!(keys(m.range("ns:c"..)), [r#""ns:c""#, r#""other""#]);
+    assert_eq!(m.range::<&str, _>(..).count(), 5);
+}
+
+#[cfg(feature = "format")]
+#[test]
+fn map_from_iter() {
+    let pairs = vec![(1, "a"), (2, "b"), (1, "c")];
+
+    let m: Map = pairs.iter().cloned().collect();
+    assert_eq!(CBOR::from(m).diagnostic(), r#"{1: "c", 2: "b"}"#);
+
+    let m = Map::from_iter_with(pairs.clone(), DuplicatePolicy::KeepFirst).unwrap();
+    assert_eq!(CBOR::from(m).diagnostic(), r#"{1: "a", 2: "b"}"#);
+
+    let m = Map::from_iter_with(pairs.clone(), DuplicatePolicy::KeepLast).unwrap();
+    assert_eq!(CBOR::from(m).diagnostic(), r#"{1: "c", 2: "b"}"#);
+
+    let err = Map::from_iter_with(pairs, DuplicatePolicy::Error).unwrap_err();
+    assert!(matches!(err.downcast_ref::<CBORError>(), Some(CBORError::DuplicateMapKey)));
+
+    let m = Map::from([(1, "a"), (2, "b")]);
+    assert_eq!(CBOR::from(m).diagnostic(), r#"{1: "a", 2: "b"}"#);
+}
+
+#[cfg(feature = "format")]
+#[test]
+fn map_collection_traits() {
+    let mut m = sample_map();
+    m.extend([(5, 0), (10, 5)]);
+    assert_eq!(CBOR::from(&m).diagnostic_flat(), r#"{5: 0, 10: 5, 100: 2, -1: 3, "z": 4}"#);
+
+    let mut keys = Vec::new();
+    for (key, _) in &m {
+        keys.push(key.diagnostic_flat());
+    }
+    assert_eq!(keys, ["5", "10", "100", "-1", r#""z""#]);
+
+    m.retain(|key, value| {
+        *value = CBOR::from(i64::try_from(value.clone()).unwrap() * 10);
+        key.is_number()
+    });
+    assert_eq!(CBOR::from(&m).diagnostic_flat(), "{5: 0, 10: 50, 100: 20, -1: 30}");
+
+    let entries: Vec<(CBOR, CBOR)> = m.into_iter().rev().collect();
+    assert_eq!(entries.len(), 4);
+    assert_eq!(entries[0], (CBOR::from(-1), CBOR::from(30)));
+    assert_eq!(entries[3], (CBOR::from(5), CBOR::from(0)));
+}
+
+#[cfg(feature = "format")]
+#[test]
+fn map_from_pair_containers() {
+    use std::collections::{BTreeMap, LinkedList, VecDeque};
+
+    let expected = "{1: 2, 3: 4}";
+    assert_eq!(CBOR::from(Map::from(BTreeMap::from([(3, 4), (1, 2)]))).diagnostic_flat(), expected);
+    assert_eq!(CBOR::from(Map::from(vec![(3, 4), (1, 2)])).diagnostic_flat(), expected);
+    assert_eq!(CBOR::from(Map::from(VecDeque::from([(3, 4), (1, 2)]))).diagnostic_flat(), expected);
+    assert_eq!(CBOR::from(Map::from(LinkedList::from([(3, 4), (1, 2)]))).diagnostic_flat(), expected);
+    assert_eq!(CBOR::from(Map::from([(3, 4), (1, 2)])).diagnostic_flat(), expected);
+
+    // Hash maps convert with any hasher. Without `std` the crate cannot name
+    // the standard `HashMap`, so the conversions are for hashbrown's instead.
+    #[cfg(feature = "std")]
+    use std::collections::{hash_map::{DefaultHasher, RandomState}, HashMap};
+    #[cfg(not(feature = "std"))]
+    use hashbrown::{hash_map::DefaultHashBuilder as RandomState, HashMap};
+    #[cfg(not(feature = "std"))]
+    use std::collections::hash_map::DefaultHasher;
+
+    let mut hashed = HashMap::with_hasher(std::hash::BuildHasherDefault::<DefaultHasher>::default());
+    hashed.extend([(3, 4), (1, 2)]);
+    assert_eq!(CBOR::from(Map::from(hashed.clone())).diagnostic_flat(), expected);
+    assert_eq!(CBOR::from(hashed).diagnostic_flat(), expected);
+    let mut hashed: HashMap<_, _, RandomState> = HashMap::default();
+    hashed.extend([(3, 4), (1, 2)]);
+    assert_eq!(CBOR::from(Map::from(hashed)).diagnostic_flat(), expected);
+}
+
+#[cfg(feature = "format")]
+#[test]
+fn record_fields() {
+    let record = Record::new()
+        .with(1, "Alice")
+        .with(2, 42);
+    let cbor: CBOR = record.clone().into();
+    assert_eq!(cbor.diagnostic_flat(), r#"{1: "Alice", 2: 42}"#);
+
+    let record = Record::try_from(cbor).unwrap();
+    assert_eq!(record.get_required::<String>(1).unwrap(), "Alice");
+    assert_eq!(record.get_optional::<u32>(2).unwrap(), Some(42));
+    assert_eq!(record.get_optional::<u32>(3).unwrap(), None);
+    assert!(record.contains(1));
+    assert!(!record.contains(3));
+
+    let err = record.get_required::<String>(3).unwrap_err();
+    assert_eq!(err.to_string(), "field 3 missing");
+    let err = record.get_required::<String>(2).unwrap_err();
+    assert_eq!(err.to_string(), "field 2: the decoded CBOR value was not the expected type");
+    let err = record.get_optional::<u8>(1).unwrap_err();
+    assert_eq!(err.to_string(), "field 1: the decoded CBOR value was not the expected type");
+    // The conversion error is kept as the source.
+    let Some(CBORError::InvalidField(1, source)) = err.downcast_ref::<CBORError>() else {
+        panic!("expected an invalid field error");
+    };
+    assert!(matches!(source.downcast_ref::<CBORError>(), Some(CBORError::WrongType)));
+    #[cfg(feature = "std")]
+    assert!(std::error::Error::source(err.downcast_ref::<CBORError>().unwrap()).is_some());
+
+    assert!(Record::try_from(CBOR::from(1)).is_err());
+}
+
+#[test]
+fn map_struct_fields() {
+    let mut map = Map::new();
+    map.insert("name", "Alice");
+    map.insert("age", 42);
+    map.insert("extra", true);
+
+    let fields = map.to_struct_fields(&[("name", FieldKind::Text), ("age", FieldKind::Unsigned)]).unwrap();
+    let name: String = fields[0].clone().try_into().unwrap();
+    let age: u32 = fields[1].clone().try_into().unwrap();
+    assert_eq!((name.as_str(), age), ("Alice", 42));
+
+    let err = map.to_struct_fields(&[
+        ("id", FieldKind::ByteString),
+        ("name", FieldKind::Integer),
+        ("age", FieldKind::Number),
+        ("extra", FieldKind::Tagged(1)),
+    ]).unwrap_err();
+    assert_eq!(err.to_string(), r#"invalid fields: field "id" missing; field "name": expected integer; field "extra": expected tag 1"#);
+    let Some(CBORError::InvalidFields(problems)) = err.downcast_ref::<CBORError>() else {
+        panic!("expected an invalid fields error");
+    };
+    assert_eq!(problems[0], dcbor::FieldProblem::Missing("id".to_string()));
+    assert_eq!(problems[1], dcbor::FieldProblem::WrongKind("name".to_string(), FieldKind::Integer));
+}
+
+#[test]
+fn map_typed_key_lookups() {
+    let mut map = Map::new();
+    map.insert(1, "one");
+    map.insert(-2, "minus two");
+    map.insert("\u{00E9}", "e-acute");
+    map.insert(CBOR::to_byte_string([0xde, 0xad]), true);
+
+    assert_eq!(map.get_by_int::<String>(1).as_deref(), Some("one"));
+    assert_eq!(map.get_by_int::<String>(-2).as_deref(), Some("minus two"));
+    assert_eq!(map.get_by_int::<String>(2), None);
+    assert_eq!(map.get_by_int::<u32>(1), None);
+    // Text keys are normalized before lookup, as they are on insertion.
+    assert_eq!(map.get_by_str::<String>("\u{0065}\u{0301}").as_deref(), Some("e-acute"));
+    assert_eq!(map.get_by_bytes::<bool>(&[0xde, 0xad]), Some(true));
+
+    assert_eq!(map.extract_by_str::<String>("\u{00E9}").unwrap(), "e-acute");
+    assert!(map.extract_by_int::<String>(3).is_err());
+    assert!(map.extract_by_bytes::<bool>(&[]).is_err());
+}
+
+#[cfg(feature = "format")]
+#[test]
+fn map_from_presorted() {
+    let map = Map::from_presorted([(CBOR::from(1), "a"), (2.into(), "b"), ("x".into(), "c")]).unwrap();
+    assert_eq!(CBOR::from(map.clone()).diagnostic_flat(), r#"{1: "a", 2: "b", "x": "c"}"#);
+
+    // The entries of a map are in canonical order.
+    let copy = Map::from_presorted(map.iter().map(|(key, value)| (key.clone(), value.clone()))).unwrap();
+    assert_eq!(CBOR::from(copy), CBOR::from(map));
+    assert!(Map::from_presorted(Vec::<(CBOR, CBOR)>::new()).unwrap().is_empty());
+}
+
+#[test]
+fn map_from_presorted_misordered() {
+    let err = Map::from_presorted([(2, "b"), (1, "a")]).unwrap_err();
+    assert!(matches!(err.downcast_ref::<CBORError>(), Some(CBORError::MisorderedMapKey)));
+    let err = Map::from_presorted([(1, "a"), (1, "b")]).unwrap_err();
+    assert!(matches!(err.downcast_ref::<CBORError>(), Some(CBORError::DuplicateMapKey)));
+}
+
+#[test]
+fn map_encoded_key_lookup() {
+    let mut map = Map::new();
+    map.insert(1, "one");
+    map.insert("name", "Alice");
+
+    // Keys whose encodings are cached ahead of time.
+    let key_one = CBOR::from(1).to_cbor_data();
+    let key_name = CBOR::from("name").to_cbor_data();
+    assert_eq!(map.get_with_encoded_key(&key_one), Some(&CBOR::from("one")));
+    assert_eq!(map.get_with_encoded_key(&key_name), Some(&CBOR::from("Alice")));
+    assert_eq!(map.get_with_encoded_key(&[0x02]), None);
+    // Non-canonical encodings of a key do not match.
+    assert_eq!(map.get_with_encoded_key(&[0x18, 0x01]), None);
+}
+
+#[cfg(feature = "insertion-order")]
+#[test]
+fn map_insertion_order() {
+    let mut map = Map::new();
+    map.insert("zebra", 1);
+    map.insert("apple", 2);
+    map.insert(10, 3);
+    map.insert("zebra", 4);
+    let ordered: Vec<_> = map.insertion_order()
+        .map(|(key, value)| format!("{}: {}", key.diagnostic_flat(), value.diagnostic_flat()))
+        .collect();
+    assert_eq!(ordered, [r#""zebra": 4"#, r#""apple": 2"#, "10: 3"]);
+
+    // Encoding is unaffected.
+    assert_eq!(CBOR::from(&map).diagnostic_flat(), r#"{10: 3, "apple": 2, "zebra": 4}"#);
+
+    // Retained and extended entries keep their positions.
+    map.retain(|key, _| *key != CBOR::from("apple"));
+    map.extend([("mango", 5), ("zebra", 6)]);
+    let ordered: Vec<_> = map.insertion_order()
+        .map(|(key, value)| format!("{}: {}", key.diagnostic_flat(), value.diagnostic_flat()))
+        .collect();
+    assert_eq!(ordered, [r#""zebra": 6"#, "10: 3", r#""mango": 5"#]);
+}
+
+#[test]
+fn map_string_keyed() {
+    use std::collections::{BTreeMap, HashMap};
+
+    let mut map = Map::new();
+    map.insert("name", "Alice");
+    map.insert(1, "one");
+    map.insert(-2, "minus two");
+    map.insert(true, "yes");
+    let strings: BTreeMap<String, CBOR> = map.to_string_keyed_map(false).unwrap();
+    let keys: Vec<_> = strings.keys().cloned().collect();
+    assert_eq!(keys, ["-2", "1", "name", "true"]);
+    assert_eq!(strings["1"], CBOR::from("one"));
+    let hashed: HashMap<String, CBOR> = map.to_string_keyed_map(false).unwrap();
+    assert_eq!(hashed.len(), 4);
+
+    // Keys that collide or cannot be represented require lossy conversion.
+    let mut colliding = map.clone();
+    colliding.insert("1", "text one");
+    assert!(colliding.to_string_keyed_map::<BTreeMap<String, CBOR>>(false).is_err());
+    let lossy: BTreeMap<String, CBOR> = colliding.to_string_keyed_map(true).unwrap();
+    assert_eq!(lossy["1"], CBOR::from("text one"));
+
+    let mut bytes = Map::new();
+    bytes.insert(CBOR::to_byte_string([1, 2]), 1);
+    assert!(bytes.to_string_keyed_map::<BTreeMap<String, CBOR>>(false).is_err());
+    let lossy: BTreeMap<String, CBOR> = bytes.to_string_keyed_map(true).unwrap();
+    assert_eq!(lossy["h'0102'"], CBOR::from(1));
+}
+
+#[test]
+fn field_space() {
+    use dcbor::{FieldCriticality, FieldSpace};
+
+    let space = FieldSpace::new()
+        .reserve(-8..=-1, FieldCriticality::Optional)
+        .reserve(1..16, FieldCriticality::Critical)
+        .reserve(16.., FieldCriticality::Optional)
+        .understand([1, 2, 16]);
+    assert_eq!(space.criticality(15), Some(FieldCriticality::Critical));
+    assert_eq!(space.criticality(i64::MAX), Some(FieldCriticality::Optional));
+    assert_eq!(space.criticality(0), None);
+
+    let mut map = Map::new();
+    map.insert(1, "a");
+    map.insert(16, "b");
+    map.insert(17, "c");
+    map.insert(-2, "d");
+    assert!(space.validate(&map).is_ok());
+    assert_eq!(space.ignored_keys(&map), [17, -2]);
+
+    map.insert(0, "e");
+    map.insert(3, "f");
+    map.insert("name", "g");
+    let err = space.validate(&map).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        r#"invalid fields: key 0: not in a reserved range; key 3: critical but not understood; key "name": not an allowed integer"#
+    );
+    assert!(matches!(
+        err.downcast_ref::<CBORError>(),
+        Some(CBORError::InvalidFields(problems)) if problems[1] == dcbor::FieldProblem::NotUnderstood(3)
+    ));
+}
+
+#[test]
+#[should_panic(expected = "overlaps")]
+fn field_space_overlap() {
+    use dcbor::{FieldCriticality, FieldSpace};
+    let _ = FieldSpace::new()
+        .reserve(1..=15, FieldCriticality::Critical)
+        .reserve(10.., FieldCriticality::Optional);
+}
+
+#[cfg(feature = "float")]
+#[test]
+fn float_key_policy() {
+    let mut inner = Map::new();
+    inner.insert(1.5, "x");
+    let mut middle = Map::new();
+    middle.insert("a", vec![CBOR::from(inner)]);
+    let mut map = Map::new();
+    map.insert(1, middle);
+    map.insert(2, "two");
+
+    assert!(map.validate_keys(FloatKeyPolicy::Allow).is_ok());
+    assert!(map.validate_keys(FloatKeyPolicy::RejectNan).is_ok());
+    let error = map.validate_keys(FloatKeyPolicy::Reject).unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::FloatMapKey)));
+    assert_eq!(error.downcast_ref::<ValuePath>().unwrap().0, r#"$[1]["a"][0]"#);
+
+    let mut nan = Map::new();
+    nan.insert(f64::NAN, 0);
+    let error = nan.validate_keys(FloatKeyPolicy::RejectNan).unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::NanMapKey)));
+    assert_eq!(error.to_string(), "the CBOR map has a NaN key");
+
+    let data = CBOR::from(map).to_cbor_data();
+    assert!(CBOR::try_from_data_opt(&data, &DecodeOptions::default()).is_ok());
+    let options = DecodeOptions::default().float_key_policy(FloatKeyPolicy::Reject);
+    let error = CBOR::try_from_data_opt(&data, &options).unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::FloatMapKey)));
+    assert_eq!(format!("{:#}", error), r#"the CBOR map has a floating point key: at $[1]["a"][0]: at offset 7"#);
+    let options = DecodeOptions::default().float_key_policy(FloatKeyPolicy::RejectNan);
+    assert!(CBOR::try_from_data_opt(&data, &options).is_ok());
+    assert!(CBOR::try_from_data_opt(CBOR::from(nan).to_cbor_data(), &options).is_err());
+}
+
+#[test]
+fn try_into_vec() {
+    // Entries are in canonical order, that of the encodings of the keys.
+    let mut map = Map::new();
+    map.insert(100, "c");
+    map.insert(-1, "b");
+    map.insert(10, "a");
+    let entries: Vec<(i64, String)> = map.try_into_vec().unwrap();
+    assert_eq!(entries, [(10, "a".to_string()), (100, "c".to_string()), (-1, "b".to_string())]);
+
+    let err = sample_map().try_into_vec::<i32, i32>().unwrap_err();
+    assert_eq!(format!("{:#}", err), r#"map key "z": the decoded CBOR value was not the expected type"#);
+
+    let mut map = Map::new();
+    map.insert(1, 2);
+    map.insert(2, -3);
+    let err = map.try_into_vec::<u8, u8>().unwrap_err();
+    assert_eq!(format!("{:#}", err), "map value for key 2: the CBOR numeric value could not be represented in the specified numeric type");
+}
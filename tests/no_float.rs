@@ -0,0 +1,54 @@
+// Run with `cargo test --no-default-features --features std --test no_float`.
+#![cfg(not(feature = "float"))]
+
+use dcbor::{prelude::*, Date, NumberPolicy};
+use hex_literal::hex;
+
+#[test]
+fn floats_rejected() {
+    // 1.5 as half, single, and double precision floats.
+    for data in [&hex!("f93e00")[..], &hex!("fa3fc00000"), &hex!("fb3ff8000000000000")] {
+        let err = CBOR::try_from_data(data).unwrap_err();
+        assert!(matches!(err.downcast_ref::<CBORError>(), Some(CBORError::UnsupportedFloat)));
+
+        let report = CBOR::forensic_scan(data);
+        assert!(!report.is_valid());
+        assert!(matches!(report.events[0].issues[..], [CBORError::UnsupportedFloat]));
+    }
+
+    // Other simple values are unaffected.
+    let simple = vec![CBOR::r#false(), CBOR::r#true(), CBOR::null()];
+    assert_eq!(CBOR::try_from_data(hex!("83f4f5f6")).unwrap(), CBOR::from(simple));
+
+    for policy in [NumberPolicy::Preserve, NumberPolicy::Float, NumberPolicy::IntegersOnly] {
+        assert_eq!(dcbor::from_json_canonical("[1, -2]", policy).unwrap(), CBOR::from(vec![1, -2]));
+        assert!(dcbor::from_json_canonical("1.5", policy).is_err());
+    }
+
+    #[cfg(feature = "format")]
+    {
+        assert!(CBOR::from_debug_compact("[1, 2]").is_ok());
+        assert!(CBOR::from_debug_compact("1.5").is_err());
+    }
+}
+
+#[test]
+fn dates_without_floats() {
+    // The infallible constructors drop fractional seconds, so every date is
+    // encoded exactly.
+    let date = Date::from_timestamp(1675854714.5);
+    assert_eq!(date.to_cbor(), CBOR::to_tagged_value(1, 1675854714));
+    let date = Date::from_timestamp(-0.5);
+    assert_eq!(date.to_cbor(), CBOR::to_tagged_value(1, -1));
+
+    // The fallible ones reject them.
+    let errors = [
+        Date::try_from_timestamp(0.5).unwrap_err(),
+        Date::from_string("2023-02-08T15:30:45.5Z").unwrap_err(),
+    ];
+    for err in errors {
+        assert!(matches!(err.downcast_ref::<CBORError>(), Some(CBORError::UnsupportedFloat)));
+    }
+    assert_eq!(Date::try_from_timestamp(1.0).unwrap(), Date::from_timestamp(1.0));
+    assert!(Date::from_string("2023-02-08T15:30:45Z").is_ok());
+}
@@ -0,0 +1,512 @@
+use std::sync::{Arc, Mutex};
+
+use dcbor::{prelude::*, Date};
+#[cfg(feature = "format")]
+use dcbor::TagConflictPolicy;
+use indoc::indoc;
+
+#[test]
+fn tags_store_csv_snapshot() {
+    let csv = indoc! {r#"
+        Tag,Name,Description
+        # Standard tags
+        32,uri,URI
+        37,"uuid",Binary UUID
+
+        18,COSE_Sign1
+    "#};
+    let mut store = TagsStore::default();
+    assert_eq!(store.import_csv(csv).unwrap(), 3);
+    assert_eq!(store.name_for_value(37), "uuid");
+    assert_eq!(store.tag_for_name("COSE_Sign1").unwrap().value(), 18);
+    assert_eq!(store.export_csv(), "18,COSE_Sign1\n32,uri\n37,uuid\n");
+
+    // Malformed snapshots are rejected before any tag is added.
+    let err = store.import_csv("40,array\nforty,bad").unwrap_err();
+    assert!(matches!(err.downcast_ref::<CBORError>(), Some(CBORError::InvalidTagsCsv(2, _))));
+    let err = store.import_csv("41").unwrap_err();
+    assert!(matches!(err.downcast_ref::<CBORError>(), Some(CBORError::InvalidTagsCsv(1, _))));
+    let err = store.import_csv("42,\"unterminated").unwrap_err();
+    assert!(matches!(err.downcast_ref::<CBORError>(), Some(CBORError::InvalidTagsCsv(1, _))));
+    assert!(store.import_csv("Tag,Data Item\n43,map").is_err());
+    let err = store.import_csv("44,\"a\"b").unwrap_err();
+    assert!(matches!(err.downcast_ref::<CBORError>(), Some(CBORError::InvalidTagsCsv(1, _))));
+    assert!(store.tag_for_value(40).is_none());
+    assert_eq!(store.len(), 3);
+
+    // Quoted names are written and read back intact.
+    let mut store = TagsStore::new([Tag::new(100, "a, b"), Tag::new(101, "say \"hi\"")]);
+    let csv = store.export_csv();
+    assert_eq!(csv, "100,\"a, b\"\n101,\"say \"\"hi\"\"\"\n");
+    let mut restored = TagsStore::default();
+    assert_eq!(restored.import_csv(&csv).unwrap(), 2);
+    assert_eq!(restored.export_csv(), csv);
+    store.remove(100);
+    assert_eq!(store.export_csv(), "101,\"say \"\"hi\"\"\"\n");
+
+    // Whitespace inside quotes is kept, and whitespace around them is not.
+    let store = TagsStore::new([Tag::new(102, " padded ")]);
+    let csv = store.export_csv();
+    assert_eq!(csv, "102,\" padded \"\n");
+    let mut restored = TagsStore::default();
+    assert_eq!(restored.import_csv(&csv).unwrap(), 1);
+    assert_eq!(restored.name_for_value(102), " padded ");
+    assert_eq!(restored.export_csv(), csv);
+    let mut restored = TagsStore::default();
+    assert_eq!(restored.import_csv("103 , \" spaced\" \r\n").unwrap(), 1);
+    assert_eq!(restored.name_for_value(103), " spaced");
+}
+
+#[test]
+fn tags_store_iana_csv() {
+    // The layout of the IANA CBOR Tags registry's CSV, whose names are in the
+    // Semantics column and whose unassigned ranges are skipped.
+    let csv = indoc! {r#"
+        Tag,Data Item,Semantics,Reference,Template
+        0,UTF-8 string,"Standard date/time string, see Section 3.4.1",[RFC8949],
+        6-15,,Unassigned,,
+        32,UTF-8 string,URI,"[RFC8949, Section 3.4.5.3]",
+        33,UTF-8 string,"base64url, with
+        no padding",[RFC8949],
+    "#};
+    let mut store = TagsStore::default();
+    assert_eq!(store.import_csv(csv).unwrap(), 3);
+    assert_eq!(store.name_for_value(32), "URI");
+    assert_eq!(store.name_for_value(0), "Standard date/time string, see Section 3.4.1");
+    assert_eq!(store.name_for_value(33), "base64url, with\nno padding");
+    assert!(store.tag_for_value(6).is_none());
+}
+
+#[cfg(feature = "format")]
+#[test]
+fn tags_store_cbor_snapshot() {
+    let store = TagsStore::new([Tag::new(32, "uri"), Tag::new(37, "uuid")]);
+    let snapshot = store.export_cbor();
+    assert_eq!(snapshot.diagnostic(), r#"{32: "uri", 37: "uuid"}"#);
+
+    let mut restored = TagsStore::default();
+    assert_eq!(restored.import_cbor(&snapshot).unwrap(), 2);
+    assert_eq!(restored.name_for_value(32), "uri");
+    assert_eq!(restored.export_csv(), store.export_csv());
+
+    assert!(restored.import_cbor(&CBOR::from(vec![1, 2])).is_err());
+}
+
+#[cfg(feature = "format")]
+#[test]
+fn standard_tag_names() {
+    dcbor::register_tags();
+    let tags = dcbor::tags_for_values(&[dcbor::TAG_URI, dcbor::TAG_UUID, dcbor::TAG_ENCODED_CBOR, dcbor::TAG_SET]);
+    let names: Vec<_> = tags.iter().map(|tag| tag.to_string()).collect();
+    assert_eq!(names, ["uri", "uuid", "encoded-cbor", "set"]);
+
+    let cbor = CBOR::to_tagged_value(dcbor::TAG_URI, "urn:x");
+    assert_eq!(cbor.diagnostic_annotated(), r#"32("urn:x")   / uri /"#);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Name(String);
+
+dcbor::impl_cbor_tagged_newtype!(Name, 800, 801);
+
+#[cfg(feature = "format")]
+#[test]
+fn tagged_newtype() {
+    let name = Name("Alice".to_string());
+    let cbor: CBOR = name.clone().into();
+    assert_eq!(cbor.diagnostic(), r#"800("Alice")"#);
+    assert_eq!(Name::try_from(cbor).unwrap(), name);
+
+    let legacy = CBOR::to_tagged_value(801, "Bob");
+    assert_eq!(Name::try_from(legacy).unwrap(), Name("Bob".to_string()));
+
+    assert!(Name::try_from(CBOR::to_tagged_value(802, "Carol")).is_err());
+    assert!(Name::try_from(CBOR::to_tagged_value(800, 42)).is_err());
+}
+
+#[test]
+fn tagged_decode_reports_tag() {
+    let (name, tag) = Name::from_tagged_cbor_with_tag(CBOR::to_tagged_value(800, "Alice")).unwrap();
+    assert_eq!(name, Name("Alice".to_string()));
+    assert_eq!(tag.value(), 800);
+
+    let (name, tag) = Name::from_tagged_cbor_with_tag(CBOR::to_tagged_value(801, "Bob")).unwrap();
+    assert_eq!(name, Name("Bob".to_string()));
+    assert_eq!(tag.value(), 801);
+
+    let err = Name::from_tagged_cbor_with_tag(CBOR::to_tagged_value(802, "Carol")).unwrap_err();
+    assert_eq!(err.to_string(), "expected CBOR tag 800 or 801, but got 802");
+
+    // Decoding within containers reports where the tag was found.
+    let names = CBOR::from(vec![CBOR::to_tagged_value(800, "Alice"), CBOR::to_tagged_value(717, "Bob")]);
+    let err = Vec::<Name>::try_from(names.clone()).unwrap_err();
+    assert_eq!(format!("{:#}", err), "array element 1: expected CBOR tag 800 or 801, but got 717: at $[1]");
+    assert!(matches!(
+        err.downcast_ref::<CBORError>(),
+        Some(CBORError::WrongTagOf(expected, found)) if expected.len() == 2 && found.value() == 717
+    ));
+
+    // The path locates the value through every enclosing container.
+    let mut map = Map::new();
+    map.insert("names", names);
+    let err = Vec::<std::collections::BTreeMap<String, Vec<Name>>>::try_from(CBOR::from(vec![map])).unwrap_err();
+    assert_eq!(
+        format!("{:#}", err),
+        r#"array element 0: map value for key "names": array element 1: expected CBOR tag 800 or 801, but got 717: at $[0]["names"][1]"#,
+    );
+    assert_eq!(err.downcast_ref::<dcbor::ValuePath>().unwrap().0, r#"$[0]["names"][1]"#);
+    assert!(Name::from_tagged_cbor_with_tag(CBOR::from("Dave")).is_err());
+}
+
+#[test]
+fn decode_any_registry() {
+    let mut store = TagsStore::default();
+    store.register_decoder::<Name>();
+    store.register_decoder::<Date>();
+
+    let name = store.decode_any(Name("Alice".to_string()).into()).unwrap();
+    assert_eq!(name.downcast_ref::<Name>(), Some(&Name("Alice".to_string())));
+
+    let date = Date::from_timestamp(1675854714.0);
+    let decoded = store.decode_any(date.clone().into()).unwrap();
+    assert_eq!(decoded.downcast_ref::<Date>(), Some(&date));
+
+    let err = store.decode_any(CBOR::to_tagged_value(900, 1)).unwrap_err();
+    assert_eq!(err.to_string(), "no decoder is registered for CBOR tag 900");
+    assert!(store.decode_any(CBOR::from(1)).is_err());
+
+    with_tags_mut!(|tags: &mut TagsStore| tags.register_decoder::<Name>());
+    let data = CBOR::to_tagged_value(801, "Bob").to_cbor_data();
+    let decoded = dcbor::decode_any(data).unwrap();
+    assert_eq!(*decoded.downcast::<Name>().unwrap(), Name("Bob".to_string()));
+}
+
+#[test]
+fn parse_tag() {
+    let mut store = TagsStore::default();
+    store.insert(Tag::new(1, "date"));
+    let tag = Tag::parse_with("date", &store).unwrap();
+    assert_eq!(tag.value(), 1);
+    assert_eq!(tag.to_string(), "date");
+    assert_eq!(Tag::parse_with("1", &store).unwrap().name().as_deref(), Some("date"));
+    assert_eq!(Tag::parse_with("12345", &store).unwrap().to_string(), "12345");
+    let err = Tag::parse_with("nonesuch", &store).unwrap_err();
+    assert_eq!(err.to_string(), "unknown CBOR tag name: nonesuch");
+
+    // `FromStr` uses the global store, so display and parsing round-trip.
+    with_tags_mut!(|tags: &mut TagsStore| tags.insert(Tag::new(12346, "round-trip")));
+    let tag: Tag = "round-trip".parse().unwrap();
+    assert_eq!(tag.value(), 12346);
+    assert_eq!(tag.to_string().parse::<Tag>().unwrap(), tag);
+    assert_eq!("12347".parse::<Tag>().unwrap().value(), 12347);
+}
+
+#[cfg(feature = "format")]
+#[test]
+fn tags_store_introspection() {
+    let mut store = TagsStore::new([Tag::new(40, "forty"), Tag::new(4, "four")]);
+    store.set_summarizer(4, std::sync::Arc::new(|_, _, _| Ok("four!".to_string())));
+    store.set_summarizer(5, std::sync::Arc::new(|_, _, _| Ok("five!".to_string())));
+    assert_eq!(store.len(), 2);
+    let names: Vec<String> = store.tags().iter().map(|tag| tag.to_string()).collect();
+    assert_eq!(names, vec!["four", "forty"]);
+    assert_eq!(store.summarized_tag_values(), vec![4, 5]);
+
+    assert_eq!(store.remove(4).unwrap().value(), 4);
+    assert!(store.tag_for_name("four").is_none());
+    assert!(store.summarizer(4).is_none());
+    assert!(store.remove(4).is_none());
+    assert!(store.remove_summarizer(5).is_some());
+    assert!(store.summarized_tag_values().is_empty());
+    assert_eq!(store.len(), 1);
+
+    with_tags_mut!(|tags: &mut TagsStore| tags.insert(Tag::new(12348, "snapshot")));
+    let snapshot = dcbor::tags_snapshot();
+    assert_eq!(snapshot.tag_for_name("snapshot").unwrap().value(), 12348);
+}
+
+#[test]
+fn eq_ignoring_tags() {
+    let plain: CBOR = vec![CBOR::from(1), CBOR::from("a")].into();
+    let annotated: CBOR = CBOR::to_tagged_value(500, vec![
+        CBOR::to_tagged_value(501, 1),
+        CBOR::to_tagged_value(500, CBOR::to_tagged_value(502, "a")),
+    ]);
+    assert_ne!(plain, annotated);
+    assert!(plain.eq_ignoring_tags(&annotated));
+    assert!(annotated.eq_ignoring_tags(&plain));
+    assert!(!plain.eq_ignoring_tags(&CBOR::to_tagged_value(500, vec![1, 2])));
+
+    assert!(!plain.eq_ignoring_tags_in(&annotated, &[500, 501]));
+    assert!(plain.eq_ignoring_tags_in(&annotated, &[500, 501, 502]));
+    let partly: CBOR = vec![CBOR::to_tagged_value(501, 1), CBOR::to_tagged_value(502, "a")].into();
+    assert!(partly.eq_ignoring_tags_in(&annotated, &[500]));
+
+    // Map keys are compared without their tags too.
+    let mut m1 = Map::new();
+    m1.insert(CBOR::to_tagged_value(503, "k"), 1);
+    let mut m2 = Map::new();
+    m2.insert("k", CBOR::to_tagged_value(504, 1));
+    assert!(CBOR::from(m1).eq_ignoring_tags(&m2.into()));
+
+    // Keys that collide once their tags are removed are not merged, so a map
+    // with such keys equals nothing, not even itself.
+    let mut colliding = Map::new();
+    colliding.insert(CBOR::to_tagged_value(503, "k"), 1);
+    colliding.insert("k", 2);
+    let colliding = CBOR::from(colliding);
+    let mut merged = Map::new();
+    merged.insert("k", 2);
+    assert!(!colliding.eq_ignoring_tags(&merged.into()));
+    assert!(!colliding.eq_ignoring_tags(&colliding));
+    assert!(!colliding.eq_ignoring_tags_in(&colliding, &[503]));
+    assert!(colliding.eq_ignoring_tags_in(&colliding, &[504]));
+}
+
+#[cfg(feature = "format")]
+#[test]
+fn idempotent_tagging() {
+    let once = CBOR::from(1).tagged_if_needed(500);
+    assert_eq!(once.diagnostic_flat(), "500(1)");
+    assert_eq!(once.clone().tagged_if_needed(500), once);
+    assert_eq!(once.clone().tagged_if_needed(501).diagnostic_flat(), "501(500(1))");
+
+    let nested = CBOR::to_tagged_value(500, CBOR::to_tagged_value(500, CBOR::to_tagged_value(501, 1)));
+    assert_eq!(nested.clone().untag_all(500).diagnostic_flat(), "501(1)");
+    assert_eq!(nested.clone().untag_all(501), nested);
+    assert_eq!(CBOR::from(1).untag_all(500), CBOR::from(1));
+
+    let (tags, content) = nested.untag_chain();
+    assert_eq!(tags.iter().map(|tag| tag.value()).collect::<Vec<_>>(), [500, 500, 501]);
+    assert_eq!(*content, CBOR::from(1));
+    let untagged = CBOR::from(vec![CBOR::to_tagged_value(500, 1)]);
+    let (tags, content) = untagged.untag_chain();
+    assert!(tags.is_empty());
+    assert!(std::ptr::eq(content, &untagged));
+}
+
+#[cfg(feature = "format")]
+#[test]
+fn tag_conflict_policy() {
+    // By default the last registration wins, and stale names are dropped.
+    let mut store = TagsStore::new([Tag::new(600, "alpha")]);
+    store.insert(Tag::new(600, "beta"));
+    assert_eq!(store.name_for_value(600), "beta");
+    assert!(store.tag_for_name("alpha").is_none());
+    // Rebinding a name to another value drops the stale value.
+    store.insert(Tag::new(601, "beta"));
+    assert_eq!(store.tag_for_name("beta").unwrap().value(), 601);
+    assert!(store.tag_for_value(600).is_none());
+    assert_eq!(store.len(), 1);
+    store.insert(Tag::new(600, "beta"));
+
+    // Tags without names are refused rather than panicking.
+    let err = store.try_insert(Tag::with_value(602)).unwrap_err();
+    assert!(matches!(err.downcast_ref::<CBORError>(), Some(CBORError::MissingTagName(602))));
+
+    store.set_conflict_policy(TagConflictPolicy::Error);
+    store.insert(Tag::new(600, "beta"));
+    let err = store.try_insert(Tag::new(600, "gamma")).unwrap_err();
+    assert_eq!(err.to_string(), r#"tag 600 is already registered as "beta", and cannot also be registered as "gamma""#);
+    let err = store.try_insert(Tag::new(601, "beta")).unwrap_err();
+    assert_eq!(err.to_string(), r#"tag name "beta" is already registered for tag 600, and cannot also be registered for tag 601"#);
+    assert_eq!(store.name_for_value(600), "beta");
+
+    let summarizer: CBORSummarizer = Arc::new(|_, _, _| Ok("summary".to_string()));
+    store.try_set_summarizer(600, summarizer.clone()).unwrap();
+    store.try_set_summarizer(600, summarizer).unwrap();
+    assert!(store.try_set_summarizer(600, Arc::new(|_, _, _| Ok("other".to_string()))).is_err());
+
+    let warnings = Arc::new(Mutex::new(Vec::new()));
+    let sink = warnings.clone();
+    store.set_conflict_policy(TagConflictPolicy::Warn(Arc::new(move |conflict| {
+        sink.lock().unwrap().push(conflict.to_string());
+    })));
+    store.insert(Tag::new(600, "delta"));
+    assert_eq!(store.name_for_value(600), "delta");
+    assert_eq!(warnings.lock().unwrap().len(), 1);
+
+    // Registering the standard tags again is not a conflict.
+    let mut store = TagsStore::default();
+    store.set_conflict_policy(TagConflictPolicy::Error);
+    dcbor::register_tags_in(&mut store);
+    dcbor::register_tags_in(&mut store);
+
+    // The standard date summarizer replaces any other, whatever the policy.
+    let mut store = TagsStore::default();
+    store.set_summarizer(1, Arc::new(|_, _, _| Ok("custom".to_string())));
+    let date = CBOR::to_tagged_value(1, 0);
+    assert_eq!(date.summary_opt(&store), "custom");
+    store.set_conflict_policy(TagConflictPolicy::Error);
+    dcbor::register_tags_in(&mut store);
+    assert_eq!(date.summary_opt(&store), "1970-01-01");
+}
+
+#[cfg(feature = "format")]
+#[test]
+fn rewrite_tags() {
+    let leaf = CBOR::to_tagged_value(24, "leaf");
+    let mut map = Map::new();
+    map.insert(CBOR::to_tagged_value(24, "k"), vec![leaf.clone(), CBOR::to_tagged_value(500, 1)]);
+    map.insert("plain", CBOR::to_tagged_value(500, leaf));
+    let cbor = CBOR::from(map);
+
+    let migrated = cbor.replace_tag(24, 201);
+    assert_eq!(migrated.diagnostic_flat(), r#"{"plain": 500(201("leaf")), 201("k"): [201("leaf"), 500(1)]}"#);
+
+    let mapped = cbor.map_tags(|tag| match tag.value() {
+        24 => Some(Tag::with_value(201)),
+        500 => Some(Tag::with_value(501)),
+        _ => None,
+    });
+    assert_eq!(mapped.diagnostic_flat(), r#"{"plain": 501(201("leaf")), 201("k"): [201("leaf"), 501(1)]}"#);
+
+    // Trees without the tag are returned as they are.
+    assert_eq!(cbor.replace_tag(600, 601), cbor);
+    assert_eq!(CBOR::from(1).replace_tag(24, 201), CBOR::from(1));
+}
+
+#[test]
+fn tag_allocator() {
+    use dcbor::{TagAllocator, TagRange};
+
+    assert_eq!(TagRange::of(1), TagRange::StandardsAction);
+    assert_eq!(TagRange::of(40000), TagRange::FirstComeFirstServed);
+    assert_eq!(TagRange::of(65535), TagRange::Invalid);
+
+    let mut allocator = TagAllocator::private();
+    let first = allocator.allocate("first").unwrap();
+    assert_eq!(first.value(), 0x8000_0000_0000_0000);
+    let err = allocator.allocate("first").unwrap_err();
+    assert!(matches!(err.downcast_ref::<CBORError>(), Some(CBORError::TagNameConflict(..))));
+    assert_eq!(allocator.allocate("second").unwrap().value(), first.value() + 1);
+    assert!(allocator.allocate_value(1, "low").is_err());
+    // The crate's own tags are outside the range others allocate from.
+    assert!(allocator.allocate_value(dcbor::TAG_BYTE_STRING_CHUNK, "chunk").is_err());
+    assert!(TagAllocator::DCBOR_PRIVATE.contains(&dcbor::TAG_BYTE_STRING_CHUNK));
+    assert!(TagAllocator::DCBOR_PRIVATE.contains(&dcbor::TAG_REDACTED));
+
+    // Invalid values are skipped, and the range can run out.
+    let mut allocator = TagAllocator::first_come_first_served(65534);
+    allocator.allocate_value(65536, "taken").unwrap();
+    assert_eq!(allocator.allocate("a").unwrap().value(), 65534);
+    assert_eq!(allocator.allocate("b").unwrap().value(), 65537);
+    let mut allocator = TagAllocator::new(32768..=32768);
+    allocator.allocate("only").unwrap();
+    let err = allocator.allocate("more").unwrap_err();
+    assert!(matches!(err.downcast_ref::<CBORError>(), Some(CBORError::TagRangeExhausted)));
+
+    // Values in the ranges for standard tags are allowed, with a warning.
+    let warnings = Arc::new(Mutex::new(Vec::new()));
+    let captured = warnings.clone();
+    let mut allocator = TagAllocator::new(20..=30)
+        .with_warning_handler(Arc::new(move |warning| captured.lock().unwrap().push(warning.to_string())));
+    allocator.allocate("mine").unwrap();
+    assert_eq!(*warnings.lock().unwrap(), ["tag 20 is in the standards action range of tag values"]);
+
+    let mut tags = TagsStore::default();
+    allocator.register_in(&mut tags).unwrap();
+    assert_eq!(tags.name_for_value(20), "mine");
+}
+
+#[cfg(feature = "float")]
+#[test]
+fn checked_tagged_value() {
+    use dcbor::{TagPolicy, TAG_BIGFLOAT, TAG_DATE, TAG_DATE_TIME_STRING, TAG_DECIMAL_FRACTION, TAG_NEGATIVE_BIGNUM, TAG_POSITIVE_BIGNUM, TAG_SELF_DESCRIBED_CBOR};
+
+    let date = CBOR::try_to_tagged_value(TAG_DATE, 1675854714).unwrap();
+    assert_eq!(date.hex(), "c11a63e3837a");
+    assert!(CBOR::try_to_tagged_value(TAG_DATE, 1.5).is_ok());
+    assert!(CBOR::try_to_tagged_value(TAG_DATE, "2023-02-08").is_err());
+    assert!(CBOR::try_to_tagged_value(TAG_DATE_TIME_STRING, "2023-02-08T15:30:00Z").is_ok());
+    assert!(CBOR::try_to_tagged_value(TAG_DATE_TIME_STRING, 0).is_err());
+    assert!(CBOR::try_to_tagged_value(TAG_POSITIVE_BIGNUM, CBOR::to_byte_string([1, 0])).is_ok());
+    assert!(CBOR::try_to_tagged_value(TAG_NEGATIVE_BIGNUM, "1").is_err());
+
+    let bignum = CBOR::to_tagged_value(TAG_POSITIVE_BIGNUM, CBOR::to_byte_string([1, 0]));
+    assert!(CBOR::try_to_tagged_value(TAG_DECIMAL_FRACTION, vec![-2, 27315]).is_ok());
+    assert!(CBOR::try_to_tagged_value(TAG_BIGFLOAT, CBOR::from(vec![CBOR::from(-1), bignum])).is_ok());
+    assert!(CBOR::try_to_tagged_value(TAG_DECIMAL_FRACTION, vec![1.5, 2.0]).is_err());
+    assert!(CBOR::try_to_tagged_value(TAG_BIGFLOAT, vec![1, 2, 3]).is_err());
+
+    let error = CBOR::try_to_tagged_value(TAG_SELF_DESCRIBED_CBOR, 1).unwrap_err();
+    assert_eq!(error.to_string(), "tag 55799 cannot be applied to this value: the self-described CBOR tag is not allowed");
+    let policy = TagPolicy::default().allow_self_described(true);
+    let described = CBOR::try_to_tagged_value_with(TAG_SELF_DESCRIBED_CBOR, 1, &policy).unwrap();
+    assert_eq!(described.hex(), "d9d9f701");
+
+    // Other tags are not checked.
+    assert!(CBOR::try_to_tagged_value(100, "anything").is_ok());
+}
+
+#[test]
+fn tagged_pair() {
+    // The tag distinguishes kinds of measurement sharing one layout.
+    let cbor = CBOR::to_tagged_value(40001, vec![20, 25]);
+    let (tag, range): (Tag, Vec<i32>) = cbor.clone().try_into_tagged_pair().unwrap();
+    assert_eq!(tag.value(), 40001);
+    assert_eq!(range, vec![20, 25]);
+
+    let error = cbor.try_into_tagged_pair::<String>().unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::WrongType)));
+    let error = CBOR::from(1).try_into_tagged_pair::<u8>().unwrap_err();
+    assert!(matches!(error.downcast_ref::<CBORError>(), Some(CBORError::WrongType)));
+}
+
+#[cfg(feature = "format")]
+#[test]
+fn recursive_summarizer() {
+    let mut store = TagsStore::new([Tag::new(300, "envelope"), Tag::new(301, "person")]);
+    store.set_summarizer(300, Arc::new(|untagged_cbor, tags, opts| {
+        let items: Vec<String> = untagged_cbor.try_into_array()?
+            .iter()
+            .map(|item| item.diagnostic_with_opts(opts))
+            .collect();
+        let name = tags.name_for_value(300);
+        Ok(format!("{}({})", name, items.join(", ")))
+    }));
+    store.set_summarizer(301, Arc::new(|untagged_cbor, _, _| {
+        untagged_cbor.try_into_text()
+    }));
+
+    let person = |name: &str| CBOR::to_tagged_value(301, name);
+    let envelope = CBOR::to_tagged_value(300, vec![person("alice"), CBOR::from("knows"), person("bob")]);
+    assert_eq!(envelope.summary_opt(&store), r#"envelope(alice, "knows", bob)"#);
+}
+
+/// A tags store that knows a single tag, implemented without summarizers so
+/// that it compiles with or without the `format` feature.
+struct OneTag;
+
+impl TagsStoreTrait for OneTag {
+    fn assigned_name_for_tag(&self, tag: &Tag) -> Option<String> {
+        (tag.value() == 700).then(|| "seven-hundred".to_string())
+    }
+
+    fn name_for_tag(&self, tag: &Tag) -> String {
+        self.assigned_name_for_tag(tag).unwrap_or_else(|| tag.value().to_string())
+    }
+
+    fn tag_for_value(&self, value: u64) -> Option<Tag> {
+        (value == 700).then(|| Tag::new(700, "seven-hundred"))
+    }
+
+    fn tag_for_name(&self, name: &str) -> Option<Tag> {
+        (name == "seven-hundred").then(|| Tag::new(700, "seven-hundred"))
+    }
+
+    fn name_for_value(&self, value: u64) -> String {
+        self.name_for_tag(&Tag::with_value(value))
+    }
+}
+
+#[test]
+fn custom_tags_store() {
+    assert_eq!(OneTag.name_for_value(700), "seven-hundred");
+    #[cfg(feature = "format")]
+    {
+        assert!(OneTag.summarizer(700).is_none());
+        let cbor = CBOR::to_tagged_value(700, 1);
+        assert_eq!(cbor.diagnostic_opt(true, true, true, Some(&OneTag)), "700(1)   / seven-hundred /");
+    }
+}
@@ -0,0 +1,223 @@
+#![cfg(feature = "format")]
+
+use dcbor::{prelude::*, EdgeType, WalkElement};
+
+fn sample() -> CBOR {
+    // [1, {"a": 2(h'01')}, "b"]
+    let mut map = Map::new();
+    map.insert("a", CBOR::to_tagged_value(2, CBOR::to_byte_string([1])));
+    vec![CBOR::from(1), map.into(), CBOR::from("b")].into()
+}
+
+#[test]
+fn walk_accumulates_mutably() {
+    let mut lines = vec![];
+    sample().walk((), &mut |element, level, edge, state| {
+        let description = match element {
+            WalkElement::Single(cbor) => cbor.diagnostic_flat(),
+            WalkElement::KeyValue { key, value } => format!("{}: {}", key.diagnostic_flat(), value.diagnostic_flat()),
+        };
+        lines.push(format!("{} {:?} {}", level, edge, description));
+        (state, false)
+    });
+    assert_eq!(lines, vec![
+        r#"0 None [1, {"a": 2(h'01')}, "b"]"#,
+        "1 ArrayElement(0) 1",
+        r#"1 ArrayElement(1) {"a": 2(h'01')}"#,
+        r#"2 MapKeyValue "a": 2(h'01')"#,
+        r#"2 MapKey "a""#,
+        "2 MapValue 2(h'01')",
+        "3 TaggedContent(Tag { value: 2, name: None }) h'01'",
+        r#"1 ArrayElement(2) "b""#,
+    ]);
+}
+
+#[test]
+fn iter_tree_matches_walk() {
+    let describe = |element: &WalkElement<'_>, level: usize, edge: &EdgeType| {
+        let description = match element {
+            WalkElement::Single(cbor) => cbor.diagnostic_flat(),
+            WalkElement::KeyValue { key, value } => format!("{}: {}", key.diagnostic_flat(), value.diagnostic_flat()),
+        };
+        format!("{} {:?} {}", level, edge, description)
+    };
+    let cbor = sample();
+    let mut walked = vec![];
+    cbor.walk((), &mut |element, level, edge, state| {
+        walked.push(describe(element, level, &edge));
+        (state, false)
+    });
+    let iterated: Vec<_> = cbor.iter_tree()
+        .map(|(element, level, edge)| describe(&element, level, &edge))
+        .collect();
+    assert_eq!(iterated, walked);
+
+    // Standard adapters work on the lazily produced elements.
+    let texts: Vec<_> = cbor.iter_tree()
+        .filter_map(|(element, _, _)| element.as_single().and_then(|c| c.clone().try_into_text().ok()))
+        .collect();
+    assert_eq!(texts, ["a", "b"]);
+    assert_eq!(cbor.iter_tree().take_while(|(_, level, _)| *level < 2).count(), 3);
+}
+
+#[test]
+fn walk_conveniences() {
+    let cbor = sample();
+    assert_eq!(cbor.count_items(), 7);
+    assert_eq!(cbor.count_items(), cbor.stats().total_items);
+    assert_eq!(cbor.max_depth(), 3);
+    assert_eq!(cbor.max_depth(), cbor.stats().max_depth);
+    assert_eq!(cbor.collect_text_strings(), ["a", "b"]);
+    assert_eq!(cbor.collect_byte_strings(), [&[1u8][..]]);
+
+    assert_eq!(CBOR::from(1).count_items(), 1);
+    assert_eq!(CBOR::from(1).max_depth(), 0);
+}
+
+#[test]
+fn walk_sees_enclosing_tag() {
+    let mut contents = vec![];
+    sample().walk((), &mut |element, _, edge, state| {
+        if let EdgeType::TaggedContent(tag) = edge {
+            contents.push((tag.value(), element.as_single().unwrap().diagnostic_flat()));
+        }
+        (state, false)
+    });
+    assert_eq!(contents, vec![(2, "h'01'".to_string())]);
+}
+
+#[test]
+fn walk_state_and_stop() {
+    // State carries the path from the root; descent stops at maps.
+    let mut paths = vec![];
+    sample().walk(String::new(), &mut |element, _, edge, path| {
+        let path = match edge {
+            EdgeType::ArrayElement(index) => format!("{}[{}]", path, index),
+            _ => path,
+        };
+        if let Some(cbor) = element.as_single() {
+            paths.push((path.clone(), cbor.diagnostic_flat()));
+        }
+        let stop = matches!(element.as_single().map(CBOR::as_case), Some(CBORCase::Map(_)));
+        (path, stop)
+    });
+    assert_eq!(paths, vec![
+        ("".to_string(), r#"[1, {"a": 2(h'01')}, "b"]"#.to_string()),
+        ("[0]".to_string(), "1".to_string()),
+        ("[1]".to_string(), r#"{"a": 2(h'01')}"#.to_string()),
+        ("[2]".to_string(), r#""b""#.to_string()),
+    ]);
+}
+
+#[test]
+fn redaction() {
+    use dcbor::RedactionPolicy;
+
+    // A stand-in digest; real uses pass a cryptographic hash.
+    let digest = |data: &[u8]| vec![data.len() as u8, data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))];
+
+    let mut person = Map::new();
+    person.insert("name", "Alice");
+    person.insert("ssn", "123-45-6789");
+    person.insert("born", CBOR::to_tagged_value(1, 0));
+    let mut record = Map::new();
+    record.insert("people", vec![CBOR::from(person.clone()), CBOR::from(person)]);
+    record.insert("note", "hello");
+    let record = CBOR::from(record);
+
+    let policy = RedactionPolicy::new()
+        .key("ssn")
+        .tag(1)
+        .path([dcbor::PathElement::Key("people".into()), 0.into(), "name".into()]);
+    let redacted = record.redacted(&policy, digest);
+    assert_eq!(redacted.diagnostic_flat(), concat!(
+        r#"{"note": "hello", "people": ["#,
+        r#"{"ssn": 18446744073709551361(h'0ca2'), "born": 18446744073709551361(h'02c1'), "name": 18446744073709551361(h'0643')}, "#,
+        r#"{"ssn": 18446744073709551361(h'0ca2'), "born": 18446744073709551361(h'02c1'), "name": "Alice"}"#,
+        r#"]}"#,
+    ));
+    assert!(redacted.is_redaction_of(&record, digest));
+    assert!(record.is_redaction_of(&record, digest));
+    assert!(!redacted.is_redaction_of(&record, |_: &[u8]| vec![0]));
+
+    // Redacting again leaves redacted values as they are.
+    assert_eq!(redacted.redacted(&RedactionPolicy::new().tag(dcbor::TAG_REDACTED), digest), redacted);
+}
+
+#[test]
+fn path_matcher() {
+    use dcbor::{PathElement, PathMatcher, PathPattern};
+
+    let record = |id: i32, name: &str| {
+        let mut map = Map::new();
+        map.insert("id", id);
+        map.insert("name", name);
+        CBOR::from(map)
+    };
+    let mut document = Map::new();
+    document.insert("records", vec![record(1, "Alice"), CBOR::to_tagged_value(100, record(2, "Bob"))]);
+    document.insert(-1, "negative");
+    document.insert(10, sample());
+    let data = CBOR::from(document).to_cbor_data();
+
+    let matcher = PathMatcher::new()
+        .pattern("$.records[*].id".parse().unwrap())
+        .pattern(r#"$[10][1]["a"]"#.parse().unwrap())
+        .pattern("$[-1]".parse().unwrap())
+        .pattern("$.records[1]".parse().unwrap())
+        .pattern("$.missing".parse().unwrap());
+    let matches: Vec<_> = matcher.collect(&data).unwrap().into_iter()
+        .map(|m| {
+            assert_eq!(CBOR::try_from_data(&data[m.span.clone()]).unwrap(), m.value);
+            (m.pattern, m.value.diagnostic_flat())
+        })
+        .collect();
+    // Values are reported as they end, so in the order of the encoding, and
+    // a value inside another before it.
+    assert_eq!(matches, [
+        (1, "2(h'01')".to_string()),
+        (2, r#""negative""#.to_string()),
+        (0, "1".to_string()),
+        (0, "2".to_string()),
+        (3, r#"100({"id": 2, "name": "Bob"})"#.to_string()),
+    ]);
+
+    let first = &matcher.collect(&data).unwrap()[2];
+    assert_eq!(first.path, [PathElement::Key("records".into()), 0.into(), "id".into()]);
+
+    // Patterns display in the notation of `ValuePath`.
+    for text in ["$", "$[*]", r#"$[1]["price"]"#, r#"$[-1]["a \"b\""]"#] {
+        assert_eq!(text.parse::<PathPattern>().unwrap().to_string(), text);
+    }
+    assert_eq!("$.a.*[0]".parse::<PathPattern>().unwrap().to_string(), r#"$["a"][*][0]"#);
+    for (text, offset) in [("a", 0), ("$.", 2), ("$[1", 3), ("$[x]", 2), (r#"$["a]"#, 5), ("$.a b", 3)] {
+        let err = text.parse::<PathPattern>().unwrap_err();
+        assert!(
+            matches!(err.downcast_ref::<CBORError>(), Some(CBORError::InvalidPathPattern(o, _)) if *o == offset),
+            "{text}: {err}",
+        );
+    }
+
+    // The data is still checked throughout.
+    let matcher = PathMatcher::new().pattern("$[0]".parse().unwrap());
+    let err = matcher.collect(&data[..data.len() - 1]).unwrap_err();
+    assert!(matches!(err.downcast_ref::<CBORError>(), Some(CBORError::Underrun)));
+    let err = matcher.collect([0x82, 0x01, 0x02, 0x03]).unwrap_err();
+    assert!(matches!(err.downcast_ref::<CBORError>(), Some(CBORError::UnusedData(1))));
+    // {2: 0, 1: 0}
+    let err = matcher.collect([0xa2, 0x02, 0x00, 0x01, 0x00]).unwrap_err();
+    assert!(matches!(err.downcast_ref::<CBORError>(), Some(CBORError::MisorderedMapKey)));
+    assert_eq!(err.downcast_ref::<dcbor::DecodeOffset>().unwrap().0, 3);
+    let err = matcher.collect([0xa2, 0x01, 0x00, 0x01, 0x00]).unwrap_err();
+    assert!(matches!(err.downcast_ref::<CBORError>(), Some(CBORError::DuplicateMapKey)));
+
+    // Nesting is limited, by default deeply enough for ordinary documents.
+    let mut nested = vec![0x81; 100_000];
+    nested.push(0x00);
+    let err = matcher.collect(&nested).unwrap_err();
+    assert!(matches!(err.downcast_ref::<CBORError>(), Some(CBORError::DepthLimitExceeded)));
+    assert_eq!(err.downcast_ref::<dcbor::DecodeOffset>().unwrap().0, PathMatcher::DEFAULT_MAX_DEPTH + 1);
+    let nested = &nested[nested.len() - 4..];
+    assert!(matcher.clone().max_depth(Some(2)).collect(nested).is_err());
+    assert_eq!(matcher.max_depth(Some(3)).collect(nested).unwrap()[0].value.diagnostic_flat(), "[[0]]");
+}
@@ -0,0 +1,20 @@
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use dcbor::prelude::*;
+use js_sys::{Object, Reflect};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn proto_key_is_an_own_property() {
+    let mut map = Map::new();
+    map.insert("__proto__", 1);
+    map.insert("a", 2);
+    let value = CBOR::from(map).to_js_value().unwrap();
+    let object = Object::from(value);
+    let key = JsValue::from_str("__proto__");
+    assert!(Object::has_own(&object, &key));
+    assert_eq!(Reflect::get(&object, &key).unwrap(), JsValue::from_f64(1.0));
+    assert_eq!(Object::get_prototype_of(&object), Object::get_prototype_of(&Object::new()));
+    assert_eq!(Object::keys(&object).length(), 2);
+}